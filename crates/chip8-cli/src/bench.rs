@@ -0,0 +1,148 @@
+//! Interpreter benchmark (`bench` subcommand): a ROM, or a synthetic workload,
+//! runs for a fixed number of instructions without rendering and the throughput is printed
+
+use crate::cli::BenchArgs;
+use chip8_core::{Chip8, CLOCK_FREQUENCY};
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::{Duration, Instant};
+use tracing::trace;
+
+/// synthetic workload: an endless loop of draws, arithmetic, random numbers, BCD conversions
+/// and subroutine calls (most instruction categories are executed)
+const SYNTHETIC_WORKLOAD: [u8; 37] = [
+    0x60, 0x00, // 0x200: LD V0, 0x00
+    0x61, 0x00, // 0x202: LD V1, 0x00
+    0xA2, 0x20, // 0x204: LD I, 0x220 (loop)
+    0xD0, 0x15, // 0x206: DRW V0, V1, 5
+    0x70, 0x01, // 0x208: ADD V0, 0x01
+    0x82, 0x04, // 0x20A: ADD V2, V0
+    0x83, 0x26, // 0x20C: SHR V3, V2
+    0xC4, 0xFF, // 0x20E: RND V4, 0xFF
+    0xF4, 0x33, // 0x210: LD B, V4
+    0x22, 0x18, // 0x212: CALL 0x218
+    0x12, 0x04, // 0x214: JP 0x204
+    0x00, 0x00, // 0x216: padding
+    0x65, 0x00, // 0x218: LD V5, 0x00 (subroutine)
+    0x00, 0xEE, // 0x21A: RET
+    0x00, 0x00, 0x00, 0x00, // 0x21C: padding
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0x220: sprite (digit 0)
+];
+
+/// Runs the benchmark and prints the throughput of every run and the statistics of all the runs
+///
+/// # Arguments
+///
+/// * `args` - The bench subcommand arguments
+/// * `seed` - The seed for the random number generator
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the ROM file
+/// or if the ROM executes an instruction that cannot be executed
+pub fn run(args: &BenchArgs, seed: u64) {
+    trace!("bench::run: start");
+
+    match &args.rom {
+        Some(rom) => println!("workload: {}", rom.display()),
+        None => println!("workload: synthetic"),
+    }
+
+    let mut speeds = Vec::new();
+    for run in 1..=args.runs {
+        let mut chip8 = Chip8::new();
+        match &args.rom {
+            Some(rom) => chip8.load_rom(rom),
+            None => chip8.load_rom_bytes(&SYNTHETIC_WORKLOAD),
+        }
+
+        let elapsed = run_cycles(&mut chip8, args.cycles, seed);
+        let speed = args.cycles as f64 / elapsed.as_secs_f64();
+        println!(
+            "run {run}: {} instructions in {:.3} s ({})",
+            args.cycles,
+            elapsed.as_secs_f64(),
+            format_speed(speed)
+        );
+        speeds.push(speed);
+    }
+
+    speeds.sort_by(f64::total_cmp);
+    let mean = speeds.iter().sum::<f64>() / speeds.len() as f64;
+    let median = if speeds.len() % 2 == 0 {
+        (speeds[speeds.len() / 2 - 1] + speeds[speeds.len() / 2]) / 2.0
+    } else {
+        speeds[speeds.len() / 2]
+    };
+    println!(
+        "min {}, max {}, mean {}, median {} ({} runs, {:.0}x real time at {CLOCK_FREQUENCY} Hz)",
+        format_speed(speeds[0]),
+        format_speed(speeds[speeds.len() - 1]),
+        format_speed(mean),
+        format_speed(median),
+        speeds.len(),
+        median / CLOCK_FREQUENCY
+    );
+
+    trace!("bench::run: exit");
+}
+
+/// Executes a number of instructions (updating the timers at the emulated 60Hz rate)
+/// and returns the host time spent
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance with the ROM loaded
+/// * `cycles` - The number of instructions to execute
+/// * `seed` - The seed for the random number generator
+fn run_cycles(chip8: &mut Chip8, cycles: u64, seed: u64) -> Duration {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let start = Instant::now();
+    for _ in 0..cycles {
+        if let Err(e) = chip8.step(&mut rng) {
+            panic!("{e}")
+        }
+    }
+
+    start.elapsed()
+}
+
+/// Returns the instructions per second in millions (e.g. `18.52 M instructions/s`)
+///
+/// # Arguments
+///
+/// * `speed` - The instructions per second
+fn format_speed(speed: f64) -> String {
+    format!("{:.2} M instructions/s", speed / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_workload_runs_endlessly() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&SYNTHETIC_WORKLOAD);
+        run_cycles(&mut chip8, 100_000, 10);
+
+        assert_eq!(chip8.cycles(), 100_000);
+        // the call returned: the stack is empty between two iterations
+        assert!(chip8.stack().len() <= 1);
+        assert!(chip8.display_pixels().iter().any(|pixel| *pixel));
+    }
+
+    #[test]
+    #[should_panic(expected = "Stack underflow")]
+    fn execution_errors_stop_the_benchmark() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x00, 0xEE]);
+        run_cycles(&mut chip8, 10, 10);
+    }
+
+    #[test]
+    fn speeds_are_formatted_in_millions() {
+        assert_eq!(format_speed(18_523_000.0), "18.52 M instructions/s");
+        assert_eq!(format_speed(500.0), "0.00 M instructions/s");
+    }
+}
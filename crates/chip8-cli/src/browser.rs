@@ -0,0 +1,257 @@
+//! Terminal ROM browser of the launcher (ratatui): lists the ROM files of a directory with their size,
+//! SHA-1 hash and, if the ROM is known by the database, title and platforms
+
+use crate::launcher::RomEntry;
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind},
+        execute,
+        terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+use std::{
+    io::{self, Stdout},
+    path::Path,
+};
+use tracing::trace;
+
+/// number of hex digits of the hashes in the ROM list
+const SHORT_HASH_LENGTH: usize = 10;
+
+/// Terminal in raw mode and in the alternate screen (restored when dropped)
+struct BrowserTerminal(Terminal<CrosstermBackend<Stdout>>);
+
+impl BrowserTerminal {
+    /// Switches the terminal to raw mode and to the alternate screen
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during setup of the terminal
+    fn new() -> Self {
+        if let Err(e) = terminal::enable_raw_mode() {
+            panic!("setting up terminal: {e}")
+        }
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen) {
+            panic!("setting up terminal: {e}")
+        }
+        match Terminal::new(CrosstermBackend::new(io::stdout())) {
+            Ok(terminal) => Self(terminal),
+            Err(e) => panic!("setting up terminal: {e}"),
+        }
+    }
+}
+
+impl Drop for BrowserTerminal {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen);
+        let _ = self.0.show_cursor();
+    }
+}
+
+/// Shows the ROM browser and returns the index of the ROM selected by the user (Enter),
+/// or None if the user quits the browser (q, Esc)
+///
+/// # Arguments
+///
+/// * `dir` - The Path reference that holds the path to the directory
+/// * `roms` - The ROM files of the directory
+/// * `selected` - The index of the ROM initially selected
+/// * `status` - The exit status of the last ROM run
+///
+/// # Panics
+///
+/// The function panics in case of errors during drawing of the browser or during reading of the terminal events
+pub fn select(
+    dir: &Path,
+    roms: &[RomEntry],
+    selected: usize,
+    status: Option<&str>,
+) -> Option<usize> {
+    trace!("browser::select: start");
+
+    let mut terminal = BrowserTerminal::new();
+    let mut state = TableState::default().with_selected(selected);
+    let choice = loop {
+        if let Err(e) = terminal
+            .0
+            .draw(|frame| draw(frame, dir, roms, &mut state, status))
+        {
+            panic!("drawing rom browser: {e}")
+        }
+
+        let event = match event::read() {
+            Ok(event) => event,
+            Err(e) => panic!("reading terminal events: {e}"),
+        };
+        let Event::Key(key) = event else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+            KeyCode::Home => state.select_first(),
+            KeyCode::End => state.select_last(),
+            KeyCode::Enter => break state.selected().map(|n| n.min(roms.len() - 1)),
+            KeyCode::Char('q') | KeyCode::Esc => break None,
+            _ => {}
+        }
+    };
+
+    trace!("browser::select: exit");
+
+    choice
+}
+
+/// Draws the ROM list, the details of the selected ROM and the key help
+///
+/// # Arguments
+///
+/// * `frame` - The frame to draw
+/// * `dir` - The Path reference that holds the path to the directory
+/// * `roms` - The ROM files of the directory
+/// * `state` - The selection of the ROM list
+/// * `status` - The exit status of the last ROM run
+fn draw(
+    frame: &mut Frame,
+    dir: &Path,
+    roms: &[RomEntry],
+    state: &mut TableState,
+    status: Option<&str>,
+) {
+    let [list_area, details_area, help_area] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let rows = roms.iter().map(|rom| {
+        Row::new([
+            rom.name(),
+            rom.title.unwrap_or("-").to_string(),
+            format!("{} B", rom.size),
+            rom.sha1[..SHORT_HASH_LENGTH].to_string(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(35),
+            Constraint::Percentage(40),
+            Constraint::Length(8),
+            Constraint::Length(SHORT_HASH_LENGTH as u16),
+        ],
+    )
+    .header(
+        Row::new(["File", "Title", "Size", "SHA-1"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+    .block(Block::bordered().title(format!(" ROMs in {} ", dir.display())));
+    frame.render_stateful_widget(table, list_area, state);
+
+    let details = state.selected().and_then(|n| roms.get(n)).map(|rom| {
+        let platforms = if rom.platforms.is_empty() {
+            String::from("-")
+        } else {
+            rom.platforms.join(", ")
+        };
+        vec![
+            Line::from(format!("Path:      {}", rom.path.display())),
+            Line::from(format!("SHA-1:     {}", rom.sha1)),
+            Line::from(format!("Platforms: {platforms}")),
+        ]
+    });
+    frame.render_widget(
+        Paragraph::new(details.unwrap_or_default()).block(Block::bordered().title(" Details ")),
+        details_area,
+    );
+
+    let help = "[↑↓] select  [Enter] launch  [q] quit";
+    let help = match status {
+        Some(status) => format!("{help}  |  last run: {status}"),
+        None => help.to_string(),
+    };
+    frame.render_widget(Paragraph::new(help), help_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use std::path::PathBuf;
+
+    /// platforms of the known ROM of the tests
+    static PLATFORMS: [String; 0] = [];
+
+    /// Returns the lines drawn by the browser on a 100x16 terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `roms` - The ROM files
+    /// * `selected` - The index of the selected ROM
+    /// * `status` - The exit status of the last ROM run
+    fn drawn(roms: &[RomEntry], selected: usize, status: Option<&str>) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        let mut state = TableState::default().with_selected(selected);
+        terminal
+            .draw(|frame| draw(frame, Path::new("roms"), roms, &mut state, status))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// Returns a ROM entry
+    fn rom(name: &str, title: Option<&'static str>) -> RomEntry {
+        RomEntry {
+            path: PathBuf::from("roms").join(name),
+            size: 132,
+            sha1: String::from("0123456789abcdef0123456789abcdef01234567"),
+            title,
+            platforms: &PLATFORMS,
+        }
+    }
+
+    #[test]
+    fn roms_are_listed_with_the_details_of_the_selected_one() {
+        let roms = [rom("a.ch8", Some("Game A")), rom("b.ch8", None)];
+        let lines = drawn(&roms, 1, None).join("\n");
+
+        assert!(lines.contains(" ROMs in roms "));
+        assert!(lines.contains("a.ch8"));
+        assert!(lines.contains("Game A"));
+        assert!(lines.contains("132 B"));
+        assert!(lines.contains("0123456789 "));
+        assert!(lines.contains(&format!(
+            "Path:      {}",
+            Path::new("roms").join("b.ch8").display()
+        )));
+        assert!(lines.contains("Platforms: -"));
+    }
+
+    #[test]
+    fn last_run_status_is_shown() {
+        let roms = [rom("a.ch8", None)];
+        let lines = drawn(&roms, 0, Some("a.ch8: exit code 4"));
+
+        assert!(lines[15]
+            .starts_with("[↑↓] select  [Enter] launch  [q] quit  |  last run: a.ch8: exit code 4"));
+    }
+}
@@ -0,0 +1,1088 @@
+//! CLI arguments parsing and validation
+
+use crate::console::{LogFormat, LogRotation};
+use crate::frontend::Frontend;
+use crate::report::ReportFormat;
+use chip8_core::{
+    InstructionTiming, MachineCodePolicy, Profile, Quirks, SelfModifyingCodePolicy, Variants,
+    Waveform, ETI_660_PROGRAM_START, MAX_MEMORY_SIZE, MAX_VOLUME, MIN_MEMORY_SIZE,
+};
+use chip8_frontend::{Color, IdlePolicy, Keymap, RandomMode, Theme, MAX_SCALE};
+use clap::{
+    builder::{BoolishValueParser, PossibleValue, PossibleValuesParser, TypedValueParser},
+    Args, Parser, Subcommand,
+};
+use std::{ops::RangeInclusive, path::PathBuf};
+use tracing::trace;
+
+/// cli -f command help
+const ARG_ROM_FILE_HELP: &str =
+    "Path to CHIP-8 ROM file to run, or to a directory of ROM files to choose from";
+
+/// cli -f command value name
+const ARG_ROM_FILE_VALUE_NAME: &str = "FILE";
+
+/// cli -s command help
+const ARG_STEPPING_HELP: &str = "Enable one step at time execution";
+
+/// cli -r command help
+const ARG_RANDOM_SEED_HELP: &str = "Random seed";
+
+/// cli -r command value name
+const ARG_RANDOM_SEED_VALUE_NAME: &str = "SEED";
+
+/// cli -r command default value
+const ARG_RANDOM_SEED_DEFAULT_VALUE: u64 = 10;
+
+/// cli --random command help
+const ARG_RANDOM_HELP: &str =
+    "Random number generator of the CXNN instructions (the counter starts from the random seed, the constant is the random seed) [default: seeded]";
+
+/// cli --random command value name
+const ARG_RANDOM_VALUE_NAME: &str = "MODE";
+
+/// cli --log-format command help
+const ARG_LOG_FORMAT_HELP: &str =
+    "Format of the log lines: text, or JSON lines with the cycle, PC and opcode of the instruction (e.g. for jq) [default: text]";
+
+/// cli --log-format command value name
+const ARG_LOG_FORMAT_VALUE_NAME: &str = "FORMAT";
+
+/// cli --log-file command help
+const ARG_LOG_FILE_HELP: &str =
+    "Write the log lines into a file instead of stderr (by a writer thread, so that trace logging does not slow down the emulation)";
+
+/// cli --log-file command value name
+const ARG_LOG_FILE_VALUE_NAME: &str = "FILE";
+
+/// cli --log-rotation command help
+const ARG_LOG_ROTATION_HELP: &str =
+    "Rotation of the log file: never, hourly, daily or a size (e.g. 10MB); 5 old log files are kept [default: never]";
+
+/// cli --log-rotation command value name
+const ARG_LOG_ROTATION_VALUE_NAME: &str = "ROTATION";
+
+/// cli --trace-sample command help
+const ARG_TRACE_SAMPLE_HELP: &str =
+    "Log the debug and trace events of one instruction every N instructions, so that long logging sessions stay fast [default: 1]";
+
+/// cli --trace-sample command value name
+const ARG_TRACE_SAMPLE_VALUE_NAME: &str = "N";
+
+/// cli --history-depth command help
+const ARG_HISTORY_DEPTH_HELP: &str =
+    "Number of last executed instructions logged (disassembled) when an instruction cannot be executed, and kept in the crash dump; 0 disables the history [default: 32]";
+
+/// cli --history-depth command value name
+const ARG_HISTORY_DEPTH_VALUE_NAME: &str = "N";
+
+/// cli --trace-memory command help
+const ARG_TRACE_MEMORY_HELP: &str =
+    "Record the reads and writes of the instructions within an address range (e.g. 0x300-0x3FF, or 0x300), with their PC and cycle, in the access log of the debugger frontend; can be repeated";
+
+/// cli --trace-memory command value name
+const ARG_TRACE_MEMORY_VALUE_NAME: &str = "RANGE";
+
+/// cli --record-wav command help
+const ARG_RECORD_WAV_HELP: &str = "Record the emulated beeper audio into a WAV file";
+
+/// cli --record-wav command value name
+const ARG_RECORD_WAV_VALUE_NAME: &str = "FILE";
+
+/// cli --beep-freq command help
+const ARG_BEEP_FREQ_HELP: &str =
+    "Frequency of the beeper tone of the live audio output (audio feature) and of the WAV recording [default: 440]";
+
+/// cli --beep-freq command value name
+const ARG_BEEP_FREQ_VALUE_NAME: &str = "HZ";
+
+/// cli --beep-wave command help
+const ARG_BEEP_WAVE_HELP: &str =
+    "Waveform of the beeper tone of the live audio output (audio feature) and of the WAV recording [default: square]";
+
+/// cli --beep-wave command value name
+const ARG_BEEP_WAVE_VALUE_NAME: &str = "WAVE";
+
+/// cli --volume command help
+const ARG_VOLUME_HELP: &str =
+    "Volume of the beeper of the live audio output (audio feature) and of the WAV recording, from 0 (silent) to 100 (full volume); F9 mutes it at runtime in the window frontends [default: 100]";
+
+/// cli --volume command value name
+const ARG_VOLUME_VALUE_NAME: &str = "PERCENT";
+
+/// cli --script command help
+const ARG_SCRIPT_HELP: &str =
+    "Rhai script called at the end of every frame, with access to the CHIP-8 state and the keypad (requires the `scripting` feature)";
+
+/// cli --script command value name
+const ARG_SCRIPT_VALUE_NAME: &str = "FILE";
+
+/// cli --input-script command help
+const ARG_INPUT_SCRIPT_HELP: &str =
+    "Input script: key presses and releases scheduled at instruction counts, one per line (e.g. `@1200 press 5`, `@1300 release 5`)";
+
+/// cli --input-script command value name
+const ARG_INPUT_SCRIPT_VALUE_NAME: &str = "FILE";
+
+/// cli --control-socket command help
+const ARG_CONTROL_SOCKET_HELP: &str =
+    "Unix domain socket accepting control commands, one per line: pause, resume, reset, load <FILE>, save-state <FILE>, load-state <FILE>, dump-registers, screenshot <FILE>";
+
+/// cli --control-socket command value name
+const ARG_CONTROL_SOCKET_VALUE_NAME: &str = "PATH";
+
+/// cli --http command help
+const ARG_HTTP_HELP: &str =
+    "Serve JSON snapshots of the registers, memory and display and a live PNG of the screen over HTTP (requires the `http` feature)";
+
+/// cli --http command value name
+const ARG_HTTP_VALUE_NAME: &str = "ADDRESS";
+
+/// cli --load-dump command help
+const ARG_LOAD_DUMP_HELP: &str =
+    "Restore a crash dump (written when an instruction cannot be executed) paused, for inspection (e.g. with --frontend debugger)";
+
+/// cli --load-dump command value name
+const ARG_LOAD_DUMP_VALUE_NAME: &str = "FILE";
+
+/// cli --recent command help
+const ARG_RECENT_HELP: &str =
+    "Print the recently played ROMs (last time played, total play time and path) and exit";
+
+/// cli --last command help
+const ARG_LAST_HELP: &str = "Run the most recently played ROM again";
+
+/// cli --speed command help
+const ARG_SPEED_HELP: &str =
+    "Emulation speed multiplier of instructions and timers: fast (e.g. 4x) or slow motion (e.g. 0.25x) [default: 1x]";
+
+/// cli --speed command value name
+const ARG_SPEED_VALUE_NAME: &str = "SPEED";
+
+/// cli --clock command help
+const ARG_CLOCK_HELP: &str =
+    "CHIP-8 clock frequency: instructions executed per second of emulated time (timers always count at 60Hz) [default: 500]";
+
+/// cli --clock command value name
+const ARG_CLOCK_VALUE_NAME: &str = "HZ";
+
+/// cli --timing command help
+const ARG_TIMING_HELP: &str =
+    "Timing model of the instructions: uniform (one cycle of the clock frequency) or cosmac-vip (the machine cycles of the COSMAC VIP interpreter, for speed-sensitive original ROMs) [default: uniform]";
+
+/// cli --start-address command help
+const ARG_START_ADDRESS_HELP: &str =
+    "Program start address: the ROM is loaded and executed from it, in hex (e.g. 0x200) or eti-660 (0x600) [default: 0x200]";
+
+/// cli --start-address command value name
+const ARG_START_ADDRESS_VALUE_NAME: &str = "ADDRESS";
+
+/// cli --memory-size command help
+const ARG_MEMORY_SIZE_HELP: &str =
+    "RAM memory size: a power of two from 1KB to 64KB (e.g. 64KB for XO-CHIP programs) [default: 4KB]";
+
+/// cli --memory-size command value name
+const ARG_MEMORY_SIZE_VALUE_NAME: &str = "SIZE";
+
+/// cli --profile command help
+const ARG_PROFILE_HELP: &str =
+    "Compatibility profile: the quirks of the interpreter the program was written for, the quirk flags override it [default: chip8]";
+
+/// cli --quirk-wrap-sprites command help
+const ARG_QUIRK_WRAP_SPRITES_HELP: &str =
+    "Wrap the sprites drawn by DXYN around the screen edges instead of clipping them (quirk of some platforms): on or off";
+
+/// cli --quirk-half-pixel-scroll command help
+const ARG_QUIRK_HALF_PIXEL_SCROLL_HELP: &str =
+    "Scroll the lores display by half the distance of the SUPER-CHIP scroll instructions (quirk of SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-lores-tall-sprites command help
+const ARG_QUIRK_LORES_TALL_SPRITES_HELP: &str =
+    "Draw 8x16 sprites with the SUPER-CHIP DXY0 instruction in lores mode instead of 16x16 sprites (quirk of SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-collision-rows command help
+const ARG_QUIRK_COLLISION_ROWS_HELP: &str =
+    "Set VF to the number of sprite rows that collide or are clipped by DXYN in hires mode instead of 1 (quirk of SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-vf-reset command help
+const ARG_QUIRK_VF_RESET_HELP: &str =
+    "Reset VF with the logical instructions 8XY1, 8XY2 and 8XY3 (as in COSMAC VIP, off in SUPER-CHIP 1.1 and XO-CHIP): on or off";
+
+/// cli --quirk-shift-vy command help
+const ARG_QUIRK_SHIFT_VY_HELP: &str =
+    "Shift VY into VX with 8XY6 and 8XYE (as in COSMAC VIP) instead of shifting VX in place (off in SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-memory-increment command help
+const ARG_QUIRK_MEMORY_INCREMENT_HELP: &str =
+    "Increment I with FX55 and FX65 (as in COSMAC VIP) instead of leaving it unchanged (off in SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-jump-vx command help
+const ARG_QUIRK_JUMP_VX_HELP: &str =
+    "Jump to XNN + VX with BNNN instead of NNN + V0 (quirk of SUPER-CHIP 1.1): on or off";
+
+/// cli --quirk-* commands value name
+const ARG_QUIRK_VALUE_NAME: &str = "on|off";
+
+/// cli --quirk-* commands value without the value (e.g. `--quirk-wrap-sprites`)
+const ARG_QUIRK_DEFAULT_MISSING_VALUE: &str = "on";
+
+/// cli --list-quirks command help
+const ARG_LIST_QUIRKS_HELP: &str =
+    "Print the supported quirks (default of every compatibility profile and description) and exit";
+
+/// cli --machine-code command help
+const ARG_MACHINE_CODE_HELP: &str =
+    "What to do with the 0NNN machine code routine calls, that an interpreter cannot execute [default: halt]";
+
+/// cli --self-modifying-code command help
+const ARG_SELF_MODIFYING_CODE_HELP: &str =
+    "What to do when an instruction writes to an address already executed, or the execution reaches bytes modified at runtime: ignore, warn, or break (warn and pause) [default: ignore]";
+
+/// cli --warn-uninitialized command help
+const ARG_WARN_UNINITIALIZED_HELP: &str =
+    "Log a warning when an instruction reads a memory byte never written by the fontset or ROM loading or by the program (e.g. an off-by-one address beyond the ROM image)";
+
+/// cli --font command help
+const ARG_FONT_HELP: &str =
+    "Path to an alternate font file loaded into low memory: 80 bytes (16 glyphs of 4x5 pixels) or 160 bytes (hires, 16 glyphs of 8x10 pixels)";
+
+/// cli --font command value name
+const ARG_FONT_VALUE_NAME: &str = "FILE";
+
+/// cli --uncapped command help
+const ARG_UNCAPPED_HELP: &str =
+    "Run as fast as possible without sleeps (display refreshed at 60 fps), reporting the instructions per second";
+
+/// cli --max-cycles command help
+const ARG_MAX_CYCLES_HELP: &str = "Stop the emulation after the given number of instructions";
+
+/// cli --max-cycles command value name
+const ARG_MAX_CYCLES_VALUE_NAME: &str = "CYCLES";
+
+/// cli --max-seconds command help
+const ARG_MAX_SECONDS_HELP: &str = "Stop the emulation after the given number of seconds";
+
+/// cli --max-seconds command value name
+const ARG_MAX_SECONDS_VALUE_NAME: &str = "SECONDS";
+
+/// cli --on-idle command help
+const ARG_ON_IDLE_HELP: &str = "What to do when the program jumps to itself or waits for a key without keypad input [default: sleep]";
+
+/// cli --headless command help
+const ARG_HEADLESS_HELP: &str = "Run without drawing the display (e.g. automated testing)";
+
+/// cli --report command help
+const ARG_REPORT_HELP: &str =
+    "Print a report (instructions, stop reason, final registers and display hash) to stdout at the end of the emulation";
+
+/// cli --report command value name
+const ARG_REPORT_VALUE_NAME: &str = "FORMAT";
+
+/// cli -c command help
+const ARG_CONFIG_HELP: &str =
+    "Path to configuration file [default: $XDG_CONFIG_HOME/rust-chip-8/config.toml]";
+
+/// cli -c command value name
+const ARG_CONFIG_VALUE_NAME: &str = "FILE";
+
+/// cli --frontend command help
+const ARG_FRONTEND_HELP: &str = "Display frontend [default: terminal]";
+
+/// cli --theme command help
+const ARG_THEME_HELP: &str = "Display color theme [default: mono]";
+
+/// cli --fg-color command help
+const ARG_FG_COLOR_HELP: &str = "Color of the pixels that are on: ANSI color name or #RRGGBB";
+
+/// cli --bg-color command help
+const ARG_BG_COLOR_HELP: &str = "Color of the pixels that are off: ANSI color name or #RRGGBB";
+
+/// cli --fg-color and --bg-color commands value name
+const ARG_COLOR_VALUE_NAME: &str = "COLOR";
+
+/// cli --ghosting command help
+const ARG_GHOSTING_HELP: &str =
+    "Fade out pixels over the given number of frames to reduce flicker [default: 0 (disabled)]";
+
+/// cli --ghosting command value name
+const ARG_GHOSTING_VALUE_NAME: &str = "FRAMES";
+
+/// cli --crt command help
+const ARG_CRT_HELP: &str =
+    "Enable CRT effects (scanlines, curvature, bloom) in the gpu frontend, toggled at runtime with F1-F3";
+
+/// cli --scale command help
+const ARG_SCALE_HELP: &str =
+    "Integer scaling factor of the display in the sixel, kitty and gpu frontends, changed at runtime with + and - in the gpu frontend [default: 8 (sixel, kitty), fit the window (gpu)]";
+
+/// cli --scale command value name
+const ARG_SCALE_VALUE_NAME: &str = "N";
+
+/// cli --fullscreen command help
+const ARG_FULLSCREEN_HELP: &str =
+    "Open the window of the gpu and debugger frontends in fullscreen, toggled at runtime with F11";
+
+/// cli --pause-unfocused command help
+const ARG_PAUSE_UNFOCUSED_HELP: &str =
+    "Pause the emulation while the window of the gpu and debugger frontends is not focused, resuming it on focus";
+
+/// cli --threaded command help
+const ARG_THREADED_HELP: &str =
+    "Run the emulation in its own thread, sending the frames to the frontend without waiting for it, so that slow rendering never stalls the emulation (not with the debugger frontend)";
+
+/// cli --keypad-overlay command help
+const ARG_KEYPAD_OVERLAY_HELP: &str =
+    "Show the keys held down and the key read by the last FX0A: a 4x4 keypad on the right of the display (terminal frontend) or in the window title (gpu frontend)";
+
+/// cli --status-bar command help
+const ARG_STATUS_BAR_HELP: &str =
+    "Show the frames per second, the instructions per second, pause and beep below the display (terminal frontend), in the window title (gpu frontend) or in the toolbar (debugger frontend)";
+
+/// cli --keymap command help
+const ARG_KEYMAP_HELP: &str =
+    "Keyboard keys of the CHIP-8 keypad in the window frontends: qwerty, qwertz, azerty \
+or the 16 characters of the keys, row by row (123C 456D 789E A0BF) [default: qwerty]";
+
+/// cli --keymap command value name
+const ARG_KEYMAP_VALUE_NAME: &str = "KEYMAP";
+
+/// cli test --suite command help
+const ARG_SUITE_HELP: &str = "Directory with the test ROMs and the suite.toml manifest";
+
+/// cli test --suite command value name
+const ARG_SUITE_VALUE_NAME: &str = "DIR";
+
+/// cli test --suite command default value
+const ARG_SUITE_DEFAULT_VALUE: &str = "roms/timendus";
+
+/// cli test --bless command help
+const ARG_BLESS_HELP: &str =
+    "Record the final displays as the expected ones in the manifest (run only with a known-good interpreter)";
+
+/// cli diff -f command help
+const ARG_DIFF_ROM_FILE_HELP: &str = "Path to CHIP-8 ROM file to run on both interpreters";
+
+/// cli diff --cycles command help
+const ARG_DIFF_CYCLES_HELP: &str = "Max number of instructions to compare";
+
+/// cli diff --cycles command value name
+const ARG_DIFF_CYCLES_VALUE_NAME: &str = "CYCLES";
+
+/// cli diff --cycles command default value
+const ARG_DIFF_CYCLES_DEFAULT_VALUE: u64 = 100_000;
+
+/// cli diff --random-input command help
+const ARG_DIFF_RANDOM_INPUT_HELP: &str =
+    "Press random keys (same on both interpreters, generated from the random seed)";
+
+/// cli snapshot -f command help
+const ARG_SNAPSHOT_ROM_FILE_HELP: &str = "Path to CHIP-8 ROM file to snapshot";
+
+/// cli snapshot --cycles command help
+const ARG_SNAPSHOT_CYCLES_HELP: &str = "Number of instructions to run before taking the snapshot";
+
+/// cli snapshot --cycles command value name
+const ARG_SNAPSHOT_CYCLES_VALUE_NAME: &str = "CYCLES";
+
+/// cli snapshot -o command help
+const ARG_SNAPSHOT_OUTPUT_HELP: &str = "Write the final display to the golden file";
+
+/// cli snapshot --verify-snapshot command help
+const ARG_VERIFY_SNAPSHOT_HELP: &str =
+    "Compare the final display with the golden file and fail printing a diff if it differs";
+
+/// cli snapshot -o and --verify-snapshot commands value name
+const ARG_SNAPSHOT_FILE_VALUE_NAME: &str = "FILE";
+
+/// cli bench -f command help
+const ARG_BENCH_ROM_FILE_HELP: &str =
+    "Path to CHIP-8 ROM file to benchmark [default: synthetic workload]";
+
+/// cli bench --cycles command help
+const ARG_BENCH_CYCLES_HELP: &str = "Number of instructions executed by every run";
+
+/// cli bench --cycles command value name
+const ARG_BENCH_CYCLES_VALUE_NAME: &str = "CYCLES";
+
+/// cli bench --cycles command default value
+const ARG_BENCH_CYCLES_DEFAULT_VALUE: u64 = 10_000_000;
+
+/// cli bench --runs command help
+const ARG_BENCH_RUNS_HELP: &str = "Number of runs";
+
+/// cli bench --runs command value name
+const ARG_BENCH_RUNS_VALUE_NAME: &str = "RUNS";
+
+/// cli bench --runs command default value
+const ARG_BENCH_RUNS_DEFAULT_VALUE: u32 = 5;
+
+/// cli font --font command help
+const ARG_FONT_PREVIEW_HELP: &str =
+    "Path to a font file to preview (80 bytes, or 160 bytes for a hires font) [default: built-in font]";
+
+/// cli detect-quirks -f command help
+const ARG_DETECT_ROM_FILE_HELP: &str = "Path to the quirks test ROM of the Timendus test suite";
+
+/// cli detect-quirks -f command default value
+const ARG_DETECT_ROM_FILE_DEFAULT_VALUE: &str = "roms/timendus/5-quirks.ch8";
+
+/// cli detect-quirks --cycles command help
+const ARG_DETECT_CYCLES_HELP: &str =
+    "Max number of instructions to run (the test stops earlier if the program jumps to itself)";
+
+/// cli detect-quirks --cycles command value name
+const ARG_DETECT_CYCLES_VALUE_NAME: &str = "CYCLES";
+
+/// cli detect-quirks --cycles command default value
+const ARG_DETECT_CYCLES_DEFAULT_VALUE: u64 = 100_000;
+
+/// cli -q command help
+const ARG_QUIET_HELP: &str = "Enable quiet logging";
+
+/// cli -d command help
+const ARG_DEBUG_HELP: &str = "Enable debug logging";
+
+/// cli -t command help
+const ARG_TRACE_HELP: &str = "Enable trace logging";
+
+/// CLI arguments structure
+#[derive(Parser, Debug)]
+#[command(author, version, about, subcommand_negates_reqs = true)]
+pub struct Cli {
+    /// Subcommand (the ROM file is run if no subcommand is given)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// ROM file path
+    #[arg(short = 'f', long = "rom-file", required_unless_present_any = ["load_dump", "recent", "last", "list_quirks"], overrides_with = "rom", help=ARG_ROM_FILE_HELP, value_name=ARG_ROM_FILE_VALUE_NAME)]
+    pub rom: Option<PathBuf>,
+
+    /// Crash dump file path
+    #[arg(long, conflicts_with = "rom", help=ARG_LOAD_DUMP_HELP, value_name=ARG_LOAD_DUMP_VALUE_NAME)]
+    pub load_dump: Option<PathBuf>,
+
+    /// Recently played ROMs flag
+    #[arg(long, conflicts_with_all = ["rom", "load_dump", "last"], help=ARG_RECENT_HELP)]
+    pub recent: bool,
+
+    /// Most recently played ROM flag
+    #[arg(long, conflicts_with_all = ["rom", "load_dump"], help=ARG_LAST_HELP)]
+    pub last: bool,
+
+    /// Logging levels flags
+    #[command(flatten)]
+    pub log: Log,
+
+    /// Log format
+    #[arg(long, help=ARG_LOG_FORMAT_HELP, value_name=ARG_LOG_FORMAT_VALUE_NAME)]
+    pub log_format: Option<LogFormat>,
+
+    /// Log file path
+    #[arg(long, help=ARG_LOG_FILE_HELP, value_name=ARG_LOG_FILE_VALUE_NAME)]
+    pub log_file: Option<PathBuf>,
+
+    /// Log file rotation
+    #[arg(long, help=ARG_LOG_ROTATION_HELP, value_name=ARG_LOG_ROTATION_VALUE_NAME, requires="log_file")]
+    pub log_rotation: Option<LogRotation>,
+
+    /// Per-instruction logging sample
+    #[arg(long, help=ARG_TRACE_SAMPLE_HELP, value_name=ARG_TRACE_SAMPLE_VALUE_NAME, value_parser=clap::value_parser!(u64).range(1..))]
+    pub trace_sample: Option<u64>,
+
+    /// Executed instructions history depth
+    #[arg(long, help=ARG_HISTORY_DEPTH_HELP, value_name=ARG_HISTORY_DEPTH_VALUE_NAME)]
+    pub history_depth: Option<usize>,
+
+    /// Traced memory address ranges
+    #[arg(long, help=ARG_TRACE_MEMORY_HELP, value_name=ARG_TRACE_MEMORY_VALUE_NAME, value_parser=parse_address_range)]
+    pub trace_memory: Vec<RangeInclusive<u16>>,
+
+    /// Stepping execution flag
+    #[arg(short, long, help=ARG_STEPPING_HELP)]
+    pub stepping: bool,
+
+    /// Random seed
+    #[arg(short, long, help=ARG_RANDOM_SEED_HELP, value_name=ARG_RANDOM_SEED_VALUE_NAME, default_value_t=ARG_RANDOM_SEED_DEFAULT_VALUE)]
+    pub random_seed: u64,
+
+    /// Random number generator mode
+    #[arg(long, help=ARG_RANDOM_HELP, value_name=ARG_RANDOM_VALUE_NAME, value_parser=variants::<RandomMode>())]
+    pub random: Option<RandomMode>,
+
+    /// WAV file path for beeper audio recording
+    #[arg(long, help=ARG_RECORD_WAV_HELP, value_name=ARG_RECORD_WAV_VALUE_NAME)]
+    pub record_wav: Option<PathBuf>,
+
+    /// Beeper tone frequency
+    #[arg(long, help=ARG_BEEP_FREQ_HELP, value_name=ARG_BEEP_FREQ_VALUE_NAME, value_parser=parse_beep_freq)]
+    pub beep_freq: Option<f64>,
+
+    /// Beeper tone waveform
+    #[arg(long, help=ARG_BEEP_WAVE_HELP, value_name=ARG_BEEP_WAVE_VALUE_NAME, value_parser=variants::<Waveform>())]
+    pub beep_wave: Option<Waveform>,
+
+    /// Beeper volume
+    #[arg(long, help=ARG_VOLUME_HELP, value_name=ARG_VOLUME_VALUE_NAME, value_parser=clap::value_parser!(u8).range(0..=MAX_VOLUME as i64))]
+    pub volume: Option<u8>,
+
+    /// Script file path
+    #[arg(long, help=ARG_SCRIPT_HELP, value_name=ARG_SCRIPT_VALUE_NAME)]
+    pub script: Option<PathBuf>,
+
+    /// Input script file path
+    #[arg(long, help=ARG_INPUT_SCRIPT_HELP, value_name=ARG_INPUT_SCRIPT_VALUE_NAME)]
+    pub input_script: Option<PathBuf>,
+
+    /// Control socket path
+    #[arg(long, help=ARG_CONTROL_SOCKET_HELP, value_name=ARG_CONTROL_SOCKET_VALUE_NAME)]
+    pub control_socket: Option<PathBuf>,
+
+    /// HTTP server address
+    #[arg(long, help=ARG_HTTP_HELP, value_name=ARG_HTTP_VALUE_NAME)]
+    pub http: Option<String>,
+
+    /// Emulation speed multiplier
+    #[arg(long, help=ARG_SPEED_HELP, value_name=ARG_SPEED_VALUE_NAME, value_parser=parse_speed)]
+    pub speed: Option<f64>,
+
+    /// CHIP-8 clock frequency
+    #[arg(long, help=ARG_CLOCK_HELP, value_name=ARG_CLOCK_VALUE_NAME, value_parser=parse_clock)]
+    pub clock: Option<f64>,
+
+    /// Instruction timing model
+    #[arg(long, help=ARG_TIMING_HELP, value_parser=variants::<InstructionTiming>())]
+    pub timing: Option<InstructionTiming>,
+
+    /// Program start address
+    #[arg(long, help=ARG_START_ADDRESS_HELP, value_name=ARG_START_ADDRESS_VALUE_NAME, value_parser=parse_start_address)]
+    pub start_address: Option<u16>,
+
+    /// RAM memory size
+    #[arg(long, help=ARG_MEMORY_SIZE_HELP, value_name=ARG_MEMORY_SIZE_VALUE_NAME, value_parser=parse_memory_size)]
+    pub memory_size: Option<usize>,
+
+    /// Compatibility profile
+    #[arg(long, help=ARG_PROFILE_HELP, value_parser=variants::<Profile>())]
+    pub profile: Option<Profile>,
+
+    /// Sprite wrapping quirk
+    #[arg(long, alias = "wrap-sprites", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_WRAP_SPRITES_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_wrap_sprites: Option<bool>,
+
+    /// Half pixel scroll quirk
+    #[arg(long, alias = "half-pixel-scroll", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_HALF_PIXEL_SCROLL_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_half_pixel_scroll: Option<bool>,
+
+    /// Lores tall sprites quirk
+    #[arg(long, alias = "lores-tall-sprites", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_LORES_TALL_SPRITES_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_lores_tall_sprites: Option<bool>,
+
+    /// Collision rows quirk
+    #[arg(long, alias = "collision-rows", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_COLLISION_ROWS_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_collision_rows: Option<bool>,
+
+    /// VF reset quirk
+    #[arg(long, alias = "vf-reset", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_VF_RESET_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_vf_reset: Option<bool>,
+
+    /// Shift quirk
+    #[arg(long, alias = "shift-vy", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_SHIFT_VY_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_shift_vy: Option<bool>,
+
+    /// Memory increment quirk
+    #[arg(long, alias = "memory-increment", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_MEMORY_INCREMENT_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_memory_increment: Option<bool>,
+
+    /// Jump quirk
+    #[arg(long, alias = "jump-vx", num_args = 0..=1, require_equals = true, default_missing_value = ARG_QUIRK_DEFAULT_MISSING_VALUE, value_parser = BoolishValueParser::new(), help=ARG_QUIRK_JUMP_VX_HELP, value_name=ARG_QUIRK_VALUE_NAME)]
+    pub quirk_jump_vx: Option<bool>,
+
+    /// Supported quirks flag
+    #[arg(long, conflicts_with_all = ["rom", "load_dump", "recent", "last"], help=ARG_LIST_QUIRKS_HELP)]
+    pub list_quirks: bool,
+
+    /// Machine code routine calls policy
+    #[arg(long, help=ARG_MACHINE_CODE_HELP, value_parser=variants::<MachineCodePolicy>())]
+    pub machine_code: Option<MachineCodePolicy>,
+
+    /// Self-modifying code policy
+    #[arg(long, help=ARG_SELF_MODIFYING_CODE_HELP, value_parser=variants::<SelfModifyingCodePolicy>())]
+    pub self_modifying_code: Option<SelfModifyingCodePolicy>,
+
+    /// Uninitialized memory reads warnings flag
+    #[arg(long, help=ARG_WARN_UNINITIALIZED_HELP)]
+    pub warn_uninitialized: bool,
+
+    /// Font file path
+    #[arg(long, help=ARG_FONT_HELP, value_name=ARG_FONT_VALUE_NAME)]
+    pub font: Option<PathBuf>,
+
+    /// Uncapped speed flag
+    #[arg(long, help=ARG_UNCAPPED_HELP, conflicts_with_all=["speed", "stepping"])]
+    pub uncapped: bool,
+
+    /// Max instructions run limit
+    #[arg(long, help=ARG_MAX_CYCLES_HELP, value_name=ARG_MAX_CYCLES_VALUE_NAME)]
+    pub max_cycles: Option<u64>,
+
+    /// Max seconds run limit
+    #[arg(long, help=ARG_MAX_SECONDS_HELP, value_name=ARG_MAX_SECONDS_VALUE_NAME)]
+    pub max_seconds: Option<f64>,
+
+    /// Idle loop policy
+    #[arg(long, help=ARG_ON_IDLE_HELP, value_parser=variants::<IdlePolicy>())]
+    pub on_idle: Option<IdlePolicy>,
+
+    /// Headless flag
+    #[arg(long, help=ARG_HEADLESS_HELP, conflicts_with="frontend")]
+    pub headless: bool,
+
+    /// Report format
+    #[arg(long, help=ARG_REPORT_HELP, value_name=ARG_REPORT_VALUE_NAME)]
+    pub report: Option<ReportFormat>,
+
+    /// Configuration file path
+    #[arg(short, long, help=ARG_CONFIG_HELP, value_name=ARG_CONFIG_VALUE_NAME)]
+    pub config: Option<PathBuf>,
+
+    /// Display frontend
+    #[arg(long, help=ARG_FRONTEND_HELP)]
+    pub frontend: Option<Frontend>,
+
+    /// Display color theme
+    #[arg(long, help=ARG_THEME_HELP, value_parser=variants::<Theme>())]
+    pub theme: Option<Theme>,
+
+    /// Foreground color
+    #[arg(long, help=ARG_FG_COLOR_HELP, value_name=ARG_COLOR_VALUE_NAME)]
+    pub fg_color: Option<Color>,
+
+    /// Background color
+    #[arg(long, help=ARG_BG_COLOR_HELP, value_name=ARG_COLOR_VALUE_NAME)]
+    pub bg_color: Option<Color>,
+
+    /// Phosphor persistence frames
+    #[arg(long, help=ARG_GHOSTING_HELP, value_name=ARG_GHOSTING_VALUE_NAME)]
+    pub ghosting: Option<u8>,
+
+    /// CRT effects flag
+    #[arg(long, help=ARG_CRT_HELP)]
+    pub crt: bool,
+
+    /// Display scaling factor
+    #[arg(long, help=ARG_SCALE_HELP, value_name=ARG_SCALE_VALUE_NAME, value_parser=clap::value_parser!(u32).range(1..=MAX_SCALE as i64))]
+    pub scale: Option<u32>,
+
+    /// Fullscreen flag
+    #[arg(long, help=ARG_FULLSCREEN_HELP)]
+    pub fullscreen: bool,
+
+    /// Pause when unfocused flag
+    #[arg(long, help=ARG_PAUSE_UNFOCUSED_HELP)]
+    pub pause_unfocused: bool,
+
+    /// Threaded emulation flag
+    #[arg(long, help=ARG_THREADED_HELP)]
+    pub threaded: bool,
+
+    /// Keypad overlay flag
+    #[arg(long, help=ARG_KEYPAD_OVERLAY_HELP)]
+    pub keypad_overlay: bool,
+
+    /// Status bar flag
+    #[arg(long, help=ARG_STATUS_BAR_HELP)]
+    pub status_bar: bool,
+
+    /// Keypad mapping
+    #[arg(long, help=ARG_KEYMAP_HELP, value_name=ARG_KEYMAP_VALUE_NAME)]
+    pub keymap: Option<Keymap>,
+}
+
+/// Subcommands
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the CHIP-8 test suite ROMs headlessly and print pass/fail per test
+    Test(TestArgs),
+
+    /// Run a ROM on this interpreter and on a simple reference interpreter in lockstep
+    /// and print the first instruction after which their states diverge
+    Diff(DiffArgs),
+
+    /// Run a ROM for a number of instructions and write the final display to a golden file,
+    /// or verify it against a golden file
+    Snapshot(SnapshotArgs),
+
+    /// Run a ROM (or a synthetic workload) without rendering and print the instructions per second
+    Bench(BenchArgs),
+
+    /// Print the glyphs of the built-in font, or of a font file
+    Font(FontArgs),
+
+    /// Run the quirks test ROM headlessly and print the quirk behaviors it reports
+    /// with the profile and quirk options
+    DetectQuirks(DetectQuirksArgs),
+}
+
+/// test subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct TestArgs {
+    /// Test suite directory
+    #[arg(long, help=ARG_SUITE_HELP, value_name=ARG_SUITE_VALUE_NAME, default_value=ARG_SUITE_DEFAULT_VALUE)]
+    pub suite: PathBuf,
+
+    /// Bless flag
+    #[arg(long, help=ARG_BLESS_HELP)]
+    pub bless: bool,
+}
+
+/// diff subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// ROM file path
+    #[arg(short = 'f', long = "rom-file", help=ARG_DIFF_ROM_FILE_HELP, value_name=ARG_ROM_FILE_VALUE_NAME)]
+    pub rom: PathBuf,
+
+    /// Max instructions to compare
+    #[arg(long, help=ARG_DIFF_CYCLES_HELP, value_name=ARG_DIFF_CYCLES_VALUE_NAME, default_value_t=ARG_DIFF_CYCLES_DEFAULT_VALUE)]
+    pub cycles: u64,
+
+    /// Random keypad input flag
+    #[arg(long, help=ARG_DIFF_RANDOM_INPUT_HELP)]
+    pub random_input: bool,
+}
+
+/// snapshot subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+    /// ROM file path
+    #[arg(short = 'f', long = "rom-file", help=ARG_SNAPSHOT_ROM_FILE_HELP, value_name=ARG_ROM_FILE_VALUE_NAME)]
+    pub rom: PathBuf,
+
+    /// Instructions to run
+    #[arg(long, help=ARG_SNAPSHOT_CYCLES_HELP, value_name=ARG_SNAPSHOT_CYCLES_VALUE_NAME)]
+    pub cycles: u64,
+
+    /// Golden file path to write
+    #[arg(short, long, help=ARG_SNAPSHOT_OUTPUT_HELP, value_name=ARG_SNAPSHOT_FILE_VALUE_NAME, required_unless_present="verify_snapshot")]
+    pub output: Option<PathBuf>,
+
+    /// Golden file path to verify
+    #[arg(long, help=ARG_VERIFY_SNAPSHOT_HELP, value_name=ARG_SNAPSHOT_FILE_VALUE_NAME, conflicts_with="output")]
+    pub verify_snapshot: Option<PathBuf>,
+}
+
+/// bench subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// ROM file path
+    #[arg(short = 'f', long = "rom-file", help=ARG_BENCH_ROM_FILE_HELP, value_name=ARG_ROM_FILE_VALUE_NAME)]
+    pub rom: Option<PathBuf>,
+
+    /// Instructions per run
+    #[arg(long, help=ARG_BENCH_CYCLES_HELP, value_name=ARG_BENCH_CYCLES_VALUE_NAME, default_value_t=ARG_BENCH_CYCLES_DEFAULT_VALUE)]
+    pub cycles: u64,
+
+    /// Number of runs
+    #[arg(long, help=ARG_BENCH_RUNS_HELP, value_name=ARG_BENCH_RUNS_VALUE_NAME, default_value_t=ARG_BENCH_RUNS_DEFAULT_VALUE, value_parser=clap::value_parser!(u32).range(1..))]
+    pub runs: u32,
+}
+
+/// font subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct FontArgs {
+    /// Font file path
+    #[arg(long, help=ARG_FONT_PREVIEW_HELP, value_name=ARG_FONT_VALUE_NAME)]
+    pub font: Option<PathBuf>,
+}
+
+/// detect-quirks subcommand arguments structure
+#[derive(Args, Debug)]
+pub struct DetectQuirksArgs {
+    /// Quirks test ROM file path
+    #[arg(short = 'f', long = "rom-file", help=ARG_DETECT_ROM_FILE_HELP, value_name=ARG_ROM_FILE_VALUE_NAME, default_value=ARG_DETECT_ROM_FILE_DEFAULT_VALUE)]
+    pub rom: PathBuf,
+
+    /// Max instructions to run
+    #[arg(long, help=ARG_DETECT_CYCLES_HELP, value_name=ARG_DETECT_CYCLES_VALUE_NAME, default_value_t=ARG_DETECT_CYCLES_DEFAULT_VALUE)]
+    pub cycles: u64,
+}
+
+/// Log group arguments structure
+#[derive(Args, Debug)]
+#[group(multiple = false)]
+pub struct Log {
+    /// Quiet logging flag
+    #[arg(short, long, help=ARG_QUIET_HELP)]
+    pub quiet: bool,
+
+    /// Debug logging flag
+    #[arg(short, long, help=ARG_DEBUG_HELP)]
+    pub debug: bool,
+
+    /// Trace logging flag
+    #[arg(short, long, help=ARG_TRACE_HELP)]
+    pub trace: bool,
+}
+
+impl Cli {
+    /// Validates the CLI arguments
+    pub fn validate(&self) {
+        // validate ROM path
+        trace!("validate: start");
+
+        // subcommands do not run a ROM file
+        let Some(path) = self.rom.as_deref() else {
+            trace!("validate: exit");
+            return;
+        };
+
+        match path.try_exists() {
+            Ok(exists) => {
+                if !exists {
+                    panic!("rom file `{}` does not exist", path.to_str().unwrap())
+                }
+            }
+            Err(e) => {
+                // check file error occurred
+                panic!("rom file error: {e}")
+            }
+        }
+
+        trace!("validate: exit");
+    }
+
+    /// Returns the quirks of the profile and quirk options of the CLI
+    /// (the quirk flags override the profile)
+    pub fn quirks(&self) -> Quirks {
+        let mut quirks = self.profile.unwrap_or_default().quirks();
+        if let Some(wrap) = self.quirk_wrap_sprites {
+            quirks.sprite_wrapping = wrap;
+        }
+        if let Some(half) = self.quirk_half_pixel_scroll {
+            quirks.half_pixel_scroll = half;
+        }
+        if let Some(tall) = self.quirk_lores_tall_sprites {
+            quirks.lores_tall_sprites = tall;
+        }
+        if let Some(count) = self.quirk_collision_rows {
+            quirks.collision_rows = count;
+        }
+        if let Some(reset) = self.quirk_vf_reset {
+            quirks.vf_reset = reset;
+        }
+        if let Some(shift_vy) = self.quirk_shift_vy {
+            quirks.shift_vy = shift_vy;
+        }
+        if let Some(increment) = self.quirk_memory_increment {
+            quirks.memory_increment = increment;
+        }
+        if let Some(jump_vx) = self.quirk_jump_vx {
+            quirks.jump_vx = jump_vx;
+        }
+
+        quirks
+    }
+
+    /// Parses and returns CLI arguments
+    pub fn parse_opts() -> Self {
+        trace!("parse_opts: start");
+        trace!("parse_opts: end");
+
+        // parse cli args
+        Self::parse()
+    }
+}
+
+/// Parses an emulation speed multiplier (e.g. `4x`, `0.5x`, `2`)
+///
+/// # Arguments
+///
+/// * `s` - The speed string
+fn parse_speed(s: &str) -> Result<f64, String> {
+    let speed: f64 = s
+        .trim()
+        .trim_end_matches(['x', 'X'])
+        .parse()
+        .map_err(|e| format!("invalid speed `{s}`: {e}"))?;
+
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(format!("invalid speed `{s}`: must be greater than 0"));
+    }
+
+    Ok(speed)
+}
+
+/// Parses a clock frequency in Hz (e.g. `700`), that must be greater than 0
+///
+/// # Arguments
+///
+/// * `s` - The clock frequency string
+fn parse_clock(s: &str) -> Result<f64, String> {
+    let clock: f64 = s
+        .trim()
+        .trim_end_matches("Hz")
+        .parse()
+        .map_err(|e| format!("invalid clock `{s}`: {e}"))?;
+
+    if !clock.is_finite() || clock <= 0.0 {
+        return Err(format!("invalid clock `{s}`: must be greater than 0"));
+    }
+
+    Ok(clock)
+}
+
+/// Parses a beeper tone frequency in Hz (e.g. `880`), that must be greater than 0
+///
+/// # Arguments
+///
+/// * `s` - The beeper tone frequency string
+fn parse_beep_freq(s: &str) -> Result<f64, String> {
+    let frequency: f64 = s
+        .trim()
+        .trim_end_matches("Hz")
+        .parse()
+        .map_err(|e| format!("invalid beep frequency `{s}`: {e}"))?;
+
+    if !frequency.is_finite() || frequency <= 0.0 {
+        return Err(format!(
+            "invalid beep frequency `{s}`: must be greater than 0"
+        ));
+    }
+
+    Ok(frequency)
+}
+
+/// Parses a program start address: hex digits (e.g. `0x600`) or the `eti-660` preset,
+/// that must be after the end of the fontset (0x050)
+///
+/// # Arguments
+///
+/// * `s` - The start address string
+fn parse_start_address(s: &str) -> Result<u16, String> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("eti-660") {
+        return Ok(ETI_660_PROGRAM_START);
+    }
+
+    let digits = s.strip_prefix("0x").or(s.strip_prefix("0X")).unwrap_or(s);
+    let address =
+        u16::from_str_radix(digits, 16).map_err(|e| format!("invalid start address `{s}`: {e}"))?;
+    if address < 0x050 {
+        return Err(format!(
+            "invalid start address `{s}`: must be between 0x050 and 0xFFFF"
+        ));
+    }
+
+    Ok(address)
+}
+
+/// Parses an inclusive memory address range in hex (e.g. `0x300-0x3FF`), or a single address (e.g. `0x300`)
+///
+/// # Arguments
+///
+/// * `s` - The address range string
+fn parse_address_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let parse = |address: &str| {
+        let address = address.trim();
+        let digits = address
+            .strip_prefix("0x")
+            .or(address.strip_prefix("0X"))
+            .unwrap_or(address);
+        u16::from_str_radix(digits, 16).map_err(|e| format!("invalid address range `{s}`: {e}"))
+    };
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => (parse(s)?, parse(s)?),
+    };
+    if start > end {
+        return Err(format!(
+            "invalid address range `{s}`: the start is after the end"
+        ));
+    }
+
+    Ok(start..=end)
+}
+
+/// Parses a RAM memory size in bytes (e.g. `4096`) or KB (e.g. `64KB`),
+/// that must be a power of two between 1KB and 64KB
+///
+/// # Arguments
+///
+/// * `s` - The memory size string
+fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let lowercase = s.to_lowercase();
+    let (number, multiplier) = match lowercase.strip_suffix("kb") {
+        Some(number) => (number, 1 << 10),
+        None => (lowercase.strip_suffix('b').unwrap_or(&lowercase), 1),
+    };
+    let size = number
+        .trim()
+        .parse::<usize>()
+        .map_err(|e| format!("invalid memory size `{s}`: {e}"))?
+        .saturating_mul(multiplier);
+    if !size.is_power_of_two() || !(MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size) {
+        return Err(format!(
+            "invalid memory size `{s}`: must be a power of two between 1KB and 64KB"
+        ));
+    }
+
+    Ok(size)
+}
+
+/// Returns the parser of the values of an option enum, whose possible values are the names of its variants
+fn variants<T: Variants + Send + Sync>() -> impl TypedValueParser<Value = T> {
+    PossibleValuesParser::new(
+        T::VARIANTS
+            .iter()
+            .map(|variant| PossibleValue::new(variant.name()).help(variant.help())),
+    )
+    .map(|name| T::from_name(&name).expect("possible value not a variant name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the CLI arguments of a ROM with the given options
+    fn parse(options: &[&str]) -> Cli {
+        let args = ["rust-chip-8", "-f", "game.ch8"].iter().chain(options);
+        Cli::try_parse_from(args).unwrap()
+    }
+
+    #[test]
+    fn quirks_default_to_the_profile() {
+        assert_eq!(parse(&[]).quirks(), Profile::Chip8.quirks());
+        assert_eq!(
+            parse(&["--profile", "schip"]).quirks(),
+            Profile::Schip.quirks()
+        );
+    }
+
+    #[test]
+    fn quirk_flags_override_the_profile() {
+        let quirks = parse(&[
+            "--profile",
+            "schip",
+            "--quirk-vf-reset=on",
+            "--quirk-jump-vx=off",
+        ])
+        .quirks();
+        assert!(quirks.vf_reset);
+        assert!(!quirks.jump_vx);
+        assert!(quirks.collision_rows);
+    }
+
+    #[test]
+    fn quirk_flag_without_value_enables_the_quirk() {
+        assert!(parse(&["--quirk-wrap-sprites"]).quirks().sprite_wrapping);
+        // the flags of the previous versions are kept as aliases
+        assert!(parse(&["--wrap-sprites"]).quirks().sprite_wrapping);
+    }
+
+    #[test]
+    fn quirk_flag_rejects_other_values() {
+        let args = ["rust-chip-8", "-f", "game.ch8", "--quirk-shift-vy=maybe"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+}
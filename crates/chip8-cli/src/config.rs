@@ -0,0 +1,600 @@
+//! Configuration file loading
+//!
+//! The configuration file is a TOML file, by default located at
+//! `$XDG_CONFIG_HOME/rust-chip-8/config.toml` (or `$HOME/.config/rust-chip-8/config.toml`).
+//! Options given on the command line take precedence over the configuration file ones.
+//!
+//! Options of a specific ROM are given in a `[roms."sha1:<hash>"]` section (SHA-1 of the ROM file)
+//! or in a sidecar TOML file next to the ROM (e.g. `game.toml` for `game.ch8`), which takes precedence.
+//! Known ROMs also get the options of their profile in the ROM database
+
+use crate::database;
+use crate::frontend::Frontend;
+use chip8_core::{
+    InstructionTiming, MachineCodePolicy, Profile, Waveform, MAX_MEMORY_SIZE, MAX_VOLUME,
+    MIN_MEMORY_SIZE,
+};
+use chip8_frontend::{Color, Keymap, Theme, MAX_SCALE};
+use serde::Deserialize;
+use sha1_smol::Sha1;
+use std::{collections::HashMap, env, fs, io::ErrorKind, path::Path, path::PathBuf};
+use toml_edit::DocumentMut;
+use tracing::{debug, trace};
+
+/// configuration directory name
+const CONFIG_DIR_NAME: &str = "rust-chip-8";
+
+/// configuration file name
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// extension of the sidecar configuration file of a ROM
+const ROM_CONFIG_EXTENSION: &str = "toml";
+
+/// Configuration file structure
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Display configuration
+    pub display: DisplayConfig,
+
+    /// Input configuration
+    pub input: InputConfig,
+
+    /// Audio configuration
+    pub audio: AudioConfig,
+
+    /// Configurations of specific ROMs, keyed by `sha1:<hash>` of the ROM file
+    pub roms: HashMap<String, RomConfig>,
+}
+
+/// Display configuration structure (`[display]` section)
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Display frontend
+    pub frontend: Option<Frontend>,
+
+    /// Color theme
+    pub theme: Option<Theme>,
+
+    /// Color of the pixels that are on (overrides the theme)
+    pub foreground: Option<Color>,
+
+    /// Color of the pixels that are off (overrides the theme)
+    pub background: Option<Color>,
+
+    /// Number of frames a pixel takes to fade out
+    pub ghosting: Option<u8>,
+
+    /// CRT post-processing effects (gpu frontend)
+    pub crt: Option<bool>,
+
+    /// Integer scaling factor of the display (sixel, kitty and gpu frontends)
+    pub scale: Option<u32>,
+
+    /// Fullscreen window (gpu and debugger frontends)
+    pub fullscreen: Option<bool>,
+
+    /// Pause the emulation while the window is not focused (gpu and debugger frontends)
+    pub pause_unfocused: Option<bool>,
+
+    /// Emulation in its own thread, separated from the frontend
+    pub threaded: Option<bool>,
+
+    /// Keypad overlay (terminal and gpu frontends)
+    pub keypad_overlay: Option<bool>,
+
+    /// Status bar (terminal, gpu and debugger frontends)
+    pub status_bar: Option<bool>,
+}
+
+/// Input configuration structure (`[input]` section)
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// Mapping of the CHIP-8 keypad to the keyboard
+    pub keymap: Option<Keymap>,
+}
+
+/// Audio configuration structure (`[audio]` section)
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Volume of the beeper, from 0 to 100
+    pub volume: Option<u8>,
+
+    /// Beeper muted at start (F9 toggles it at runtime, and the state is saved on exit)
+    pub muted: Option<bool>,
+
+    /// Frequency of the beeper tone (Hz)
+    pub beep_freq: Option<f64>,
+
+    /// Waveform of the beeper tone
+    pub beep_wave: Option<Waveform>,
+}
+
+/// ROM configuration structure (`[roms."sha1:<hash>"]` section or sidecar file of the ROM)
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RomConfig {
+    /// CHIP-8 clock frequency (instructions per second)
+    pub clock: Option<f64>,
+
+    /// Instruction timing model (`uniform` or `cosmac-vip`)
+    pub timing: Option<InstructionTiming>,
+
+    /// Emulation speed multiplier
+    pub speed: Option<f64>,
+
+    /// Mapping of the CHIP-8 keypad to the keyboard
+    pub keymap: Option<Keymap>,
+
+    /// Program start address (e.g. `0x600` for ETI-660 programs)
+    pub start_address: Option<u16>,
+
+    /// RAM memory size in bytes (e.g. `65536` for XO-CHIP programs)
+    pub memory_size: Option<usize>,
+
+    /// Compatibility profile (`chip8`, `schip` or `xo-chip`): the quirks set (true or false) override it
+    pub profile: Option<Profile>,
+
+    /// Boolean set to true to wrap the sprites around the screen edges instead of clipping them
+    pub wrap_sprites: Option<bool>,
+
+    /// Boolean set to true to scroll the lores display by half the distance of the scroll instructions
+    pub half_pixel_scroll: Option<bool>,
+
+    /// Boolean set to true to draw 8x16 sprites with DXY0 in lores mode
+    pub lores_tall_sprites: Option<bool>,
+
+    /// Boolean set to true to count the collision rows of DXYN in hires mode
+    pub collision_rows: Option<bool>,
+
+    /// Boolean set to true to reset VF with 8XY1, 8XY2 and 8XY3
+    pub vf_reset: Option<bool>,
+
+    /// Boolean set to true to shift VY into VX with 8XY6 and 8XYE, false to shift VX in place
+    pub shift_vy: Option<bool>,
+
+    /// Boolean set to true to increment I with FX55 and FX65
+    pub memory_increment: Option<bool>,
+
+    /// Boolean set to true to jump to XNN + VX with BNNN
+    pub jump_vx: Option<bool>,
+
+    /// Policy of the `0NNN` machine code routine calls (`ignore`, `warn` or `halt`)
+    pub machine_code: Option<MachineCodePolicy>,
+}
+
+impl RomConfig {
+    /// Returns the configuration with the options not set taken from another configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The configuration of lower precedence
+    fn or(self, other: RomConfig) -> Self {
+        Self {
+            clock: self.clock.or(other.clock),
+            timing: self.timing.or(other.timing),
+            speed: self.speed.or(other.speed),
+            keymap: self.keymap.or(other.keymap),
+            start_address: self.start_address.or(other.start_address),
+            memory_size: self.memory_size.or(other.memory_size),
+            profile: self.profile.or(other.profile),
+            wrap_sprites: self.wrap_sprites.or(other.wrap_sprites),
+            half_pixel_scroll: self.half_pixel_scroll.or(other.half_pixel_scroll),
+            lores_tall_sprites: self.lores_tall_sprites.or(other.lores_tall_sprites),
+            collision_rows: self.collision_rows.or(other.collision_rows),
+            vf_reset: self.vf_reset.or(other.vf_reset),
+            shift_vy: self.shift_vy.or(other.shift_vy),
+            memory_increment: self.memory_increment.or(other.memory_increment),
+            jump_vx: self.jump_vx.or(other.jump_vx),
+            machine_code: self.machine_code.or(other.machine_code),
+        }
+    }
+
+    /// Checks the values of the options
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin of the configuration, in the error message
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the clock or the speed are not greater than 0,
+    /// if the start address overlaps the fontset or if the memory size is not a power of two between 1KB and 64KB
+    fn validate(&self, origin: &str) {
+        for (name, value) in [("clock", self.clock), ("speed", self.speed)] {
+            if value.is_some_and(|value| !value.is_finite() || value <= 0.0) {
+                panic!("invalid {name} in {origin}: must be greater than 0");
+            }
+        }
+        if self.start_address.is_some_and(|address| address < 0x050) {
+            panic!("invalid start_address in {origin}: must be between 0x050 and 0xFFFF");
+        }
+        if self.memory_size.is_some_and(|size| {
+            !size.is_power_of_two() || !(MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size)
+        }) {
+            panic!(
+                "invalid memory_size in {origin}: must be a power of two between {MIN_MEMORY_SIZE} and {MAX_MEMORY_SIZE}"
+            );
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file.
+    /// If no path is given, the default configuration file is loaded if it exists,
+    /// otherwise the default configuration is returned
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Optional Path reference to the configuration file
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during reading or parsing of the configuration file
+    pub fn load(path: Option<&Path>) -> Self {
+        trace!("Config::load: start");
+
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => {
+                    debug!("no configuration file found: using default configuration");
+                    return Self::default();
+                }
+            },
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                panic!("reading config file `{}`: {e}", path.display())
+            }
+        };
+
+        let config: Self = match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                panic!("parsing config file `{}`: {e}", path.display())
+            }
+        };
+        if config
+            .display
+            .scale
+            .is_some_and(|scale| !(1..=MAX_SCALE).contains(&scale))
+        {
+            panic!(
+                "invalid scale in config file `{}`: must be between 1 and {MAX_SCALE}",
+                path.display()
+            );
+        }
+        if config
+            .audio
+            .volume
+            .is_some_and(|volume| volume > MAX_VOLUME)
+        {
+            panic!(
+                "invalid volume in config file `{}`: must be between 0 and {MAX_VOLUME}",
+                path.display()
+            );
+        }
+        if config
+            .audio
+            .beep_freq
+            .is_some_and(|frequency| !frequency.is_finite() || frequency <= 0.0)
+        {
+            panic!(
+                "invalid beep_freq in config file `{}`: must be greater than 0",
+                path.display()
+            );
+        }
+
+        debug!("config loaded from `{}`: {:?}", path.display(), config);
+        trace!("Config::load: exit");
+
+        config
+    }
+
+    /// Returns the configuration of a ROM file: the options of its sidecar file take precedence over
+    /// the ones of its `[roms."sha1:<hash>"]` section, which take precedence over the profile
+    /// of the ROM database (if the ROM is known)
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The Path reference that holds the path to the ROM file
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during reading of the ROM file or reading or parsing
+    /// of the sidecar file, or if the options are not valid
+    pub fn rom_config(&self, rom: &Path) -> RomConfig {
+        trace!("Config::rom_config: start");
+
+        let contents = match fs::read(rom) {
+            Ok(contents) => contents,
+            Err(e) => panic!("reading rom file: {e}"),
+        };
+        let sha1 = Sha1::from(&contents).digest().to_string();
+        let key = format!("sha1:{sha1}");
+        debug!("rom key: {key}");
+
+        // the profile of the ROM database has the lowest precedence
+        let profile = database::lookup(&sha1).unwrap_or_default();
+
+        let section = self
+            .roms
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&key))
+            .map(|(_, config)| config.clone())
+            .unwrap_or_default();
+        section.validate(&format!("config section `roms.\"{key}\"`"));
+        let section = section.or(profile);
+
+        let sidecar = rom.with_extension(ROM_CONFIG_EXTENSION);
+        let config = if sidecar.exists() {
+            let contents = match fs::read_to_string(&sidecar) {
+                Ok(contents) => contents,
+                Err(e) => panic!("reading rom config file `{}`: {e}", sidecar.display()),
+            };
+            let sidecar_config: RomConfig = match toml::from_str(&contents) {
+                Ok(config) => config,
+                Err(e) => panic!("parsing rom config file `{}`: {e}", sidecar.display()),
+            };
+            sidecar_config.validate(&format!("rom config file `{}`", sidecar.display()));
+            debug!("rom config loaded from `{}`", sidecar.display());
+            sidecar_config.or(section)
+        } else {
+            section
+        };
+
+        debug!("rom config: {:?}", config);
+        trace!("Config::rom_config: exit");
+
+        config
+    }
+}
+
+/// Returns the state directory of the emulator (e.g. recently played ROMs, RPL user flags):
+/// `$XDG_STATE_HOME/rust-chip-8` (or `$HOME/.local/state/rust-chip-8`), if known
+pub fn state_dir() -> Option<PathBuf> {
+    let state_dir = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+
+    Some(state_dir.join(CONFIG_DIR_NAME))
+}
+
+/// Writes the mute state of the beeper (toggled with F9) into the `[audio]` section of the configuration
+/// file, keeping its other options, comments and formatting: the file is created if it does not exist.
+/// Returns the path of the written file, or the error message
+///
+/// # Arguments
+///
+/// * `path` - Optional Path reference to the configuration file (the default one if None)
+/// * `muted` - Boolean set to true if the beeper is muted
+pub fn save_muted(path: Option<&Path>, muted: bool) -> Result<PathBuf, String> {
+    trace!("config::save_muted: start");
+
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_path().ok_or("configuration directory unknown: $HOME not set")?,
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("reading config file `{}`: {e}", path.display())),
+    };
+    let mut document: DocumentMut = contents
+        .parse()
+        .map_err(|e| format!("parsing config file `{}`: {e}", path.display()))?;
+    let audio = document
+        .entry("audio")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| format!("config file `{}`: `audio` is not a table", path.display()))?;
+    audio.insert("muted", toml_edit::value(muted));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("creating config directory `{}`: {e}", dir.display()))?;
+    }
+    fs::write(&path, document.to_string())
+        .map_err(|e| format!("writing config file `{}`: {e}", path.display()))?;
+
+    trace!("config::save_muted: exit");
+
+    Ok(path)
+}
+
+/// Returns the default configuration file path, if the configuration directory is known
+fn default_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a new temporary directory of a test
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust-chip-8-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a ROM and a configuration file with a section for it, and returns their paths
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory of the files
+    /// * `rom` - The ROM
+    /// * `section` - The options of the ROM section
+    fn rom_with_section(dir: &Path, rom: &[u8], section: &str) -> (PathBuf, PathBuf) {
+        let rom_path = dir.join("game.ch8");
+        fs::write(&rom_path, rom).unwrap();
+        // the hash is matched case insensitively
+        let sha1 = Sha1::from(rom).digest().to_string().to_uppercase();
+        let config_path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&config_path, format!("[roms.\"sha1:{sha1}\"]\n{section}")).unwrap();
+        (rom_path, config_path)
+    }
+
+    #[test]
+    fn rom_section_is_found_by_hash() {
+        let dir = temp_dir("config-section");
+        let (rom, config) =
+            rom_with_section(&dir, &[0x12, 0x00, 0xAB], "clock = 700.0\nspeed = 2.0");
+        let config = Config::load(Some(&config));
+
+        let rom_config = config.rom_config(&rom);
+        assert_eq!(rom_config.clock, Some(700.0));
+        assert_eq!(rom_config.speed, Some(2.0));
+
+        // other ROMs do not get the options
+        let other = dir.join("other.ch8");
+        fs::write(&other, [0x12, 0x00]).unwrap();
+        assert_eq!(config.rom_config(&other).clock, None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sidecar_file_takes_precedence() {
+        let dir = temp_dir("config-sidecar");
+        let (rom, config) =
+            rom_with_section(&dir, &[0x12, 0x00, 0xCD], "clock = 700.0\nspeed = 2.0");
+        fs::write(
+            rom.with_extension("toml"),
+            "clock = 1000.0\nstart_address = 0x600",
+        )
+        .unwrap();
+        let rom_config = Config::load(Some(&config)).rom_config(&rom);
+
+        assert_eq!(rom_config.clock, Some(1000.0));
+        assert_eq!(rom_config.speed, Some(2.0));
+        assert_eq!(rom_config.start_address, Some(0x600));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn database_profile_has_the_lowest_precedence() {
+        let dir = temp_dir("config-database");
+        let ibm_logo =
+            fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../roms/IBM_logo.ch8"));
+        let (rom, config) = rom_with_section(&dir, &ibm_logo.unwrap(), "shift_vy = false");
+        let rom_config = Config::load(Some(&config)).rom_config(&rom);
+
+        // IBM Logo runs on the original CHIP-8: its profile, except for the option of the section
+        assert_eq!(rom_config.profile, Some(Profile::Chip8));
+        assert_eq!(rom_config.shift_vy, Some(false));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_rom_options_are_reported() {
+        let options = [
+            ("clock = 0.0", "invalid clock"),
+            ("speed = -1.0", "invalid speed"),
+            ("start_address = 0x10", "invalid start_address"),
+            ("memory_size = 3000", "invalid memory_size"),
+        ];
+        for (option, error) in options {
+            let config: RomConfig = toml::from_str(option).unwrap();
+            let panic = std::panic::catch_unwind(|| config.validate("test")).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(
+                message.starts_with(&format!("{error} in test")),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn mute_state_is_saved_keeping_the_file() {
+        let dir = temp_dir("config-muted");
+        let path = dir.join("config").join(CONFIG_FILE_NAME);
+        assert_eq!(save_muted(Some(&path), true), Ok(path.clone()));
+        assert_eq!(Config::load(Some(&path)).audio.muted, Some(true));
+
+        let contents =
+            "# my settings\n[display]\ntheme = \"amber\"\n\n[audio]\nvolume = 40\nmuted = true\n";
+        fs::write(&path, contents).unwrap();
+        save_muted(Some(&path), false).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            contents.replace("muted = true", "muted = false")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_config_file_is_not_overwritten() {
+        let dir = temp_dir("config-muted-invalid");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, "audio = 1\n").unwrap();
+
+        let error = save_muted(Some(&path), true).unwrap_err();
+        assert!(error.ends_with("`audio` is not a table"), "{error}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "audio = 1\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_file_is_loaded() {
+        let dir = temp_dir("config-load");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            "[display]\ntheme = \"amber\"\nforeground = \"#00FF00\"\nghosting = 4\n\n[input]\nkeymap = \"azerty\"\n",
+        )
+        .unwrap();
+        let config = Config::load(Some(&path));
+
+        assert_eq!(config.display.theme, Some(Theme::Amber));
+        assert!(config.display.foreground.is_some());
+        assert_eq!(config.display.ghosting, Some(4));
+        assert_eq!(config.input.keymap, Some("azerty".parse().unwrap()));
+        assert_eq!(config.audio.volume, None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_config_files_are_reported() {
+        let dir = temp_dir("config-invalid");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let files = [
+            ("[display]\ncolour = \"red\"\n", "parsing config file"),
+            ("[display]\nscale = 0\n", "invalid scale in config file"),
+            ("[audio]\nvolume = 101\n", "invalid volume in config file"),
+        ];
+        for (contents, error) in files {
+            fs::write(&path, contents).unwrap();
+            let panic = std::panic::catch_unwind(|| Config::load(Some(&path))).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.starts_with(error), "{message}");
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+//! Conformance test runner (`test` subcommand)
+//!
+//! The test suite directory holds the test ROMs, the `suite.toml` manifest
+//! (ROM, instructions limit, memory presets and key input of every test)
+//! and the `expected.toml` file with the hashes of the known-good final displays
+
+use crate::cli::TestArgs;
+use crate::snapshot::display_ascii;
+use chip8_core::Chip8;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+use tracing::{debug, trace};
+
+/// test suite manifest file name
+const MANIFEST_FILE_NAME: &str = "suite.toml";
+
+/// expected displays file name
+const EXPECTED_FILE_NAME: &str = "expected.toml";
+
+/// Test suite manifest structure
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// Tests, in execution order
+    test: Vec<Test>,
+}
+
+/// Test structure (`[[test]]` section)
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Test {
+    /// Test name (key of the expected display)
+    name: String,
+
+    /// ROM file name, relative to the test suite directory
+    rom: String,
+
+    /// Max number of instructions (the test stops earlier if the program jumps to itself)
+    cycles: u64,
+
+    /// Bytes written into memory after loading the ROM (e.g. to skip a menu)
+    #[serde(default)]
+    memory: Vec<MemoryPreset>,
+
+    /// Keypad input
+    #[serde(default)]
+    input: Vec<KeyInput>,
+}
+
+/// Byte written into memory before running a test
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MemoryPreset {
+    /// Memory address
+    address: u16,
+
+    /// Byte value
+    value: u8,
+}
+
+/// Key pressed or released while running a test
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct KeyInput {
+    /// Instruction count at which the key changes state
+    cycle: u64,
+
+    /// Key of the hex keypad (0x0-0xF)
+    key: u8,
+
+    /// Boolean set to true if the key is pressed, false if released
+    pressed: bool,
+}
+
+/// Result of a test
+enum Outcome {
+    /// The final display matches the expected one
+    Pass,
+    /// The final display does not match the expected one
+    Fail,
+    /// The emulation panicked (e.g. illegal opcode)
+    Panic(String),
+    /// There is no expected display for the test (counted as a failure)
+    New,
+    /// The final display has been recorded as the expected one
+    Blessed,
+    /// The test ROM is missing
+    Skip,
+}
+
+/// Runs the test suite and prints pass/fail per test.
+/// Returns true if no test failed
+///
+/// # Arguments
+///
+/// * `args` - The test subcommand arguments
+/// * `seed` - The seed for the random number generator
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading or parsing of the manifest
+/// and the expected displays files, or during writing of the expected displays file
+pub fn run(args: &TestArgs, seed: u64) -> bool {
+    trace!("conformance::run: start");
+
+    let manifest_path = args.suite.join(MANIFEST_FILE_NAME);
+    let manifest: Manifest = match fs::read_to_string(&manifest_path) {
+        Ok(contents) => match toml::from_str(&contents) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                panic!("parsing manifest `{}`: {e}", manifest_path.display())
+            }
+        },
+        Err(e) => {
+            panic!("reading manifest `{}`: {e}", manifest_path.display())
+        }
+    };
+
+    let expected_path = args.suite.join(EXPECTED_FILE_NAME);
+    let mut expected: BTreeMap<String, String> = if expected_path.exists() {
+        match fs::read_to_string(&expected_path) {
+            Ok(contents) => match toml::from_str(&contents) {
+                Ok(expected) => expected,
+                Err(e) => {
+                    panic!(
+                        "parsing expected displays `{}`: {e}",
+                        expected_path.display()
+                    )
+                }
+            },
+            Err(e) => {
+                panic!(
+                    "reading expected displays `{}`: {e}",
+                    expected_path.display()
+                )
+            }
+        }
+    } else {
+        BTreeMap::new()
+    };
+
+    // panics of the tests are reported as failures: the default panic hook (that exits) is disabled
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut failed = 0;
+    for test in &manifest.test {
+        let rom = args.suite.join(&test.rom);
+        let outcome = if !rom.exists() {
+            Outcome::Skip
+        } else {
+            match panic::catch_unwind(AssertUnwindSafe(|| run_test(test, &rom, seed))) {
+                Ok(chip8) => {
+                    let hash = format!("{:016x}", chip8.display_hash());
+                    debug!("test {}: display hash {hash}", test.name);
+                    if args.bless {
+                        expected.insert(test.name.clone(), hash);
+                        Outcome::Blessed
+                    } else {
+                        match expected.get(&test.name) {
+                            Some(expected) if *expected == hash => Outcome::Pass,
+                            Some(_) => {
+                                println!("{}", display_ascii(&chip8));
+                                Outcome::Fail
+                            }
+                            None => Outcome::New,
+                        }
+                    }
+                }
+                Err(e) => Outcome::Panic(
+                    e.downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+                        .unwrap_or_else(|| String::from("emulation error")),
+                ),
+            }
+        };
+
+        match outcome {
+            Outcome::Pass => println!("PASS {}", test.name),
+            Outcome::Fail => {
+                failed += 1;
+                println!(
+                    "FAIL {}: the final display (above) differs from the expected one",
+                    test.name
+                );
+            }
+            Outcome::Panic(message) => {
+                failed += 1;
+                println!("FAIL {}: {message}", test.name);
+            }
+            Outcome::New => {
+                failed += 1;
+                println!(
+                    "FAIL {}: no expected display (record it with --bless)",
+                    test.name
+                );
+            }
+            Outcome::Blessed => println!("BLESSED {}", test.name),
+            Outcome::Skip => println!("SKIP {}: ROM `{}` not found", test.name, rom.display()),
+        }
+    }
+
+    panic::set_hook(hook);
+
+    if args.bless {
+        let contents = match toml::to_string(&expected) {
+            Ok(contents) => contents,
+            Err(e) => {
+                panic!("serializing expected displays: {e}")
+            }
+        };
+        if let Err(e) = fs::write(&expected_path, contents) {
+            panic!(
+                "writing expected displays `{}`: {e}",
+                expected_path.display()
+            )
+        }
+    }
+
+    println!("{} tests, {failed} failed", manifest.test.len());
+    trace!("conformance::run: exit");
+
+    failed == 0
+}
+
+/// Runs a test and returns the CHIP-8 instance at the end of the test
+///
+/// # Arguments
+///
+/// * `test` - The test to run
+/// * `rom` - The Path reference to the test ROM file
+/// * `seed` - The seed for the random number generator
+fn run_test(test: &Test, rom: &Path, seed: u64) -> Chip8 {
+    let (mut chip8, mut rng) = Chip8::builder().rng_seed(seed).build_with_rng();
+    chip8.load_rom(rom);
+    for preset in &test.memory {
+        chip8.write_memory(preset.address, preset.value);
+    }
+
+    for cycle in 0..test.cycles {
+        if chip8.idle_loop() {
+            break;
+        }
+        for input in test.input.iter().filter(|input| input.cycle == cycle) {
+            if input.pressed {
+                chip8.key_down(input.key);
+            } else {
+                chip8.key_up(input.key);
+            }
+        }
+
+        if let Err(e) = chip8.step(&mut rng) {
+            panic!("{e}")
+        }
+    }
+
+    chip8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, path::PathBuf};
+
+    /// seed of the random number generator (default of `--random-seed`)
+    const SEED: u64 = 10;
+
+    /// Returns the path of a file or directory of the repository
+    fn repository_path(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(path)
+    }
+
+    #[test]
+    fn repository_suite_passes() {
+        // every test whose ROM is in roms/timendus must have an expected display that matches
+        let args = TestArgs {
+            suite: repository_path("roms/timendus"),
+            bless: false,
+        };
+        assert!(run(&args, SEED));
+    }
+
+    #[test]
+    fn test_without_expected_display_fails_until_blessed() {
+        let suite = env::temp_dir().join(format!("rust-chip-8-suite-{}", std::process::id()));
+        fs::create_dir_all(&suite).unwrap();
+        let manifest = format!(
+            "[[test]]\nname = \"ibm\"\nrom = {:?}\ncycles = 1000\n",
+            repository_path("roms/IBM_logo.ch8")
+        );
+        fs::write(suite.join(MANIFEST_FILE_NAME), manifest).unwrap();
+
+        let mut args = TestArgs {
+            suite,
+            bless: false,
+        };
+        assert!(!run(&args, SEED));
+
+        args.bless = true;
+        assert!(run(&args, SEED));
+        let expected = fs::read_to_string(args.suite.join(EXPECTED_FILE_NAME)).unwrap();
+        assert_eq!(expected, "ibm = \"c094f65422bd4e58\"\n");
+
+        args.bless = false;
+        assert!(run(&args, SEED));
+
+        fs::remove_dir_all(&args.suite).unwrap();
+    }
+}
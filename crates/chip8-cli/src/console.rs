@@ -0,0 +1,422 @@
+//! Console logging
+
+use crate::ARGS;
+use clap::ValueEnum;
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+use tracing::{debug, Level};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+/// number of rotated log files kept besides the current one
+const LOG_FILE_BACKUPS: usize = 5;
+
+/// guard of the log file writer thread: dropping it flushes the buffered log lines
+static LOG_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+/// Format of the log lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines (colored)
+    #[default]
+    Text,
+    /// JSON lines, with the fields of the current span (e.g. cycle, PC and opcode of the instruction)
+    Json,
+}
+
+/// Rotation policy of the log file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    /// The log file grows without limits
+    #[default]
+    Never,
+    /// A new log file every hour (the date and hour are added to the file name)
+    Hourly,
+    /// A new log file every day (the date is added to the file name)
+    Daily,
+    /// A new log file when the current one reaches the size in bytes (the old ones get the `.1`-`.5` suffixes)
+    Size(u64),
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    /// Parses a log rotation policy: `never`, `hourly`, `daily` or a size (e.g. `10MB`, `512KB`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "never" => return Ok(Self::Never),
+            "hourly" => return Ok(Self::Hourly),
+            "daily" => return Ok(Self::Daily),
+            _ => {}
+        }
+
+        let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) => s.split_at(index),
+            None => (s.as_str(), ""),
+        };
+        let multiplier: u64 = match unit.trim() {
+            "" | "b" => 1,
+            "kb" => 1 << 10,
+            "mb" => 1 << 20,
+            "gb" => 1 << 30,
+            _ => 0,
+        };
+        if multiplier == 0 {
+            return Err(format!(
+                "invalid log rotation `{s}`: expected never, hourly, daily or a size (e.g. 10MB)"
+            ));
+        }
+        match number.parse::<u64>() {
+            Ok(size) if size > 0 => Ok(Self::Size(size * multiplier)),
+            _ => Err(format!(
+                "invalid log rotation `{s}`: the size must be greater than 0"
+            )),
+        }
+    }
+}
+
+/// Log file writer that rotates the file when it reaches a size
+struct SizeRotatingFile {
+    /// Path of the current log file
+    path: PathBuf,
+
+    /// The current log file
+    file: File,
+
+    /// Bytes written into the current log file
+    written: u64,
+
+    /// Max size of a log file in bytes
+    max_size: u64,
+}
+
+impl SizeRotatingFile {
+    /// Opens the log file (appending to it if it exists)
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The Path reference that holds the path to the log file
+    /// * `max_size` - The max size of a log file in bytes
+    fn new(path: &Path, max_size: u64) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            written,
+            max_size,
+        })
+    }
+
+    /// Moves the log files to the next suffix (the oldest one is deleted) and opens a new log file
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..LOG_FILE_BACKUPS).rev() {
+            let from = backup_path(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, backup_path(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, backup_path(&self.path, 1))?;
+
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Returns the path of a rotated log file (e.g. `trace.log.1`)
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the log file
+/// * `n` - The number of the rotated log file
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{n}"));
+
+    PathBuf::from(name)
+}
+
+/// Returns the non-blocking writer of the log file and the guard of its writer thread
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the log file
+/// * `rotation` - The rotation policy of the log file
+///
+/// # Panics
+///
+/// The function panics in case of errors during opening of the log file
+fn log_file_writer(path: &Path, rotation: LogRotation) -> (BoxMakeWriter, WorkerGuard) {
+    let (writer, guard) = match rotation {
+        LogRotation::Size(max_size) => match SizeRotatingFile::new(path, max_size) {
+            Ok(file) => tracing_appender::non_blocking(file),
+            Err(e) => panic!("opening log file `{}`: {e}", path.display()),
+        },
+        LogRotation::Never | LogRotation::Hourly | LogRotation::Daily => {
+            let rotation = match rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                _ => Rotation::NEVER,
+            };
+            let mut builder = RollingFileAppender::builder().rotation(rotation.clone());
+            if rotation != Rotation::NEVER {
+                builder = builder.max_log_files(LOG_FILE_BACKUPS + 1);
+            }
+            // with time rotation the date is added between the file stem and the extension
+            if let Some(stem) = path.file_stem() {
+                builder = builder.filename_prefix(stem.to_string_lossy());
+            }
+            if let Some(extension) = path.extension() {
+                builder = builder.filename_suffix(extension.to_string_lossy());
+            }
+            let directory = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            match builder.build(directory) {
+                Ok(appender) => tracing_appender::non_blocking(appender),
+                Err(e) => panic!("opening log file `{}`: {e}", path.display()),
+            }
+        }
+    };
+
+    (BoxMakeWriter::new(writer), guard)
+}
+
+/// Initialize tracing console logging
+pub fn init() {
+    let level: Level;
+    let mut subscriber = tracing_subscriber::fmt();
+
+    // set logging level from args
+    if ARGS.log.trace {
+        // enable trace, debug, info, warn, error levels
+        level = Level::TRACE;
+        subscriber = subscriber.with_target(true).with_thread_ids(true);
+    } else if ARGS.log.debug {
+        // enable debug, info, warn, error levels
+        level = Level::DEBUG;
+        subscriber = subscriber.with_target(true);
+    } else if ARGS.log.quiet {
+        // enable warn, error levels
+        level = Level::WARN;
+    } else {
+        // enable info, warn, error levels
+        level = Level::INFO;
+    }
+
+    // log lines are written to stderr, or to the log file by a writer thread
+    // (so that trace logging does not slow down the emulation)
+    let writer = match ARGS.log_file.as_deref() {
+        Some(path) => {
+            let (writer, guard) = log_file_writer(path, ARGS.log_rotation.unwrap_or_default());
+            *lock_guard() = Some(guard);
+            writer
+        }
+        None => BoxMakeWriter::new(io::stderr),
+    };
+
+    // initialize tracing logging
+    let subscriber = subscriber
+        .with_level(true)
+        .with_writer(writer)
+        .with_max_level(level);
+    match ARGS.log_format.unwrap_or_default() {
+        LogFormat::Text => subscriber.with_ansi(ARGS.log_file.is_none()).init(),
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+    }
+
+    debug!("logging level set: {}", level.as_str());
+}
+
+/// Flushes the log lines buffered for the log file: it must be called before the process exits
+pub fn flush() {
+    lock_guard().take();
+}
+
+/// Locks the guard of the log file writer thread
+///
+/// # Panics
+///
+/// The function panics if the lock is poisoned
+fn lock_guard() -> std::sync::MutexGuard<'static, Option<WorkerGuard>> {
+    match LOG_GUARD.lock() {
+        Ok(guard) => guard,
+        Err(e) => panic!("locking log file guard: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "cycle-logging")]
+    use chip8_core::Chip8;
+    use std::env;
+    #[cfg(feature = "cycle-logging")]
+    use std::sync::Arc;
+
+    /// Log writer of the tests, collecting the log lines
+    #[cfg(feature = "cycle-logging")]
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    #[cfg(feature = "cycle-logging")]
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns the JSON log lines of a number of instructions of a program
+    ///
+    /// # Arguments
+    ///
+    /// * `trace_sample` - The sampling of the per-instruction logging
+    /// * `cycles` - The number of instructions
+    #[cfg(feature = "cycle-logging")]
+    fn json_log(trace_sample: u64, cycles: usize) -> Vec<serde_json::Value> {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let (mut chip8, mut rng) = Chip8::builder().trace_sample(trace_sample).build_with_rng();
+            // 7001 (V0 += 1), 1200 (jump to 0x200)
+            chip8.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]);
+            for _ in 0..cycles {
+                chip8.step(&mut rng).unwrap();
+            }
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn log_rotations_are_parsed() {
+        assert_eq!("never".parse(), Ok(LogRotation::Never));
+        assert_eq!("Hourly".parse(), Ok(LogRotation::Hourly));
+        assert_eq!("daily".parse(), Ok(LogRotation::Daily));
+        assert_eq!("512".parse(), Ok(LogRotation::Size(512)));
+        assert_eq!("512KB".parse(), Ok(LogRotation::Size(512 << 10)));
+        assert_eq!("10mb".parse(), Ok(LogRotation::Size(10 << 20)));
+        assert_eq!("1 GB".parse(), Ok(LogRotation::Size(1 << 30)));
+
+        assert!("0MB".parse::<LogRotation>().is_err());
+        assert!("10TB".parse::<LogRotation>().is_err());
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+
+    #[test]
+    fn log_files_are_rotated_by_size() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace.log");
+        assert_eq!(backup_path(&path, 1), dir.join("trace.log.1"));
+
+        let mut file = SizeRotatingFile::new(&path, 10).unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path, 1)).unwrap(),
+            "second\n"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_path(&path, 2)).unwrap(),
+            "first\n"
+        );
+
+        // the oldest backups are deleted
+        for _ in 0..LOG_FILE_BACKUPS + 2 {
+            file.write_all(b"line line\n").unwrap();
+        }
+        assert!(backup_path(&path, LOG_FILE_BACKUPS).exists());
+        assert!(!backup_path(&path, LOG_FILE_BACKUPS + 1).exists());
+
+        // an existing log file is appended to
+        let file = SizeRotatingFile::new(&path, 10).unwrap();
+        assert_eq!(file.written, 10);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // the per-instruction events are compiled only with the cycle-logging feature
+    #[cfg(feature = "cycle-logging")]
+    #[test]
+    fn json_lines_carry_the_instruction_span() {
+        // the events outside of the instructions (e.g. loading the ROM) have no span
+        let lines = json_log(1, 2);
+        let span = &lines
+            .iter()
+            .find(|line| line.get("span").is_some())
+            .unwrap()["span"];
+        assert_eq!(span["name"], "step");
+        assert_eq!(span["cycle"], 0);
+        assert_eq!(span["pc"], "0x200");
+        assert_eq!(span["opcode"], "0x7001");
+        assert!(lines
+            .iter()
+            .any(|line| line["span"]["pc"] == "0x202" && line["span"]["cycle"] == 1));
+    }
+
+    #[cfg(feature = "cycle-logging")]
+    #[test]
+    fn instructions_are_logged_every_trace_sample_cycles() {
+        let mut cycles: Vec<u64> = json_log(4, 10)
+            .iter()
+            .filter_map(|line| line["span"]["cycle"].as_u64())
+            .collect();
+        cycles.dedup();
+        assert_eq!(cycles, [0, 4, 8]);
+    }
+}
@@ -0,0 +1,492 @@
+//! Control socket (`--control-socket` option): a Unix domain socket that accepts one command per line,
+//! so that external tools and scripts can control a running emulator
+//! (e.g. `echo pause | socat - UNIX-CONNECT:chip8.sock`).
+//!
+//! Every command gets a reply line: `ok` (followed by the output of the command, if any) or `error: <message>`.
+//! The commands are executed by the emulation thread between two instructions
+
+use chip8_core::Chip8;
+use chip8_frontend::{EmulationStatus, Hotkey, Palette, Renderer};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, Sender},
+};
+use tracing::{debug, info, trace};
+
+/// Command accepted by the control socket
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    /// Pauses the emulation
+    Pause,
+    /// Resumes the paused emulation
+    Resume,
+    /// Restarts the ROM from the beginning
+    Reset,
+    /// Resets the emulation and boots the ROM file
+    Load(PathBuf),
+    /// Writes a save state to the file
+    SaveState(PathBuf),
+    /// Restores a save state from the file
+    LoadState(PathBuf),
+    /// Replies with the registers, the stack and the timers
+    DumpRegisters,
+    /// Writes a PNG image of the display to the file
+    Screenshot(PathBuf),
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses a command line of the control socket (e.g. `pause`, `save-state state.bin`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, argument) = match s.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(PathBuf::from(argument.trim()))),
+            None => (s, None),
+        };
+
+        match (name, argument) {
+            ("pause", None) => Ok(Self::Pause),
+            ("resume", None) => Ok(Self::Resume),
+            ("reset", None) => Ok(Self::Reset),
+            ("dump-registers", None) => Ok(Self::DumpRegisters),
+            ("load", Some(path)) => Ok(Self::Load(path)),
+            ("save-state", Some(path)) => Ok(Self::SaveState(path)),
+            ("load-state", Some(path)) => Ok(Self::LoadState(path)),
+            ("screenshot", Some(path)) => Ok(Self::Screenshot(path)),
+            ("load" | "save-state" | "load-state" | "screenshot", None) => {
+                Err(format!("command `{name}` requires a file path"))
+            }
+            ("pause" | "resume" | "reset" | "dump-registers", Some(_)) => {
+                Err(format!("command `{name}` takes no arguments"))
+            }
+            _ => Err(format!(
+                "unknown command `{name}`: expected pause, resume, reset, load <FILE>, save-state <FILE>, \
+                 load-state <FILE>, dump-registers or screenshot <FILE>"
+            )),
+        }
+    }
+}
+
+/// Command received by the control socket, with the channel of its reply
+struct Request {
+    /// The command to execute
+    command: Command,
+
+    /// Sender of the reply line
+    reply: Sender<String>,
+}
+
+/// Renderer that executes the commands of the control socket on the CHIP-8 instance
+struct ControlRenderer {
+    /// The wrapped renderer
+    renderer: Box<dyn Renderer>,
+
+    /// Receiver of the commands of the connections
+    requests: Receiver<Request>,
+
+    /// Colors of the screenshots
+    palette: Palette,
+
+    /// Path of the socket file (removed when the emulation stops)
+    path: PathBuf,
+}
+
+impl Renderer for ControlRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        self.renderer.render(display, dirty_rows);
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.renderer.tick(display);
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.renderer.status(status);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        self.renderer.debug(chip8)
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        while let Ok(request) = self.requests.try_recv() {
+            let reply = match self.execute(&request.command, chip8) {
+                Ok(output) if output.is_empty() => String::from("ok"),
+                Ok(output) => format!("ok {output}"),
+                Err(e) => format!("error: {e}"),
+            };
+            // the connection may be closed before the reply
+            let _ = request.reply.send(reply);
+        }
+
+        self.renderer.control(chip8);
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        self.renderer.hotkeys()
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.renderer.fast_forward()
+    }
+
+    fn has_keypad(&self) -> bool {
+        self.renderer.has_keypad()
+    }
+
+    fn keypad(&self) -> u16 {
+        self.renderer.keypad()
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.renderer.quit_requested()
+    }
+}
+
+impl ControlRenderer {
+    /// Executes a command of the control socket and returns its output
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to execute
+    /// * `chip8` - Mutable reference to the CHIP-8 instance
+    fn execute(&self, command: &Command, chip8: &mut Chip8) -> Result<String, String> {
+        debug!("control socket: {command:?}");
+
+        match command {
+            Command::Pause => {
+                info!("emulation paused");
+                chip8.pause();
+            }
+            Command::Resume => {
+                info!("emulation resumed");
+                chip8.resume();
+            }
+            Command::Reset => {
+                info!("emulation reset");
+                chip8.soft_reset();
+            }
+            Command::Load(path) => {
+                let rom = fs::read(path).map_err(|e| format!("reading rom file: {e}"))?;
+                if rom.len() > chip8.max_rom_size() {
+                    return Err(format!(
+                        "ROM too big: {} bytes (max {} bytes)",
+                        rom.len(),
+                        chip8.max_rom_size()
+                    ));
+                }
+                chip8.reset(false);
+                chip8.load_rom_bytes(&rom);
+                info!("rom loaded: {}", path.display());
+            }
+            Command::SaveState(path) => {
+                fs::write(path, chip8.save_state())
+                    .map_err(|e| format!("writing state file: {e}"))?;
+                info!("state saved: {}", path.display());
+            }
+            Command::LoadState(path) => {
+                let state = fs::read(path).map_err(|e| format!("reading state file: {e}"))?;
+                chip8
+                    .load_state(&state)
+                    .map_err(|e| format!("loading state file: {e}"))?;
+                info!("state loaded: {}", path.display());
+            }
+            Command::DumpRegisters => return Ok(dump_registers(chip8)),
+            Command::Screenshot(path) => {
+                crate::screenshot::save(path, chip8, self.palette)
+                    .map_err(|e| format!("writing screenshot file: {e}"))?;
+                info!("screenshot saved: {}", path.display());
+            }
+        }
+
+        Ok(String::new())
+    }
+}
+
+impl Drop for ControlRenderer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns the registers, the stack and the timers of the CHIP-8 instance on one line
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+pub fn dump_registers(chip8: &Chip8) -> String {
+    let v: Vec<String> = chip8.v().iter().map(|v| format!("{v:02X}")).collect();
+    let stack: Vec<String> = chip8.stack().iter().map(|a| format!("{a:03X}")).collect();
+
+    format!(
+        "pc={:03X} i={:03X} v=[{}] stack=[{}] dt={} st={} cycles={} paused={}",
+        chip8.pc(),
+        chip8.i(),
+        v.join(","),
+        stack.join(","),
+        chip8.delay_timer(),
+        chip8.sound_timer(),
+        chip8.cycles(),
+        chip8.paused()
+    )
+}
+
+/// Creates the control socket and returns the renderer that executes its commands.
+/// The connections are served by background threads
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the socket file
+/// * `palette` - The colors of the screenshots
+/// * `renderer` - The renderer of the emulation
+///
+/// # Panics
+///
+/// The function panics in case of errors during creation of the socket
+/// (e.g. another emulator is listening on the same path), or if the platform does not support Unix domain sockets
+pub fn attach(path: &Path, palette: Palette, renderer: Box<dyn Renderer>) -> Box<dyn Renderer> {
+    trace!("control::attach: start");
+
+    let (sender, requests) = mpsc::channel();
+    listen(path, sender);
+
+    info!("control socket listening: {}", path.display());
+
+    trace!("control::attach: exit");
+
+    Box::new(ControlRenderer {
+        renderer,
+        requests,
+        palette,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Binds the socket and spawns the thread that accepts the connections
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the socket file
+/// * `sender` - Sender of the commands to the emulation thread
+///
+/// # Panics
+///
+/// The function panics in case of errors during creation of the socket
+#[cfg(unix)]
+fn listen(path: &Path, sender: Sender<Request>) {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::thread;
+
+    // the socket file of an emulator that did not stop cleanly is replaced
+    if path.exists() && UnixStream::connect(path).is_err() {
+        debug!("removing stale control socket: {}", path.display());
+        if let Err(e) = fs::remove_file(path) {
+            panic!("removing stale control socket: {e}")
+        }
+    }
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(e) => panic!("creating control socket `{}`: {e}", path.display()),
+    };
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let sender = sender.clone();
+                    thread::spawn(move || serve(stream, sender));
+                }
+                Err(e) => debug!("control socket connection failed: {e}"),
+            }
+        }
+    });
+}
+
+/// Binds the socket (not supported on this platform)
+///
+/// # Panics
+///
+/// The function always panics
+#[cfg(not(unix))]
+fn listen(path: &Path, _sender: Sender<Request>) {
+    panic!(
+        "control socket {} not available: Unix domain sockets are not supported on this platform",
+        path.display()
+    );
+}
+
+/// Serves a connection of the control socket: reads the commands, one per line,
+/// and writes their replies until the connection is closed
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `sender` - Sender of the commands to the emulation thread
+#[cfg(unix)]
+fn serve(stream: std::os::unix::net::UnixStream, sender: Sender<Request>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    debug!("control socket: client connected");
+
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            debug!("control socket connection failed: {e}");
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match line.parse() {
+            Ok(command) => {
+                let (reply, receiver) = mpsc::channel();
+                match sender.send(Request { command, reply }) {
+                    Ok(()) => receiver
+                        .recv()
+                        .unwrap_or_else(|_| String::from("error: emulation stopped")),
+                    Err(_) => String::from("error: emulation stopped"),
+                }
+            }
+            Err(e) => format!("error: {e}"),
+        };
+        if writeln!(writer, "{reply}").is_err() {
+            break;
+        }
+    }
+
+    debug!("control socket: client disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+    use chip8_frontend::Theme;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::env;
+
+    /// Returns a control renderer without socket, and the sender of its commands
+    fn renderer() -> (ControlRenderer, Sender<Request>) {
+        let (sender, requests) = mpsc::channel();
+        let renderer = ControlRenderer {
+            renderer: Box::new(HeadlessRenderer),
+            requests,
+            palette: Theme::Mono.palette(),
+            path: env::temp_dir().join(format!("rust-chip-8-control-{}", std::process::id())),
+        };
+        (renderer, sender)
+    }
+
+    /// Returns an instance with a ROM loaded
+    fn chip8() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x02]);
+        chip8
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(" pause ".parse(), Ok(Command::Pause));
+        assert_eq!("dump-registers".parse(), Ok(Command::DumpRegisters));
+        assert_eq!(
+            "save-state  my state.bin".parse(),
+            Ok(Command::SaveState(PathBuf::from("my state.bin")))
+        );
+        assert_eq!(
+            "load".parse::<Command>(),
+            Err(String::from("command `load` requires a file path"))
+        );
+        assert_eq!(
+            "reset now".parse::<Command>(),
+            Err(String::from("command `reset` takes no arguments"))
+        );
+        assert!("quit"
+            .parse::<Command>()
+            .unwrap_err()
+            .starts_with("unknown command `quit`"));
+    }
+
+    #[test]
+    fn commands_are_executed_between_instructions() {
+        let (mut renderer, sender) = renderer();
+        let mut chip8 = chip8();
+        chip8.step(&mut StdRng::seed_from_u64(0)).unwrap();
+
+        let mut send = |command: &str| {
+            let (reply, receiver) = mpsc::channel();
+            let command = command.parse().unwrap();
+            sender.send(Request { command, reply }).unwrap();
+            renderer.control(&mut chip8);
+            receiver.recv().unwrap()
+        };
+        assert_eq!(send("pause"), "ok");
+        assert_eq!(
+            send("dump-registers"),
+            "ok pc=202 i=000 v=[2A,00,00,00,00,00,00,00,00,00,00,00,00,00,00,00] stack=[] dt=0 st=0 cycles=1 paused=true"
+        );
+        assert_eq!(send("resume"), "ok");
+        assert!(send("load-state /nonexistent/state.bin").starts_with("error: reading state file"));
+        assert_eq!(send("reset"), "ok");
+        assert_eq!(chip8.pc(), 0x200);
+    }
+
+    #[test]
+    fn states_are_saved_and_restored() {
+        let (renderer, _sender) = renderer();
+        let path = env::temp_dir().join(format!("rust-chip-8-state-{}.bin", std::process::id()));
+        let mut chip8 = chip8();
+        chip8.step(&mut StdRng::seed_from_u64(0)).unwrap();
+        let command = Command::SaveState(path.clone());
+        assert_eq!(renderer.execute(&command, &mut chip8), Ok(String::new()));
+
+        chip8.soft_reset();
+        let command = Command::LoadState(path.clone());
+        assert_eq!(renderer.execute(&command, &mut chip8), Ok(String::new()));
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.v()[0], 0x2A);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_replies_to_every_line() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+        use std::thread;
+
+        let path = env::temp_dir().join(format!("rust-chip-8-control-{}.sock", std::process::id()));
+        let mut renderer = attach(&path, Theme::Mono.palette(), Box::new(HeadlessRenderer));
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            let mut stream = UnixStream::connect(client_path).unwrap();
+            stream.write_all(b"pause\n\nfly\n").unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            [lines.next(), lines.next()].map(|line| line.unwrap().unwrap())
+        });
+        let mut chip8 = chip8();
+        while !client.is_finished() {
+            renderer.control(&mut chip8);
+            thread::yield_now();
+        }
+
+        let replies = client.join().unwrap();
+        assert_eq!(replies[0], "ok");
+        assert!(replies[1].starts_with("error: unknown command `fly`"));
+        assert!(chip8.paused());
+
+        // the socket file is removed with the renderer
+        drop(renderer);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,297 @@
+//! Crash dumps: when an instruction cannot be executed (e.g. illegal opcode) a self-contained dump
+//! (error, registers, last executed instructions, ROM hash, ROM image and save state) is written to a JSON file,
+//! that can be restored with `--load-dump` for inspection (e.g. in the debugger frontend).
+//!
+//! The state at the end of a recent 60Hz frame (at most `TRACK_INTERVAL` old, in host time) is also kept
+//! for the panic hook, that writes it when the program panics (e.g. a frontend error)
+
+use chip8_core::{disassemble, Chip8};
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, trace};
+
+/// min host time between two states kept for the panic hook: serializing the state at every frame
+/// would slow down the uncapped runs (more than 100000 frames per second)
+const TRACK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// crash dump of the state at the end of a recent 60Hz frame, written by the panic hook
+static LAST_FRAME_DUMP: Mutex<Option<CrashDump>> = Mutex::new(None);
+
+/// Crash dump file contents
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CrashDump {
+    /// The error of the instruction that could not be executed (or the panic message)
+    pub error: String,
+
+    /// Path of the ROM file
+    pub rom: Option<PathBuf>,
+
+    /// SHA-1 hash of the ROM image (lowercase hex digits)
+    pub rom_sha1: String,
+
+    /// Registers, stack and timers (human readable)
+    pub registers: String,
+
+    /// The last executed instructions (address, opcode and mnemonic), from the oldest to the failed one
+    pub history: Vec<String>,
+
+    /// ROM image (hex digits)
+    rom_image: String,
+
+    /// Save state of the instance (hex digits)
+    state: String,
+}
+
+impl CrashDump {
+    /// Returns the crash dump of the instance stopped by an error
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance, after the error
+    /// * `error` - The error message
+    /// * `rom` - Optional Path reference that holds the path to the ROM file
+    pub fn new(chip8: &Chip8, error: &str, rom: Option<&Path>) -> Self {
+        Self {
+            error: error.to_string(),
+            rom: rom.map(Path::to_path_buf),
+            rom_sha1: Sha1::from(chip8.rom()).digest().to_string(),
+            registers: crate::control::dump_registers(chip8),
+            history: chip8
+                .last_instructions()
+                .into_iter()
+                .map(|(pc, opcode)| format!("{pc:#05X}: {opcode:04X}  {}", disassemble(opcode)))
+                .collect(),
+            rom_image: hex(chip8.rom()),
+            state: hex(&chip8.save_state()),
+        }
+    }
+
+    /// Writes the crash dump to a new file in the temporary directory and returns its path
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during writing of the crash dump file
+    pub fn write(&self) -> PathBuf {
+        trace!("CrashDump::write: start");
+
+        let path = match self.try_write() {
+            Ok(path) => path,
+            Err(e) => panic!("{e}"),
+        };
+
+        trace!("CrashDump::write: exit");
+
+        path
+    }
+
+    /// Writes the crash dump to a new file in the temporary directory and returns its path,
+    /// or the error message (e.g. from the panic hook, that must not panic)
+    fn try_write(&self) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        let path = env::temp_dir().join(format!("rust-chip-8-crash-{timestamp}.json"));
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("serializing crash dump: {e}"))?;
+        fs::write(&path, contents)
+            .map_err(|e| format!("writing crash dump file `{}`: {e}", path.display()))?;
+
+        Ok(path)
+    }
+
+    /// Reads a crash dump file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The Path reference that holds the path to the crash dump file
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during reading or parsing of the crash dump file
+    pub fn load(path: &Path) -> Self {
+        trace!("CrashDump::load: start");
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => panic!("reading crash dump file: {e}"),
+        };
+        let dump = match serde_json::from_str(&contents) {
+            Ok(dump) => dump,
+            Err(e) => panic!("parsing crash dump file: {e}"),
+        };
+        debug!("crash dump loaded: {}", path.display());
+
+        trace!("CrashDump::load: exit");
+
+        dump
+    }
+
+    /// Loads the ROM image and restores the state of the crash dump into the instance
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - Mutable reference to the CHIP-8 instance
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the ROM image or the save state of the crash dump are not valid
+    pub fn restore(&self, chip8: &mut Chip8) {
+        let (Some(rom), Some(state)) = (unhex(&self.rom_image), unhex(&self.state)) else {
+            panic!("parsing crash dump file: invalid hex digits");
+        };
+        chip8.load_rom_bytes(&rom);
+        if let Err(e) = chip8.load_state(&state) {
+            panic!("restoring crash dump state: {e}")
+        }
+    }
+}
+
+/// Keeps the crash dump of the instance at the end of a 60Hz frame every `TRACK_INTERVAL` of host time,
+/// so that the panic hook can write the last state with `write_last_frame`
+///
+/// # Arguments
+///
+/// * `chip8` - Mutable reference to the CHIP-8 instance
+/// * `rom` - Optional Path reference that holds the path to the ROM file
+pub fn track(chip8: &mut Chip8, rom: Option<&Path>) {
+    let rom = rom.map(Path::to_path_buf);
+    let mut kept: Option<Instant> = None;
+    chip8.on_frame(move |chip8| {
+        if kept.is_some_and(|kept| kept.elapsed() < TRACK_INTERVAL) {
+            return;
+        }
+        kept = Some(Instant::now());
+
+        let dump = CrashDump::new(chip8, "", rom.as_deref());
+        if let Ok(mut last) = LAST_FRAME_DUMP.lock() {
+            *last = Some(dump);
+        }
+    });
+}
+
+/// Writes the last crash dump kept at the end of a 60Hz frame with the panic message, and returns its path
+/// (None if no frame has been completed, or if the dump cannot be written)
+///
+/// # Arguments
+///
+/// * `message` - The panic message
+pub fn write_last_frame(message: &str) -> Option<PathBuf> {
+    // the panic can happen while the dump is kept: it is not waited for
+    let mut dump = LAST_FRAME_DUMP.try_lock().ok()?.take()?;
+    dump.error = format!(
+        "{message} (state at the end of a frame at most {} ms before)",
+        TRACK_INTERVAL.as_millis()
+    );
+
+    // the panic hook must not panic again
+    dump.try_write().ok()
+}
+
+/// Returns the bytes as lowercase hex digits
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to encode
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns the bytes of a string of hex digits, or None if the string is not valid
+///
+/// # Arguments
+///
+/// * `hex` - The hex digits to decode
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// ROM that stops on an illegal opcode: 6A05 (VA = 5), 2206 (call 0x206), 0000, E000 (illegal)
+    const CRASHING_ROM: [u8; 8] = [0x6A, 0x05, 0x22, 0x06, 0x00, 0x00, 0xE0, 0x00];
+
+    /// Returns an instance stopped by the error of the crashing ROM, and the error
+    fn crashed() -> (Chip8, String) {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&CRASHING_ROM);
+        let mut rng = StdRng::seed_from_u64(0);
+        let error = loop {
+            if let Err(e) = chip8.step(&mut rng) {
+                break e.to_string();
+            }
+        };
+        (chip8, error)
+    }
+
+    #[test]
+    fn hex_digits_are_decoded() {
+        assert_eq!(hex(&[0x00, 0xAB, 0x7F]), "00ab7f");
+        assert_eq!(unhex("00ab7F"), Some(vec![0x00, 0xAB, 0x7F]));
+        assert_eq!(unhex("abc"), None);
+        assert_eq!(unhex("zz"), None);
+    }
+
+    #[test]
+    fn dump_has_the_history_of_the_error() {
+        let (chip8, error) = crashed();
+        let dump = CrashDump::new(&chip8, &error, Some(Path::new("crash.ch8")));
+
+        assert_eq!(dump.rom, Some(PathBuf::from("crash.ch8")));
+        assert_eq!(dump.rom_sha1, Sha1::from(CRASHING_ROM).digest().to_string());
+        assert!(dump.registers.starts_with("pc=206"));
+        assert_eq!(dump.history.len(), 3);
+        assert!(dump.history[2].starts_with("0x206: E000"));
+    }
+
+    #[test]
+    fn dump_file_restores_the_state() {
+        let (chip8, error) = crashed();
+        let path = CrashDump::new(&chip8, &error, None).write();
+        let dump = CrashDump::load(&path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(dump.error, error);
+
+        let mut restored = Chip8::new();
+        dump.restore(&mut restored);
+        assert_eq!(restored.pc(), 0x206);
+        assert_eq!(restored.v()[0xA], 5);
+        assert_eq!(restored.stack(), chip8.stack());
+        assert_eq!(restored.rom(), CRASHING_ROM);
+    }
+
+    #[test]
+    fn last_frame_is_written_on_panics() {
+        // 7001 (V0 += 1), 1200 (jump to 0x200)
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]);
+        track(&mut chip8, None);
+        chip8.run_frame(&mut StdRng::seed_from_u64(0)).unwrap();
+
+        let path = write_last_frame("frontend error").unwrap();
+        let dump = CrashDump::load(&path);
+        fs::remove_file(path).unwrap();
+        assert!(dump
+            .error
+            .starts_with("frontend error (state at the end of a frame"));
+        assert!(dump.registers.contains("cycles=9"));
+
+        // the dump is written once
+        assert_eq!(write_last_frame("frontend error"), None);
+    }
+}
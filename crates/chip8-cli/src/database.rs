@@ -0,0 +1,295 @@
+//! ROM database lookup: the metadata of known programs (title, platforms, quirks, clock) is looked up
+//! by the SHA-1 hash of the ROM file in an embedded database, in the format of the
+//! [CHIP-8 database](https://github.com/chip-8/chip-8-database) `programs.json` file.
+//!
+//! The embedded `programs.json` is generated from the `database/programs.json` file of the CHIP-8 database
+//! by `scripts/programs_subset.py`, that keeps the fields read here (title, authors, ROM file names,
+//! platforms, quirks and tick rates)
+
+use crate::config::RomConfig;
+use chip8_core::{Profile, TIMERS_FREQUENCY};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::{debug, info, trace, warn};
+
+/// embedded database (subset of `programs.json` of the CHIP-8 database, see `scripts/programs_subset.py`)
+const PROGRAMS_JSON: &str = include_str!("programs.json");
+
+/// platforms of the CHIP-8 database that run on this interpreter, with the compatibility profile
+/// and the VF reset quirk (`logic` quirk of the database) that emulate them
+const SUPPORTED_PLATFORMS: [(&str, Profile, bool); 8] = [
+    ("originalChip8", Profile::Chip8, true),
+    ("hybridVIP", Profile::Chip8, true),
+    ("modernChip8", Profile::Chip8, false),
+    ("chip8x", Profile::Chip8, true),
+    ("chip48", Profile::Schip, false),
+    ("superchip1", Profile::Schip, false),
+    ("superchip", Profile::Schip, false),
+    ("xochip", Profile::XoChip, false),
+];
+
+// programs of the embedded database, parsed on first use
+lazy_static! {
+    static ref PROGRAMS: Vec<Program> = match serde_json::from_str(PROGRAMS_JSON) {
+        Ok(programs) => programs,
+        Err(e) => panic!("parsing rom database: {e}"),
+    };
+}
+
+/// Program of the database
+#[derive(Deserialize, Debug)]
+struct Program {
+    /// Title of the program
+    title: String,
+
+    /// Authors of the program
+    #[serde(default)]
+    authors: Vec<String>,
+
+    /// ROMs of the program (releases and variants), keyed by SHA-1 hash
+    roms: HashMap<String, Rom>,
+}
+
+/// ROM of a program of the database
+#[derive(Deserialize, Debug)]
+struct Rom {
+    /// Platforms the ROM was written for, from the most to the least compatible
+    #[serde(default)]
+    platforms: Vec<String>,
+
+    /// Quirks of the platforms that differ from the ones of the platform, for this ROM
+    #[serde(rename = "quirkyPlatforms", default)]
+    quirky_platforms: HashMap<String, PlatformQuirks>,
+
+    /// Instructions executed per 60Hz frame expected by the ROM
+    tickrate: Option<u32>,
+}
+
+/// Quirks of a platform of the database (quirks not set keep the value of the platform)
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct PlatformQuirks {
+    /// 8XY6 and 8XYE shift VX in place
+    shift: Option<bool>,
+
+    /// FX55 and FX65 increment I by X instead of X + 1 (not emulated)
+    memory_increment_by_x: Option<bool>,
+
+    /// FX55 and FX65 leave I unchanged
+    memory_leave_i_unchanged: Option<bool>,
+
+    /// DXYN wraps the sprites around the screen edges
+    wrap: Option<bool>,
+
+    /// BNNN jumps to XNN + VX
+    jump: Option<bool>,
+
+    /// DXYN waits for the vertical blank interrupt (not emulated)
+    vblank: Option<bool>,
+
+    /// 8XY1, 8XY2 and 8XY3 reset VF
+    logic: Option<bool>,
+}
+
+/// Looks up a ROM in the database, logs the detected title and returns the options of its profile
+/// (the compatibility profile and the quirks of the first supported platform of the ROM,
+/// the clock expected by the ROM), or None if the ROM is not known
+///
+/// # Arguments
+///
+/// * `sha1` - The SHA-1 hash of the ROM file (lowercase hex digits)
+///
+/// # Panics
+///
+/// The function panics if the embedded database cannot be parsed
+pub fn lookup(sha1: &str) -> Option<RomConfig> {
+    trace!("database::lookup: start");
+
+    let Some((program, rom)) = find(sha1) else {
+        debug!("rom not found in database: {sha1}");
+        trace!("database::lookup: exit");
+        return None;
+    };
+
+    if program.authors.is_empty() {
+        info!("detected rom: {}", program.title);
+    } else {
+        info!(
+            "detected rom: {} by {}",
+            program.title,
+            program.authors.join(", ")
+        );
+    }
+    debug!("rom platforms: {}", rom.platforms.join(", "));
+    let config = rom_config(rom);
+
+    trace!("database::lookup: exit");
+
+    Some(config)
+}
+
+/// Returns the options of the profile of a ROM of the database: the compatibility profile and the quirks
+/// of the first supported platform of the ROM, and the clock expected by the ROM
+///
+/// # Arguments
+///
+/// * `rom` - The ROM of the database
+fn rom_config(rom: &Rom) -> RomConfig {
+    let mut config = RomConfig {
+        clock: rom
+            .tickrate
+            .map(|tickrate| tickrate as f64 * TIMERS_FREQUENCY),
+        ..RomConfig::default()
+    };
+
+    // the platforms are listed from the most compatible one
+    let supported = rom.platforms.iter().find_map(|platform| {
+        SUPPORTED_PLATFORMS
+            .iter()
+            .find(|(name, _, _)| name == platform)
+    });
+    match supported {
+        Some(&(platform, profile, vf_reset)) => {
+            debug!("rom platform {platform}: profile {profile:?}");
+            config.profile = Some(profile);
+            if vf_reset != profile.quirks().vf_reset {
+                config.vf_reset = Some(vf_reset);
+            }
+            if let Some(quirks) = rom.quirky_platforms.get(platform) {
+                apply_quirks(&mut config, quirks);
+            }
+        }
+        None => warn!(
+            "rom written for {}: it may not run correctly on this interpreter",
+            rom.platforms.join(", ")
+        ),
+    }
+
+    config
+}
+
+/// Sets the quirks of a platform of the database in the options of a ROM
+///
+/// # Arguments
+///
+/// * `config` - The options of the ROM
+/// * `quirks` - The quirks of the platform
+fn apply_quirks(config: &mut RomConfig, quirks: &PlatformQuirks) {
+    debug!("rom quirks: {quirks:?}");
+    if let Some(shift) = quirks.shift {
+        config.shift_vy = Some(!shift);
+    }
+    if let Some(unchanged) = quirks.memory_leave_i_unchanged {
+        config.memory_increment = Some(!unchanged);
+    }
+    if let Some(wrap) = quirks.wrap {
+        config.wrap_sprites = Some(wrap);
+    }
+    if let Some(jump) = quirks.jump {
+        config.jump_vx = Some(jump);
+    }
+    if let Some(logic) = quirks.logic {
+        config.vf_reset = Some(logic);
+    }
+    if quirks.memory_increment_by_x == Some(true) || quirks.vblank == Some(true) {
+        warn!("rom quirks not emulated: it may not run correctly on this interpreter");
+    }
+}
+
+/// Returns the title and the platforms of a ROM of the database, or None if the ROM is not known
+///
+/// # Arguments
+///
+/// * `sha1` - The SHA-1 hash of the ROM file (lowercase hex digits)
+///
+/// # Panics
+///
+/// The function panics if the embedded database cannot be parsed
+pub fn describe(sha1: &str) -> Option<(&'static str, &'static [String])> {
+    find(sha1).map(|(program, rom)| (program.title.as_str(), rom.platforms.as_slice()))
+}
+
+/// Returns the program and the ROM of the database with the SHA-1 hash, or None if the ROM is not known
+///
+/// # Arguments
+///
+/// * `sha1` - The SHA-1 hash of the ROM file (lowercase hex digits)
+fn find(sha1: &str) -> Option<(&'static Program, &'static Rom)> {
+    PROGRAMS.iter().find_map(|program| {
+        program
+            .roms
+            .iter()
+            .find(|(hash, _)| hash.eq_ignore_ascii_case(sha1))
+            .map(|(_, rom)| (program, rom))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the ROM of the database in the JSON format of `programs.json`
+    fn rom(json: &str) -> Rom {
+        serde_json::from_str(json).expect("valid rom entry")
+    }
+
+    #[test]
+    fn embedded_database_is_parsed() {
+        assert!(!PROGRAMS.is_empty());
+        let (title, platforms) =
+            describe("1BA58656810B67FD131EB9AF3E3987863BF26C90").expect("IBM logo in database");
+        assert_eq!(title, "IBM Logo");
+        assert_eq!(platforms[0], "originalChip8");
+        assert!(describe("0000000000000000000000000000000000000000").is_none());
+    }
+
+    #[test]
+    fn first_supported_platform_selects_the_profile() {
+        let config = rom_config(&rom(
+            r#"{ "platforms": ["megachip8", "superchip", "xochip"], "tickrate": 30 }"#,
+        ));
+        assert_eq!(config.profile, Some(Profile::Schip));
+        assert_eq!(config.clock, Some(30.0 * TIMERS_FREQUENCY));
+
+        let config = rom_config(&rom(r#"{ "platforms": ["xochip"] }"#));
+        assert_eq!(config.profile, Some(Profile::XoChip));
+        assert_eq!(config.clock, None);
+    }
+
+    #[test]
+    fn vf_reset_follows_the_platform() {
+        // the VF reset of the chip8 profile is the one of originalChip8
+        let config = rom_config(&rom(r#"{ "platforms": ["originalChip8"] }"#));
+        assert_eq!(config.profile, Some(Profile::Chip8));
+        assert_eq!(config.vf_reset, None);
+
+        let config = rom_config(&rom(r#"{ "platforms": ["modernChip8"] }"#));
+        assert_eq!(config.profile, Some(Profile::Chip8));
+        assert_eq!(config.vf_reset, Some(false));
+    }
+
+    #[test]
+    fn quirky_platforms_override_the_profile() {
+        let config = rom_config(&rom(r#"{
+                "platforms": ["superchip", "xochip"],
+                "quirkyPlatforms": {
+                    "superchip": { "shift": false, "memoryLeaveIUnchanged": false, "wrap": true, "jump": false, "logic": true },
+                    "xochip": { "shift": true }
+                }
+            }"#));
+        assert_eq!(config.profile, Some(Profile::Schip));
+        assert_eq!(config.shift_vy, Some(true));
+        assert_eq!(config.memory_increment, Some(true));
+        assert_eq!(config.wrap_sprites, Some(true));
+        assert_eq!(config.jump_vx, Some(false));
+        assert_eq!(config.vf_reset, Some(true));
+    }
+
+    #[test]
+    fn unsupported_platforms_keep_the_default_profile() {
+        let config = rom_config(&rom(r#"{ "platforms": ["megachip8"], "tickrate": 1000 }"#));
+        assert_eq!(config.profile, None);
+        assert_eq!(config.clock, Some(1000.0 * TIMERS_FREQUENCY));
+    }
+}
@@ -0,0 +1,298 @@
+//! Quirk detection (`detect-quirks` subcommand): the quirks test ROM of the Timendus test suite
+//! is run headlessly and the quirk behaviors it reports on the final display are printed.
+//!
+//! The test ROM prints a text line per quirk: the label, a check (or cross) mark and the behavior
+//! measured on the interpreter, `ON` or `OFF`. The behavior is read from the last word of every line:
+//! the letters are separated by a blank column, so `ON` has two glyphs and `OFF` has three
+
+use crate::cli::DetectQuirksArgs;
+use crate::snapshot::display_ascii;
+use chip8_core::{Chip8, Quirks};
+use std::ops::Range;
+use tracing::{debug, trace};
+
+/// memory address of the platform selected in the menu of the test ROM
+const PLATFORM_ADDRESS: u16 = 0x1FF;
+
+/// platform selected in the menu of the test ROM (CHIP-8)
+const PLATFORM_CHIP8: u8 = 1;
+
+/// min number of blank columns between two words of a text line
+const WORD_GAP: usize = 2;
+
+/// Quirk reported by the test ROM
+struct ReportedQuirk {
+    /// Name of the quirk
+    name: &'static str,
+
+    /// One-line description of the behavior reported as `ON`
+    description: &'static str,
+
+    /// Returns the behavior expected with a set of quirks, or None if the behavior is not configurable
+    expected: fn(&Quirks) -> Option<bool>,
+}
+
+/// quirks reported by the test ROM, in display order
+const REPORTED_QUIRKS: [ReportedQuirk; 6] = [
+    ReportedQuirk {
+        name: "vf-reset",
+        description: "8XY1, 8XY2 and 8XY3 reset VF (--quirk-vf-reset)",
+        expected: |quirks| Some(quirks.vf_reset),
+    },
+    ReportedQuirk {
+        name: "memory",
+        description: "FX55 and FX65 increment I (--quirk-memory-increment)",
+        expected: |quirks| Some(quirks.memory_increment),
+    },
+    ReportedQuirk {
+        name: "display-wait",
+        description: "DXYN waits for the vertical blank interrupt",
+        expected: |_| None,
+    },
+    ReportedQuirk {
+        name: "clipping",
+        description: "DXYN clips the sprites at the screen edges (--quirk-wrap-sprites=off)",
+        expected: |quirks| Some(!quirks.sprite_wrapping),
+    },
+    ReportedQuirk {
+        name: "shifting",
+        description: "8XY6 and 8XYE shift VX in place (--quirk-shift-vy=off)",
+        expected: |quirks| Some(!quirks.shift_vy),
+    },
+    ReportedQuirk {
+        name: "jumping",
+        description: "BNNN jumps to XNN + VX (--quirk-jump-vx)",
+        expected: |quirks| Some(quirks.jump_vx),
+    },
+];
+
+/// width of the quirk name column
+const NAME_WIDTH: usize = 14;
+
+/// Runs the quirks test ROM with the quirks and prints the behavior reported for every quirk.
+/// Returns false if the display cannot be read, or if a reported behavior differs from the one
+/// expected with the quirks
+///
+/// # Arguments
+///
+/// * `args` - The detect-quirks subcommand arguments
+/// * `quirks` - The quirks of the instructions (profile and quirk options)
+/// * `seed` - The seed for the random number generator
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the ROM file or during the emulation
+pub fn run(args: &DetectQuirksArgs, quirks: Quirks, seed: u64) -> bool {
+    trace!("detect::run: start");
+
+    let (mut chip8, mut rng) = Chip8::builder().rng_seed(seed).build_with_rng();
+    chip8.set_quirks(quirks);
+    chip8.load_rom(&args.rom);
+    // select the platform without the menu
+    chip8.write_memory(PLATFORM_ADDRESS, PLATFORM_CHIP8);
+
+    for _ in 0..args.cycles {
+        if chip8.idle_loop() {
+            break;
+        }
+        if let Err(e) = chip8.step(&mut rng) {
+            panic!("{e}")
+        }
+    }
+
+    // the quirk lines are the last text lines of the display
+    let lines = text_lines(&chip8);
+    debug!("detect-quirks: {} text lines", lines.len());
+    let Some(lines) = lines
+        .len()
+        .checked_sub(REPORTED_QUIRKS.len())
+        .map(|first| &lines[first..])
+    else {
+        println!("{}", display_ascii(&chip8));
+        println!("the final display (above) is not the quirks report of the test ROM");
+        trace!("detect::run: exit");
+        return false;
+    };
+
+    let mut passed = true;
+    for (quirk, rows) in REPORTED_QUIRKS.iter().zip(lines) {
+        let reported = match last_word_glyphs(&chip8, rows.clone()) {
+            2 => Some(true),
+            3 => Some(false),
+            _ => None,
+        };
+        let status = match reported {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "?",
+        };
+        let note = match (reported, (quirk.expected)(&quirks)) {
+            (None, _) => {
+                passed = false;
+                String::from(" (unreadable)")
+            }
+            (Some(reported), Some(expected)) if reported != expected => {
+                passed = false;
+                format!(" (expected {})", if expected { "on" } else { "off" })
+            }
+            _ => String::new(),
+        };
+        println!(
+            "{:<NAME_WIDTH$} {status:<3} {}{note}",
+            quirk.name, quirk.description
+        );
+    }
+
+    if !passed {
+        println!("{}", display_ascii(&chip8));
+    }
+
+    trace!("detect::run: exit");
+
+    passed
+}
+
+/// Returns the row ranges of the text lines of the display (runs of rows with pixels on)
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+fn text_lines(chip8: &Chip8) -> Vec<Range<usize>> {
+    let (width, height) = chip8.display_size();
+    let mut lines = Vec::new();
+    let mut start = None;
+    for y in 0..=height {
+        let blank = y == height || (0..width).all(|x| !chip8.pixel(x, y));
+        match (start, blank) {
+            (None, false) => start = Some(y),
+            (Some(first), true) => {
+                lines.push(first..y);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    lines
+}
+
+/// Returns the number of glyphs (runs of columns with pixels on) of the last word of a text line
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+/// * `rows` - The rows of the text line
+fn last_word_glyphs(chip8: &Chip8, rows: Range<usize>) -> usize {
+    let (width, _) = chip8.display_size();
+    let column_on = |x: usize| rows.clone().any(|y| chip8.pixel(x, y));
+
+    // scan the columns right to left, counting the glyphs until a word gap
+    let mut glyphs = 0;
+    let mut blank_columns = 0;
+    let mut in_glyph = false;
+    for x in (0..width).rev() {
+        if column_on(x) {
+            if !in_glyph {
+                if glyphs > 0 && blank_columns >= WORD_GAP {
+                    break;
+                }
+                glyphs += 1;
+                in_glyph = true;
+            }
+            blank_columns = 0;
+        } else {
+            in_glyph = false;
+            blank_columns += 1;
+        }
+    }
+
+    glyphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{env, fs};
+
+    /// Returns a ROM drawing a text line of "0" glyphs per report line: a one-glyph label and
+    /// a last word of two glyphs (`ON`) or three glyphs (`OFF`)
+    ///
+    /// # Arguments
+    ///
+    /// * `report` - The behaviors reported on the lines
+    fn report_rom(report: &[bool]) -> Vec<u8> {
+        // V0 = 0, I = glyph of V0
+        let mut rom = vec![0x60, 0x00, 0xF0, 0x29];
+        for (line, &on) in report.iter().enumerate() {
+            let glyphs: &[u8] = if on { &[10, 15] } else { &[10, 15, 20] };
+            // V2 = y (glyph rows and a blank row)
+            rom.extend([0x62, line as u8 * 6]);
+            for x in [0].iter().chain(glyphs) {
+                // V1 = x, DXYN of the glyph
+                rom.extend([0x61, *x, 0xD1, 0x25]);
+            }
+        }
+        // jump to self
+        let end = 0x200 + rom.len() as u16;
+        rom.extend([0x10 | (end >> 8) as u8, end as u8]);
+
+        rom
+    }
+
+    /// Returns the arguments running a ROM written to a temporary file
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the temporary file
+    /// * `rom` - The ROM
+    fn args(name: &str, rom: &[u8]) -> DetectQuirksArgs {
+        let path = env::temp_dir().join(format!("rust-chip-8-{name}-{}", std::process::id()));
+        fs::write(&path, rom).unwrap();
+
+        DetectQuirksArgs {
+            rom: path,
+            cycles: 1000,
+        }
+    }
+
+    #[test]
+    fn text_lines_and_last_words_are_read_from_the_display() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&report_rom(&[true, false]));
+        let mut rng = StdRng::seed_from_u64(0);
+        while !chip8.idle_loop() {
+            chip8.step(&mut rng).unwrap();
+        }
+
+        let lines = text_lines(&chip8);
+        assert_eq!(lines, [0..5, 6..11]);
+        assert_eq!(last_word_glyphs(&chip8, lines[0].clone()), 2);
+        assert_eq!(last_word_glyphs(&chip8, lines[1].clone()), 3);
+    }
+
+    #[test]
+    fn reported_quirks_are_checked_against_the_expected_ones() {
+        // reported behaviors expected with the default quirks (the display wait is not configurable)
+        let report = [true, true, false, true, false, false];
+        let args = args("detect-report", &report_rom(&report));
+
+        assert!(run(&args, Quirks::default(), 0));
+        let jump_vx = Quirks {
+            jump_vx: true,
+            ..Quirks::default()
+        };
+        assert!(!run(&args, jump_vx, 0));
+
+        fs::remove_file(args.rom).unwrap();
+    }
+
+    #[test]
+    fn display_without_the_report_fails() {
+        let args = args("detect-missing", &report_rom(&[true]));
+
+        assert!(!run(&args, Quirks::default(), 0));
+
+        fs::remove_file(args.rom).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Differential testing (`diff` subcommand): the core and the reference interpreter
+//! run the same ROM in lockstep with the same random numbers and keypad input,
+//! and the first instruction after which their states differ is reported
+
+use crate::cli::DiffArgs;
+use crate::reference::Reference;
+use chip8_core::{disassemble, Chip8, DISPLAY_WIDTH, KEYPAD_SIZE};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+};
+use tracing::trace;
+
+/// number of instructions between two changes of the random keypad input
+const RANDOM_INPUT_PERIOD: u64 = 100;
+
+/// Runs the core and the reference interpreter in lockstep and prints the first divergence.
+/// Returns true if the states never diverged
+///
+/// # Arguments
+///
+/// * `args` - The diff subcommand arguments
+/// * `seed` - The seed for the random number generators (instructions and keypad input)
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the ROM file
+/// or if the ROM does not fit into memory
+pub fn run(args: &DiffArgs, seed: u64) -> bool {
+    trace!("differential::run: start");
+
+    let rom = match fs::read(&args.rom) {
+        Ok(rom) => rom,
+        Err(e) => {
+            panic!("reading rom file `{}`: {e}", args.rom.display())
+        }
+    };
+    let mut core = Chip8::new();
+    core.load_rom_bytes(&rom);
+    let mut reference = Reference::new(&rom);
+
+    let mut core_rng = StdRng::seed_from_u64(seed);
+    let mut reference_rng = StdRng::seed_from_u64(seed);
+    let mut input_rng = StdRng::seed_from_u64(seed);
+
+    // panics of the core are reported as divergences: the default panic hook (that exits) is disabled
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut cycles = 0;
+    let mut diverged = false;
+    while cycles < args.cycles {
+        // without input the state cannot change anymore (except for the timers)
+        if !args.random_input && core.idle_loop() && reference.pc == core.pc() {
+            break;
+        }
+
+        if args.random_input && cycles % RANDOM_INPUT_PERIOD == 0 {
+            let keys: u16 = input_rng.gen();
+            core.set_keys(keys);
+            for key in 0..KEYPAD_SIZE {
+                reference.keys[key] = keys & (1 << key) != 0;
+            }
+        }
+
+        let pc = core.pc();
+        let opcode = core.opcode_at(pc);
+        // the core timers are updated by step at the end of every frame: the reference follows
+        let core_result = match panic::catch_unwind(AssertUnwindSafe(|| core.step(&mut core_rng))) {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(e) => Err(e
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| String::from("emulation error"))),
+        };
+        let reference_result = reference.step(&mut reference_rng);
+        if let Ok(true) = core_result {
+            reference.update_timers();
+        }
+        cycles += 1;
+
+        let differences = match (core_result.map(|_| ()), reference_result) {
+            // both interpreters stopped on the same instruction
+            (Err(core_error), Err(reference_error)) => {
+                println!(
+                    "both interpreters stopped at instruction {cycles}: core: {core_error}, reference: {reference_error}"
+                );
+                break;
+            }
+            (Err(e), Ok(())) => vec![format!("core stopped: {e}")],
+            (Ok(()), Err(e)) => vec![format!("reference stopped: {e}")],
+            (Ok(()), Ok(())) => differences(&core, &reference),
+        };
+
+        if !differences.is_empty() {
+            println!(
+                "states diverged after instruction {cycles} (PC {pc:#05X}: {opcode:#06X} `{}`):",
+                disassemble(opcode)
+            );
+            for difference in differences {
+                println!("  {difference}");
+            }
+            diverged = true;
+            break;
+        }
+    }
+
+    panic::set_hook(hook);
+
+    if !diverged {
+        println!("no divergence in {cycles} instructions");
+    }
+    trace!("differential::run: exit");
+
+    !diverged
+}
+
+/// Returns the differences between the core and the reference states (empty if they are equal)
+///
+/// # Arguments
+///
+/// * `core` - The core CHIP-8 instance
+/// * `reference` - The reference machine
+fn differences(core: &Chip8, reference: &Reference) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    if core.pc() != reference.pc {
+        differences.push(format!(
+            "PC: core {:#05X}, reference {:#05X}",
+            core.pc(),
+            reference.pc
+        ));
+    }
+    if core.i() != reference.i {
+        differences.push(format!(
+            "I: core {:#05X}, reference {:#05X}",
+            core.i(),
+            reference.i
+        ));
+    }
+    for (register, (a, b)) in core.v().iter().zip(reference.v).enumerate() {
+        if *a != b {
+            differences.push(format!("V{register:X}: core {a:#04X}, reference {b:#04X}"));
+        }
+    }
+    if core.stack() != reference.stack.as_slice() {
+        differences.push(format!(
+            "stack: core {:X?}, reference {:X?}",
+            core.stack(),
+            reference.stack
+        ));
+    }
+    if core.delay_timer() != reference.delay_timer {
+        differences.push(format!(
+            "delay timer: core {}, reference {}",
+            core.delay_timer(),
+            reference.delay_timer
+        ));
+    }
+    if core.sound_timer() != reference.sound_timer {
+        differences.push(format!(
+            "sound timer: core {}, reference {}",
+            core.sound_timer(),
+            reference.sound_timer
+        ));
+    }
+
+    // the reference has 4KB of memory
+    let memory: Vec<usize> = (0..reference.memory.len().min(core.memory().len()))
+        .filter(|address| core.memory()[*address] != reference.memory[*address])
+        .collect();
+    if let Some(address) = memory.first() {
+        differences.push(format!(
+            "memory: {} bytes differ, first at {address:#05X}: core {:#04X}, reference {:#04X}",
+            memory.len(),
+            core.memory()[*address],
+            reference.memory[*address]
+        ));
+    }
+
+    let pixels: Vec<usize> = core
+        .display_pixels()
+        .iter()
+        .zip(reference.display)
+        .enumerate()
+        .filter(|(_, (a, b))| **a != *b)
+        .map(|(i, _)| i)
+        .collect();
+    if let Some(pixel) = pixels.first() {
+        differences.push(format!(
+            "display: {} pixels differ, first at ({}, {})",
+            pixels.len(),
+            pixel % DISPLAY_WIDTH,
+            pixel / DISPLAY_WIDTH
+        ));
+    }
+
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, path::Path};
+
+    #[test]
+    fn core_and_reference_agree_on_the_ibm_logo() {
+        let args = DiffArgs {
+            rom: Path::new(env!("CARGO_MANIFEST_DIR")).join("../../roms/IBM_logo.ch8"),
+            cycles: 1000,
+            random_input: false,
+        };
+        assert!(run(&args, 10));
+    }
+
+    #[test]
+    fn core_and_reference_agree_with_random_numbers_and_input() {
+        // C0FF (V0 = random), E09E (skip if key V0 pressed), 7101 (V1 += 1), 1200 (jump to 0x200)
+        let rom = [0xC0, 0xFF, 0xE0, 0x9E, 0x71, 0x01, 0x12, 0x00];
+        let path = env::temp_dir().join(format!("rust-chip-8-diff-{}.ch8", std::process::id()));
+        fs::write(&path, rom).unwrap();
+        let args = DiffArgs {
+            rom: path.clone(),
+            cycles: 2000,
+            random_input: true,
+        };
+        let agreed = run(&args, 10);
+        fs::remove_file(path).unwrap();
+
+        assert!(agreed);
+    }
+
+    #[test]
+    fn differences_are_listed() {
+        // 6305 (V3 = 5), A123 (I = 0x123)
+        let rom = [0x63, 0x05, 0xA1, 0x23];
+        let mut core = Chip8::new();
+        core.load_rom_bytes(&rom);
+        let reference = Reference::new(&rom);
+        assert!(differences(&core, &reference).is_empty());
+
+        core.step(&mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(
+            differences(&core, &reference),
+            [
+                "PC: core 0x202, reference 0x200",
+                "V3: core 0x05, reference 0x00"
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Process exit codes: wrapper scripts and CI can tell why the emulation stopped.
+//! The code 2 is used by the argument parser for an invalid command line
+
+use chip8_frontend::StopReason;
+
+/// exit code: the user quit the emulation (e.g. Ctrl-C, window closed) or the subcommand checks passed
+pub const QUIT: i32 = 0;
+
+/// exit code: error of the emulator (e.g. unreadable ROM, invalid configuration, script error)
+/// or failed checks of a subcommand
+pub const ERROR: i32 = 1;
+
+/// exit code: the program exited with the `00FD` instruction
+pub const PROGRAM_EXIT: i32 = 3;
+
+/// exit code: the maximum number of instructions has been executed (`--max-cycles`)
+pub const MAX_CYCLES: i32 = 4;
+
+/// exit code: the maximum host time has elapsed (`--max-seconds`)
+pub const MAX_SECONDS: i32 = 5;
+
+/// exit code: the program jumps to itself (`--on-idle halt`)
+pub const IDLE_LOOP: i32 = 6;
+
+/// exit code: the program waits for a key without keypad input (`--on-idle halt`)
+pub const KEY_WAIT: i32 = 7;
+
+/// exit code: an instruction could not be executed (illegal opcode, stack overflow or underflow,
+/// memory access out of bounds)
+pub const EXECUTION_ERROR: i32 = 8;
+
+/// exit code: a second interrupt signal stopped the process immediately (128 + SIGINT)
+pub const INTERRUPTED: i32 = 130;
+
+/// Returns the exit code of the reason why the emulation stopped
+///
+/// # Arguments
+///
+/// * `reason` - The reason why the emulation stopped
+pub fn code(reason: StopReason) -> i32 {
+    match reason {
+        StopReason::Quit => QUIT,
+        StopReason::ProgramExit => PROGRAM_EXIT,
+        StopReason::MaxCycles => MAX_CYCLES,
+        StopReason::MaxSeconds => MAX_SECONDS,
+        StopReason::IdleLoop => IDLE_LOOP,
+        StopReason::KeyWait => KEY_WAIT,
+        StopReason::ExecutionError => EXECUTION_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_reasons_have_distinct_codes() {
+        let reasons = [
+            StopReason::Quit,
+            StopReason::ProgramExit,
+            StopReason::MaxCycles,
+            StopReason::MaxSeconds,
+            StopReason::IdleLoop,
+            StopReason::KeyWait,
+            StopReason::ExecutionError,
+        ];
+        let codes: Vec<i32> = reasons.into_iter().map(code).collect();
+        assert_eq!(codes, [0, 3, 4, 5, 6, 7, 8]);
+        // the codes of the emulator errors and of the argument parser are not used
+        assert!(!codes.contains(&ERROR));
+        assert!(!codes.contains(&2));
+    }
+}
@@ -0,0 +1,132 @@
+//! Alternate fonts (`--font`) and fontset preview (`font` subcommand): the glyphs of the fontset
+//! (built-in or of a font file) are printed on the terminal
+
+use crate::cli::FontArgs;
+use chip8_core::Chip8;
+use std::{fs, path::Path};
+use tracing::trace;
+
+/// number of glyphs printed on every line of the preview
+const GLYPHS_PER_LINE: usize = 8;
+
+/// number of glyphs of a fontset (0-F)
+const GLYPHS: usize = 16;
+
+/// Reads a font file: 80 bytes (fontset) or 160 bytes (hires fontset)
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the font file
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the font file
+pub fn read(path: &Path) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(font) => font,
+        Err(e) => panic!("reading font file `{}`: {e}", path.display()),
+    }
+}
+
+/// Prints the glyphs of the fontset and of the hires fontset (built-in, or of the font file)
+///
+/// # Arguments
+///
+/// * `args` - The font subcommand arguments
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the font file or if the font is not valid
+pub fn run(args: &FontArgs) {
+    trace!("font::run: start");
+
+    let mut chip8 = Chip8::new();
+    if let Some(path) = &args.font {
+        chip8.set_font(&read(path));
+    }
+
+    println!("fontset (4x5):");
+    print!("{}", glyphs(chip8.font(), 4));
+    println!();
+    println!("hires fontset (8x10):");
+    print!("{}", glyphs(chip8.hires_font(), 8));
+
+    trace!("font::run: exit");
+}
+
+/// Returns the preview of the 16 glyphs of a fontset, `GLYPHS_PER_LINE` glyphs side by side
+///
+/// # Arguments
+///
+/// * `font` - The fontset data (16 glyphs)
+/// * `width` - The width of the glyphs (pixels, from the most significant bit of every byte)
+fn glyphs(font: &[u8], width: usize) -> String {
+    let mut preview = String::new();
+    let height = font.len() / GLYPHS;
+    for first in (0..GLYPHS).step_by(GLYPHS_PER_LINE) {
+        let glyphs = first..first + GLYPHS_PER_LINE;
+        let labels: Vec<String> = glyphs
+            .clone()
+            .map(|glyph| format!("{glyph:<width$X}"))
+            .collect();
+        preview.push_str(labels.join("  ").trim_end());
+        preview.push('\n');
+        for row in 0..height {
+            let rows: Vec<String> = glyphs
+                .clone()
+                .map(|glyph| {
+                    let byte = font[glyph * height + row];
+                    (0..width)
+                        .map(|bit| {
+                            if byte & (0x80 >> bit) != 0 {
+                                '█'
+                            } else {
+                                '·'
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            preview.push_str(&rows.join("  "));
+            preview.push('\n');
+        }
+        preview.push('\n');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_are_previewed_side_by_side() {
+        let chip8 = Chip8::new();
+        let preview = glyphs(chip8.font(), 4);
+        let lines: Vec<&str> = preview.lines().collect();
+
+        // 2 lines of 8 glyphs: a line of labels, 5 rows of pixels and a blank line each
+        assert_eq!(lines.len(), 2 * (1 + 5 + 1));
+        assert_eq!(lines[0], "0     1     2     3     4     5     6     7");
+        assert_eq!(lines[7], "8     9     A     B     C     D     E     F");
+        // top row of the glyphs 0 (0xF0) and 1 (0x20)
+        assert!(lines[1].starts_with("████  ··█·  "));
+        assert_eq!(lines[6], "");
+    }
+
+    #[test]
+    fn hires_glyphs_are_8_pixels_wide() {
+        let chip8 = Chip8::new();
+        let preview = glyphs(chip8.hires_font(), 8);
+        let lines: Vec<&str> = preview.lines().collect();
+
+        assert_eq!(lines.len(), 2 * (1 + 10 + 1));
+        assert_eq!(lines[1].chars().count(), 8 * 8 + 7 * 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "reading font file")]
+    fn missing_font_file_panics() {
+        read(Path::new("/nonexistent/rust-chip-8.font"));
+    }
+}
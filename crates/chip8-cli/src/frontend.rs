@@ -0,0 +1,130 @@
+//! Display frontends of the command line (terminal renderers of chip8-terminal, window renderers of chip8-gpu)
+
+use chip8_frontend::{RenderOptions, Renderer};
+#[cfg(feature = "debugger")]
+use chip8_gpu::DebuggerRenderer;
+#[cfg(feature = "gpu")]
+use chip8_gpu::GpuRenderer;
+use chip8_terminal::{BrailleRenderer, KittyRenderer, SixelRenderer, TerminalRenderer};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::warn;
+
+/// default number of screen pixels drawn for every CHIP-8 pixel (in both directions) by the bitmap renderers
+const BITMAP_SCALE: u32 = 8;
+
+/// Available display frontends
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Frontend {
+    /// Terminal with colored half blocks (2 pixels per cell)
+    Terminal,
+    /// Terminal with Unicode braille patterns (8 pixels per cell)
+    Braille,
+    /// Terminal with sixel graphics support (scaled bitmap)
+    Sixel,
+    /// Terminal with kitty graphics protocol support (scaled bitmap)
+    Kitty,
+    /// GPU accelerated window (requires the `gpu` feature)
+    Gpu,
+    /// Graphical debugger window (requires the `debugger` feature)
+    Debugger,
+}
+
+impl Frontend {
+    /// Returns true if the frontend can be driven by a thread other than the emulation one
+    /// (the debugger holds the execution of the instructions, so it runs with the emulation)
+    pub fn threadable(&self) -> bool {
+        *self != Frontend::Debugger
+    }
+
+    /// Returns a new renderer of the frontend
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The renderer options
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frontend is not available in this build or cannot be initialized
+    pub fn renderer(&self, options: RenderOptions) -> Box<dyn Renderer> {
+        let RenderOptions {
+            palette, ghosting, ..
+        } = options;
+
+        if options.crt && *self != Frontend::Gpu {
+            warn!("CRT effects are available only in the gpu frontend: ignored");
+        }
+        if options.keypad_overlay && matches!(self, Frontend::Braille | Frontend::Sixel) {
+            warn!("keypad overlay is available only in the terminal, gpu and debugger frontends: ignored");
+        }
+        if options.status_bar && matches!(self, Frontend::Braille | Frontend::Sixel) {
+            warn!(
+                "status bar is available only in the terminal, gpu and debugger frontends: ignored"
+            );
+        }
+        if options.scale.is_some()
+            && matches!(
+                self,
+                Frontend::Terminal | Frontend::Braille | Frontend::Debugger
+            )
+        {
+            warn!(
+                "display scaling is available only in the sixel, kitty and gpu frontends: ignored"
+            );
+        }
+        if options.fullscreen && !matches!(self, Frontend::Gpu | Frontend::Debugger) {
+            warn!("fullscreen is available only in the gpu and debugger frontends: ignored");
+        }
+        if options.pause_unfocused && !matches!(self, Frontend::Gpu | Frontend::Debugger) {
+            warn!(
+                "pause when unfocused is available only in the gpu and debugger frontends: ignored"
+            );
+        }
+        let bitmap_scale = options.scale.unwrap_or(BITMAP_SCALE) as usize;
+
+        match self {
+            Frontend::Terminal => Box::new(TerminalRenderer::new(
+                palette,
+                ghosting,
+                options.keypad_overlay,
+                options.status_bar,
+            )),
+            Frontend::Braille => Box::new(BrailleRenderer::new(palette, ghosting)),
+            Frontend::Sixel => Box::new(SixelRenderer::new(palette, ghosting, bitmap_scale)),
+            Frontend::Kitty => {
+                if KittyRenderer::supported() {
+                    Box::new(KittyRenderer::new(palette, ghosting, bitmap_scale))
+                } else {
+                    warn!("terminal does not support kitty graphics protocol: falling back to terminal frontend");
+                    Box::new(TerminalRenderer::new(
+                        palette,
+                        ghosting,
+                        options.keypad_overlay,
+                        options.status_bar,
+                    ))
+                }
+            }
+            #[cfg(feature = "gpu")]
+            Frontend::Gpu => Box::new(GpuRenderer::new(&options)),
+            #[cfg(not(feature = "gpu"))]
+            Frontend::Gpu => {
+                panic!("gpu frontend not available: chip8-cli must be built with the `gpu` feature")
+            }
+            #[cfg(feature = "debugger")]
+            Frontend::Debugger => Box::new(DebuggerRenderer::new(
+                palette,
+                ghosting,
+                options.keymap,
+                options.status_bar,
+                options.fullscreen,
+                options.pause_unfocused,
+                options.session_dir.clone(),
+            )),
+            #[cfg(not(feature = "debugger"))]
+            Frontend::Debugger => {
+                panic!("debugger frontend not available: chip8-cli must be built with the `debugger` feature")
+            }
+        }
+    }
+}
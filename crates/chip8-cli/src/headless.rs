@@ -0,0 +1,10 @@
+//! Headless display renderer (nothing is drawn)
+
+use chip8_frontend::Renderer;
+
+/// Renderer that does not draw the CHIP-8 display, for automated runs (e.g. testing pipelines)
+pub struct HeadlessRenderer;
+
+impl Renderer for HeadlessRenderer {
+    fn render(&mut self, _display: &[bool], _dirty_rows: u64) {}
+}
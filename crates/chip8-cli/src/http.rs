@@ -0,0 +1,359 @@
+//! HTTP state-inspection endpoint (`--http` option): an embedded HTTP server that serves JSON snapshots
+//! of the CHIP-8 state and a PNG image of the display, for remote monitoring of headless instances
+//! (e.g. `curl http://127.0.0.1:8080/registers`) and for live demos in a browser.
+//!
+//! The snapshots are taken by the emulation thread between two instructions (also while paused),
+//! the responses are built by the server thread
+
+use crate::snapshot::display_ascii;
+use chip8_core::Chip8;
+use chip8_frontend::{EmulationStatus, Hotkey, Palette, Renderer};
+use serde::Serialize;
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use tiny_http::{Header, Method, Response, Server};
+use tracing::{debug, info, trace};
+
+/// page served at `/`: the live display and the registers, refreshed by the browser
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>rust-chip-8</title>
+<style>
+body { background: #202020; color: #e0e0e0; font-family: monospace; }
+img { width: 100%; max-width: 1024px; image-rendering: pixelated; }
+</style>
+</head>
+<body>
+<img id="screen" src="/screen.png" alt="CHIP-8 display">
+<pre id="registers"></pre>
+<script>
+const screen = document.getElementById("screen");
+const registers = document.getElementById("registers");
+setInterval(() => { screen.src = "/screen.png?" + Date.now(); }, 100);
+setInterval(async () => {
+    const response = await fetch("/registers");
+    registers.textContent = JSON.stringify(await response.json(), null, 2);
+}, 250);
+</script>
+</body>
+</html>
+"#;
+
+/// Registers snapshot (`/registers`)
+#[derive(Serialize, Debug)]
+struct Registers {
+    /// Program counter
+    pc: u16,
+
+    /// Index register
+    i: u16,
+
+    /// Registers V0-VF
+    v: Vec<u8>,
+
+    /// Stack levels in use (from the bottom to the top of the stack)
+    stack: Vec<u16>,
+
+    /// Delay timer
+    delay_timer: u8,
+
+    /// Sound timer
+    sound_timer: u8,
+
+    /// Number of executed instructions
+    cycles: u64,
+
+    /// Boolean set to true if the emulation is paused
+    paused: bool,
+}
+
+/// Memory snapshot (`/memory`)
+#[derive(Serialize, Debug)]
+struct Memory {
+    /// The CHIP-8 RAM memory
+    memory: Vec<u8>,
+}
+
+/// Display snapshot (`/display`)
+#[derive(Serialize, Debug)]
+struct Display {
+    /// Display width
+    width: usize,
+
+    /// Display height
+    height: usize,
+
+    /// Display rows (`#` if the pixel is on, `.` otherwise)
+    rows: Vec<String>,
+
+    /// Hash of the display (64-bit FNV-1a of the packed rows, hex string)
+    hash: String,
+}
+
+/// Renderer that sends copies of the CHIP-8 instance to the HTTP server when requested
+struct HttpRenderer {
+    /// The wrapped renderer
+    renderer: Box<dyn Renderer>,
+
+    /// Receiver of the snapshot requests of the server (the channel of the copy)
+    requests: Receiver<Sender<Box<Chip8>>>,
+}
+
+impl Renderer for HttpRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        self.renderer.render(display, dirty_rows);
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.renderer.tick(display);
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.renderer.status(status);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        self.renderer.debug(chip8)
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        while let Ok(reply) = self.requests.try_recv() {
+            // the request may be dropped before the reply
+            let _ = reply.send(Box::new(chip8.clone()));
+        }
+
+        self.renderer.control(chip8);
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        self.renderer.hotkeys()
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.renderer.fast_forward()
+    }
+
+    fn has_keypad(&self) -> bool {
+        self.renderer.has_keypad()
+    }
+
+    fn keypad(&self) -> u16 {
+        self.renderer.keypad()
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.renderer.quit_requested()
+    }
+}
+
+/// Starts the HTTP server and returns the renderer that takes the snapshots of the CHIP-8 state.
+/// The requests are served by a background thread
+///
+/// # Arguments
+///
+/// * `address` - The address the server listens on (e.g. `127.0.0.1:8080`)
+/// * `palette` - The colors of the display image
+/// * `renderer` - The renderer of the emulation
+///
+/// # Panics
+///
+/// The function panics in case of errors during creation of the server (e.g. address already in use)
+pub fn attach(address: &str, palette: Palette, renderer: Box<dyn Renderer>) -> Box<dyn Renderer> {
+    trace!("http::attach: start");
+
+    let server = match Server::http(address) {
+        Ok(server) => server,
+        Err(e) => panic!("starting http server on `{address}`: {e}"),
+    };
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || serve(server, sender, palette));
+
+    info!("http server listening: http://{address}/");
+
+    trace!("http::attach: exit");
+
+    Box::new(HttpRenderer { renderer, requests })
+}
+
+/// Serves the requests until the emulation stops
+///
+/// # Arguments
+///
+/// * `server` - The HTTP server
+/// * `sender` - Sender of the snapshot requests to the emulation thread
+/// * `palette` - The colors of the display image
+fn serve(server: Server, sender: Sender<Sender<Box<Chip8>>>, palette: Palette) {
+    for request in server.incoming_requests() {
+        debug!("http request: {} {}", request.method(), request.url());
+
+        let path = request
+            .url()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let (status, content_type, body) = if *request.method() != Method::Get {
+            (405, "text/plain", b"method not allowed\n".to_vec())
+        } else if path == "/" {
+            (
+                200,
+                "text/html; charset=utf-8",
+                INDEX_HTML.as_bytes().to_vec(),
+            )
+        } else {
+            match snapshot(&sender) {
+                Some(chip8) => match response(&path, &chip8, palette) {
+                    Some((content_type, body)) => (200, content_type, body),
+                    None => (404, "text/plain", b"not found\n".to_vec()),
+                },
+                None => (503, "text/plain", b"emulation stopped\n".to_vec()),
+            }
+        };
+
+        let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+            .expect("valid content type header");
+        let response = Response::from_data(body)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            debug!("http response failed: {e}");
+        }
+    }
+}
+
+/// Returns a copy of the CHIP-8 instance taken by the emulation thread, or None if the emulation stopped
+///
+/// # Arguments
+///
+/// * `sender` - Sender of the snapshot requests to the emulation thread
+fn snapshot(sender: &Sender<Sender<Box<Chip8>>>) -> Option<Box<Chip8>> {
+    let (reply, receiver) = mpsc::channel();
+    sender.send(reply).ok()?;
+    receiver.recv().ok()
+}
+
+/// Returns the content type and the body of the response of a path, or None if the path is not found
+///
+/// # Arguments
+///
+/// * `path` - The path of the request
+/// * `chip8` - The snapshot of the CHIP-8 instance
+/// * `palette` - The colors of the display image
+///
+/// # Panics
+///
+/// The function panics in case of errors during the serialization of the snapshots
+fn response(path: &str, chip8: &Chip8, palette: Palette) -> Option<(&'static str, Vec<u8>)> {
+    let json = match path {
+        "/screen.png" => return Some(("image/png", crate::screenshot::png(chip8, palette))),
+        "/registers" => serde_json::to_vec(&Registers {
+            pc: chip8.pc(),
+            i: chip8.i(),
+            v: chip8.v().to_vec(),
+            stack: chip8.stack().to_vec(),
+            delay_timer: chip8.delay_timer(),
+            sound_timer: chip8.sound_timer(),
+            cycles: chip8.cycles(),
+            paused: chip8.paused(),
+        }),
+        "/memory" => serde_json::to_vec(&Memory {
+            memory: chip8.memory().to_vec(),
+        }),
+        "/display" => serde_json::to_vec(&Display {
+            width: chip8.display_size().0,
+            height: chip8.display_size().1,
+            rows: display_ascii(chip8).lines().map(String::from).collect(),
+            hash: format!("{:016x}", chip8.display_hash()),
+        }),
+        _ => return None,
+    };
+
+    match json {
+        Ok(json) => Some(("application/json", json)),
+        Err(e) => panic!("serializing http snapshot: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+    use chip8_frontend::Theme;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance that executed `6A05` (VA = 5) and `A123` (I = 0x123)
+    fn chip8() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x6A, 0x05, 0xA1, 0x23]);
+        let mut rng = StdRng::seed_from_u64(0);
+        chip8.step(&mut rng).unwrap();
+        chip8.step(&mut rng).unwrap();
+        chip8
+    }
+
+    /// Returns the JSON body of the response of a path
+    fn json(path: &str, chip8: &Chip8) -> serde_json::Value {
+        let (content_type, body) = response(path, chip8, Theme::Mono.palette()).unwrap();
+        assert_eq!(content_type, "application/json");
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn registers_are_served() {
+        let registers = json("/registers", &chip8());
+        assert_eq!(registers["pc"], 0x204);
+        assert_eq!(registers["i"], 0x123);
+        assert_eq!(registers["v"][0xA], 5);
+        assert_eq!(registers["cycles"], 2);
+        assert_eq!(registers["paused"], false);
+    }
+
+    #[test]
+    fn memory_and_display_are_served() {
+        let chip8 = chip8();
+        let memory = json("/memory", &chip8);
+        assert_eq!(
+            memory["memory"].as_array().unwrap().len(),
+            chip8.memory().len()
+        );
+        assert_eq!(memory["memory"][0x200], 0x6A);
+
+        let display = json("/display", &chip8);
+        assert_eq!(display["width"], 64);
+        assert_eq!(display["height"], 32);
+        assert_eq!(display["rows"].as_array().unwrap().len(), 32);
+        assert_eq!(display["hash"], format!("{:016x}", chip8.display_hash()));
+    }
+
+    #[test]
+    fn screen_is_served_as_png() {
+        let (content_type, body) =
+            response("/screen.png", &chip8(), Theme::Mono.palette()).unwrap();
+        assert_eq!(content_type, "image/png");
+        assert_eq!(body[..8], *b"\x89PNG\r\n\x1a\n");
+
+        assert!(response("/unknown", &chip8(), Theme::Mono.palette()).is_none());
+    }
+
+    #[test]
+    fn snapshots_are_taken_between_instructions() {
+        let (sender, requests) = mpsc::channel();
+        let mut renderer = HttpRenderer {
+            renderer: Box::new(HeadlessRenderer),
+            requests,
+        };
+
+        let (reply, receiver) = mpsc::channel();
+        sender.send(reply).unwrap();
+        renderer.control(&mut chip8());
+        assert_eq!(receiver.recv().unwrap().pc(), 0x204);
+
+        // no snapshot once the emulation stopped
+        drop(renderer);
+        assert!(snapshot(&sender).is_none());
+    }
+}
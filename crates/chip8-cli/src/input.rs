@@ -0,0 +1,275 @@
+//! Scripted keypad input (`--input-script` option): every line of the input script schedules a key press
+//! or release at an instruction count (e.g. `@1200 press 5`), so keypad-dependent ROMs can be run headlessly
+
+use chip8_core::Chip8;
+use chip8_frontend::{EmulationStatus, Hotkey, Renderer};
+use std::{fs, path::Path};
+use tracing::{debug, trace};
+
+/// Key press or release scheduled by the input script
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct InputEvent {
+    /// Number of instructions executed before the event is applied
+    cycle: u64,
+
+    /// The key (0x0-0xF)
+    key: u8,
+
+    /// Boolean set to true if the key is pressed, false if it is released
+    pressed: bool,
+}
+
+/// Renderer that adds the keys of the input script to the keypad of the wrapped renderer
+struct InputScriptRenderer {
+    /// The wrapped renderer
+    renderer: Box<dyn Renderer>,
+
+    /// The events of the input script, sorted by instruction count
+    events: Vec<InputEvent>,
+
+    /// Index of the next event to apply
+    next: usize,
+
+    /// Keys pressed by the input script (bit n set if key n is pressed)
+    keys: u16,
+
+    /// Boolean set to true if events were applied before the next instruction
+    changed: bool,
+}
+
+impl Renderer for InputScriptRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        self.renderer.render(display, dirty_rows);
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.renderer.tick(display);
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.renderer.status(status);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        // called before every instruction: apply the events due
+        self.changed = false;
+        while let Some(event) = self.events.get(self.next) {
+            if event.cycle > chip8.cycles() {
+                break;
+            }
+            debug!(
+                "input script: {} key {:X} at instruction {}",
+                if event.pressed { "press" } else { "release" },
+                event.key,
+                chip8.cycles()
+            );
+            if event.pressed {
+                self.keys |= 1 << event.key;
+            } else {
+                self.keys &= !(1 << event.key);
+            }
+            self.next += 1;
+            self.changed = true;
+        }
+
+        self.renderer.debug(chip8)
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        self.renderer.control(chip8);
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        self.renderer.hotkeys()
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.renderer.fast_forward()
+    }
+
+    fn has_keypad(&self) -> bool {
+        // the keys can change until the last event is applied (and read by the next instruction)
+        self.renderer.has_keypad() || self.next < self.events.len() || self.changed
+    }
+
+    fn keypad(&self) -> u16 {
+        self.renderer.keypad() | self.keys
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.renderer.quit_requested()
+    }
+}
+
+/// Loads the input script and returns the renderer that applies its key presses and releases
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the input script file
+/// * `renderer` - The renderer of the emulation
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading or parsing of the input script
+pub fn attach(path: &Path, renderer: Box<dyn Renderer>) -> Box<dyn Renderer> {
+    trace!("input::attach: start");
+
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) => panic!("reading input script file: {e}"),
+    };
+    let mut events = Vec::new();
+    for (n, line) in script.lines().enumerate() {
+        match parse_line(line) {
+            Ok(Some(event)) => events.push(event),
+            Ok(None) => {}
+            Err(e) => panic!("parsing input script line {}: {e}", n + 1),
+        }
+    }
+    // events of the same instruction keep the order of the file
+    events.sort_by_key(|event| event.cycle);
+
+    debug!("input script loaded: {} events", events.len());
+
+    trace!("input::attach: exit");
+
+    Box::new(InputScriptRenderer {
+        renderer,
+        events,
+        next: 0,
+        keys: 0,
+        changed: false,
+    })
+}
+
+/// Parses a line of the input script (`@<instructions> press|release <key>`).
+/// Returns None for empty lines and comments (starting with `#`)
+///
+/// # Arguments
+///
+/// * `line` - The line of the input script
+fn parse_line(line: &str) -> Result<Option<InputEvent>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [cycle, action, key] = fields[..] else {
+        return Err(format!(
+            "expected `@<instructions> press|release <key>`, found `{line}`"
+        ));
+    };
+
+    let cycle = match cycle.strip_prefix('@').map(str::parse) {
+        Some(Ok(cycle)) => cycle,
+        _ => {
+            return Err(format!(
+                "invalid instruction count `{cycle}`: expected e.g. `@1200`"
+            ))
+        }
+    };
+    let pressed = match action {
+        "press" => true,
+        "release" => false,
+        _ => {
+            return Err(format!(
+                "invalid action `{action}`: expected `press` or `release`"
+            ))
+        }
+    };
+    let key = match u8::from_str_radix(key, 16) {
+        Ok(key) if key <= 0xF => key,
+        _ => return Err(format!("invalid key `{key}`: expected a hex digit (0-F)")),
+    };
+
+    Ok(Some(InputEvent {
+        cycle,
+        key,
+        pressed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+    use chip8_frontend::{IdlePolicy, RandomMode, Run, RunOptions, StopReason};
+    use std::env;
+
+    #[test]
+    fn lines_are_parsed() {
+        assert_eq!(
+            parse_line("  @1200 press a "),
+            Ok(Some(InputEvent {
+                cycle: 1200,
+                key: 0xA,
+                pressed: true
+            }))
+        );
+        assert_eq!(
+            parse_line("@0 release F"),
+            Ok(Some(InputEvent {
+                cycle: 0,
+                key: 0xF,
+                pressed: false
+            }))
+        );
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("# comment"), Ok(None));
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        assert_eq!(
+            parse_line("@10 press"),
+            Err(String::from(
+                "expected `@<instructions> press|release <key>`, found `@10 press`"
+            ))
+        );
+        assert_eq!(
+            parse_line("10 press 1"),
+            Err(String::from(
+                "invalid instruction count `10`: expected e.g. `@1200`"
+            ))
+        );
+        assert_eq!(
+            parse_line("@10 hold 1"),
+            Err(String::from(
+                "invalid action `hold`: expected `press` or `release`"
+            ))
+        );
+        assert_eq!(
+            parse_line("@10 press 10"),
+            Err(String::from("invalid key `10`: expected a hex digit (0-F)"))
+        );
+    }
+
+    #[test]
+    fn keys_are_pressed_at_the_instruction_counts() {
+        let path = env::temp_dir().join(format!("rust-chip-8-input-{}.txt", std::process::id()));
+        fs::write(&path, "# answer the key wait\n@50 release 7\n@20 press 7\n").unwrap();
+        let mut renderer = attach(&path, Box::new(HeadlessRenderer));
+        fs::remove_file(path).unwrap();
+
+        // F30A (wait for a key into V3), 1202 (jump to itself)
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0xF3, 0x0A, 0x12, 0x02]);
+        let options = RunOptions {
+            stepping: false,
+            seed: 0,
+            random: RandomMode::Seeded,
+            speed: 1.0,
+            uncapped: true,
+            max_cycles: Some(1000),
+            max_seconds: None,
+            on_idle: IdlePolicy::Halt,
+        };
+        let summary = chip8.run(options, &mut *renderer);
+
+        // the key is read when released, then the program halts
+        assert_eq!(summary.stop_reason, StopReason::IdleLoop);
+        assert_eq!(summary.cycles, 51);
+        assert_eq!(chip8.v()[3], 7);
+    }
+}
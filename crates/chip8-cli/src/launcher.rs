@@ -0,0 +1,262 @@
+//! ROM launcher: when the emulator is started with a directory instead of a ROM file, the `.ch8` files
+//! of the directory are listed (in a terminal browser with size, hash and database metadata with the `tui`
+//! feature) and the selected one is run in a child process; the launcher comes back when the game exits
+
+use crate::database;
+use sha1_smol::Sha1;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+use tracing::{debug, info, trace};
+
+/// extension of the ROM files listed by the launcher
+const ROM_EXTENSION: &str = "ch8";
+
+/// ROM file listed by the launcher
+pub struct RomEntry {
+    /// Path of the ROM file
+    pub path: PathBuf,
+
+    /// Size of the ROM file (bytes)
+    pub size: usize,
+
+    /// SHA-1 hash of the ROM file (lowercase hex digits)
+    pub sha1: String,
+
+    /// Title of the program, if the ROM is known by the database
+    pub title: Option<&'static str>,
+
+    /// Platforms the ROM was written for, if the ROM is known by the database
+    pub platforms: &'static [String],
+}
+
+impl RomEntry {
+    /// Returns the file name of the ROM
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Returns the ROM files of the directory, sorted by name
+///
+/// # Arguments
+///
+/// * `dir` - The Path reference that holds the path to the directory
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the directory or of the ROM files
+fn roms(dir: &Path) -> Vec<RomEntry> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => panic!("reading rom directory: {e}"),
+    };
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => panic!("reading rom directory: {e}"),
+        };
+        let is_rom = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(ROM_EXTENSION));
+        if is_rom && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = match fs::read(&path) {
+                Ok(contents) => contents,
+                Err(e) => panic!("reading rom file `{}`: {e}", path.display()),
+            };
+            let sha1 = Sha1::from(&contents).digest().to_string();
+            let (title, platforms) = match database::describe(&sha1) {
+                Some((title, platforms)) => (Some(title), platforms),
+                None => (None, [].as_slice()),
+            };
+
+            RomEntry {
+                path,
+                size: contents.len(),
+                sha1,
+                title,
+                platforms,
+            }
+        })
+        .collect()
+}
+
+/// Lists the ROM files of the directory and runs the selected ones, until the user quits the launcher.
+/// Returns the exit code of the launcher
+///
+/// # Arguments
+///
+/// * `dir` - The Path reference that holds the path to the directory
+///
+/// # Panics
+///
+/// The function panics if the directory has no ROM files, in case of errors during reading of the directory,
+/// of the user input or during start of the emulator
+pub fn run(dir: &Path) -> i32 {
+    trace!("launcher::run: start");
+
+    let roms = roms(dir);
+    if roms.is_empty() {
+        panic!("no `.{ROM_EXTENSION}` rom files in `{}`", dir.display());
+    }
+
+    let mut selected = 0;
+    let mut status = None;
+    loop {
+        #[cfg(feature = "tui")]
+        let choice = crate::browser::select(dir, &roms, selected, status.as_deref());
+        #[cfg(not(feature = "tui"))]
+        let choice = prompt(dir, &roms, selected, status.as_deref());
+
+        let Some(choice) = choice else {
+            break;
+        };
+        selected = choice;
+        status = Some(launch(&roms[selected]));
+    }
+
+    trace!("launcher::run: exit");
+
+    crate::exit::QUIT
+}
+
+/// Runs the emulator on the ROM in a child process, with the same options, and returns its exit status
+///
+/// # Arguments
+///
+/// * `rom` - The ROM to run
+///
+/// # Panics
+///
+/// The function panics in case of errors during start of the emulator
+fn launch(rom: &RomEntry) -> String {
+    info!("launching rom: {} (sha1 {})", rom.path.display(), rom.sha1);
+
+    let executable = match env::current_exe() {
+        Ok(executable) => executable,
+        Err(e) => panic!("launching rom: {e}"),
+    };
+    // the last ROM file option overrides the directory
+    let status = process::Command::new(executable)
+        .args(env::args_os().skip(1))
+        .arg("--rom-file")
+        .arg(&rom.path)
+        .status();
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => panic!("launching rom: {e}"),
+    };
+    debug!("rom exited: {status}");
+
+    match status.code() {
+        Some(code) => format!("{}: exit code {code}", rom.name()),
+        None => format!("{}: {status}", rom.name()),
+    }
+}
+
+/// Lists the ROM files on the terminal and returns the index of the one selected by the user
+/// (an empty line selects the default one), or None if the user quits the launcher
+///
+/// # Arguments
+///
+/// * `dir` - The Path reference that holds the path to the directory
+/// * `roms` - The ROM files of the directory
+/// * `selected` - The index of the default ROM
+/// * `status` - The exit status of the last ROM run
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the user input
+#[cfg(not(feature = "tui"))]
+fn prompt(dir: &Path, roms: &[RomEntry], selected: usize, status: Option<&str>) -> Option<usize> {
+    use std::io::{self, Write};
+
+    let mut stdout = io::stdout();
+    if let Some(status) = status {
+        println!("{status}");
+    }
+    println!("ROMs in {}:", dir.display());
+    for (n, rom) in roms.iter().enumerate() {
+        let name = rom.name();
+        match rom.title {
+            Some(title) => println!(
+                "{:>4}  {name:<32} {:>5} B  {title} [{}]",
+                n + 1,
+                rom.size,
+                rom.platforms.join(", ")
+            ),
+            None => println!("{:>4}  {name:<32} {:>5} B", n + 1, rom.size),
+        }
+    }
+
+    loop {
+        print!(
+            "select a ROM [1-{}] (default {}) or [q] quit: ",
+            roms.len(),
+            selected + 1
+        );
+        if let Err(e) = stdout.flush() {
+            panic!("writing launcher menu: {e}")
+        }
+
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => panic!("reading launcher input: {e}"),
+        }
+        match line.trim().parse::<usize>() {
+            Ok(n) if (1..=roms.len()).contains(&n) => return Some(n - 1),
+            _ if line.trim().is_empty() => return Some(selected),
+            _ if line.trim() == "q" => return None,
+            _ => println!("invalid selection `{}`", line.trim()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_files_are_listed_with_their_metadata() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-launcher-{}", std::process::id()));
+        fs::create_dir_all(dir.join("subdir.ch8")).unwrap();
+        fs::write(dir.join("b.ch8"), [0x12, 0x00]).unwrap();
+        fs::write(dir.join("notes.txt"), "not a rom").unwrap();
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../roms/IBM_logo.ch8"),
+            dir.join("A.CH8"),
+        )
+        .unwrap();
+
+        let roms = roms(&dir);
+        let names: Vec<String> = roms.iter().map(RomEntry::name).collect();
+        assert_eq!(names, ["A.CH8", "b.ch8"]);
+
+        // the known ROM gets the title and the platforms of the database
+        assert_eq!(roms[0].title, Some("IBM Logo"));
+        assert_eq!(roms[0].platforms, ["originalChip8", "modernChip8"]);
+        assert_eq!(roms[1].size, 2);
+        assert_eq!(roms[1].sha1, Sha1::from([0x12, 0x00]).digest().to_string());
+        assert_eq!(roms[1].title, None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,469 @@
+//! # rust-chip-8
+//!
+//! `rust-chip-8` is a simple implementation of CHIP-8 written in Rust for fun and training purposes
+
+mod bench;
+#[cfg(feature = "tui")]
+mod browser;
+mod cli;
+mod config;
+mod conformance;
+mod console;
+mod control;
+mod crash;
+mod database;
+mod detect;
+mod differential;
+mod exit;
+mod font;
+mod frontend;
+mod headless;
+#[cfg(feature = "http")]
+mod http;
+mod input;
+mod launcher;
+mod quirks;
+mod recent;
+mod reference;
+mod report;
+mod rpl;
+mod screenshot;
+#[cfg(feature = "scripting")]
+mod script;
+mod signals;
+mod snapshot;
+mod threaded;
+
+use crate::cli::{Cli, Command};
+use crate::config::{Config, RomConfig};
+use crate::crash::CrashDump;
+use crate::frontend::Frontend;
+use crate::headless::HeadlessRenderer;
+use crate::recent::Recent;
+use crate::report::Report;
+use crate::threaded::ThreadedRenderer;
+use chip8_core::{Chip8, BEEP_FREQUENCY, MAX_VOLUME, TIMERS_FREQUENCY};
+#[cfg(feature = "audio")]
+use chip8_frontend::AudioOutput;
+use chip8_frontend::{
+    Palette, RenderOptions, Renderer, Run, RunOptions, RunSummary, Theme, WavRecorder,
+};
+use lazy_static::lazy_static;
+use std::{panic, thread};
+use tracing::{debug, error, info, trace, warn};
+
+/// directory of the debugger session files (in the state directory)
+const DEBUGGER_DIR_NAME: &str = "debugger";
+
+// static that contains CLI args
+lazy_static! {
+    static ref ARGS: Cli = Cli::parse_opts();
+}
+
+// static that contains configuration file options
+lazy_static! {
+    static ref CONFIG: Config = Config::load(ARGS.config.as_deref());
+}
+
+fn main() {
+    // initialize args
+    lazy_static::initialize(&ARGS);
+    // initialize console
+    console::init();
+
+    trace!("main thread: executing...");
+
+    // panics will use tracing::error for printing panic info
+    // and will exit with the error code
+    panic::set_hook(Box::new(|panic_info| {
+        error!("{}", panic_info.to_string());
+        // the state of the emulation at the end of the last frame, if any
+        if let Some(path) = crash::write_last_frame(&panic_info.to_string()) {
+            error!("crash dump written: {}", path.display());
+        }
+        console::flush();
+        std::process::exit(exit::ERROR);
+    }));
+
+    // validate args
+    ARGS.validate();
+
+    debug!("args: {:?}", *ARGS);
+
+    // load configuration file
+    lazy_static::initialize(&CONFIG);
+
+    // run the subcommand instead of the ROM file
+    if let Some(command) = &ARGS.command {
+        let passed = match command {
+            Command::Test(args) => conformance::run(args, ARGS.random_seed),
+            Command::Diff(args) => differential::run(args, ARGS.random_seed),
+            Command::Snapshot(args) => snapshot::run(args, ARGS.random_seed),
+            Command::Bench(args) => {
+                bench::run(args, ARGS.random_seed);
+                true
+            }
+            Command::Font(args) => {
+                font::run(args);
+                true
+            }
+            Command::DetectQuirks(args) => detect::run(args, ARGS.quirks(), ARGS.random_seed),
+        };
+        console::flush();
+        std::process::exit(if passed { exit::QUIT } else { exit::ERROR });
+    }
+
+    // ROM file options (ROM section or sidecar file of the configuration)
+    // a directory of ROM files: the launcher runs the ROMs selected by the user
+    if let Some(dir) = ARGS.rom.as_deref().filter(|rom| rom.is_dir()) {
+        let code = launcher::run(dir);
+        console::flush();
+        std::process::exit(code);
+    }
+
+    // print the supported quirks
+    if ARGS.list_quirks {
+        quirks::print();
+        console::flush();
+        std::process::exit(exit::QUIT);
+    }
+
+    // print the recently played ROMs, or run the most recent one again
+    let mut recent = Recent::load();
+    if ARGS.recent {
+        recent.print();
+        console::flush();
+        std::process::exit(exit::QUIT);
+    }
+    let rom = if ARGS.last {
+        match recent.last() {
+            Some(rom) => Some(rom.to_path_buf()),
+            None => panic!("no recently played rom"),
+        }
+    } else {
+        ARGS.rom.clone()
+    };
+    let rom = rom.as_deref();
+    let dump = ARGS.load_dump.as_deref().map(CrashDump::load);
+    let rom_config = match rom {
+        Some(rom) => CONFIG.rom_config(rom),
+        None => RomConfig::default(),
+    };
+
+    // create display renderer (command line options take precedence over configuration file)
+    let palette = Palette::new(
+        ARGS.theme.or(CONFIG.display.theme).unwrap_or(Theme::Mono),
+        ARGS.fg_color.or(CONFIG.display.foreground),
+        ARGS.bg_color.or(CONFIG.display.background),
+    );
+    let options = RenderOptions {
+        palette,
+        ghosting: ARGS.ghosting.or(CONFIG.display.ghosting).unwrap_or(0),
+        crt: ARGS.crt || CONFIG.display.crt.unwrap_or(false),
+        keypad_overlay: ARGS.keypad_overlay || CONFIG.display.keypad_overlay.unwrap_or(false),
+        status_bar: ARGS.status_bar || CONFIG.display.status_bar.unwrap_or(false),
+        scale: ARGS.scale.or(CONFIG.display.scale),
+        fullscreen: ARGS.fullscreen || CONFIG.display.fullscreen.unwrap_or(false),
+        pause_unfocused: ARGS.pause_unfocused || CONFIG.display.pause_unfocused.unwrap_or(false),
+        keymap: ARGS
+            .keymap
+            .or(rom_config.keymap)
+            .or(CONFIG.input.keymap)
+            .unwrap_or_default(),
+        session_dir: config::state_dir().map(|dir| dir.join(DEBUGGER_DIR_NAME)),
+    };
+    let frontend = ARGS
+        .frontend
+        .or(CONFIG.display.frontend)
+        .unwrap_or(Frontend::Terminal);
+    let renderer: Box<dyn Renderer> = if ARGS.headless {
+        Box::new(HeadlessRenderer)
+    } else {
+        frontend.renderer(options)
+    };
+
+    // create CHIP-8 instance
+    let mut chip8 = Chip8::new();
+
+    if let Some(clock) = ARGS.clock.or(rom_config.clock) {
+        chip8.set_clock_frequency(clock);
+    }
+    if let Some(timing) = ARGS.timing.or(rom_config.timing) {
+        chip8.set_instruction_timing(timing);
+    }
+    if let Some(sample) = ARGS.trace_sample {
+        chip8.set_trace_sample(sample);
+    }
+    if let Some(depth) = ARGS.history_depth {
+        chip8.set_history_depth(depth);
+    }
+    if let Some(size) = ARGS.memory_size.or(rom_config.memory_size) {
+        chip8.set_memory_size(size);
+    }
+    if let Some(address) = ARGS.start_address.or(rom_config.start_address) {
+        chip8.set_start_address(address);
+    }
+    if let Some(path) = &ARGS.font {
+        chip8.set_font(&font::read(path));
+    }
+    // the quirk flags override the profile
+    if let Some(profile) = ARGS.profile.or(rom_config.profile) {
+        chip8.set_profile(profile);
+    }
+    if let Some(wrap) = ARGS.quirk_wrap_sprites.or(rom_config.wrap_sprites) {
+        chip8.set_sprite_wrapping(wrap);
+    }
+    if let Some(half) = ARGS
+        .quirk_half_pixel_scroll
+        .or(rom_config.half_pixel_scroll)
+    {
+        chip8.set_half_pixel_scroll(half);
+    }
+    if let Some(tall) = ARGS
+        .quirk_lores_tall_sprites
+        .or(rom_config.lores_tall_sprites)
+    {
+        chip8.set_lores_tall_sprites(tall);
+    }
+    if let Some(count) = ARGS.quirk_collision_rows.or(rom_config.collision_rows) {
+        chip8.set_collision_rows(count);
+    }
+    if let Some(reset) = ARGS.quirk_vf_reset.or(rom_config.vf_reset) {
+        chip8.set_vf_reset(reset);
+    }
+    if let Some(shift_vy) = ARGS.quirk_shift_vy.or(rom_config.shift_vy) {
+        chip8.set_shift_vy(shift_vy);
+    }
+    if let Some(increment) = ARGS.quirk_memory_increment.or(rom_config.memory_increment) {
+        chip8.set_memory_increment(increment);
+    }
+    if let Some(jump_vx) = ARGS.quirk_jump_vx.or(rom_config.jump_vx) {
+        chip8.set_jump_vx(jump_vx);
+    }
+    if let Some(policy) = ARGS.machine_code.or(rom_config.machine_code) {
+        chip8.set_machine_code_policy(policy);
+    }
+    if let Some(policy) = ARGS.self_modifying_code {
+        chip8.set_self_modifying_code_policy(policy);
+    }
+    if ARGS.warn_uninitialized {
+        chip8.set_uninitialized_read_warnings(true);
+    }
+    for range in &ARGS.trace_memory {
+        chip8.trace_memory(range.clone());
+    }
+    if !ARGS.trace_memory.is_empty() && (ARGS.headless || frontend != Frontend::Debugger) {
+        warn!("the memory access log is shown only in the debugger frontend");
+    }
+
+    // load ROM file and its RPL user flags, or restore the crash dump (paused, for inspection)
+    let rpl_flags = match (rom, &dump) {
+        (Some(rom), _) => {
+            chip8.load_rom(rom);
+            Some(rpl::load(&mut chip8))
+        }
+        (None, Some(dump)) => {
+            dump.restore(&mut chip8);
+            chip8.pause();
+            info!("crash dump restored (paused): {}", dump.error);
+            info!("registers: {}", dump.registers);
+            info!("last instructions:\n{}", dump.history.join("\n"));
+            None
+        }
+        (None, None) => {
+            unreachable!("the ROM file or the crash dump is required without subcommands")
+        }
+    };
+
+    // keep the state of every frame for the crash dump written by the panic hook
+    crash::track(&mut chip8, rom);
+
+    // record the beeper audio of every frame
+    let beep_wave = ARGS
+        .beep_wave
+        .or(CONFIG.audio.beep_wave)
+        .unwrap_or_default();
+    let beep_freq = ARGS
+        .beep_freq
+        .or(CONFIG.audio.beep_freq)
+        .unwrap_or(BEEP_FREQUENCY);
+    let volume = ARGS.volume.or(CONFIG.audio.volume).unwrap_or(MAX_VOLUME);
+    if let Some(path) = &ARGS.record_wav {
+        let mut recorder = WavRecorder::new(path);
+        recorder.set_tone(beep_wave, beep_freq);
+        recorder.set_volume(volume);
+        // the muted beeper is recorded as silence, keeping the audio synchronized
+        chip8.on_frame(move |chip8| {
+            recorder.record(chip8.beeping() && !chip8.muted(), 1.0 / TIMERS_FREQUENCY)
+        });
+    }
+    // play the beeper on the audio device (kept open until the end of the emulation)
+    #[cfg(feature = "audio")]
+    let _audio = if ARGS.headless {
+        None
+    } else {
+        match AudioOutput::new(beep_wave, beep_freq, volume) {
+            Ok(audio) => {
+                audio.play(&mut chip8);
+                Some(audio)
+            }
+            Err(e) => {
+                warn!("no live audio: {e}");
+                None
+            }
+        }
+    };
+    chip8.set_muted(CONFIG.audio.muted.unwrap_or(false));
+    chip8.on_sound_start(|_| debug!("beeper: on"));
+    chip8.on_sound_stop(|_| debug!("beeper: off"));
+
+    // start emulation: in its own thread, while the frontend is driven by the main thread (window frontends
+    // must stay on it), or in the main thread together with the frontend
+    let speed = ARGS.speed.or(rom_config.speed).unwrap_or(1.0);
+    let threaded = ARGS.threaded || CONFIG.display.threaded.unwrap_or(false);
+    if threaded && !frontend.threadable() {
+        warn!("the debugger frontend runs in the emulation thread: --threaded ignored");
+    }
+    let summary = if threaded && frontend.threadable() {
+        let (renderer, frontend) = ThreadedRenderer::new(renderer);
+        let chip8 = &mut chip8;
+        thread::scope(|scope| {
+            let emulation = match thread::Builder::new()
+                .name(String::from("emulation"))
+                .spawn_scoped(scope, move || {
+                    emulate(chip8, Box::new(renderer), palette, speed)
+                }) {
+                Ok(emulation) => emulation,
+                Err(e) => panic!("starting emulation thread: {e}"),
+            };
+            frontend.run();
+            match emulation.join() {
+                Ok(result) => result,
+                Err(e) => panic::resume_unwind(e),
+            }
+        })
+    } else {
+        emulate(&mut chip8, renderer, palette, speed)
+    };
+
+    info!(
+        "emulation stopped ({}): {} instructions in {:.2} s ({:.0} instructions per second), PC {:#05X}",
+        summary.stop_reason,
+        summary.cycles,
+        summary.elapsed.as_secs_f64(),
+        summary.cycles as f64 / summary.elapsed.as_secs_f64(),
+        chip8.pc()
+    );
+    // the error has been logged with the last executed instructions
+    if let Some(e) = summary.error {
+        let path = CrashDump::new(&chip8, &e.to_string(), rom).write();
+        error!("crash dump written: {}", path.display());
+    }
+    info!("timing: {}", summary.timing);
+    info!("session: {}", summary.session);
+
+    // keep the mute state toggled at runtime (F9) for the next sessions
+    if chip8.muted() != CONFIG.audio.muted.unwrap_or(false) {
+        match config::save_muted(ARGS.config.as_deref(), chip8.muted()) {
+            Ok(path) => info!("mute state saved: {}", path.display()),
+            Err(e) => warn!("saving mute state: {e}"),
+        }
+    }
+
+    // save the RPL user flags changed by the program (SUPER-CHIP save data)
+    if rpl_flags.is_some_and(|flags| flags != *chip8.rpl_flags()) {
+        rpl::save(&chip8);
+    }
+
+    // keep the ROM in the recently played list
+    if let Some(rom) = rom {
+        recent.record(rom, summary.elapsed);
+    }
+
+    // print the machine-readable report
+    if let Some(format) = ARGS.report {
+        Report::new(&chip8, &summary).print(format);
+    }
+
+    trace!("main thread: exit");
+    console::flush();
+    std::process::exit(exit::code(summary.stop_reason));
+}
+
+/// Attaches the script, the input script, the control socket, the HTTP endpoint and the signal handlers
+/// to the renderer, runs the emulation and returns its summary with the last instructions executed
+/// (the renderer is dropped first, e.g. restoring the terminal)
+///
+/// # Arguments
+///
+/// * `chip8` - Mutable reference to the CHIP-8 instance, with the ROM loaded
+/// * `renderer` - The display renderer
+/// * `palette` - The colors used to draw the pixels (screenshots)
+/// * `speed` - The emulation speed multiplier
+///
+/// # Panics
+///
+/// The function panics in case of errors during setup of the attached controllers
+fn emulate(
+    chip8: &mut Chip8,
+    mut renderer: Box<dyn Renderer>,
+    palette: Palette,
+    speed: f64,
+) -> RunSummary {
+    // attach the script (its keys are added to the keypad of the renderer)
+    if let Some(path) = &ARGS.script {
+        #[cfg(feature = "scripting")]
+        {
+            renderer = script::attach(path, chip8, renderer);
+        }
+        #[cfg(not(feature = "scripting"))]
+        panic!(
+            "script {} not available: rust-chip-8 must be built with the `scripting` feature",
+            path.display()
+        );
+    }
+
+    // schedule the keys of the input script
+    if let Some(path) = &ARGS.input_script {
+        renderer = input::attach(path, renderer);
+    }
+
+    // accept the commands of the control socket
+    if let Some(path) = &ARGS.control_socket {
+        renderer = control::attach(path, palette, renderer);
+    }
+
+    // serve the snapshots of the state over HTTP
+    if let Some(address) = &ARGS.http {
+        #[cfg(feature = "http")]
+        {
+            renderer = http::attach(address, palette, renderer);
+        }
+        #[cfg(not(feature = "http"))]
+        panic!(
+            "http server {address} not available: rust-chip-8 must be built with the `http` feature"
+        );
+    }
+
+    // stop gracefully on SIGINT/SIGTERM, dump the state on SIGUSR1
+    renderer = signals::attach(renderer);
+
+    let summary = chip8.run(
+        RunOptions {
+            stepping: ARGS.stepping,
+            seed: ARGS.random_seed,
+            random: ARGS.random.unwrap_or_default(),
+            speed,
+            uncapped: ARGS.uncapped,
+            max_cycles: ARGS.max_cycles,
+            max_seconds: ARGS.max_seconds,
+            on_idle: ARGS.on_idle.unwrap_or_default(),
+        },
+        renderer.as_mut(),
+    );
+
+    // restore the terminal before printing the summary
+    drop(renderer);
+
+    summary
+}
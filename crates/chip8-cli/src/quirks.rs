@@ -0,0 +1,151 @@
+//! Supported quirks (`--list-quirks`): the quirks of the instructions are printed
+//! with their default in every compatibility profile
+
+use chip8_core::{Profile, Quirks, Variants};
+use tracing::trace;
+
+/// Quirk of the instructions
+struct Quirk {
+    /// Name of the quirk (`--quirk-<name>` flag)
+    name: &'static str,
+
+    /// One-line description of the quirk
+    description: &'static str,
+
+    /// Returns true if the quirk is enabled in a set of quirks
+    enabled: fn(&Quirks) -> bool,
+}
+
+/// supported quirks
+const QUIRKS: [Quirk; 8] = [
+    Quirk {
+        name: "wrap-sprites",
+        description: "DXYN wraps the sprites around the screen edges instead of clipping them",
+        enabled: |quirks| quirks.sprite_wrapping,
+    },
+    Quirk {
+        name: "half-pixel-scroll",
+        description: "the scroll instructions scroll the lores display by half the distance",
+        enabled: |quirks| quirks.half_pixel_scroll,
+    },
+    Quirk {
+        name: "lores-tall-sprites",
+        description: "DXY0 draws 8x16 sprites in lores mode instead of 16x16 sprites",
+        enabled: |quirks| quirks.lores_tall_sprites,
+    },
+    Quirk {
+        name: "collision-rows",
+        description: "DXYN sets VF to the number of collision and clipped rows in hires mode",
+        enabled: |quirks| quirks.collision_rows,
+    },
+    Quirk {
+        name: "vf-reset",
+        description: "8XY1, 8XY2 and 8XY3 reset VF",
+        enabled: |quirks| quirks.vf_reset,
+    },
+    Quirk {
+        name: "shift-vy",
+        description: "8XY6 and 8XYE shift VY into VX instead of shifting VX in place",
+        enabled: |quirks| quirks.shift_vy,
+    },
+    Quirk {
+        name: "memory-increment",
+        description: "FX55 and FX65 increment I",
+        enabled: |quirks| quirks.memory_increment,
+    },
+    Quirk {
+        name: "jump-vx",
+        description: "BNNN jumps to XNN + VX instead of NNN + V0",
+        enabled: |quirks| quirks.jump_vx,
+    },
+];
+
+/// width of the quirk name column
+const NAME_WIDTH: usize = 20;
+
+/// Prints the supported quirks: the name, the default of every compatibility profile and the description
+pub fn print() {
+    trace!("quirks::print: start");
+
+    for line in table() {
+        println!("{line}");
+    }
+
+    trace!("quirks::print: exit");
+}
+
+/// Returns the lines of the supported quirks table, starting with the header
+fn table() -> Vec<String> {
+    let profiles: Vec<(&str, Quirks)> = Profile::VARIANTS
+        .iter()
+        .map(|profile| (profile.name(), profile.quirks()))
+        .collect();
+
+    let mut header = format!("{:<NAME_WIDTH$}", "QUIRK");
+    for (name, _) in &profiles {
+        header += &format!(" {:<8}", name.to_uppercase());
+    }
+    let mut lines = vec![format!("{header} DESCRIPTION")];
+    for quirk in &QUIRKS {
+        let mut line = format!("{:<NAME_WIDTH$}", quirk.name);
+        for (_, quirks) in &profiles {
+            line += &format!(" {:<8}", if (quirk.enabled)(quirks) { "on" } else { "off" });
+        }
+        lines.push(format!("{line} {}", quirk.description));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    /// Returns the columns of a line of the table
+    fn columns(line: &str) -> Vec<&str> {
+        line.split_whitespace().collect()
+    }
+
+    #[test]
+    fn table_has_a_line_per_quirk() {
+        let table = table();
+        assert_eq!(table.len(), QUIRKS.len() + 1);
+        assert_eq!(
+            columns(&table[0])[..Profile::VARIANTS.len() + 1],
+            ["QUIRK", "CHIP8", "SCHIP", "XO-CHIP"]
+        );
+    }
+
+    #[test]
+    fn table_shows_the_defaults_of_the_profiles() {
+        let table = table();
+        let line = table
+            .iter()
+            .find(|line| line.starts_with("vf-reset "))
+            .unwrap();
+        assert_eq!(columns(line)[..4], ["vf-reset", "on", "off", "off"]);
+
+        let line = table
+            .iter()
+            .find(|line| line.starts_with("jump-vx "))
+            .unwrap();
+        assert_eq!(columns(line)[..4], ["jump-vx", "off", "on", "off"]);
+    }
+
+    #[test]
+    fn quirk_names_are_cli_flags() {
+        for quirk in &QUIRKS {
+            for value in [true, false] {
+                let flag = format!(
+                    "--quirk-{}={}",
+                    quirk.name,
+                    if value { "on" } else { "off" }
+                );
+                let cli = Cli::try_parse_from(["rust-chip-8", "-f", "game.ch8", &flag]).unwrap();
+                assert_eq!((quirk.enabled)(&cli.quirks()), value, "{flag}");
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Recently played ROMs: the path, the last time played and the total play time of the last ROMs run
+//! are kept in a TOML file, by default located at `$XDG_STATE_HOME/rust-chip-8/recent.toml`
+//! (or `$HOME/.local/state/rust-chip-8/recent.toml`), so that `--recent` can print them
+//! and `--last` can run the most recent one again
+
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, trace, warn};
+
+/// recently played ROMs file name
+const RECENT_FILE_NAME: &str = "recent.toml";
+
+/// number of ROMs kept in the recently played list
+const RECENT_LIMIT: usize = 10;
+
+/// Recently played ROMs file structure
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Recent {
+    /// Recently played ROMs, from the most recent one
+    #[serde(default)]
+    roms: Vec<RecentRom>,
+}
+
+/// Recently played ROM
+#[derive(Serialize, Deserialize, Debug)]
+struct RecentRom {
+    /// Path of the ROM file
+    path: PathBuf,
+
+    /// Last time the ROM was played (seconds since the Unix epoch)
+    last_played: u64,
+
+    /// Total play time (seconds)
+    play_time: f64,
+}
+
+impl Recent {
+    /// Loads the recently played ROMs file (an empty list if the file does not exist or is not valid)
+    pub fn load() -> Self {
+        trace!("Recent::load: start");
+
+        let Some(path) = default_path() else {
+            debug!("state directory unknown: no recently played roms");
+            return Self::default();
+        };
+        let recent = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                warn!("parsing recently played roms file: {e}");
+                Self::default()
+            }),
+            Err(_) => {
+                debug!("recently played roms file not found: {}", path.display());
+                Self::default()
+            }
+        };
+
+        trace!("Recent::load: exit");
+
+        recent
+    }
+
+    /// Returns the path of the most recently played ROM, if any
+    pub fn last(&self) -> Option<&Path> {
+        self.roms.first().map(|rom| rom.path.as_path())
+    }
+
+    /// Moves the ROM to the top of the list, adds the play time and writes the recently played ROMs file
+    /// (errors are logged: they do not stop the emulator)
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The Path reference that holds the path to the ROM file
+    /// * `played` - The time the ROM was played
+    pub fn record(&mut self, rom: &Path, played: Duration) {
+        trace!("Recent::record: start");
+
+        // the ROM can be run again from any working directory
+        let path = fs::canonicalize(rom).unwrap_or_else(|_| rom.to_path_buf());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        self.add(path, played, now);
+
+        if let Err(e) = self.save() {
+            warn!("writing recently played roms file: {e}");
+        }
+
+        trace!("Recent::record: exit");
+    }
+
+    /// Moves the ROM to the top of the list and adds the play time (the oldest ROMs beyond
+    /// `RECENT_LIMIT` are dropped)
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the ROM file
+    /// * `played` - The time the ROM was played
+    /// * `now` - The present time (seconds since the Unix epoch)
+    fn add(&mut self, path: PathBuf, played: Duration, now: u64) {
+        let play_time = match self.roms.iter().position(|recent| recent.path == path) {
+            Some(n) => self.roms.remove(n).play_time,
+            None => 0.0,
+        };
+        self.roms.insert(
+            0,
+            RecentRom {
+                path,
+                last_played: now,
+                play_time: play_time + played.as_secs_f64(),
+            },
+        );
+        self.roms.truncate(RECENT_LIMIT);
+    }
+
+    /// Writes the recently played ROMs file
+    fn save(&self) -> Result<(), String> {
+        let path = default_path().ok_or("state directory unknown")?;
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, contents).map_err(|e| e.to_string())
+    }
+
+    /// Prints the recently played ROMs to stdout: last time played, total play time and path
+    pub fn print(&self) {
+        if self.roms.is_empty() {
+            println!("no recently played roms");
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        println!("{:<16} {:>10}  ROM", "LAST PLAYED", "PLAY TIME");
+        for rom in &self.roms {
+            println!(
+                "{:<16} {:>10}  {}",
+                format_age(now.saturating_sub(rom.last_played)),
+                format_duration(rom.play_time as u64),
+                rom.path.display()
+            );
+        }
+    }
+}
+
+/// Returns the time elapsed since an event in a human readable form (e.g. `5 minutes ago`)
+///
+/// # Arguments
+///
+/// * `seconds` - The seconds elapsed since the event
+fn format_age(seconds: u64) -> String {
+    let (value, unit) = match seconds {
+        0..=59 => return String::from("just now"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    format!("{value} {unit}{} ago", if value == 1 { "" } else { "s" })
+}
+
+/// Returns a duration as hours, minutes and seconds (e.g. `1h 02m 05s`)
+///
+/// # Arguments
+///
+/// * `seconds` - The duration in seconds
+fn format_duration(seconds: u64) -> String {
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s:02}s"),
+        (h, m, s) => format!("{h}h {m:02}m {s:02}s"),
+    }
+}
+
+/// Returns the default recently played ROMs file path, if the state directory is known
+fn default_path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join(RECENT_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn played_roms_move_to_the_top() {
+        let mut recent = Recent::default();
+        assert_eq!(recent.last(), None);
+
+        recent.add(PathBuf::from("/roms/a.ch8"), Duration::from_secs(30), 100);
+        recent.add(PathBuf::from("/roms/b.ch8"), Duration::from_secs(10), 200);
+        assert_eq!(recent.last(), Some(Path::new("/roms/b.ch8")));
+
+        // the play time is added up
+        recent.add(PathBuf::from("/roms/a.ch8"), Duration::from_secs(15), 300);
+        assert_eq!(recent.last(), Some(Path::new("/roms/a.ch8")));
+        assert_eq!(recent.roms.len(), 2);
+        assert_eq!(recent.roms[0].play_time, 45.0);
+        assert_eq!(recent.roms[0].last_played, 300);
+    }
+
+    #[test]
+    fn oldest_roms_are_dropped() {
+        let mut recent = Recent::default();
+        for n in 0..=RECENT_LIMIT {
+            recent.add(PathBuf::from(format!("/roms/{n}.ch8")), Duration::ZERO, 0);
+        }
+
+        assert_eq!(recent.roms.len(), RECENT_LIMIT);
+        assert_eq!(recent.roms[RECENT_LIMIT - 1].path, Path::new("/roms/1.ch8"));
+    }
+
+    #[test]
+    fn recent_file_is_serialized() {
+        let mut recent = Recent::default();
+        recent.add(PathBuf::from("/roms/a.ch8"), Duration::from_secs(5), 100);
+        let contents = toml::to_string(&recent).unwrap();
+        let recent: Recent = toml::from_str(&contents).unwrap();
+
+        assert_eq!(recent.last(), Some(Path::new("/roms/a.ch8")));
+        assert_eq!(recent.roms[0].play_time, 5.0);
+    }
+
+    #[test]
+    fn ages_and_durations_are_formatted() {
+        assert_eq!(format_age(59), "just now");
+        assert_eq!(format_age(60), "1 minute ago");
+        assert_eq!(format_age(7200), "2 hours ago");
+        assert_eq!(format_age(86400 * 3), "3 days ago");
+
+        assert_eq!(format_duration(5), "5s");
+        assert_eq!(format_duration(65), "1m 05s");
+        assert_eq!(format_duration(3725), "1h 02m 05s");
+    }
+}
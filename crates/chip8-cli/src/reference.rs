@@ -0,0 +1,255 @@
+//! Reference CHIP-8 interpreter used by differential testing (`diff` subcommand).
+//!
+//! It is written to be as simple as possible (one pixel per byte, one match per instruction)
+//! and follows the original COSMAC VIP behavior: 8XY1/8XY2/8XY3 reset VF, 8XY6/8XYE shift VY,
+//! FX55/FX65 increment I, sprites are clipped at the display edges and FX0A reads the key when released.
+//! Illegal opcodes are returned as errors instead of panicking.
+
+use chip8_core::{Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH, KEYPAD_SIZE, RPL_FLAGS_SIZE};
+use rand::Rng;
+
+/// memory size (4 KB)
+const MEMORY_SIZE: usize = 4096;
+
+/// display size (64 x 32)
+const DISPLAY_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGTH;
+
+/// program start address
+const PROGRAM_START: u16 = 0x200;
+
+/// font sprites of the hex digits (5 bytes each), loaded at address 0
+const FONTSET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, // 0, 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0, // 2, 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, // 4, 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, // 6, 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0, 0x10, 0xF0, // 8, 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, // A, B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, // C, D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80, // E, F
+];
+
+/// Reference CHIP-8 machine
+pub struct Reference {
+    /// RAM memory
+    pub memory: [u8; MEMORY_SIZE],
+
+    /// Registers V0-VF
+    pub v: [u8; 16],
+
+    /// Index register
+    pub i: u16,
+
+    /// Program counter
+    pub pc: u16,
+
+    /// Stack levels in use (from the bottom to the top of the stack)
+    pub stack: Vec<u16>,
+
+    /// Delay timer
+    pub delay_timer: u8,
+
+    /// Sound timer
+    pub sound_timer: u8,
+
+    /// Display pixels, row by row (true if the pixel is on)
+    pub display: [bool; DISPLAY_SIZE],
+
+    /// Keypad state (true if the key is pressed)
+    pub keys: [bool; KEYPAD_SIZE],
+
+    /// Keys pressed while waiting for a key with FX0A
+    waiting_keys: [bool; KEYPAD_SIZE],
+
+    /// RPL user flags (SUPER-CHIP FX75/FX85)
+    rpl: [u8; RPL_FLAGS_SIZE],
+}
+
+impl Reference {
+    /// Returns a new reference machine with the ROM loaded at 0x200
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM contents
+    pub fn new(rom: &[u8]) -> Self {
+        let mut memory = [0; MEMORY_SIZE];
+        memory[..FONTSET.len()].copy_from_slice(&FONTSET);
+        // the SUPER-CHIP hires fontset is not used by the COSMAC VIP instructions:
+        // it is loaded after the fontset as in the core, so that memory can be compared
+        let hires_font = Chip8::new().hires_font().to_owned();
+        memory[FONTSET.len()..FONTSET.len() + hires_font.len()].copy_from_slice(&hires_font);
+        let start = PROGRAM_START as usize;
+        memory[start..start + rom.len()].copy_from_slice(rom);
+
+        Self {
+            memory,
+            v: [0; 16],
+            i: 0,
+            pc: PROGRAM_START,
+            stack: Vec::new(),
+            delay_timer: 0,
+            sound_timer: 0,
+            display: [false; DISPLAY_SIZE],
+            keys: [false; KEYPAD_SIZE],
+            waiting_keys: [false; KEYPAD_SIZE],
+            rpl: [0; RPL_FLAGS_SIZE],
+        }
+    }
+
+    /// Decrements the delay and sound timers (one 60Hz tick)
+    pub fn update_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// Executes one instruction. Returns an error message if the opcode cannot be executed
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The random number generator (CXNN draws one byte, as the core does)
+    pub fn step<R: Rng>(&mut self, rng: &mut R) -> Result<(), String> {
+        let pc = self.pc as usize;
+        let opcode = (self.memory[pc % MEMORY_SIZE] as u16) << 8
+            | self.memory[(pc + 1) % MEMORY_SIZE] as u16;
+        let x = ((opcode >> 8) & 0xF) as usize;
+        let y = ((opcode >> 4) & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+        let (vx, vy) = (self.v[x], self.v[y]);
+
+        self.pc = self.pc.wrapping_add(2);
+        match (opcode >> 12, n, nn) {
+            (0x0, _, 0xE0) if nnn == 0x0E0 => self.display = [false; DISPLAY_SIZE],
+            (0x0, _, 0xEE) if nnn == 0x0EE => match self.stack.pop() {
+                Some(address) => self.pc = address,
+                None => return Err(String::from("stack underflow")),
+            },
+            (0x0, _, 0xFD) if nnn == 0x0FD => self.pc = self.pc.wrapping_sub(2),
+            (0x1, _, _) => self.pc = nnn,
+            (0x2, _, _) => {
+                if self.stack.len() == 16 {
+                    return Err(String::from("stack overflow"));
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            }
+            (0x3, _, _) => self.skip_if(vx == nn),
+            (0x4, _, _) => self.skip_if(vx != nn),
+            (0x5, 0x0, _) => self.skip_if(vx == vy),
+            (0x6, _, _) => self.v[x] = nn,
+            (0x7, _, _) => self.v[x] = vx.wrapping_add(nn),
+            (0x8, 0x0, _) => self.v[x] = vy,
+            (0x8, 0x1, _) => self.set_with_flag(x, vx | vy, 0),
+            (0x8, 0x2, _) => self.set_with_flag(x, vx & vy, 0),
+            (0x8, 0x3, _) => self.set_with_flag(x, vx ^ vy, 0),
+            (0x8, 0x4, _) => {
+                let (result, carry) = vx.overflowing_add(vy);
+                self.set_with_flag(x, result, carry as u8);
+            }
+            (0x8, 0x5, _) => self.set_with_flag(x, vx.wrapping_sub(vy), (vx >= vy) as u8),
+            (0x8, 0x6, _) => self.set_with_flag(x, vy >> 1, vy & 0x01),
+            (0x8, 0x7, _) => self.set_with_flag(x, vy.wrapping_sub(vx), (vy >= vx) as u8),
+            (0x8, 0xE, _) => self.set_with_flag(x, vy << 1, vy >> 7),
+            (0x9, 0x0, _) => self.skip_if(vx != vy),
+            (0xA, _, _) => self.i = nnn,
+            (0xB, _, _) => self.pc = nnn + self.v[0] as u16,
+            (0xC, _, _) => self.v[x] = rng.gen::<u8>() & nn,
+            (0xD, _, _) => self.draw(vx as usize, vy as usize, n),
+            (0xE, _, 0x9E) => self.skip_if(self.keys[(vx & 0xF) as usize]),
+            (0xE, _, 0xA1) => self.skip_if(!self.keys[(vx & 0xF) as usize]),
+            (0xF, _, 0x07) => self.v[x] = self.delay_timer,
+            (0xF, _, 0x0A) => {
+                // the key is read when released: until then the instruction is executed again
+                match (0..KEYPAD_SIZE).find(|&key| self.waiting_keys[key] && !self.keys[key]) {
+                    Some(key) => {
+                        self.v[x] = key as u8;
+                        self.waiting_keys = [false; KEYPAD_SIZE];
+                    }
+                    None => {
+                        for key in 0..KEYPAD_SIZE {
+                            self.waiting_keys[key] |= self.keys[key];
+                        }
+                        self.pc -= 2;
+                    }
+                }
+            }
+            (0xF, _, 0x15) => self.delay_timer = vx,
+            (0xF, _, 0x18) => self.sound_timer = vx,
+            (0xF, _, 0x1E) => self.i = self.i.wrapping_add(vx as u16),
+            (0xF, _, 0x29) => self.i = (vx & 0xF) as u16 * 5,
+            (0xF, _, 0x33) => {
+                for (offset, digit) in [vx / 100, vx / 10 % 10, vx % 10].into_iter().enumerate() {
+                    self.memory[(self.i as usize + offset) % MEMORY_SIZE] = digit;
+                }
+            }
+            (0xF, _, 0x55) => {
+                for register in 0..=x {
+                    self.memory[self.i as usize % MEMORY_SIZE] = self.v[register];
+                    self.i = self.i.wrapping_add(1);
+                }
+            }
+            (0xF, _, 0x65) => {
+                for register in 0..=x {
+                    self.v[register] = self.memory[self.i as usize % MEMORY_SIZE];
+                    self.i = self.i.wrapping_add(1);
+                }
+            }
+            (0xF, _, 0x75) if x < RPL_FLAGS_SIZE => self.rpl[..=x].copy_from_slice(&self.v[..=x]),
+            (0xF, _, 0x85) if x < RPL_FLAGS_SIZE => self.v[..=x].copy_from_slice(&self.rpl[..=x]),
+            _ => return Err(format!("illegal opcode {opcode:#06X}")),
+        }
+
+        Ok(())
+    }
+
+    /// Skips the next instruction if the condition is true
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The skip condition
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// Sets VX, then VF (so that VF holds the flag also if X is F)
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The register index
+    /// * `value` - The value of VX
+    /// * `flag` - The value of VF
+    fn set_with_flag(&mut self, x: usize, value: u8, flag: u8) {
+        self.v[x] = value;
+        self.v[0xF] = flag;
+    }
+
+    /// Draws a sprite of `height` rows read from I at (x, y), clipped at the display edges
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The sprite column (wrapped to the display width)
+    /// * `y` - The sprite row (wrapped to the display height)
+    /// * `height` - The number of sprite rows
+    fn draw(&mut self, x: usize, y: usize, height: usize) {
+        let (x, y) = (x % DISPLAY_WIDTH, y % DISPLAY_HEIGTH);
+        self.v[0xF] = 0;
+        for row in 0..height {
+            let sprite = self.memory[(self.i as usize + row) % MEMORY_SIZE];
+            for column in 0..8 {
+                if y + row >= DISPLAY_HEIGTH || x + column >= DISPLAY_WIDTH {
+                    continue;
+                }
+                if sprite & (0x80 >> column) != 0 {
+                    let pixel = &mut self.display[(y + row) * DISPLAY_WIDTH + x + column];
+                    if *pixel {
+                        self.v[0xF] = 1;
+                    }
+                    *pixel = !*pixel;
+                }
+            }
+        }
+    }
+}
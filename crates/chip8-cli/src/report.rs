@@ -0,0 +1,179 @@
+//! Machine-readable report of a finished emulation
+
+use chip8_core::Chip8;
+use chip8_frontend::{RunSummary, StopReason};
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::trace;
+
+/// Report formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// JSON object
+    Json,
+}
+
+/// Report of a finished emulation: run summary and final machine state
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// Number of executed instructions
+    pub cycles: u64,
+
+    /// Instructions executed since the program was loaded or reset (the cycle counter)
+    pub total_cycles: u64,
+
+    /// Completed 60Hz frames since the program was loaded or reset
+    pub frames: u64,
+
+    /// Executed draw instructions (00E0, DXYN) since the program was loaded or reset
+    pub draws: u64,
+
+    /// Key presses and releases since the program was loaded or reset
+    pub key_events: u64,
+
+    /// Host time of the emulation (seconds)
+    pub elapsed_seconds: f64,
+
+    /// Reason why the emulation stopped
+    pub stop_reason: StopReason,
+
+    /// The error of the instruction that could not be executed (execution error stop reason)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Final program counter
+    pub pc: u16,
+
+    /// Final index register
+    pub i: u16,
+
+    /// Final registers V0-VF
+    pub v: Vec<u8>,
+
+    /// Final stack levels in use (from the bottom to the top of the stack)
+    pub stack: Vec<u16>,
+
+    /// Final delay timer
+    pub delay_timer: u8,
+
+    /// Final sound timer
+    pub sound_timer: u8,
+
+    /// Hash of the final display (64-bit FNV-1a of the packed rows, hex string)
+    pub display_hash: String,
+}
+
+impl Report {
+    /// Returns the report of a finished emulation
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance, after the emulation
+    /// * `summary` - The summary returned by the emulation
+    pub fn new(chip8: &Chip8, summary: &RunSummary) -> Self {
+        Self {
+            cycles: summary.cycles,
+            total_cycles: summary.session.cycles,
+            frames: summary.session.frames,
+            draws: summary.session.draws,
+            key_events: summary.session.key_events,
+            elapsed_seconds: summary.elapsed.as_secs_f64(),
+            stop_reason: summary.stop_reason,
+            error: summary.error.map(|e| e.to_string()),
+            pc: chip8.pc(),
+            i: chip8.i(),
+            v: chip8.v().to_vec(),
+            stack: chip8.stack().to_vec(),
+            delay_timer: chip8.delay_timer(),
+            sound_timer: chip8.sound_timer(),
+            display_hash: format!("{:016x}", chip8.display_hash()),
+        }
+    }
+
+    /// Prints the report to stdout
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The report format
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during the serialization of the report
+    pub fn print(&self, format: ReportFormat) {
+        trace!("Report::print: start");
+
+        let output = match format {
+            ReportFormat::Json => match serde_json::to_string(self) {
+                Ok(output) => output,
+                Err(e) => {
+                    panic!("serializing report: {e}")
+                }
+            },
+        };
+        println!("{output}");
+
+        trace!("Report::print: exit");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+    use chip8_frontend::{IdlePolicy, RandomMode, Run, RunOptions};
+
+    /// Returns the report of a headless run of a ROM
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM
+    /// * `max_cycles` - The number of instructions after which the emulation stops
+    fn report(rom: &[u8], max_cycles: u64) -> serde_json::Value {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom);
+        let options = RunOptions {
+            stepping: false,
+            seed: 0,
+            random: RandomMode::Seeded,
+            speed: 1.0,
+            uncapped: true,
+            max_cycles: Some(max_cycles),
+            max_seconds: None,
+            on_idle: IdlePolicy::Halt,
+        };
+        let summary = chip8.run(options, &mut HeadlessRenderer);
+
+        serde_json::to_value(Report::new(&chip8, &summary)).unwrap()
+    }
+
+    #[test]
+    fn report_has_the_final_state() {
+        // 6005 (V0 = 5), A123 (I = 0x123), 2208 (call 0x208), 1206 (jump to itself), 1208 (jump to itself)
+        let report = report(
+            &[0x60, 0x05, 0xA1, 0x23, 0x22, 0x08, 0x12, 0x06, 0x12, 0x08],
+            10,
+        );
+
+        assert_eq!(report["cycles"], 3);
+        assert_eq!(report["total_cycles"], 3);
+        assert_eq!(report["stop_reason"], "idle-loop");
+        assert_eq!(report["pc"], 0x208);
+        assert_eq!(report["i"], 0x123);
+        assert_eq!(report["v"][0], 5);
+        assert_eq!(report["stack"], serde_json::json!([0x206]));
+        assert_eq!(report["display_hash"].as_str().unwrap().len(), 16);
+        assert!(report.get("error").is_none());
+    }
+
+    #[test]
+    fn report_has_the_execution_error() {
+        // 00EE with an empty stack
+        let report = report(&[0x00, 0xEE], 10);
+
+        assert_eq!(report["stop_reason"], "execution-error");
+        assert_eq!(
+            report["error"],
+            chip8_core::Error::StackUnderflow.to_string()
+        );
+    }
+}
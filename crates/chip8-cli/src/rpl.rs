@@ -0,0 +1,152 @@
+//! Persistent RPL user flags: the SUPER-CHIP flags written with FX75 are saved to a file per ROM
+//! (`rpl/<sha1>.bin` in the state directory, keyed by the SHA-1 hash of the ROM), so that games
+//! that use them as save data keep their progress across sessions
+
+use crate::config;
+use chip8_core::{Chip8, RPL_FLAGS_SIZE};
+use sha1_smol::Sha1;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, trace, warn};
+
+/// directory of the RPL user flags files (in the state directory)
+const RPL_DIR_NAME: &str = "rpl";
+
+/// Restores the RPL user flags saved for the loaded ROM, if any, and returns the flags of the instance
+/// (errors are logged: they do not stop the emulator)
+///
+/// # Arguments
+///
+/// * `chip8` - Mutable reference to the CHIP-8 instance, with the ROM loaded
+pub fn load(chip8: &mut Chip8) -> [u8; RPL_FLAGS_SIZE] {
+    trace!("rpl::load: start");
+
+    if let Some(dir) = config::state_dir() {
+        restore(chip8, &path(&dir, chip8));
+    }
+
+    trace!("rpl::load: exit");
+
+    *chip8.rpl_flags()
+}
+
+/// Saves the RPL user flags of the loaded ROM (errors are logged: they do not stop the emulator)
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+pub fn save(chip8: &Chip8) {
+    trace!("rpl::save: start");
+
+    match config::state_dir() {
+        Some(dir) => store(chip8, &path(&dir, chip8)),
+        None => warn!("writing rpl user flags file: state directory unknown"),
+    }
+
+    trace!("rpl::save: exit");
+}
+
+/// Restores the RPL user flags of a file, if it exists and is valid
+///
+/// # Arguments
+///
+/// * `chip8` - Mutable reference to the CHIP-8 instance
+/// * `path` - The Path reference that holds the path to the RPL user flags file
+fn restore(chip8: &mut Chip8, path: &Path) {
+    match fs::read(path) {
+        Ok(contents) => match <[u8; RPL_FLAGS_SIZE]>::try_from(contents.as_slice()) {
+            Ok(flags) => {
+                chip8.set_rpl_flags(&flags);
+                debug!("rpl user flags loaded: {}", path.display());
+            }
+            Err(_) => warn!(
+                "reading rpl user flags file `{}`: invalid length {} bytes",
+                path.display(),
+                contents.len()
+            ),
+        },
+        Err(_) => debug!("rpl user flags file not found: {}", path.display()),
+    }
+}
+
+/// Writes the RPL user flags into a file, creating its directory
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+/// * `path` - The Path reference that holds the path to the RPL user flags file
+fn store(chip8: &Chip8, path: &Path) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, chip8.rpl_flags()));
+    match result {
+        Ok(()) => debug!("rpl user flags saved: {}", path.display()),
+        Err(e) => warn!("writing rpl user flags file `{}`: {e}", path.display()),
+    }
+}
+
+/// Returns the path of the RPL user flags file of the loaded ROM
+///
+/// # Arguments
+///
+/// * `dir` - The state directory
+/// * `chip8` - The CHIP-8 instance
+fn path(dir: &Path, chip8: &Chip8) -> PathBuf {
+    let sha1 = Sha1::from(chip8.rom()).digest().to_string();
+
+    dir.join(RPL_DIR_NAME).join(format!("{sha1}.bin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Returns an instance with a ROM loaded
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM
+    fn chip8(rom: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom);
+        chip8
+    }
+
+    #[test]
+    fn flags_are_kept_per_rom() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-rpl-{}", std::process::id()));
+        let mut game = chip8(&[0x12, 0x00]);
+        game.set_rpl_flags(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        store(&game, &path(&dir, &game));
+
+        let mut same_game = chip8(&[0x12, 0x00]);
+        let same_path = path(&dir, &same_game);
+        restore(&mut same_game, &same_path);
+        assert_eq!(same_game.rpl_flags(), game.rpl_flags());
+
+        let mut other_game = chip8(&[0x12, 0x02]);
+        let other_path = path(&dir, &other_game);
+        restore(&mut other_game, &other_path);
+        assert_eq!(*other_game.rpl_flags(), [0; RPL_FLAGS_SIZE]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn files_of_invalid_length_are_ignored() {
+        let dir = env::temp_dir().join(format!("rust-chip-8-rpl-invalid-{}", std::process::id()));
+        let mut game = chip8(&[0x12, 0x00]);
+        let path = path(&dir, &game);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, [0xFF; RPL_FLAGS_SIZE + 1]).unwrap();
+
+        restore(&mut game, &path);
+        assert_eq!(*game.rpl_flags(), [0; RPL_FLAGS_SIZE]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Screenshots of the CHIP-8 display: PNG images with the colors of the palette,
+//! every CHIP-8 pixel scaled to a square of pixels (the image has the same size in both display modes)
+
+use chip8_core::{Chip8, DISPLAY_WIDTH};
+use chip8_frontend::Palette;
+use std::{fs, path::Path};
+use tracing::trace;
+
+/// size (in image pixels) of the side of a CHIP-8 pixel in lores mode (half of it in hires mode)
+const SCREENSHOT_SCALE: usize = 8;
+
+/// Returns the PNG image of the CHIP-8 display
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+/// * `palette` - The colors of the pixels that are on and off
+///
+/// # Panics
+///
+/// The function panics in case of errors during encoding of the image
+pub fn png(chip8: &Chip8, palette: Palette) -> Vec<u8> {
+    trace!("screenshot::png: start");
+
+    let (display_width, display_height) = chip8.display_size();
+    let scale = SCREENSHOT_SCALE * DISPLAY_WIDTH / display_width;
+    let width = display_width * scale;
+    let height = display_height * scale;
+    let (on, off) = (palette.foreground.to_rgb(), palette.background.to_rgb());
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = if chip8.pixel(x / scale, y / scale) {
+                on
+            } else {
+                off
+            };
+            pixels.extend_from_slice(&[r, g, b]);
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let result = encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels));
+    if let Err(e) = result {
+        panic!("encoding screenshot: {e}")
+    }
+
+    trace!("screenshot::png: exit");
+
+    image
+}
+
+/// Writes the PNG image of the CHIP-8 display to a file
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the image file
+/// * `chip8` - The CHIP-8 instance
+/// * `palette` - The colors of the pixels that are on and off
+pub fn save(path: &Path, chip8: &Chip8, palette: Palette) -> std::io::Result<()> {
+    fs::write(path, png(chip8, palette))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_frontend::Color;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// colors of the pixels that are on and off
+    const PALETTE: Palette = Palette {
+        foreground: Color::Rgb(255, 255, 255),
+        background: Color::Rgb(0, 0, 0),
+    };
+
+    /// Returns the size and the RGB pixels of a PNG image
+    fn decode(image: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(image).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        (info.width, info.height, pixels)
+    }
+
+    /// Returns the RGB color of a pixel of the image
+    fn color(image: &(u32, u32, Vec<u8>), x: usize, y: usize) -> &[u8] {
+        let offset = (y * image.0 as usize + x) * 3;
+        &image.2[offset..offset + 3]
+    }
+
+    /// Returns an instance with the glyph of 0 (4 pixels wide) drawn at the top left corner
+    fn chip8(hires: bool) -> Chip8 {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut chip8 = Chip8::new();
+        if hires {
+            chip8.op_00ff(0x00FF, &mut rng).unwrap();
+        }
+        chip8.set_i(0);
+        chip8.op_dxyn(0xD005, &mut rng).unwrap();
+        chip8
+    }
+
+    #[test]
+    fn screenshot_has_the_same_size_in_both_modes() {
+        let lores = decode(&png(&chip8(false), PALETTE));
+        let hires = decode(&png(&chip8(true), PALETTE));
+        assert_eq!((lores.0, lores.1), (512, 256));
+        assert_eq!((hires.0, hires.1), (512, 256));
+    }
+
+    #[test]
+    fn hires_pixels_are_scaled_by_half() {
+        let lores = decode(&png(&chip8(false), PALETTE));
+        assert_eq!(color(&lores, 31, 0), [255, 255, 255]);
+        assert_eq!(color(&lores, 32, 0), [0, 0, 0]);
+
+        let hires = decode(&png(&chip8(true), PALETTE));
+        assert_eq!(color(&hires, 15, 0), [255, 255, 255]);
+        assert_eq!(color(&hires, 16, 0), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,376 @@
+//! Scripting of the emulation (`--script` option): a rhai script is called at the end of every
+//! 60Hz frame with read access to the CHIP-8 state (registers, memory, display) and can press
+//! the keypad keys or stop the emulation (e.g. bots, automated test assertions, trainers)
+
+use chip8_core::Chip8;
+use chip8_frontend::{EmulationStatus, Hotkey, Renderer};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tracing::{debug, info, trace};
+
+/// name of the script function called at the end of every frame
+const ON_FRAME_FN: &str = "on_frame";
+
+/// number of keys of the hex keypad
+const KEYS: i64 = 16;
+
+/// State shared between the script functions, the frame hook and the renderer
+struct State {
+    /// Copy of the CHIP-8 instance at the end of the last frame (read by the script)
+    chip8: Chip8,
+
+    /// Keys pressed by the script (bit n set if key n is pressed)
+    keys: u16,
+
+    /// Boolean set to true if the script stopped the emulation
+    stop: bool,
+}
+
+/// Shared reference to the script state
+type SharedState = Arc<Mutex<State>>;
+
+/// Renderer that adds the keys pressed by the script to the keypad of the wrapped renderer
+/// and reports a quit request when the script stops the emulation
+struct ScriptedRenderer {
+    /// The wrapped renderer
+    renderer: Box<dyn Renderer>,
+
+    /// The script state
+    state: SharedState,
+}
+
+impl Renderer for ScriptedRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        self.renderer.render(display, dirty_rows);
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.renderer.tick(display);
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.renderer.status(status);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        self.renderer.debug(chip8)
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        self.renderer.control(chip8);
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        self.renderer.hotkeys()
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.renderer.fast_forward()
+    }
+
+    fn has_keypad(&self) -> bool {
+        // the script can press keys at any frame
+        true
+    }
+
+    fn keypad(&self) -> u16 {
+        self.renderer.keypad() | lock(&self.state).keys
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.renderer.quit_requested() || lock(&self.state).stop
+    }
+}
+
+/// Loads the script and registers a hook that calls its `on_frame` function at the end of every frame.
+/// Returns the renderer that applies the keys pressed by the script
+///
+/// # Arguments
+///
+/// * `path` - The Path reference that holds the path to the script file
+/// * `chip8` - Mutable reference to the CHIP-8 instance where the hook is registered
+/// * `renderer` - The renderer of the emulation
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading or compilation of the script,
+/// if the script does not define the `on_frame` function, and (during the emulation)
+/// in case of errors of the script (e.g. `throw` of a failed assertion)
+pub fn attach(path: &Path, chip8: &mut Chip8, renderer: Box<dyn Renderer>) -> Box<dyn Renderer> {
+    trace!("script::attach: start");
+
+    let state = Arc::new(Mutex::new(State {
+        chip8: chip8.clone(),
+        keys: 0,
+        stop: false,
+    }));
+    let engine = engine(&state);
+
+    let ast = match engine.compile_file(path.to_path_buf()) {
+        Ok(ast) => ast,
+        Err(e) => panic!("compiling script: {e}"),
+    };
+    if !ast.iter_functions().any(|f| f.name == ON_FRAME_FN) {
+        panic!("compiling script: function `{ON_FRAME_FN}` not defined");
+    }
+
+    // top-level statements are run once, `this` is kept between the frames
+    let mut scope = Scope::new();
+    if let Err(e) = engine.run_ast_with_scope(&mut scope, &ast) {
+        panic!("running script: {e}")
+    }
+    let mut this = Dynamic::from_map(Map::new());
+
+    debug!("script loaded: {}", path.display());
+
+    let hook_state = Arc::clone(&state);
+    chip8.on_frame(move |chip8| {
+        lock(&hook_state).chip8 = chip8.clone();
+        if let Err(e) = call_on_frame(&engine, &mut scope, &ast, &mut this) {
+            panic!("running script: {e}")
+        }
+    });
+
+    trace!("script::attach: exit");
+
+    Box::new(ScriptedRenderer { renderer, state })
+}
+
+/// Calls the `on_frame` function of the script
+///
+/// # Arguments
+///
+/// * `engine` - The script engine
+/// * `scope` - The scope of the script
+/// * `ast` - The compiled script
+/// * `this` - The value bound to `this` in the function
+fn call_on_frame(
+    engine: &Engine,
+    scope: &mut Scope,
+    ast: &AST,
+    this: &mut Dynamic,
+) -> Result<(), Box<EvalAltResult>> {
+    let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(this);
+    engine.call_fn_with_options::<()>(options, scope, ast, ON_FRAME_FN, ())
+}
+
+/// Returns the script engine with the CHIP-8 functions registered
+///
+/// # Arguments
+///
+/// * `state` - The script state read and written by the functions
+fn engine(state: &SharedState) -> Engine {
+    let mut engine = Engine::new();
+    // same nesting limits of the release builds of rhai (debug builds are more restrictive)
+    engine.set_max_expr_depths(64, 32);
+    engine.on_print(|text| info!("script: {text}"));
+    engine.on_debug(|text, _, position| debug!("script ({position}): {text}"));
+
+    let s = Arc::clone(state);
+    engine.register_fn("v", move |x: i64| -> Result<i64, Box<EvalAltResult>> {
+        let state = lock(&s);
+        let x = index(x, state.chip8.v().len() as i64, "register")?;
+        Ok(state.chip8.v()[x] as i64)
+    });
+    let s = Arc::clone(state);
+    engine.register_fn("i", move || lock(&s).chip8.i() as i64);
+    let s = Arc::clone(state);
+    engine.register_fn("pc", move || lock(&s).chip8.pc() as i64);
+    let s = Arc::clone(state);
+    engine.register_fn("delay_timer", move || lock(&s).chip8.delay_timer() as i64);
+    let s = Arc::clone(state);
+    engine.register_fn("sound_timer", move || lock(&s).chip8.sound_timer() as i64);
+    let s = Arc::clone(state);
+    engine.register_fn("cycles", move || lock(&s).chip8.cycles() as i64);
+    let s = Arc::clone(state);
+    engine.register_fn(
+        "mem",
+        move |address: i64| -> Result<i64, Box<EvalAltResult>> {
+            let state = lock(&s);
+            let address = index(address, state.chip8.memory().len() as i64, "memory address")?;
+            Ok(state.chip8.memory()[address] as i64)
+        },
+    );
+    let s = Arc::clone(state);
+    engine.register_fn(
+        "pixel",
+        move |x: i64, y: i64| -> Result<bool, Box<EvalAltResult>> {
+            let state = lock(&s);
+            // 64x32 display, 128x64 in the SUPER-CHIP hires mode
+            let (width, height) = state.chip8.display_size();
+            let x = index(x, width as i64, "pixel column")?;
+            let y = index(y, height as i64, "pixel row")?;
+            Ok(state.chip8.pixel(x, y))
+        },
+    );
+    let s = Arc::clone(state);
+    engine.register_fn("press", move |key: i64| -> Result<(), Box<EvalAltResult>> {
+        let key = index(key, KEYS, "key")?;
+        lock(&s).keys |= 1 << key;
+        Ok(())
+    });
+    let s = Arc::clone(state);
+    engine.register_fn(
+        "release",
+        move |key: i64| -> Result<(), Box<EvalAltResult>> {
+            let key = index(key, KEYS, "key")?;
+            lock(&s).keys &= !(1 << key);
+            Ok(())
+        },
+    );
+    let s = Arc::clone(state);
+    engine.register_fn("stop", move || lock(&s).stop = true);
+
+    engine
+}
+
+/// Returns the value as an index if it is in the range 0..len, or an error of the script
+///
+/// # Arguments
+///
+/// * `value` - The value passed by the script
+/// * `len` - The number of valid indexes
+/// * `what` - The name of the value in the error message
+fn index(value: i64, len: i64, what: &str) -> Result<usize, Box<EvalAltResult>> {
+    if (0..len).contains(&value) {
+        Ok(value as usize)
+    } else {
+        Err(format!("{what} out of range: {value}").into())
+    }
+}
+
+/// Locks the script state
+///
+/// # Arguments
+///
+/// * `state` - The script state
+///
+/// # Panics
+///
+/// The function panics if the lock is poisoned (a script function panicked)
+fn lock(state: &SharedState) -> MutexGuard<'_, State> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(e) => panic!("locking script state: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+    use chip8_frontend::{IdlePolicy, RandomMode, Run, RunOptions, StopReason};
+    use std::{env, fs, path::PathBuf};
+
+    /// Writes a script into a temporary file and returns its path
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the test
+    /// * `script` - The script source
+    fn script(name: &str, script: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("rust-chip-8-{name}-{}.rhai", std::process::id()));
+        fs::write(&path, script).unwrap();
+        path
+    }
+
+    /// Runs a ROM with a script attached, uncapped, for at most 10000 instructions
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the test
+    /// * `rom` - The ROM
+    /// * `source` - The script source
+    fn run(name: &str, rom: &[u8], source: &str) -> (Chip8, StopReason) {
+        let path = script(name, source);
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom);
+        let mut renderer = attach(&path, &mut chip8, Box::new(HeadlessRenderer));
+        fs::remove_file(path).unwrap();
+
+        let options = RunOptions {
+            stepping: false,
+            seed: 0,
+            random: RandomMode::Seeded,
+            speed: 1.0,
+            uncapped: true,
+            max_cycles: Some(10000),
+            max_seconds: None,
+            on_idle: IdlePolicy::Halt,
+        };
+        let summary = chip8.run(options, &mut *renderer);
+        (chip8, summary.stop_reason)
+    }
+
+    #[test]
+    fn script_reads_the_state_and_stops_the_emulation() {
+        // 7001 (V0 += 1), 1200 (jump to 0x200)
+        let (chip8, reason) = run(
+            "script-stop",
+            &[0x70, 0x01, 0x12, 0x00],
+            "fn on_frame() { if v(0) >= 20 && pc() == 0x200 { stop(); } }",
+        );
+
+        assert_eq!(reason, StopReason::Quit);
+        assert!(chip8.v()[0] >= 20);
+    }
+
+    #[test]
+    fn script_presses_the_keys() {
+        // 6505 (V5 = 5), E59E (skip if key V5 pressed), 1202 (jump to 0x202), 1206 (jump to itself)
+        let (chip8, reason) = run(
+            "script-press",
+            &[0x65, 0x05, 0xE5, 0x9E, 0x12, 0x02, 0x12, 0x06],
+            "fn on_frame() { press(5); }",
+        );
+
+        assert_eq!(reason, StopReason::IdleLoop);
+        assert_eq!(chip8.pc(), 0x206);
+    }
+
+    #[test]
+    fn this_is_kept_between_the_frames() {
+        // 7001 (V0 += 1), 1200 (jump to 0x200): the frames are counted by the script
+        let (chip8, reason) = run(
+            "script-this",
+            &[0x70, 0x01, 0x12, 0x00],
+            "fn on_frame() { this.frames = (this.frames ?? 0) + 1; if this.frames == 3 { stop(); } }",
+        );
+
+        assert_eq!(reason, StopReason::Quit);
+        assert_eq!(chip8.session_stats().frames, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "function `on_frame` not defined")]
+    fn script_without_on_frame_panics() {
+        let path = script("script-missing", "let x = 1;");
+        let mut chip8 = Chip8::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            attach(&path, &mut chip8, Box::new(HeadlessRenderer));
+        }));
+        fs::remove_file(path).unwrap();
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    fn arguments_out_of_range_are_script_errors() {
+        let state = Arc::new(Mutex::new(State {
+            chip8: Chip8::new(),
+            keys: 0,
+            stop: false,
+        }));
+        let engine = engine(&state);
+
+        assert_eq!(engine.eval::<i64>("v(15)").unwrap(), 0);
+        let error = engine.eval::<i64>("v(16)").unwrap_err();
+        assert!(error.to_string().contains("register out of range: 16"));
+        let error = engine.eval::<()>("press(-1)").unwrap_err();
+        assert!(error.to_string().contains("key out of range: -1"));
+        let error = engine.eval::<bool>("pixel(64, 0)").unwrap_err();
+        assert!(error.to_string().contains("pixel column out of range: 64"));
+    }
+}
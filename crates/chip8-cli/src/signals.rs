@@ -0,0 +1,229 @@
+//! Signal handling: SIGINT (Ctrl-C) and SIGTERM stop the emulation gracefully (the terminal is restored
+//! and the run summary is printed, a second signal exits immediately), SIGUSR1 dumps the machine state
+//! (registers, stack and memory hexdump) to the log without stopping the emulation.
+//!
+//! Signals are handled on Unix platforms only
+
+use chip8_core::Chip8;
+use chip8_frontend::{EmulationStatus, Hotkey, Renderer};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tracing::{info, trace};
+
+/// number of bytes of a hexdump line
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Renderer that stops the emulation on SIGINT/SIGTERM and dumps the machine state on SIGUSR1
+struct SignalRenderer {
+    /// The wrapped renderer
+    renderer: Box<dyn Renderer>,
+
+    /// Flag set by the SIGINT and SIGTERM handlers
+    interrupt: Arc<AtomicBool>,
+
+    /// Flag set by the SIGUSR1 handler (cleared when the state is dumped)
+    dump: Arc<AtomicBool>,
+
+    /// Boolean set to true if the emulation was interrupted
+    interrupted: bool,
+}
+
+impl Renderer for SignalRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        self.renderer.render(display, dirty_rows);
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.renderer.tick(display);
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.renderer.status(status);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        self.renderer.debug(chip8)
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        if self.dump.swap(false, Ordering::Relaxed) {
+            info!(
+                "state dump: {}\n{}",
+                crate::control::dump_registers(chip8),
+                hexdump(chip8.memory())
+            );
+        }
+        if !self.interrupted && self.interrupt.load(Ordering::Relaxed) {
+            info!("interrupt received: stopping the emulation");
+            self.interrupted = true;
+        }
+
+        self.renderer.control(chip8);
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        self.renderer.hotkeys()
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.renderer.fast_forward()
+    }
+
+    fn has_keypad(&self) -> bool {
+        self.renderer.has_keypad()
+    }
+
+    fn keypad(&self) -> u16 {
+        self.renderer.keypad()
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.renderer.quit_requested() || self.interrupted
+    }
+}
+
+/// Installs the signal handlers and returns the renderer that acts on the signals received
+///
+/// # Arguments
+///
+/// * `renderer` - The renderer of the emulation
+///
+/// # Panics
+///
+/// The function panics in case of errors during installation of the signal handlers
+pub fn attach(renderer: Box<dyn Renderer>) -> Box<dyn Renderer> {
+    trace!("signals::attach: start");
+
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let dump = Arc::new(AtomicBool::new(false));
+    register(&interrupt, &dump);
+
+    trace!("signals::attach: exit");
+
+    Box::new(SignalRenderer {
+        renderer,
+        interrupt,
+        dump,
+        interrupted: false,
+    })
+}
+
+/// Registers the signal handlers that set the flags
+///
+/// # Arguments
+///
+/// * `interrupt` - The flag set by SIGINT and SIGTERM
+/// * `dump` - The flag set by SIGUSR1
+///
+/// # Panics
+///
+/// The function panics in case of errors during installation of the signal handlers
+#[cfg(unix)]
+fn register(interrupt: &Arc<AtomicBool>, dump: &Arc<AtomicBool>) {
+    use signal_hook::{consts, flag};
+
+    for signal in [consts::SIGINT, consts::SIGTERM] {
+        // the second signal exits immediately (e.g. the emulation does not stop)
+        let result = flag::register_conditional_shutdown(
+            signal,
+            crate::exit::INTERRUPTED,
+            Arc::clone(interrupt),
+        )
+        .and_then(|_| flag::register(signal, Arc::clone(interrupt)));
+        if let Err(e) = result {
+            panic!("installing signal handler: {e}")
+        }
+    }
+    if let Err(e) = flag::register(consts::SIGUSR1, Arc::clone(dump)) {
+        panic!("installing signal handler: {e}")
+    }
+}
+
+/// Registers the signal handlers (not supported on this platform: the flags are never set)
+#[cfg(not(unix))]
+fn register(_interrupt: &Arc<AtomicBool>, _dump: &Arc<AtomicBool>) {}
+
+/// Returns the hexdump of the memory: the address and 16 bytes per line,
+/// repeated lines are replaced by a `*` line
+///
+/// # Arguments
+///
+/// * `memory` - The memory to dump
+fn hexdump(memory: &[u8]) -> String {
+    let mut lines = Vec::new();
+    let mut previous: Option<&[u8]> = None;
+    let mut repeated = false;
+    for (n, line) in memory.chunks(HEXDUMP_WIDTH).enumerate() {
+        if previous == Some(line) {
+            if !repeated {
+                lines.push(String::from("*"));
+                repeated = true;
+            }
+            continue;
+        }
+        let bytes: Vec<String> = line.iter().map(|byte| format!("{byte:02X}")).collect();
+        lines.push(format!("{:03X}: {}", n * HEXDUMP_WIDTH, bytes.join(" ")));
+        previous = Some(line);
+        repeated = false;
+    }
+    lines.push(format!("{:03X}", memory.len()));
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessRenderer;
+
+    /// Returns a signal renderer without signal handlers
+    fn renderer() -> SignalRenderer {
+        SignalRenderer {
+            renderer: Box::new(HeadlessRenderer),
+            interrupt: Arc::new(AtomicBool::new(false)),
+            dump: Arc::new(AtomicBool::new(false)),
+            interrupted: false,
+        }
+    }
+
+    #[test]
+    fn interrupt_stops_the_emulation() {
+        let mut renderer = renderer();
+        let mut chip8 = Chip8::new();
+        renderer.control(&mut chip8);
+        assert!(!renderer.quit_requested());
+
+        renderer.interrupt.store(true, Ordering::Relaxed);
+        renderer.control(&mut chip8);
+        assert!(renderer.quit_requested());
+    }
+
+    #[test]
+    fn dump_request_is_served_once() {
+        let mut renderer = renderer();
+        renderer.dump.store(true, Ordering::Relaxed);
+        renderer.control(&mut Chip8::new());
+
+        assert!(!renderer.dump.load(Ordering::Relaxed));
+        assert!(!renderer.quit_requested());
+    }
+
+    #[test]
+    fn repeated_hexdump_lines_are_collapsed() {
+        let mut memory = [0; 80];
+        memory[1] = 0xAB;
+        memory[79] = 0xCD;
+
+        let zeros = ["00"; HEXDUMP_WIDTH].join(" ");
+        let last = format!("{} CD", ["00"; HEXDUMP_WIDTH - 1].join(" "));
+        assert_eq!(
+            hexdump(&memory),
+            format!(
+                "000: 00 AB {}\n010: {zeros}\n*\n040: {last}\n050",
+                ["00"; HEXDUMP_WIDTH - 2].join(" ")
+            )
+        );
+    }
+}
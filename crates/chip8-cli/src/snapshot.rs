@@ -0,0 +1,277 @@
+//! Golden-frame snapshots (`snapshot` subcommand): the display after running a ROM
+//! for a number of instructions is written to a golden file, or compared with it.
+//!
+//! Golden files are plain text, one line per display row (`#` if the pixel is on, `.` otherwise),
+//! so that they can be reviewed and diffed in version control: 32 lines of 64 pixels,
+//! or 64 lines of 128 pixels for a display in the SUPER-CHIP hires mode
+
+use crate::cli::SnapshotArgs;
+use chip8_core::{Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH};
+use std::fs;
+use tracing::{info, trace};
+
+/// golden file character of the pixels that are on
+const PIXEL_ON: char = '#';
+
+/// golden file character of the pixels that are off
+const PIXEL_OFF: char = '.';
+
+/// diff character of the pixels that are on but are off in the golden file
+const PIXEL_ADDED: char = '+';
+
+/// diff character of the pixels that are off but are on in the golden file
+const PIXEL_REMOVED: char = '-';
+
+/// Runs the ROM and writes the final display to the golden file, or verifies it against the golden file.
+/// Returns false if the display differs from the golden one
+///
+/// # Arguments
+///
+/// * `args` - The snapshot subcommand arguments
+/// * `seed` - The seed for the random number generator
+///
+/// # Panics
+///
+/// The function panics in case of errors during reading of the ROM file, during reading or parsing
+/// of the golden file to verify, or during writing of the golden file
+pub fn run(args: &SnapshotArgs, seed: u64) -> bool {
+    trace!("snapshot::run: start");
+
+    let (mut chip8, mut rng) = Chip8::builder().rng_seed(seed).build_with_rng();
+    chip8.load_rom(&args.rom);
+
+    for _ in 0..args.cycles {
+        if let Err(e) = chip8.step(&mut rng) {
+            panic!("{e}")
+        }
+    }
+
+    let passed = if let Some(path) = &args.output {
+        if let Err(e) = fs::write(path, display_ascii(&chip8) + "\n") {
+            panic!("writing snapshot `{}`: {e}", path.display())
+        }
+        info!("snapshot written to `{}`", path.display());
+        true
+    } else if let Some(path) = &args.verify_snapshot {
+        let golden = match fs::read_to_string(path) {
+            Ok(contents) => match parse(&contents) {
+                Ok(golden) => golden,
+                Err(e) => {
+                    panic!("parsing snapshot `{}`: {e}", path.display())
+                }
+            },
+            Err(e) => {
+                panic!("reading snapshot `{}`: {e}", path.display())
+            }
+        };
+
+        let (width, height) = chip8.display_size();
+        let golden_size = (golden[0].len(), golden.len());
+        if golden_size != (width, height) {
+            println!("{}", display_ascii(&chip8));
+            println!(
+                "snapshot `{}` differs: the display (above) is {width}x{height}, the golden display is {}x{}",
+                path.display(),
+                golden_size.0,
+                golden_size.1
+            );
+            false
+        } else {
+            let mismatches = (0..height * width)
+                .filter(|i| chip8.pixel(i % width, i / width) != golden[i / width][i % width])
+                .count();
+            if mismatches == 0 {
+                println!("snapshot `{}` matches", path.display());
+            } else {
+                println!("{}", display_diff(&chip8, &golden));
+                println!(
+                    "snapshot `{}` differs: {mismatches} pixels (`{PIXEL_ADDED}` on, expected off; `{PIXEL_REMOVED}` off, expected on)",
+                    path.display()
+                );
+            }
+            mismatches == 0
+        }
+    } else {
+        unreachable!("the output or the snapshot to verify is required")
+    };
+
+    trace!("snapshot::run: exit");
+
+    passed
+}
+
+/// Returns the CHIP-8 display as ASCII art (`#` if the pixel is on, `.` otherwise)
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+pub fn display_ascii(chip8: &Chip8) -> String {
+    let (width, height) = chip8.display_size();
+
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    if chip8.pixel(x, y) {
+                        PIXEL_ON
+                    } else {
+                        PIXEL_OFF
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns the CHIP-8 display as ASCII art with the pixels that differ from the golden display
+/// marked with `+` (on, expected off) and `-` (off, expected on)
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+/// * `golden` - The golden display rows (of the size of the display)
+fn display_diff(chip8: &Chip8, golden: &[Vec<bool>]) -> String {
+    let (width, height) = chip8.display_size();
+
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| match (chip8.pixel(x, y), golden[y][x]) {
+                    (true, true) => PIXEL_ON,
+                    (false, false) => PIXEL_OFF,
+                    (true, false) => PIXEL_ADDED,
+                    (false, true) => PIXEL_REMOVED,
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Parses a golden file into the display rows (true if the pixel is on)
+///
+/// # Arguments
+///
+/// * `contents` - The golden file contents
+fn parse(contents: &str) -> Result<Vec<Vec<bool>>, String> {
+    let rows: Vec<&str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|row| !row.is_empty())
+        .collect();
+    // the display size is given by the number of rows (lores or hires mode)
+    let width = match rows.len() {
+        DISPLAY_HEIGTH => DISPLAY_WIDTH,
+        HIRES_DISPLAY_HEIGTH => HIRES_DISPLAY_WIDTH,
+        _ => {
+            return Err(format!(
+                "expected {DISPLAY_HEIGTH} or {HIRES_DISPLAY_HEIGTH} rows, found {}",
+                rows.len()
+            ))
+        }
+    };
+
+    rows.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let pixels = row
+                .chars()
+                .map(|c| match c {
+                    PIXEL_ON => Ok(true),
+                    PIXEL_OFF => Ok(false),
+                    _ => Err(format!("row {y}: invalid character `{c}`")),
+                })
+                .collect::<Result<Vec<bool>, String>>()?;
+            if pixels.len() != width {
+                return Err(format!(
+                    "row {y}: expected {width} pixels, found {}",
+                    pixels.len()
+                ));
+            }
+            Ok(pixels)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        path::{Path, PathBuf},
+    };
+
+    /// seed of the random number generator (default of `--random-seed`)
+    const SEED: u64 = 10;
+
+    /// ROM drawing the glyph of 0 at the top left corner in hires mode (00FF, A000, D005)
+    const HIRES_ROM: [u8; 6] = [0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x05];
+
+    /// ROM drawing the glyph of 0 at the top left corner in lores mode (A000, D005)
+    const LORES_ROM: [u8; 4] = [0xA0, 0x00, 0xD0, 0x05];
+
+    /// Returns a temporary directory of the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust-chip-8-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes the golden file of a ROM and returns the arguments verifying it
+    fn write_golden(dir: &Path, rom: &[u8], cycles: u64) -> SnapshotArgs {
+        let mut args = SnapshotArgs {
+            rom: dir.join("game.ch8"),
+            cycles,
+            output: Some(dir.join("game.txt")),
+            verify_snapshot: None,
+        };
+        fs::write(&args.rom, rom).unwrap();
+        assert!(run(&args, SEED));
+
+        args.verify_snapshot = args.output.take();
+        args
+    }
+
+    #[test]
+    fn hires_display_is_written_and_verified() {
+        let dir = temp_dir("snapshot-hires");
+        let args = write_golden(&dir, &HIRES_ROM, 3);
+
+        let golden = fs::read_to_string(args.verify_snapshot.as_ref().unwrap()).unwrap();
+        let rows = parse(&golden).unwrap();
+        assert_eq!((rows[0].len(), rows.len()), (128, 64));
+        assert!(golden.starts_with("####...."));
+        assert!(run(&args, SEED));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn golden_display_of_another_size_differs() {
+        let dir = temp_dir("snapshot-size");
+        let args = write_golden(&dir, &LORES_ROM, 2);
+
+        // same drawing in hires mode
+        fs::write(&args.rom, HIRES_ROM).unwrap();
+        let args = SnapshotArgs { cycles: 3, ..args };
+        assert!(!run(&args, SEED));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn golden_file_size_is_checked() {
+        assert!(parse(&".".repeat(64)).is_err());
+
+        let lores = format!("{}\n", ".".repeat(64)).repeat(32);
+        assert_eq!(parse(&lores).unwrap().len(), 32);
+
+        // 64 rows of 64 pixels: hires rows are 128 pixels wide
+        let short = format!("{}\n", ".".repeat(64)).repeat(64);
+        assert_eq!(
+            parse(&short),
+            Err(String::from("row 0: expected 128 pixels, found 64"))
+        );
+    }
+}
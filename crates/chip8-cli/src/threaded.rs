@@ -0,0 +1,358 @@
+//! Threaded frontend: the emulation runs in its own thread and talks to the display renderer through channels
+//! (display frames and status out, keys and hotkeys in), so that a slow frontend never stalls the timing loop
+
+use chip8_core::{Chip8, MAX_DISPLAY_SIZE};
+use chip8_frontend::{EmulationStatus, Hotkey, Renderer};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU16, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tracing::trace;
+
+/// number of messages queued to the frontend: beyond it the frames are dropped
+const FRONTEND_QUEUE: usize = 4;
+
+/// maximum time the frontend waits for a message before ticking on its own (60Hz),
+/// so that its window keeps handling events while the emulation is busy
+const FRONTEND_POLL_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Message sent by the emulation thread to the frontend
+enum Message {
+    /// Display frame, with the mask of the rows modified since the last frame sent
+    Render(Box<[bool; MAX_DISPLAY_SIZE]>, u64),
+    /// Timer tick, with the display pixels
+    Tick(Box<[bool; MAX_DISPLAY_SIZE]>),
+    /// Emulation status
+    Status(EmulationStatus),
+    /// Machine state, sent when the keys held down or the last key read by FX0A change (keypad overlay)
+    Machine(Box<Chip8>),
+}
+
+/// Frontend input shared with the emulation thread
+#[derive(Default)]
+struct Input {
+    /// CHIP-8 keys held down (bit n set if key n is pressed)
+    keys: AtomicU16,
+
+    /// Boolean set to true while the fast-forward key is held down
+    fast_forward: AtomicBool,
+
+    /// Boolean set to true if the user asked to quit the emulation
+    quit: AtomicBool,
+
+    /// Hotkeys pressed since the last call of `hotkeys`
+    hotkeys: Mutex<Vec<Hotkey>>,
+}
+
+/// Renderer used by the emulation thread: the frames are sent to the frontend without waiting for it
+/// (dropped if the frontend is behind) and the input is read from the last one published by the frontend
+pub struct ThreadedRenderer {
+    /// Sender of the messages to the frontend
+    sender: SyncSender<Message>,
+
+    /// Frontend input
+    input: Arc<Input>,
+
+    /// Boolean set to true if the frontend has a keypad input
+    has_keypad: bool,
+
+    /// Mask of the display rows modified in the frames dropped, redrawn with the next frame sent
+    dropped_rows: u64,
+
+    /// Keys held down and last key read by FX0A in the last machine state sent
+    keypad_state: (u16, Option<u8>),
+}
+
+/// Frontend side of the threaded frontend: drives the display renderer on the calling thread
+/// with the messages of the emulation thread
+pub struct FrontendLoop {
+    /// The display renderer
+    renderer: Box<dyn Renderer>,
+
+    /// Receiver of the messages of the emulation thread
+    receiver: Receiver<Message>,
+
+    /// Frontend input
+    input: Arc<Input>,
+}
+
+impl ThreadedRenderer {
+    /// Splits the display renderer into the renderer of the emulation thread and the loop that drives it
+    /// on the calling thread (window frontends must stay on the main thread)
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - The display renderer
+    pub fn new(renderer: Box<dyn Renderer>) -> (Self, FrontendLoop) {
+        let (sender, receiver) = mpsc::sync_channel(FRONTEND_QUEUE);
+        let input = Arc::new(Input::default());
+
+        (
+            Self {
+                sender,
+                input: input.clone(),
+                has_keypad: renderer.has_keypad(),
+                dropped_rows: 0,
+                keypad_state: (0, None),
+            },
+            FrontendLoop {
+                renderer,
+                receiver,
+                input,
+            },
+        )
+    }
+
+    /// Sends a message to the frontend, without waiting for it.
+    /// Returns false if the message has been dropped (frontend behind or stopped)
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to send
+    fn send(&mut self, message: Message) -> bool {
+        match self.sender.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Disconnected(_)) => {
+                self.input.quit.store(true, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+impl Renderer for ThreadedRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        let dirty_rows = self.dropped_rows | dirty_rows;
+        let mut pixels = Box::new([false; MAX_DISPLAY_SIZE]);
+        pixels.copy_from_slice(display);
+        self.dropped_rows = if self.send(Message::Render(pixels, dirty_rows)) {
+            0
+        } else {
+            dirty_rows
+        };
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        let mut pixels = Box::new([false; MAX_DISPLAY_SIZE]);
+        pixels.copy_from_slice(display);
+        self.send(Message::Tick(pixels));
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        self.send(Message::Status(*status));
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        let keypad_state = (chip8.keys(), chip8.last_waited_key());
+        if keypad_state != self.keypad_state && self.send(Message::Machine(Box::new(chip8.clone())))
+        {
+            self.keypad_state = keypad_state;
+        }
+
+        true
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        match self.input.hotkeys.lock() {
+            Ok(mut hotkeys) => std::mem::take(&mut *hotkeys),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.input.fast_forward.load(Ordering::Relaxed)
+    }
+
+    fn has_keypad(&self) -> bool {
+        self.has_keypad
+    }
+
+    fn keypad(&self) -> u16 {
+        self.input.keys.load(Ordering::Relaxed)
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.input.quit.load(Ordering::Relaxed)
+    }
+}
+
+impl FrontendLoop {
+    /// Drives the display renderer until the emulation thread drops its renderer,
+    /// then drops the display renderer (e.g. restoring the terminal)
+    pub fn run(mut self) {
+        trace!("FrontendLoop::run: start");
+
+        let mut display = Box::new([false; MAX_DISPLAY_SIZE]);
+        loop {
+            match self.receiver.recv_timeout(FRONTEND_POLL_TIME) {
+                Ok(Message::Render(pixels, dirty_rows)) => {
+                    self.renderer.render(&*pixels, dirty_rows);
+                    display = pixels;
+                }
+                Ok(Message::Tick(pixels)) => {
+                    self.renderer.tick(&*pixels);
+                    display = pixels;
+                }
+                Ok(Message::Status(status)) => self.renderer.status(&status),
+                Ok(Message::Machine(chip8)) => {
+                    self.renderer.debug(&chip8);
+                }
+                // the emulation is busy (or paused in stepping mode): the frontend keeps handling its events
+                Err(RecvTimeoutError::Timeout) => self.renderer.tick(&*display),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            // publish the input of the frontend
+            self.input
+                .keys
+                .store(self.renderer.keypad(), Ordering::Relaxed);
+            self.input
+                .fast_forward
+                .store(self.renderer.fast_forward(), Ordering::Relaxed);
+            if self.renderer.quit_requested() {
+                self.input.quit.store(true, Ordering::Relaxed);
+            }
+            let hotkeys = self.renderer.hotkeys();
+            if !hotkeys.is_empty() {
+                if let Ok(mut pending) = self.input.hotkeys.lock() {
+                    pending.extend(hotkeys);
+                }
+            }
+        }
+
+        trace!("FrontendLoop::run: exit");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Display renderer recording the calls of the frontend loop
+    struct Recorder {
+        /// calls of the frontend loop
+        calls: Arc<Mutex<Vec<String>>>,
+
+        /// Boolean set to true once the hotkeys have been returned
+        hotkeys_sent: bool,
+    }
+
+    impl Renderer for Recorder {
+        fn render(&mut self, display: &[bool], dirty_rows: u64) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("render {} {dirty_rows}", display[0]));
+        }
+
+        fn status(&mut self, status: &EmulationStatus) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("status {}", status.cycles));
+        }
+
+        fn debug(&mut self, chip8: &Chip8) -> bool {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("keys {}", chip8.keys()));
+            true
+        }
+
+        fn hotkeys(&mut self) -> Vec<Hotkey> {
+            if std::mem::replace(&mut self.hotkeys_sent, true) {
+                Vec::new()
+            } else {
+                vec![Hotkey::TogglePause]
+            }
+        }
+
+        fn has_keypad(&self) -> bool {
+            true
+        }
+
+        fn keypad(&self) -> u16 {
+            0x0021
+        }
+
+        fn quit_requested(&self) -> bool {
+            true
+        }
+    }
+
+    fn threaded() -> (ThreadedRenderer, FrontendLoop, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (renderer, frontend) = ThreadedRenderer::new(Box::new(Recorder {
+            calls: calls.clone(),
+            hotkeys_sent: false,
+        }));
+
+        (renderer, frontend, calls)
+    }
+
+    #[test]
+    fn messages_and_input_go_through_the_channels() {
+        let (mut renderer, frontend, calls) = threaded();
+        assert!(renderer.has_keypad());
+        let input = renderer.input.clone();
+
+        let mut display = [false; MAX_DISPLAY_SIZE];
+        display[0] = true;
+        renderer.render(&display, 1);
+        renderer.status(&EmulationStatus {
+            cycles: 42,
+            ..EmulationStatus::default()
+        });
+        let mut chip8 = Chip8::new();
+        chip8.key_down(3);
+        renderer.debug(&chip8);
+        // the machine state is sent only when the keypad state changes
+        renderer.debug(&chip8);
+        drop(renderer);
+        frontend.run();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["render true 1", "status 42", "keys 8"]
+        );
+        assert_eq!(input.keys.load(Ordering::Relaxed), 0x0021);
+        assert!(input.quit.load(Ordering::Relaxed));
+        assert_eq!(*input.hotkeys.lock().unwrap(), [Hotkey::TogglePause]);
+    }
+
+    #[test]
+    fn rows_of_dropped_frames_are_sent_with_the_next_frame() {
+        let (mut renderer, frontend, _) = threaded();
+        let display = [false; MAX_DISPLAY_SIZE];
+
+        for _ in 0..FRONTEND_QUEUE {
+            renderer.render(&display, 1);
+        }
+        // the frontend is behind: the frames are dropped
+        renderer.render(&display, 2);
+        renderer.render(&display, 4);
+        assert_eq!(renderer.dropped_rows, 6);
+
+        frontend.receiver.recv().unwrap();
+        renderer.render(&display, 8);
+        assert_eq!(renderer.dropped_rows, 0);
+        let last = frontend.receiver.try_iter().last();
+        assert!(matches!(last, Some(Message::Render(_, 14))));
+    }
+
+    #[test]
+    fn stopped_frontend_quits_the_emulation() {
+        let (mut renderer, frontend, _) = threaded();
+        drop(frontend);
+
+        assert!(!renderer.quit_requested());
+        renderer.tick(&[false; MAX_DISPLAY_SIZE]);
+        assert!(renderer.quit_requested());
+    }
+}
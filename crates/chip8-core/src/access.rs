@@ -0,0 +1,235 @@
+//! Implementation of CHIP-8 (memory access tracing)
+
+use super::{Chip8, Error};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{fmt::Display, ops::RangeInclusive};
+
+/// number of memory accesses kept in the access log: beyond it the oldest ones are discarded
+pub const ACCESS_LOG_SIZE: usize = 4096;
+
+/// Kind of a memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    /// Memory read by an instruction (DXYN, FX65)
+    Read,
+    /// Memory write by an instruction (FX33, FX55)
+    Write,
+}
+
+/// Memory access of an instruction, recorded in the access log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// Kind of the access
+    pub kind: AccessKind,
+
+    /// Memory address accessed
+    pub address: u16,
+
+    /// Byte read or written
+    pub value: u8,
+
+    /// Address of the instruction that accessed the memory
+    pub pc: u16,
+
+    /// Cycle number of the instruction (instructions executed with `step` before it)
+    pub cycle: u64,
+}
+
+/// Memory access log: the data accesses of the instructions within the traced address ranges
+#[derive(Clone, Default)]
+pub(super) struct AccessLog {
+    /// Traced address ranges (no access is recorded if empty)
+    ranges: Vec<RangeInclusive<u16>>,
+
+    /// Recorded accesses, from the oldest one
+    accesses: VecDeque<MemoryAccess>,
+}
+
+impl Chip8 {
+    /// Enables the tracing of the memory accesses within an address range: every read and write
+    /// of the instructions (DXYN, FX33, FX55, FX65; not the opcode fetches) is recorded with its PC
+    /// and cycle number, up to the last `ACCESS_LOG_SIZE` accesses (see `memory_accesses`)
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The traced address range (inclusive)
+    pub fn trace_memory(&mut self, range: RangeInclusive<u16>) {
+        if !self.access_log.ranges.contains(&range) {
+            self.access_log.ranges.push(range);
+        }
+    }
+
+    /// Disables the tracing of the memory accesses within an address range enabled with `trace_memory`
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The traced address range
+    pub fn untrace_memory(&mut self, range: &RangeInclusive<u16>) {
+        self.access_log.ranges.retain(|traced| traced != range);
+    }
+
+    /// Returns the traced address ranges
+    pub fn traced_memory(&self) -> &[RangeInclusive<u16>] {
+        &self.access_log.ranges
+    }
+
+    /// Returns the memory accesses recorded within the traced address ranges, from the oldest one
+    pub fn memory_accesses(&self) -> impl DoubleEndedIterator<Item = &MemoryAccess> {
+        self.access_log.accesses.iter()
+    }
+
+    /// Clears the recorded memory accesses (the address ranges stay traced)
+    pub fn clear_memory_accesses(&mut self) {
+        self.access_log.accesses.clear();
+    }
+
+    /// Reads a byte of memory for the current instruction, recording the access if traced
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address
+    pub(super) fn load(&mut self, address: usize) -> Result<u8, Error> {
+        let value = self.memory[self.memory_address(address)?];
+        self.record_access(AccessKind::Read, address, value);
+        self.check_initialized(address);
+
+        Ok(value)
+    }
+
+    /// Writes a byte of memory for the current instruction, recording the access if traced
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address
+    /// * `value` - The byte to write
+    pub(super) fn store(&mut self, address: usize, value: u8) -> Result<(), Error> {
+        let address = self.memory_address(address)?;
+        self.record_undo_write(address);
+        self.memory[address] = value;
+        self.record_access(AccessKind::Write, address, value);
+        self.track_write(address);
+        self.mark_initialized(address..address + 1);
+        self.invalidate_decoded(address..address + 1);
+
+        Ok(())
+    }
+
+    /// Records a memory access of the current instruction, if its address is traced
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the access
+    /// * `address` - The memory address, within memory
+    /// * `value` - The byte read or written
+    fn record_access(&mut self, kind: AccessKind, address: usize, value: u8) {
+        // cheap check: nothing is recorded when tracing is off
+        if self.access_log.ranges.is_empty() {
+            return;
+        }
+
+        let address = address as u16;
+        if !self
+            .access_log
+            .ranges
+            .iter()
+            .any(|range| range.contains(&address))
+        {
+            return;
+        }
+        if self.access_log.accesses.len() == ACCESS_LOG_SIZE {
+            self.access_log.accesses.pop_front();
+        }
+        self.access_log.accesses.push_back(MemoryAccess {
+            kind,
+            address,
+            value,
+            pc: self.pc,
+            cycle: self.cycles,
+        });
+    }
+}
+
+// Display trait implementation for MemoryAccess
+impl Display for MemoryAccess {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let kind = match self.kind {
+            AccessKind::Read => "read",
+            AccessKind::Write => "write",
+        };
+        write!(
+            f,
+            "cycle {}: {:#05X} {kind} {:#05X} = {:#04X}",
+            self.cycle, self.pc, self.address, self.value
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with a ROM loaded that writes the BCD digits of 7 at 0x300 and reads them back
+    fn with_rom() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x60, 0x07, 0xA3, 0x00, 0xF0, 0x33, 0xF2, 0x65]);
+        chip8
+    }
+
+    /// Executes instructions
+    fn run(chip8: &mut Chip8, instructions: usize) {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..instructions {
+            chip8.step(&mut rng).unwrap();
+        }
+    }
+
+    #[test]
+    fn accesses_within_the_traced_ranges_are_recorded() {
+        let mut chip8 = with_rom();
+        chip8.trace_memory(0x301..=0x302);
+        run(&mut chip8, 4);
+
+        let accesses: Vec<_> = chip8
+            .memory_accesses()
+            .map(|access| (access.kind, access.address, access.value, access.cycle))
+            .collect();
+        assert_eq!(
+            accesses,
+            [
+                (AccessKind::Write, 0x301, 0, 2),
+                (AccessKind::Write, 0x302, 7, 2),
+                (AccessKind::Read, 0x301, 0, 3),
+                (AccessKind::Read, 0x302, 7, 3),
+            ]
+        );
+        let last = chip8.memory_accesses().next_back().unwrap();
+        assert_eq!(last.to_string(), "cycle 3: 0x206 read 0x302 = 0x07");
+    }
+
+    #[test]
+    fn nothing_is_recorded_without_traced_ranges() {
+        let mut chip8 = with_rom();
+        chip8.trace_memory(0x300..=0x300);
+        chip8.trace_memory(0x300..=0x300);
+        assert_eq!(chip8.traced_memory(), [0x300..=0x300]);
+        chip8.untrace_memory(&(0x300..=0x300));
+        run(&mut chip8, 4);
+
+        assert_eq!(chip8.memory_accesses().count(), 0);
+    }
+
+    #[test]
+    fn clear_keeps_the_traced_ranges() {
+        let mut chip8 = with_rom();
+        chip8.trace_memory(0x300..=0x302);
+        run(&mut chip8, 3);
+        assert_eq!(chip8.memory_accesses().count(), 3);
+
+        chip8.clear_memory_accesses();
+        assert_eq!(chip8.memory_accesses().count(), 0);
+        run(&mut chip8, 1);
+        assert_eq!(chip8.memory_accesses().count(), 3);
+    }
+}
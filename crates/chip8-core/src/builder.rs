@@ -0,0 +1,405 @@
+//! Implementation of CHIP-8 (builder of configured instances)
+
+use super::{
+    Chip8, InstructionTiming, MachineCodePolicy, Profile, Quirks, SelfModifyingCodePolicy,
+    CLOCK_FREQUENCY, HISTORY_DEPTH, MEMORY_SIZE,
+};
+use alloc::vec::Vec;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Builder of a configured CHIP-8 instance, e.g.
+/// `Chip8::builder().clock_hz(700.0).memory_size(4096).rng_seed(42).build_with_rng()`.
+/// The options not set keep the defaults of `Chip8::new`
+#[derive(Clone, Debug)]
+pub struct Chip8Builder {
+    /// RAM memory size in bytes
+    memory_size: usize,
+
+    /// Program start address (None: the default one)
+    start_address: Option<u16>,
+
+    /// Alternate fonts (fontset or hires fontset)
+    fonts: Vec<Vec<u8>>,
+
+    /// Clock frequency (Hz) of `step`
+    clock_hz: f64,
+
+    /// Timing model of the instructions executed with `step`
+    timing: InstructionTiming,
+
+    /// Quirks of the instructions
+    quirks: Quirks,
+
+    /// Policy applied to the `0NNN` instructions
+    machine_code: MachineCodePolicy,
+
+    /// Policy applied to self-modifying code
+    self_modifying_code: SelfModifyingCodePolicy,
+
+    /// Boolean set to true to warn about the reads of uninitialized memory
+    uninitialized_read_warnings: bool,
+
+    /// Sampling of the per-instruction logging
+    trace_sample: u64,
+
+    /// Number of executed instructions that can be undone
+    undo_depth: usize,
+
+    /// Number of executed instructions kept in the history
+    history_depth: usize,
+
+    /// Seed of the random number generator returned by `build_with_rng`
+    rng_seed: u64,
+}
+
+impl Default for Chip8Builder {
+    fn default() -> Self {
+        Self {
+            memory_size: MEMORY_SIZE,
+            start_address: None,
+            fonts: Vec::new(),
+            clock_hz: CLOCK_FREQUENCY,
+            timing: InstructionTiming::Uniform,
+            quirks: Quirks::default(),
+            machine_code: MachineCodePolicy::default(),
+            self_modifying_code: SelfModifyingCodePolicy::default(),
+            uninitialized_read_warnings: false,
+            trace_sample: 1,
+            undo_depth: 0,
+            history_depth: HISTORY_DEPTH,
+            rng_seed: 0,
+        }
+    }
+}
+
+impl Chip8Builder {
+    /// Sets the RAM memory size in bytes (see `Chip8::set_memory_size`)
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The memory size in bytes, a power of two between 1KB and 64KB
+    pub fn memory_size(mut self, size: usize) -> Self {
+        self.memory_size = size;
+        self
+    }
+
+    /// Sets the program start address (see `Chip8::set_start_address`)
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The program start address
+    pub fn start_address(mut self, address: u16) -> Self {
+        self.start_address = Some(address);
+        self
+    }
+
+    /// Sets an alternate font: 80 bytes replace the fontset, 160 bytes are loaded as hires fontset
+    /// (see `Chip8::set_font`). Both can be set, calling the function twice
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The font data
+    pub fn font(mut self, font: &[u8]) -> Self {
+        self.fonts.push(font.to_vec());
+        self
+    }
+
+    /// Sets the clock frequency (Hz) of `step` (see `Chip8::set_clock_frequency`)
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The instructions per second
+    pub fn clock_hz(mut self, frequency: f64) -> Self {
+        self.clock_hz = frequency;
+        self
+    }
+
+    /// Sets the timing model of the instructions executed with `step` (see `Chip8::set_instruction_timing`)
+    ///
+    /// # Arguments
+    ///
+    /// * `timing` - The timing model
+    pub fn instruction_timing(mut self, timing: InstructionTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Sets the sprite wrapping quirk of DXYN (see `Chip8::set_sprite_wrapping`)
+    ///
+    /// # Arguments
+    ///
+    /// * `wrap` - Boolean set to true to wrap the sprites around the screen edges
+    pub fn sprite_wrapping(mut self, wrap: bool) -> Self {
+        self.quirks.sprite_wrapping = wrap;
+        self
+    }
+
+    /// Sets the half pixel scroll quirk of the scroll instructions in lores mode
+    /// (see `Chip8::set_half_pixel_scroll`)
+    ///
+    /// # Arguments
+    ///
+    /// * `half` - Boolean set to true to scroll the lores display by half the distance
+    pub fn half_pixel_scroll(mut self, half: bool) -> Self {
+        self.quirks.half_pixel_scroll = half;
+        self
+    }
+
+    /// Sets the lores quirk of DXY0 (see `Chip8::set_lores_tall_sprites`)
+    ///
+    /// # Arguments
+    ///
+    /// * `tall` - Boolean set to true to draw 8x16 sprites with DXY0 in lores mode
+    pub fn lores_tall_sprites(mut self, tall: bool) -> Self {
+        self.quirks.lores_tall_sprites = tall;
+        self
+    }
+
+    /// Sets the collision rows quirk of DXYN in hires mode (see `Chip8::set_collision_rows`)
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Boolean set to true to count the collision rows in hires mode
+    pub fn collision_rows(mut self, count: bool) -> Self {
+        self.quirks.collision_rows = count;
+        self
+    }
+
+    /// Sets the VF reset quirk of 8XY1, 8XY2 and 8XY3 (see `Chip8::set_vf_reset`)
+    ///
+    /// # Arguments
+    ///
+    /// * `reset` - Boolean set to true to reset VF with the logical instructions
+    pub fn vf_reset(mut self, reset: bool) -> Self {
+        self.quirks.vf_reset = reset;
+        self
+    }
+
+    /// Sets the shift quirk of 8XY6 and 8XYE (see `Chip8::set_shift_vy`)
+    ///
+    /// # Arguments
+    ///
+    /// * `shift_vy` - Boolean set to true to shift VY into VX
+    pub fn shift_vy(mut self, shift_vy: bool) -> Self {
+        self.quirks.shift_vy = shift_vy;
+        self
+    }
+
+    /// Sets the memory quirk of FX55 and FX65 (see `Chip8::set_memory_increment`)
+    ///
+    /// # Arguments
+    ///
+    /// * `increment` - Boolean set to true to increment I with FX55 and FX65
+    pub fn memory_increment(mut self, increment: bool) -> Self {
+        self.quirks.memory_increment = increment;
+        self
+    }
+
+    /// Sets the jump quirk of BNNN (see `Chip8::set_jump_vx`)
+    ///
+    /// # Arguments
+    ///
+    /// * `jump_vx` - Boolean set to true to jump to XNN + VX
+    pub fn jump_vx(mut self, jump_vx: bool) -> Self {
+        self.quirks.jump_vx = jump_vx;
+        self
+    }
+
+    /// Sets the quirks of a compatibility profile (see `Chip8::set_profile`)
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The compatibility profile
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.quirks = profile.quirks();
+        self
+    }
+
+    /// Sets the policy applied to the `0NNN` instructions (see `Chip8::set_machine_code_policy`)
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy of the `0NNN` instructions
+    pub fn machine_code(mut self, policy: MachineCodePolicy) -> Self {
+        self.machine_code = policy;
+        self
+    }
+
+    /// Sets the policy applied to self-modifying code (see `Chip8::set_self_modifying_code_policy`)
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy applied to self-modifying code
+    pub fn self_modifying_code(mut self, policy: SelfModifyingCodePolicy) -> Self {
+        self.self_modifying_code = policy;
+        self
+    }
+
+    /// Enables the warnings of the reads of uninitialized memory
+    /// (see `Chip8::set_uninitialized_read_warnings`)
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Boolean set to true to enable the warnings
+    pub fn uninitialized_read_warnings(mut self, enabled: bool) -> Self {
+        self.uninitialized_read_warnings = enabled;
+        self
+    }
+
+    /// Sets the sampling of the per-instruction logging (see `Chip8::set_trace_sample`)
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - The number of instructions between two logged instructions
+    pub fn trace_sample(mut self, sample: u64) -> Self {
+        self.trace_sample = sample;
+        self
+    }
+
+    /// Sets the number of executed instructions that can be undone (see `Chip8::set_undo_depth`)
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of instructions that can be undone
+    pub fn undo_depth(mut self, depth: usize) -> Self {
+        self.undo_depth = depth;
+        self
+    }
+
+    /// Sets the number of executed instructions kept in the history (see `Chip8::set_history_depth`)
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of executed instructions kept
+    pub fn history_depth(mut self, depth: usize) -> Self {
+        self.history_depth = depth;
+        self
+    }
+
+    /// Sets the seed of the random number generator returned by `build_with_rng` (0 by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed for the random number generator
+    pub fn rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = seed;
+        self
+    }
+
+    /// Returns the configured CHIP-8 instance
+    ///
+    /// # Panics
+    ///
+    /// The function panics if an option is invalid (see the setters of `Chip8`)
+    pub fn build(&self) -> Chip8 {
+        let mut chip8 = Chip8::new();
+
+        // the memory size bounds the start address, that bounds the hires fontset
+        chip8.set_memory_size(self.memory_size);
+        if let Some(address) = self.start_address {
+            chip8.set_start_address(address);
+        }
+        for font in &self.fonts {
+            chip8.set_font(font);
+        }
+        chip8.set_clock_frequency(self.clock_hz);
+        chip8.set_instruction_timing(self.timing);
+        chip8.set_quirks(self.quirks);
+        chip8.set_machine_code_policy(self.machine_code);
+        chip8.set_self_modifying_code_policy(self.self_modifying_code);
+        chip8.set_uninitialized_read_warnings(self.uninitialized_read_warnings);
+        chip8.set_trace_sample(self.trace_sample);
+        chip8.set_undo_depth(self.undo_depth);
+        chip8.set_history_depth(self.history_depth);
+
+        chip8
+    }
+
+    /// Returns the configured CHIP-8 instance and a random number generator seeded with `rng_seed`,
+    /// to pass to `step` and `run_frame`
+    ///
+    /// # Panics
+    ///
+    /// The function panics if an option is invalid (see the setters of `Chip8`)
+    pub fn build_with_rng(&self) -> (Chip8, StdRng) {
+        (self.build(), StdRng::seed_from_u64(self.rng_seed))
+    }
+}
+
+impl Chip8 {
+    /// Returns a builder of a configured CHIP-8 instance
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FONTSET_SIZE;
+    use rand::Rng;
+
+    #[test]
+    fn defaults_are_the_ones_of_new() {
+        let built = Chip8::builder().build();
+        let new = Chip8::new();
+
+        assert_eq!(built.memory_size(), new.memory_size());
+        assert_eq!(built.start_address(), new.start_address());
+        assert_eq!(built.clock_frequency(), new.clock_frequency());
+        assert_eq!(built.instruction_timing(), new.instruction_timing());
+        assert_eq!(built.quirks(), new.quirks());
+        assert_eq!(built.history_depth(), new.history_depth());
+        assert_eq!(built.memory(), new.memory());
+    }
+
+    #[test]
+    fn options_configure_the_instance() {
+        let font = [0xAA; FONTSET_SIZE];
+        let chip8 = Chip8::builder()
+            .memory_size(0x2000)
+            .start_address(0x600)
+            .font(&font)
+            .clock_hz(700.0)
+            .instruction_timing(InstructionTiming::CosmacVip)
+            .profile(Profile::Schip)
+            .sprite_wrapping(true)
+            .machine_code(MachineCodePolicy::Ignore)
+            .self_modifying_code(SelfModifyingCodePolicy::Warn)
+            .uninitialized_read_warnings(true)
+            .history_depth(4)
+            .build();
+
+        assert_eq!(chip8.memory_size(), 0x2000);
+        assert_eq!((chip8.start_address(), chip8.pc()), (0x600, 0x600));
+        assert_eq!(chip8.font(), &font);
+        assert_eq!(chip8.clock_frequency(), 700.0);
+        assert_eq!(chip8.instruction_timing(), InstructionTiming::CosmacVip);
+        assert_eq!(
+            chip8.quirks(),
+            Quirks {
+                sprite_wrapping: true,
+                ..Profile::Schip.quirks()
+            }
+        );
+        assert_eq!(chip8.machine_code, MachineCodePolicy::Ignore);
+        assert!(chip8.code_tracker.is_some());
+        assert!(chip8.initialized.is_some());
+        assert_eq!(chip8.history_depth(), 4);
+    }
+
+    #[test]
+    fn rng_is_seeded() {
+        let builder = Chip8::builder().rng_seed(42);
+        let (_, mut first) = builder.build_with_rng();
+        let (_, mut second) = builder.build_with_rng();
+
+        assert_eq!(first.gen::<u64>(), second.gen::<u64>());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid clock frequency")]
+    fn invalid_options_panic() {
+        Chip8::builder().clock_hz(0.0).build();
+    }
+}
@@ -0,0 +1,194 @@
+//! Implementation of CHIP-8 (decoded instruction cache)
+//!
+//! Every opcode is decoded once per address: the hot loop reads the handler of the instruction from
+//! the cache instead of looking it up in the jump tables at every cycle. A memory write invalidates the instructions
+//! whose opcode contains the written byte (self-modifying code is decoded again).
+//!
+//! The basic blocks executed by `run_block` (the straight-line instructions up to the next control flow change)
+//! are built once per start address from the decoded instructions, and invalidated with them
+
+use super::emulation::{handler, Handler};
+use super::{Chip8, Instruction};
+use alloc::vec;
+use core::ops::Range;
+
+/// max number of instructions of a basic block
+const MAX_BLOCK_INSTRUCTIONS: u8 = 32;
+
+/// Basic block: 2-byte straight-line instructions, the last one excepted (e.g. a jump)
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Block {
+    /// Number of instructions of the block
+    pub(super) instructions: u8,
+    /// Size of the block in bytes, from its start address
+    size: u8,
+}
+
+impl Chip8 {
+    /// Returns the handler of the current opcode, fetched from an address: the opcode is decoded
+    /// only if the handler at the address is not cached
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the opcode, within memory
+    pub(super) fn decode_at(&mut self, address: usize) -> Handler {
+        if let Some(handler) = self.decoded[address] {
+            return handler;
+        }
+
+        let handler = handler(self.opcode);
+        self.decoded[address] = Some(handler);
+
+        handler
+    }
+
+    /// Returns the basic block starting at an address: the block is built only if it is not cached
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The start address of the block, within memory
+    pub(super) fn block_at(&mut self, address: usize) -> Block {
+        if let Some(block) = self.blocks[address] {
+            return block;
+        }
+
+        // the block ends with the first instruction that may not fall through, or at the end of memory
+        let mut block = Block {
+            instructions: 0,
+            size: 0,
+        };
+        let mut next = address;
+        while block.instructions < MAX_BLOCK_INSTRUCTIONS && next + 1 < self.memory.len() {
+            block.instructions += 1;
+            block.size += 2;
+            if Instruction::decode(self.opcode_at(next as u16)).ends_block() {
+                break;
+            }
+            next += 2;
+        }
+        // a block has at least one instruction (e.g. an opcode split by the end of memory)
+        block.instructions = block.instructions.max(1);
+        block.size = block.size.max(2);
+        self.blocks[address] = Some(block);
+
+        block
+    }
+
+    /// Returns true if the basic block starting at an address is cached
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The start address of the block, within memory
+    pub(super) fn block_cached(&self, address: usize) -> bool {
+        self.blocks[address].is_some()
+    }
+
+    /// Invalidates the decoded instructions and the basic blocks of a range of written memory addresses
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - The memory addresses written
+    pub(super) fn invalidate_decoded(&mut self, addresses: Range<usize>) {
+        // the opcode at the address before the range contains its first byte
+        let start = addresses.start.saturating_sub(1);
+        let end = addresses.end.min(self.decoded.len());
+        if start < end {
+            self.decoded[start..end].fill(None);
+        }
+
+        // the blocks that start before the range and overlap it
+        let first_block = start.saturating_sub(MAX_BLOCK_INSTRUCTIONS as usize * 2);
+        for (address, block) in self.blocks[first_block..end].iter_mut().enumerate() {
+            if matches!(block, Some(cached) if first_block + address + cached.size as usize > start)
+            {
+                *block = None;
+            }
+        }
+    }
+
+    /// Forgets all the decoded instructions and basic blocks, e.g. when memory is cleared or resized
+    pub(super) fn clear_decoded(&mut self) {
+        self.decoded = vec![None; self.memory.len()];
+        self.blocks = vec![None; self.memory.len()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with a ROM loaded at 0x200
+    fn chip8(rom: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom);
+        chip8
+    }
+
+    #[test]
+    fn block_ends_with_the_control_flow_instruction() {
+        // LD V0, 0x01; LD V1, 0x02; SE V0, 0x01; JP 0x200
+        let mut chip8 = chip8(&[0x60, 0x01, 0x61, 0x02, 0x30, 0x01, 0x12, 0x00]);
+        assert_eq!(chip8.block_at(0x200).instructions, 3);
+        assert_eq!(chip8.block_at(0x206).instructions, 1);
+        assert!(chip8.block_cached(0x200));
+    }
+
+    #[test]
+    fn written_block_is_invalidated() {
+        // LD V0, 0x01; LD V1, 0x02; JP 0x200
+        let mut chip8 = chip8(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x00]);
+        chip8.block_at(0x200);
+        chip8.block_at(0x204);
+
+        // JP 0x200 replaced by LD V2, 0x00: the block at 0x200 ends with the 0000 opcode after it
+        chip8.write_memory(0x204, 0x62);
+        assert!(!chip8.block_cached(0x200));
+        assert!(!chip8.block_cached(0x204));
+        assert_eq!(chip8.block_at(0x200).instructions, 4);
+
+        // writes after the end of a block keep it
+        chip8.block_at(0x300);
+        chip8.write_memory(0x400, 0x12);
+        assert!(chip8.block_cached(0x300));
+    }
+
+    #[test]
+    fn run_block_executes_the_cached_block() {
+        // LD V0, 0x01; LD V1, 0x02; JP 0x200
+        let mut chip8 = chip8(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x00]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        chip8.run_block(&mut rng, u64::MAX, |_| true).unwrap();
+        assert_eq!(chip8.cycles(), 3);
+        assert_eq!(chip8.pc(), 0x200);
+
+        // the block stops when asked, after the first instruction
+        chip8.run_block(&mut rng, u64::MAX, |_| false).unwrap();
+        assert_eq!(chip8.cycles(), 4);
+        chip8.run_block(&mut rng, 1, |_| true).unwrap();
+        assert_eq!(chip8.cycles(), 5);
+    }
+
+    #[test]
+    fn run_block_stops_when_the_block_is_overwritten() {
+        let mut chip8 = chip8(&[
+            0x60, 0x12, // 0x200: LD V0, 0x12
+            0x61, 0x08, // 0x202: LD V1, 0x08
+            0xA2, 0x0A, // 0x204: LD I, 0x20A
+            0xF1, 0x55, // 0x206: LD [I], V1 (writes JP 0x208 at 0x20A)
+            0x63, 0x05, // 0x208: LD V3, 0x05
+            0x64, 0x06, // 0x20A: LD V4, 0x06
+            0x65, 0x07, // 0x20C: LD V5, 0x07
+            0x12, 0x0E, // 0x20E: JP 0x20E
+        ]);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(chip8.block_at(0x200).instructions, 8);
+
+        chip8.run_block(&mut rng, u64::MAX, |_| true).unwrap();
+        assert_eq!(chip8.cycles(), 4);
+        assert_eq!(chip8.pc(), 0x208);
+        // the block is built again with the new instruction
+        assert_eq!(chip8.block_at(0x200).instructions, 6);
+    }
+}
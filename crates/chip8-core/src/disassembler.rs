@@ -0,0 +1,14 @@
+//! Implementation of CHIP-8 (opcode disassembler)
+
+use super::Instruction;
+use alloc::string::{String, ToString};
+
+/// Returns the assembly mnemonic of a CHIP-8 opcode (e.g. `DRW V0, V1, 5` for `0xD015`).
+/// Opcodes that are not CHIP-8 instructions are returned as data words (e.g. `DW 0xFFFF`)
+///
+/// # Arguments
+///
+/// * `opcode` - The u16 opcode to disassemble
+pub fn disassemble(opcode: u16) -> String {
+    Instruction::decode(opcode).to_string()
+}
@@ -0,0 +1,180 @@
+//! Implementation of CHIP-8 (SUPER-CHIP display modes and scrolling)
+//!
+//! The display rows are 128-bit wide in both modes: the lores display (64x32) uses the first 32 rows
+//! and the 64 most significant bits of every row, so that the scroll instructions shift the rows
+//! of the current mode with the same bitwise operations
+
+use super::{Chip8, ALL_DISPLAY_ROWS, HIRES_DISPLAY_WIDTH};
+
+/// distance of the horizontal scroll instructions (`00FB`, `00FC`) in pixels
+const HORIZONTAL_SCROLL: usize = 4;
+
+impl Chip8 {
+    /// Sets the display mode: the SUPER-CHIP hires mode (128x64) or the lores mode (64x32).
+    /// The display is cleared when the mode changes (as in Octo), so that the rows of a mode
+    /// are never shown in the other one
+    ///
+    /// # Arguments
+    ///
+    /// * `hires` - Boolean set to true for the hires mode
+    pub(super) fn set_hires(&mut self, hires: bool) {
+        if self.hires != hires {
+            self.hires = hires;
+            self.clear_display();
+        }
+    }
+
+    /// Returns true if the display is in the SUPER-CHIP hires mode (128x64), false in lores mode (64x32)
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Sets the half pixel scroll quirk of the scroll instructions in lores mode: the display is scrolled
+    /// by half the distance (as in SUPER-CHIP 1.1, that scrolls by hires pixels, that is half lores pixels),
+    /// instead of the full distance in lores pixels (as in Octo and XO-CHIP, by default).
+    /// Odd distances are rounded down: the half lores pixels cannot be shown
+    ///
+    /// # Arguments
+    ///
+    /// * `half` - Boolean set to true to scroll the lores display by half the distance
+    pub fn set_half_pixel_scroll(&mut self, half: bool) {
+        self.half_pixel_scroll = half;
+    }
+
+    /// Returns true if the scroll instructions scroll the lores display by half the distance
+    pub fn half_pixel_scroll(&self) -> bool {
+        self.half_pixel_scroll
+    }
+
+    /// Returns a distance of the scroll instructions in pixels of the current display mode
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - The distance of the instruction
+    fn scroll_distance(&self, distance: usize) -> usize {
+        if self.half_pixel_scroll && !self.hires {
+            distance / 2
+        } else {
+            distance
+        }
+    }
+
+    /// Returns the mask of the display columns of the current display mode
+    pub(super) fn display_columns(&self) -> u128 {
+        u128::MAX << (HIRES_DISPLAY_WIDTH - self.display_size().0)
+    }
+
+    /// Scrolls the display down: the bottom rows are discarded, the top rows are cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The number of rows of the instruction (`00CN`)
+    pub(super) fn scroll_down(&mut self, rows: usize) {
+        let rows = self.scroll_distance(rows);
+        let height = self.display_size().1;
+
+        self.display.copy_within(..height - rows, rows);
+        self.display[..rows].fill(0);
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+    }
+
+    /// Scrolls the display right by 4 pixels: the rightmost columns are discarded,
+    /// the leftmost columns are cleared
+    pub(super) fn scroll_right(&mut self) {
+        let columns = self.display_columns();
+        let distance = self.scroll_distance(HORIZONTAL_SCROLL);
+
+        for row in &mut self.display {
+            *row = (*row >> distance) & columns;
+        }
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+    }
+
+    /// Scrolls the display left by 4 pixels: the leftmost columns are discarded,
+    /// the rightmost columns are cleared
+    pub(super) fn scroll_left(&mut self) {
+        let distance = self.scroll_distance(HORIZONTAL_SCROLL);
+
+        // the lores rows have no pixel in the unused columns: none is shifted in
+        for row in &mut self.display {
+            *row <<= distance;
+        }
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the display row bits of the given columns
+    fn columns(columns: &[usize]) -> u128 {
+        columns
+            .iter()
+            .map(|column| 1 << (HIRES_DISPLAY_WIDTH - 1 - column))
+            .sum()
+    }
+
+    #[test]
+    fn scroll_down_moves_the_rows_of_the_current_mode() {
+        let mut chip8 = Chip8::new();
+        chip8.display[0] = columns(&[0]);
+        chip8.display[31] = columns(&[63]);
+
+        chip8.scroll_down(4);
+        assert_eq!(chip8.display[4], columns(&[0]));
+        assert_eq!(chip8.display[..4], [0; 4]);
+        // the last lores row is discarded
+        assert!(chip8.display[32..].iter().all(|&row| row == 0));
+    }
+
+    #[test]
+    fn scroll_right_and_left_keep_the_columns_of_the_current_mode() {
+        let mut chip8 = Chip8::new();
+        chip8.display[0] = columns(&[0, 62]);
+
+        chip8.scroll_right();
+        assert_eq!(chip8.display[0], columns(&[4]));
+        chip8.scroll_left();
+        chip8.scroll_left();
+        assert_eq!(chip8.display[0], 0);
+
+        chip8.set_hires(true);
+        chip8.display[0] = columns(&[0, 62]);
+        chip8.scroll_right();
+        assert_eq!(chip8.display[0], columns(&[4, 66]));
+        chip8.scroll_left();
+        assert_eq!(chip8.display[0], columns(&[0, 62]));
+    }
+
+    #[test]
+    fn half_pixel_scroll_halves_the_lores_distance() {
+        let mut chip8 = Chip8::new();
+        chip8.set_half_pixel_scroll(true);
+        chip8.display[0] = columns(&[8]);
+
+        chip8.scroll_right();
+        assert_eq!(chip8.display[0], columns(&[10]));
+        chip8.scroll_down(3);
+        assert_eq!(chip8.display[1], columns(&[10]));
+
+        // full distance in hires mode
+        chip8.set_hires(true);
+        chip8.display[0] = columns(&[8]);
+        chip8.scroll_left();
+        assert_eq!(chip8.display[0], columns(&[4]));
+    }
+
+    #[test]
+    fn display_mode_change_clears_the_display() {
+        let mut chip8 = Chip8::new();
+        chip8.display[0] = columns(&[0]);
+        chip8.set_hires(false);
+        assert_eq!(chip8.display[0], columns(&[0]));
+
+        chip8.set_hires(true);
+        assert!(chip8.hires());
+        assert_eq!(chip8.display_size(), (128, 64));
+        assert_eq!(chip8.display[0], 0);
+    }
+}
@@ -0,0 +1,200 @@
+//! embedded-graphics adapter: draws the CHIP-8 display on any `DrawTarget`
+//! (e.g. SSD1306 or ST7789 display drivers), also on `no_std` targets.
+//!
+//! ```ignore
+//! use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*};
+//! use chip8_core::embedded::Framebuffer;
+//!
+//! // 128x64 SSD1306: the CHIP-8 display fills the screen with scale 2 (scale 1 in hires mode)
+//! Framebuffer::new(&chip8, BinaryColor::On, BinaryColor::Off)
+//!     .with_scale(if chip8.hires() { 1 } else { 2 })
+//!     .draw(&mut display)?;
+//! ```
+
+use crate::Chip8;
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    pixelcolor::PixelColor,
+    primitives::Rectangle,
+    Drawable,
+};
+
+/// Drawable view of the CHIP-8 display
+pub struct Framebuffer<'a, C> {
+    /// CHIP-8 instance
+    chip8: &'a Chip8,
+
+    /// Color of the pixels that are on
+    on: C,
+
+    /// Color of the pixels that are off
+    off: C,
+
+    /// Position of the top left corner of the display on the target
+    top_left: Point,
+
+    /// Size of a CHIP-8 pixel on the target (in target pixels)
+    scale: u32,
+}
+
+impl<'a, C: PixelColor> Framebuffer<'a, C> {
+    /// Returns a new drawable view of the CHIP-8 display, drawn at the top left corner
+    /// of the target with one target pixel for every CHIP-8 pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    /// * `on` - The color of the pixels that are on
+    /// * `off` - The color of the pixels that are off
+    pub fn new(chip8: &'a Chip8, on: C, off: C) -> Self {
+        Self {
+            chip8,
+            on,
+            off,
+            top_left: Point::zero(),
+            scale: 1,
+        }
+    }
+
+    /// Sets the position of the top left corner of the display on the target
+    ///
+    /// # Arguments
+    ///
+    /// * `top_left` - The position on the target
+    pub fn with_position(mut self, top_left: Point) -> Self {
+        self.top_left = top_left;
+        self
+    }
+
+    /// Sets the size of a CHIP-8 pixel on the target
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - The number of target pixels per CHIP-8 pixel side (at least 1)
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+}
+
+impl<C: PixelColor> Dimensions for Framebuffer<'_, C> {
+    /// The size follows the display mode: 64x32 or 128x64 (SUPER-CHIP hires mode) CHIP-8 pixels
+    fn bounding_box(&self) -> Rectangle {
+        let (width, height) = self.chip8.display_size();
+
+        Rectangle::new(
+            self.top_left,
+            Size::new(width as u32 * self.scale, height as u32 * self.scale),
+        )
+    }
+}
+
+impl<C: PixelColor> Drawable for Framebuffer<'_, C> {
+    type Color = C;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let scale = self.scale as usize;
+        let (width, height) = self.chip8.display_size();
+        let (width, height) = (width * scale, height * scale);
+
+        // the whole display is streamed row by row, so that drivers can send it in one transfer
+        let colors = (0..width * height).map(|i| {
+            if self.chip8.pixel((i % width) / scale, (i / width) / scale) {
+                self.on
+            } else {
+                self.off
+            }
+        });
+
+        target.fill_contiguous(&self.bounding_box(), colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+    use embedded_graphics_core::{pixelcolor::BinaryColor, Pixel};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Draw target recording the pixels that are on
+    struct Target(Vec<Point>);
+
+    impl Dimensions for Target {
+        fn bounding_box(&self) -> Rectangle {
+            Rectangle::new(Point::zero(), Size::new(256, 128))
+        }
+    }
+
+    impl DrawTarget for Target {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                if color.is_on() {
+                    self.0.push(point);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns an instance with the top left pixel on
+    fn chip8(hires: bool) -> Chip8 {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut chip8 = Chip8::new();
+        if hires {
+            chip8.op_00ff(0x00FF, &mut rng).unwrap();
+        }
+        // sprite of 1 pixel
+        chip8.memory[0x300] = 0x80;
+        chip8.set_i(0x300);
+        chip8.op_dxyn(0xD001, &mut rng).unwrap();
+        chip8
+    }
+
+    #[test]
+    fn bounding_box_follows_the_display_mode() {
+        let lores = chip8(false);
+        let framebuffer = Framebuffer::new(&lores, BinaryColor::On, BinaryColor::Off);
+        assert_eq!(framebuffer.bounding_box().size, Size::new(64, 32));
+
+        let hires = chip8(true);
+        let framebuffer = Framebuffer::new(&hires, BinaryColor::On, BinaryColor::Off)
+            .with_position(Point::new(4, 2))
+            .with_scale(2);
+        assert_eq!(
+            framebuffer.bounding_box(),
+            Rectangle::new(Point::new(4, 2), Size::new(256, 128))
+        );
+    }
+
+    #[test]
+    fn pixels_are_scaled() {
+        let chip8 = chip8(false);
+        let mut target = Target(Vec::new());
+        Framebuffer::new(&chip8, BinaryColor::On, BinaryColor::Off)
+            .with_scale(2)
+            .draw(&mut target)
+            .unwrap();
+        assert_eq!(
+            target.0,
+            [
+                Point::new(0, 0),
+                Point::new(1, 0),
+                Point::new(0, 1),
+                Point::new(1, 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,1431 @@
+//! Implementation of CHIP-8 (one cycle emulation)
+//!
+//! Instructions are dispatched through jump tables: the first nibble of the opcode selects an entry
+//! of a 16 entries table, that is either the handler of the instruction or a sub-table of the
+//! instructions sharing the first nibble, indexed by the last nibble or the last byte. Every handler
+//! executes one instruction and can be called on its own (e.g. `Chip8::op_8xy4`)
+
+use crate::{FONTSET_END, HIRES_DISPLAY_WIDTH, MAX_STACK_SIZE, RPL_FLAGS_SIZE};
+
+use super::{Chip8, Error, Instruction, MachineCodePolicy};
+use rand::{Rng, RngCore};
+#[cfg(feature = "cycle-logging")]
+use tracing::{debug, Level};
+use tracing::{trace, warn};
+
+/// Logs an event of the executed instruction if its cycle is logged (see `set_trace_sample`):
+/// the per-instruction events are compiled only with the `cycle-logging` feature
+macro_rules! cycle_log {
+    ($chip8:expr, $log:ident!($($arg:tt)+)) => {
+        #[cfg(feature = "cycle-logging")]
+        if $chip8.log_cycle {
+            $log!($($arg)+);
+        }
+    };
+}
+
+/// Handler of an instruction: executes the opcode, with the random number generator of `CXNN`
+pub(super) type Handler = fn(&mut Chip8, u16, &mut dyn RngCore) -> Result<(), Error>;
+
+/// Entry of the jump table of the first nibble of the opcodes
+#[derive(Clone, Copy)]
+enum Dispatch {
+    /// Handler of the only instruction of the first nibble
+    Handler(Handler),
+    /// Sub-table of the `00NN` instructions, indexed by the last byte (the other `0NNN` opcodes are
+    /// machine code routine calls)
+    System(&'static [Handler; 256]),
+    /// Sub-table of the instructions indexed by the last nibble
+    LastNibble(&'static [Handler; 16]),
+    /// Sub-table of the instructions indexed by the last byte
+    LastByte(&'static [Handler; 256]),
+}
+
+/// Returns a sub-table of handlers: the indices not listed are illegal opcodes
+///
+/// # Arguments
+///
+/// * `handlers` - The indices of the sub-table and their handlers
+const fn sub_table<const N: usize>(handlers: &[(usize, Handler)]) -> [Handler; N] {
+    let mut table = [Chip8::op_illegal as Handler; N];
+    let mut i = 0;
+    while i < handlers.len() {
+        table[handlers[i].0] = handlers[i].1;
+        i += 1;
+    }
+    table
+}
+
+/// sub-table of the `00NN` instructions, indexed by the last byte
+const SYSTEM_INSTRUCTIONS: [Handler; 256] = {
+    let mut table = [Chip8::op_0nnn as Handler; 256];
+    let mut n = 0;
+    while n < 16 {
+        table[0xC0 | n] = Chip8::op_00cn;
+        n += 1;
+    }
+    table[0xE0] = Chip8::op_00e0;
+    table[0xEE] = Chip8::op_00ee;
+    table[0xFB] = Chip8::op_00fb;
+    table[0xFC] = Chip8::op_00fc;
+    table[0xFD] = Chip8::op_00fd;
+    table[0xFE] = Chip8::op_00fe;
+    table[0xFF] = Chip8::op_00ff;
+    table
+};
+
+/// sub-table of the `5XYN` instructions, indexed by the last nibble
+const SKIP_EQUAL_INSTRUCTIONS: [Handler; 16] = sub_table(&[
+    (0x0, Chip8::op_5xy0),
+    (0x2, Chip8::op_5xy2),
+    (0x3, Chip8::op_5xy3),
+]);
+
+/// sub-table of the logical and arithmetic instructions (`8XYN`), indexed by the last nibble
+const ARITHMETIC_INSTRUCTIONS: [Handler; 16] = sub_table(&[
+    (0x0, Chip8::op_8xy0),
+    (0x1, Chip8::op_8xy1),
+    (0x2, Chip8::op_8xy2),
+    (0x3, Chip8::op_8xy3),
+    (0x4, Chip8::op_8xy4),
+    (0x5, Chip8::op_8xy5),
+    (0x6, Chip8::op_8xy6),
+    (0x7, Chip8::op_8xy7),
+    (0xE, Chip8::op_8xye),
+]);
+
+/// sub-table of the `9XYN` instructions, indexed by the last nibble
+const SKIP_NOT_EQUAL_INSTRUCTIONS: [Handler; 16] = sub_table(&[(0x0, Chip8::op_9xy0)]);
+
+/// sub-table of the key instructions (`EXNN`), indexed by the last byte
+const KEY_INSTRUCTIONS: [Handler; 256] =
+    sub_table(&[(0x9E, Chip8::op_ex9e), (0xA1, Chip8::op_exa1)]);
+
+/// sub-table of the timer, memory and register instructions (`FXNN`), indexed by the last byte
+const MISC_INSTRUCTIONS: [Handler; 256] = sub_table(&[
+    (0x00, Chip8::op_f000),
+    (0x07, Chip8::op_fx07),
+    (0x0A, Chip8::op_fx0a),
+    (0x15, Chip8::op_fx15),
+    (0x18, Chip8::op_fx18),
+    (0x1E, Chip8::op_fx1e),
+    (0x29, Chip8::op_fx29),
+    (0x30, Chip8::op_fx30),
+    (0x33, Chip8::op_fx33),
+    (0x55, Chip8::op_fx55),
+    (0x65, Chip8::op_fx65),
+    (0x75, Chip8::op_fx75),
+    (0x85, Chip8::op_fx85),
+]);
+
+/// jump table of the instructions, indexed by the first nibble of the opcode
+const INSTRUCTIONS: [Dispatch; 16] = [
+    Dispatch::System(&SYSTEM_INSTRUCTIONS),
+    Dispatch::Handler(Chip8::op_1nnn),
+    Dispatch::Handler(Chip8::op_2nnn),
+    Dispatch::Handler(Chip8::op_3xnn),
+    Dispatch::Handler(Chip8::op_4xnn),
+    Dispatch::LastNibble(&SKIP_EQUAL_INSTRUCTIONS),
+    Dispatch::Handler(Chip8::op_6xnn),
+    Dispatch::Handler(Chip8::op_7xnn),
+    Dispatch::LastNibble(&ARITHMETIC_INSTRUCTIONS),
+    Dispatch::LastNibble(&SKIP_NOT_EQUAL_INSTRUCTIONS),
+    Dispatch::Handler(Chip8::op_annn),
+    Dispatch::Handler(Chip8::op_bnnn),
+    Dispatch::Handler(Chip8::op_cxnn),
+    Dispatch::Handler(Chip8::op_dxyn),
+    Dispatch::LastByte(&KEY_INSTRUCTIONS),
+    Dispatch::LastByte(&MISC_INSTRUCTIONS),
+];
+
+/// Returns the handler of an opcode, looked up in the jump tables
+///
+/// # Arguments
+///
+/// * `opcode` - The u16 opcode
+pub(super) fn handler(opcode: u16) -> Handler {
+    match INSTRUCTIONS[(opcode >> 12) as usize] {
+        Dispatch::Handler(handler) => handler,
+        Dispatch::System(table) if opcode & 0x0F00 == 0 => table[(opcode & 0x00FF) as usize],
+        Dispatch::System(_) => Chip8::op_0nnn,
+        Dispatch::LastNibble(table) => table[(opcode & 0x000F) as usize],
+        Dispatch::LastByte(table) => table[(opcode & 0x00FF) as usize],
+    }
+}
+
+/// Returns the second nibble of an opcode: used to look up one of the 16 registers (VX) from V0-VF
+fn x(opcode: u16) -> usize {
+    ((opcode & 0x0F00) >> 8) as usize
+}
+
+/// Returns the third nibble of an opcode: used to look up one of the 16 registers (VY) from V0-VF
+fn y(opcode: u16) -> usize {
+    ((opcode & 0x00F0) >> 4) as usize
+}
+
+/// Returns the registers VX-VY of an opcode, in order from VX to VY: in descending order if X > Y
+fn register_range(opcode: u16) -> impl Iterator<Item = usize> {
+    let (x, y) = (x(opcode), y(opcode));
+
+    (0..=x.abs_diff(y)).map(move |offset| if x <= y { x + offset } else { x - offset })
+}
+
+/// Returns the fourth nibble of an opcode: a 4-bit number
+fn n(opcode: u16) -> u8 {
+    (opcode & 0x000F) as u8
+}
+
+/// Returns the second byte (third and fourth nibble) of an opcode: an 8-bit immediate number
+fn nn(opcode: u16) -> u8 {
+    (opcode & 0x00FF) as u8
+}
+
+/// Returns the second, third and fourth nibble of an opcode: a 12-bit immediate number
+fn nnn(opcode: u16) -> u16 {
+    opcode & 0x0FFF
+}
+
+impl Chip8 {
+    /// Function that emulates one CHIP-8 cycle (one opcode execution):
+    /// - fetch, decode, execute opcode
+    ///
+    /// Timers are not updated: `update_timers` must be called at a rate of 60Hz
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Mutable reference to a struct that implements the Rng trait used to generate random numbers
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the current opcode cannot be executed (see `try_emulate_cycle`)
+    pub fn emulate_cycle<R: Rng>(&mut self, rng: &mut R) {
+        trace!("Chip8::emulate_cycle: start");
+
+        if let Err(e) = self.try_emulate_cycle(rng) {
+            self.panic_error(e);
+        }
+
+        trace!("Chip8::emulate_cycle: exit");
+    }
+
+    /// Decides if the events of the next instruction are logged: the debug level must be enabled
+    /// (a cheap check, so that nothing is formatted when logging is off) and the cycle must be sampled
+    #[cfg(feature = "cycle-logging")]
+    fn update_log_cycle(&mut self) {
+        self.log_cycle =
+            tracing::enabled!(Level::DEBUG) && self.cycles.is_multiple_of(self.trace_sample);
+    }
+
+    /// Function that emulates one CHIP-8 cycle (one opcode execution) like `emulate_cycle`,
+    /// but returns an error if the current opcode cannot be executed: illegal opcode,
+    /// stack overflow or underflow, memory access beyond the end of memory.
+    /// After an error the state may be partially updated: the instance must not be stepped anymore
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Mutable reference to a struct that implements the Rng trait used to generate random numbers
+    pub fn try_emulate_cycle<R: Rng>(&mut self, rng: &mut R) -> Result<(), Error> {
+        #[cfg(feature = "cycle-logging")]
+        self.update_log_cycle();
+
+        cycle_log!(self, trace!("Chip8::try_emulate_cycle: start"));
+
+        cycle_log!(self, debug!("before fetching: {}", self));
+
+        // fetch the first byte of the opcode
+        let first_byte_opcode = self.memory[self.memory_address(self.pc as usize)?];
+        cycle_log!(
+            self,
+            debug!("opcode first byte fetch: {:#X}", first_byte_opcode)
+        );
+        // fetch the second byte of the opcode
+        let second_byte_opcode = self.memory[self.memory_address(self.pc as usize + 1)?];
+        cycle_log!(
+            self,
+            debug!("opcode second byte fetch: {:#X}", second_byte_opcode)
+        );
+        self.track_fetch();
+        // combine opcode bytes
+        self.opcode = (first_byte_opcode as u16) << 8 | (second_byte_opcode as u16);
+        self.record_instruction();
+        cycle_log!(self, debug!("opcode: {:#X}", self.opcode));
+        cycle_log!(
+            self,
+            debug!(
+                "instruction: {:?} ({})",
+                Instruction::decode(self.opcode),
+                Instruction::decode(self.opcode)
+            )
+        );
+
+        // decode the opcode, once per address: the handler is cached until the address is written
+        let handler = self.decode_at(self.pc as usize);
+        // execute the opcode
+        handler(self, self.opcode, rng)?;
+
+        // the next instruction may have been modified at runtime
+        self.track_modified_pc();
+
+        cycle_log!(self, debug!("after executing: {}", self));
+
+        cycle_log!(self, trace!("Chip8::try_emulate_cycle: exit"));
+
+        Ok(())
+    }
+
+    /// Skips the instruction after the current one: PC is advanced by the size of the skipped instruction
+    /// (4 bytes if it is `F000`, XO-CHIP), the current instruction still advances PC by 2
+    fn skip_next_instruction(&mut self) {
+        let next = self.opcode_at(self.pc.wrapping_add(2));
+        self.pc = self.pc.wrapping_add(Instruction::decode(next).size());
+    }
+
+    /// Returns the memory address if it is within memory, an error otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address
+    pub(super) fn memory_address(&self, address: usize) -> Result<usize, Error> {
+        if address < self.memory.len() {
+            Ok(address)
+        } else {
+            Err(Error::MemoryOutOfBounds(address))
+        }
+    }
+
+    /// `00CN`: scroll the display down by N rows (SUPER-CHIP)
+    pub fn op_00cn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: scroll down N rows"));
+
+        self.scroll_down(n(opcode) as usize);
+
+        // redraw screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `00E0`: clear screen
+    pub fn op_00e0(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: clear screen"));
+        // turn off all the pixels (clear display)
+        self.clear_display();
+
+        // redraw screen
+        self.draw = true;
+
+        // increment PC
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `00EE`: return from subroutine
+    pub fn op_00ee(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: subroutine return"));
+        // pop last address from stack
+        if self.sp == 0 {
+            return Err(Error::StackUnderflow);
+        }
+        self.sp -= 1;
+        let addr = self.stack[self.sp as usize];
+        cycle_log!(
+            self,
+            trace!(
+                "{:indent$}pop: return from {:#05X} to {addr:#05X} (depth {})",
+                "",
+                self.pc,
+                self.sp,
+                indent = self.sp as usize * 2
+            )
+        );
+        // set PC = addr
+        self.pc = addr;
+
+        Ok(())
+    }
+
+    /// `00FB`: scroll the display right by 4 pixels (SUPER-CHIP)
+    pub fn op_00fb(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: scroll right 4 pixels"));
+
+        self.scroll_right();
+
+        // redraw screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `00FC`: scroll the display left by 4 pixels (SUPER-CHIP)
+    pub fn op_00fc(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: scroll left 4 pixels"));
+
+        self.scroll_left();
+
+        // redraw screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `00FD`: exit the interpreter (SUPER-CHIP)
+    pub fn op_00fd(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: exit"));
+        // PC is not incremented: the program stays on the exit instruction
+        self.exited = true;
+
+        Ok(())
+    }
+
+    /// `00FE`: switch the display to lores mode: 64x32 pixels (SUPER-CHIP)
+    pub fn op_00fe(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: lores mode"));
+
+        self.set_hires(false);
+
+        // redraw screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `00FF`: switch the display to hires mode: 128x64 pixels (SUPER-CHIP)
+    pub fn op_00ff(&mut self, _opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: hires mode"));
+
+        self.set_hires(true);
+
+        // redraw screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `0NNN`: call of a machine code routine at NNN: it cannot be executed by an interpreter
+    pub fn op_0nnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        match self.machine_code {
+            MachineCodePolicy::Ignore => {}
+            MachineCodePolicy::Warn => {
+                warn!("machine code routine call skipped: {:#06X}", opcode)
+            }
+            MachineCodePolicy::Halt => {
+                return Err(Error::MachineCodeCall(opcode));
+            }
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `1NNN`: jump to memory location NNN
+    pub fn op_1nnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: jump"));
+        // set PC = NNN
+        self.pc = nnn(opcode);
+
+        Ok(())
+    }
+
+    /// `2NNN`: subroutine call
+    pub fn op_2nnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: subroutine call"));
+        // push current PC to stack, so that the subroutine can return later
+        if self.sp as usize >= MAX_STACK_SIZE {
+            return Err(Error::StackOverflow);
+        }
+        // the return address is the instruction after the call
+        self.stack[self.sp as usize] = self.pc.wrapping_add(2);
+        cycle_log!(
+            self,
+            trace!(
+                "{:indent$}push: call {:#05X} from {:#05X} (depth {})",
+                "",
+                nnn(opcode),
+                self.pc,
+                self.sp + 1,
+                indent = self.sp as usize * 2
+            )
+        );
+        self.sp += 1;
+        // set PC = NNN
+        self.pc = nnn(opcode);
+
+        Ok(())
+    }
+
+    /// `3XNN`: skip one instruction if VX == NN
+    pub fn op_3xnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: skip one instruction if VX == NN"));
+
+        if self.v[x(opcode)] == nn(opcode) {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `4XNN`: skip one instruction if VX != NN
+    pub fn op_4xnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: skip one instruction if VX != NN"));
+
+        if self.v[x(opcode)] != nn(opcode) {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `5XY0`: skip one instruction if VX == VY
+    pub fn op_5xy0(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: skip one instruction if VX == VY"));
+
+        if self.v[x(opcode)] == self.v[y(opcode)] {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `5XY2`: store registers VX-VY to memory (XO-CHIP)
+    pub fn op_5xy2(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: store registers VX-VY to memory"));
+
+        // store VX-VY from memory location I: I is not modified
+        for (offset, register) in register_range(opcode).enumerate() {
+            self.store(self.i as usize + offset, self.v[register])?;
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `5XY3`: load registers VX-VY from memory (XO-CHIP)
+    pub fn op_5xy3(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: load registers VX-VY from memory"));
+
+        // load VX-VY from memory location I: I is not modified
+        for (offset, register) in register_range(opcode).enumerate() {
+            self.v[register] = self.load(self.i as usize + offset)?;
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `6XNN`: set VX = NN
+    pub fn op_6xnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = NN"));
+
+        self.v[x(opcode)] = nn(opcode);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `7XNN`: set VX += NN (VF not affected)
+    pub fn op_7xnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: add: VX += NN (VF not affected)"));
+
+        let res = self.v[x(opcode)] as u16 + nn(opcode) as u16;
+
+        self.v[x(opcode)] = res as u8;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY0`: set VX = VY
+    pub fn op_8xy0(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = VY"));
+
+        self.v[x(opcode)] = self.v[y(opcode)];
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY1`: set VX |= VY; reset VF (see `Chip8::set_vf_reset`)
+    pub fn op_8xy1(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX |= VY"));
+
+        self.v[x(opcode)] |= self.v[y(opcode)];
+        if self.vf_reset {
+            self.v[0xF] = 0;
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY2`: set VX &= VY; reset VF (see `Chip8::set_vf_reset`)
+    pub fn op_8xy2(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX &= VY"));
+
+        self.v[x(opcode)] &= self.v[y(opcode)];
+        if self.vf_reset {
+            self.v[0xF] = 0;
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY3`: set VX ^= VY; reset VF (see `Chip8::set_vf_reset`)
+    pub fn op_8xy3(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX ^= VY"));
+
+        self.v[x(opcode)] ^= self.v[y(opcode)];
+        if self.vf_reset {
+            self.v[0xF] = 0;
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY4`: set VX += VY (VF affected)
+    pub fn op_8xy4(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX += VY (VF affected)"));
+
+        let (res, overflow) = self.v[x(opcode)].overflowing_add(self.v[y(opcode)]);
+
+        // VF is set after VX, so that it holds the carry also if X is F
+        self.v[x(opcode)] = res;
+        self.v[0xF] = overflow as u8;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY5`: set VX = VX - VY (VF affected)
+    pub fn op_8xy5(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = VX - VY (VF affected)"));
+
+        let a = self.v[x(opcode)];
+        let b = self.v[y(opcode)];
+
+        // VF is 1 if there is no borrow, set after VX
+        self.v[x(opcode)] = a.wrapping_sub(b);
+        self.v[0xF] = (a >= b) as u8;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY6`: set VX = VY; set VX >>= 1; set VF to the bit that was shifted out
+    ///
+    /// WARN: ambiguous instruction - instruction changed with SUPER-CHIP-8 (see `Chip8::set_shift_vy`)
+    pub fn op_8xy6(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = VY; VX >>= 1 (VF affected)"));
+
+        // VY is shifted into VX, or VX is shifted in place (SUPER-CHIP)
+        let value = if self.shift_vy {
+            self.v[y(opcode)]
+        } else {
+            self.v[x(opcode)]
+        };
+
+        self.v[x(opcode)] = value >> 1;
+        self.v[0xF] = value & 0x01;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XY7`: set VX = VY - VX (VF affected)
+    pub fn op_8xy7(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = VY - VX (VF affected)"));
+
+        let a = self.v[y(opcode)];
+        let b = self.v[x(opcode)];
+
+        // VF is 1 if there is no borrow, set after VX
+        self.v[x(opcode)] = a.wrapping_sub(b);
+        self.v[0xF] = (a >= b) as u8;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `8XYE`: set VX = VY; set VX <<= 1; set VF to the bit that was shifted out
+    ///
+    /// WARN: ambiguous instruction - instruction changed with SUPER-CHIP-8 (see `Chip8::set_shift_vy`)
+    pub fn op_8xye(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = VY; VX <<= 1 (VF affected)"));
+
+        // VY is shifted into VX, or VX is shifted in place (SUPER-CHIP)
+        let value = if self.shift_vy {
+            self.v[y(opcode)]
+        } else {
+            self.v[x(opcode)]
+        };
+
+        self.v[x(opcode)] = value << 1;
+        self.v[0xF] = value >> 7;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `9XY0`: skip one instruction if VX != VY
+    pub fn op_9xy0(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: skip one instruction if VX != VY"));
+
+        if self.v[x(opcode)] != self.v[y(opcode)] {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `ANNN`: set I = NNN
+    pub fn op_annn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set I = nnn"));
+
+        self.i = nnn(opcode);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `BNNN`: jump with offset: set PC = NNN + V0
+    ///
+    /// WARN: ambiguous instruction - instruction changed with SUPER-CHIP-8 (see `Chip8::set_jump_vx`)
+    pub fn op_bnnn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: jump with offset: PC = NNN + V0"));
+
+        // the offset register is V0, or VX with X the highest nibble of the address (SUPER-CHIP)
+        let offset = if self.jump_vx {
+            self.v[x(opcode)]
+        } else {
+            self.v[0]
+        };
+        self.pc = nnn(opcode) + offset as u16;
+
+        Ok(())
+    }
+
+    /// `CXNN`: random: VX = rand & nn
+    pub fn op_cxnn(&mut self, opcode: u16, rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: random: VX = rand & nn"));
+
+        let rand = rng.gen::<u8>();
+
+        self.v[x(opcode)] = rand & nn(opcode);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `DXYN`: display. `DXY0` draws a 16x16 sprite from 32 bytes at I (SUPER-CHIP),
+    /// or a 8x16 sprite from 16 bytes at I in lores mode with the lores tall sprites quirk
+    pub fn op_dxyn(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: display"));
+        // display size of the current mode (64x32 or 128x64)
+        let (display_width, display_heigth) = self.display_size();
+        let columns = self.display_columns();
+        // VX
+        let x_coord = self.v[x(opcode)] as usize % display_width;
+        // VY
+        let y_coord = self.v[y(opcode)] as usize % display_heigth;
+        // sprite size: W (columns) x H (rows)
+        let (width, heigth) = match n(opcode) {
+            0 if self.hires || !self.lores_tall_sprites => (16, 16),
+            0 => (8, 16),
+            n => (8, n as usize),
+        };
+        // bytes of every sprite row
+        let row_bytes = width / 8;
+        // reset register VF
+        self.v[0xF] = 0;
+        // sprite rows that collide or are clipped at the bottom edge (collision rows quirk)
+        let mut collision_rows = 0;
+        // iterate over sprite rows (max n height)
+        for sprite_row in 0..heigth {
+            // break if VY + current_sprite_row is beyond the last row (unless the sprite wraps to the top rows)
+            if (y_coord + sprite_row) >= display_heigth && !self.wrap_sprites {
+                collision_rows += heigth - sprite_row;
+                break;
+            }
+            // get sprite row data from memory starting at location I (big-endian in 16-pixel rows)
+            let mut sprite_row_data = 0;
+            for byte in 0..row_bytes {
+                let address = self.i as usize + sprite_row * row_bytes + byte;
+                sprite_row_data = (sprite_row_data << 8) | self.load(address)? as u128;
+            }
+
+            // align the W bits/pixels of current row to column VX of the display row:
+            // bits shifted beyond the last column (VX + current_sprite_bit >= display width) are clipped,
+            // or shifted to the first columns if the sprite wraps
+            let sprite_bits = sprite_row_data << (HIRES_DISPLAY_WIDTH - width);
+            let wrapped_bits = if self.wrap_sprites {
+                sprite_bits
+                    .checked_shl((display_width - x_coord) as u32)
+                    .unwrap_or(0)
+            } else {
+                0
+            };
+            let sprite_row_bits = ((sprite_bits >> x_coord) | wrapped_bits) & columns;
+
+            let display_row_index = (y_coord + sprite_row) % display_heigth;
+            let display_row = &mut self.display[display_row_index];
+            // if some sprite row pixels are set where the display pixels are also set
+            if *display_row & sprite_row_bits != 0 {
+                // set VF = 1
+                self.v[0xF] = 1;
+                collision_rows += 1;
+            }
+            // flip the display pixels where the sprite row pixels are set
+            *display_row ^= sprite_row_bits;
+            // mark the display row as modified
+            if sprite_row_bits != 0 {
+                self.dirty_rows |= 1 << display_row_index;
+            }
+        }
+
+        // in hires mode SUPER-CHIP 1.1 sets VF to the number of collision rows
+        if self.collision_rows && self.hires {
+            self.v[0xF] = collision_rows as u8;
+        }
+
+        // redraw the screen
+        self.draw = true;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `EX9E`: skip if key corresponding to VX is pressed
+    pub fn op_ex9e(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: skip if key corresponding to VX is pressed")
+        );
+
+        if self.key_pressed(self.v[x(opcode)]) {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `EXA1`: skip if key corresponding to VX is not pressed
+    pub fn op_exa1(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: skip if key corresponding to VX is not pressed")
+        );
+
+        if !self.key_pressed(self.v[x(opcode)]) {
+            self.skip_next_instruction();
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `F000 NNNN`: I = NNNN, the 16-bit address in the next 2 bytes (XO-CHIP)
+    pub fn op_f000(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        // FX00 is an instruction only with X = 0
+        if opcode != 0xF000 {
+            return Err(Error::IllegalOpcode(opcode));
+        }
+        cycle_log!(self, debug!("execute: I = NNNN"));
+
+        // the address follows the opcode (big-endian)
+        let address = (self.load(self.pc as usize + 2)? as u16) << 8;
+        self.i = address | self.load(self.pc as usize + 3)? as u16;
+
+        self.pc = self.pc.wrapping_add(4);
+
+        Ok(())
+    }
+
+    /// `FX07`: set VX = delay timer
+    pub fn op_fx07(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set VX = delay timer"));
+
+        self.v[x(opcode)] = self.timers.delay_timer;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX0A`: wait for key input
+    pub fn op_fx0a(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: stop executing instructions and wait for key input")
+        );
+
+        // the key is read when released (as in COSMAC VIP):
+        // until then, PC is not incremented and the instruction is executed again
+        let released = self.waiting_keys & !self.keys;
+        if released != 0 {
+            self.v[x(opcode)] = released.trailing_zeros() as u8;
+            self.waiting_keys = 0;
+            self.last_waited_key = Some(self.v[x(opcode)]);
+
+            self.pc = self.pc.wrapping_add(2);
+        } else {
+            self.waiting_keys |= self.keys;
+        }
+
+        Ok(())
+    }
+
+    /// `FX15`: set delay timer = VX
+    pub fn op_fx15(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set delay timer = VX"));
+
+        self.timers.delay_timer = self.v[x(opcode)];
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX18`: set sound timer = VX
+    pub fn op_fx18(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: set sound timer = VX"));
+
+        self.timers.sound_timer = self.v[x(opcode)];
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX1E`: I += VX (VF not affected)
+    pub fn op_fx1e(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: I += VX (VF not affected)"));
+
+        self.i = self.i.wrapping_add(self.v[x(opcode)] as u16);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX29`: I = location of the font sprite of digit VX
+    pub fn op_fx29(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: I = location of the font sprite of digit VX")
+        );
+
+        // fonts are 5 bytes long and loaded from memory location 0x00
+        self.i = (self.v[x(opcode)] & 0x0F) as u16 * 5;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX30`: I = location of the hires font sprite of digit VX (SUPER-CHIP)
+    pub fn op_fx30(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: I = location of the hires font sprite of digit VX")
+        );
+
+        // hires fonts are 10 bytes long and loaded after the fontset
+        self.i = FONTSET_END + (self.v[x(opcode)] & 0x0F) as u16 * 10;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX33`: get VX; convert it in 3 decimal digits; store them in memory
+    pub fn op_fx33(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(
+            self,
+            debug!("execute: get VX; convert it in 3 decimal digits; store them in memory")
+        );
+
+        let value = self.v[x(opcode)];
+        let i = self.i as usize;
+        let address = self.memory_address(i + 2)? - 2;
+        self.store(address, value / 100)?;
+        self.store(address + 1, value / 10 % 10)?;
+        self.store(address + 2, value % 10)?;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX55`: store registers to memory
+    ///
+    /// WARN: ambiguous instruction - instruction changed with SUPER-CHIP-8 (see `Chip8::set_memory_increment`)
+    pub fn op_fx55(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: store registers to memory"));
+
+        // store V0-VX from memory location I: I is incremented (as in COSMAC VIP),
+        // unless the memory quirk is off (SUPER-CHIP)
+        for register in 0..=x(opcode) {
+            self.store(
+                self.i.wrapping_add(register as u16) as usize,
+                self.v[register],
+            )?;
+        }
+        if self.memory_increment {
+            self.i = self.i.wrapping_add(x(opcode) as u16 + 1);
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX65`: load registers from memory
+    ///
+    /// WARN: ambiguous instruction - instruction changed with SUPER-CHIP-8 (see `Chip8::set_memory_increment`)
+    pub fn op_fx65(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        cycle_log!(self, debug!("execute: load registers from memory"));
+
+        // load V0-VX from memory location I: I is incremented (as in COSMAC VIP),
+        // unless the memory quirk is off (SUPER-CHIP)
+        for register in 0..=x(opcode) {
+            self.v[register] = self.load(self.i.wrapping_add(register as u16) as usize)?;
+        }
+        if self.memory_increment {
+            self.i = self.i.wrapping_add(x(opcode) as u16 + 1);
+        }
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX75`: store registers to RPL user flags (SUPER-CHIP)
+    pub fn op_fx75(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        // only V0-V7 can be stored (X < 8)
+        if x(opcode) >= RPL_FLAGS_SIZE {
+            return Err(Error::IllegalOpcode(opcode));
+        }
+        cycle_log!(self, debug!("execute: store registers to RPL user flags"));
+
+        // store V0-VX into the RPL user flags
+        self.rpl[..=x(opcode)].copy_from_slice(&self.v[..=x(opcode)]);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// `FX85`: load registers from RPL user flags (SUPER-CHIP)
+    pub fn op_fx85(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        // only V0-V7 can be loaded (X < 8)
+        if x(opcode) >= RPL_FLAGS_SIZE {
+            return Err(Error::IllegalOpcode(opcode));
+        }
+        cycle_log!(self, debug!("execute: load registers from RPL user flags"));
+
+        // load V0-VX from the RPL user flags
+        self.v[..=x(opcode)].copy_from_slice(&self.rpl[..=x(opcode)]);
+
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    /// Illegal opcode: nothing is executed
+    pub fn op_illegal(&mut self, opcode: u16, _rng: &mut dyn RngCore) -> Result<(), Error> {
+        Err(Error::IllegalOpcode(opcode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_MEMORY_SIZE;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with the registers set (V0, V1, ...)
+    fn with_registers(registers: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        for (register, &value) in registers.iter().enumerate() {
+            chip8.set_v(register as u8, value);
+        }
+        chip8
+    }
+
+    /// Returns the random number generator of the handlers
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    /// Returns the display row bits of the given columns
+    fn columns(columns: &[usize]) -> u128 {
+        columns
+            .iter()
+            .map(|column| 1 << (HIRES_DISPLAY_WIDTH - 1 - column))
+            .sum()
+    }
+
+    #[test]
+    fn arithmetic_flags_are_set_after_the_result() {
+        // VF as VX: the flag overwrites the result
+        let mut chip8 = with_registers(&[0, 1]);
+        chip8.set_v(0xF, 0xFF);
+        chip8.op_8xy4(0x8F14, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 1);
+
+        let mut chip8 = with_registers(&[0, 3]);
+        chip8.set_v(0xF, 5);
+        chip8.op_8xy5(0x8F15, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 1);
+
+        // VF as VY: the operand is read before the flag is set
+        let mut chip8 = with_registers(&[1]);
+        chip8.set_v(0xF, 2);
+        chip8.op_8xy5(0x80F5, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0xFF, 0));
+
+        let mut chip8 = with_registers(&[5, 3]);
+        chip8.op_8xy7(0x8017, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0xFE, 0));
+
+        let mut chip8 = with_registers(&[3, 5]);
+        chip8.op_8xy7(0x8017, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (2, 1));
+    }
+
+    #[test]
+    fn shift_flags_follow_the_shift_quirk() {
+        // VY shifted into VX (COSMAC VIP)
+        let mut chip8 = with_registers(&[0x80, 0x03]);
+        chip8.op_8xy6(0x8016, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0x01, 1));
+
+        // VX shifted in place (SUPER-CHIP)
+        let mut chip8 = with_registers(&[0x81, 0x02]);
+        chip8.set_shift_vy(false);
+        chip8.op_8xye(0x801E, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0x02, 1));
+
+        // VF shifted: the flag is the bit shifted out
+        let mut chip8 = with_registers(&[]);
+        chip8.set_shift_vy(false);
+        chip8.set_v(0xF, 0x03);
+        chip8.op_8xy6(0x8FF6, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 1);
+    }
+
+    #[test]
+    fn logical_instructions_follow_the_vf_reset_quirk() {
+        let mut chip8 = with_registers(&[0x0F, 0xF0]);
+        chip8.set_v(0xF, 7);
+        chip8.set_vf_reset(true);
+        chip8.op_8xy1(0x8011, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0xFF, 0));
+
+        let mut chip8 = with_registers(&[0x0F, 0xF0]);
+        chip8.set_v(0xF, 7);
+        chip8.set_vf_reset(false);
+        chip8.op_8xy2(0x8012, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[0xF]), (0x00, 7));
+    }
+
+    #[test]
+    fn store_and_load_follow_the_memory_quirk() {
+        let mut chip8 = with_registers(&[1, 2, 3]);
+        chip8.set_i(0x300);
+        chip8.set_memory_increment(true);
+        chip8.op_fx55(0xF255, &mut rng()).unwrap();
+        assert_eq!(&chip8.memory()[0x300..0x304], [1, 2, 3, 0]);
+        assert_eq!(chip8.i(), 0x303);
+
+        chip8.set_i(0x300);
+        chip8.set_memory_increment(false);
+        chip8.op_fx65(0xF165, &mut rng()).unwrap();
+        assert_eq!(chip8.i(), 0x300);
+
+        let mut chip8 = with_registers(&[]);
+        chip8.write_memory(0x300, 4);
+        chip8.write_memory(0x301, 5);
+        chip8.set_i(0x300);
+        chip8.set_memory_increment(true);
+        chip8.op_fx65(0xF165, &mut rng()).unwrap();
+        assert_eq!((chip8.v()[0], chip8.v()[1]), (4, 5));
+        assert_eq!(chip8.i(), 0x302);
+    }
+
+    #[test]
+    fn sprites_are_clipped_or_wrapped_at_the_edges() {
+        // one row of 8 pixels at column 62: 2 pixels on screen
+        let mut chip8 = with_registers(&[62, 0]);
+        chip8.write_memory(0x300, 0xFF);
+        chip8.set_i(0x300);
+        chip8.set_sprite_wrapping(false);
+        chip8.op_dxyn(0xD011, &mut rng()).unwrap();
+        assert_eq!(chip8.display[0], columns(&[62, 63]));
+
+        let mut chip8 = with_registers(&[62, 31]);
+        chip8.write_memory(0x300, 0xFF);
+        chip8.write_memory(0x301, 0x80);
+        chip8.set_i(0x300);
+        chip8.set_sprite_wrapping(true);
+        chip8.op_dxyn(0xD012, &mut rng()).unwrap();
+        assert_eq!(chip8.display[31], columns(&[62, 63, 0, 1, 2, 3, 4, 5]));
+        // the second row wraps to the top row
+        assert_eq!(chip8.display[0], columns(&[62]));
+    }
+
+    #[test]
+    fn collisions_set_vf() {
+        let mut chip8 = with_registers(&[0, 0]);
+        chip8.write_memory(0x300, 0xF0);
+        chip8.set_i(0x300);
+        chip8.op_dxyn(0xD011, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 0);
+        chip8.op_dxyn(0xD011, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 1);
+        assert_eq!(chip8.display[0], 0);
+    }
+
+    #[test]
+    fn collision_rows_are_counted_in_hires_mode() {
+        // 4 rows at row 62 of the hires display: 2 rows drawn, 2 clipped
+        let mut chip8 = with_registers(&[0, 62]);
+        for address in 0x300..0x304 {
+            chip8.write_memory(address, 0x80);
+        }
+        chip8.set_i(0x300);
+        chip8.set_sprite_wrapping(false);
+        chip8.set_collision_rows(true);
+        chip8.op_00ff(0x00FF, &mut rng()).unwrap();
+
+        chip8.op_dxyn(0xD014, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 2);
+        chip8.op_dxyn(0xD014, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 4);
+
+        // 0/1 flag without the quirk
+        chip8.set_collision_rows(false);
+        chip8.op_dxyn(0xD014, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 0);
+        chip8.op_dxyn(0xD014, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[0xF], 1);
+    }
+
+    #[test]
+    fn calls_are_limited_by_the_stack_size() {
+        let mut chip8 = with_registers(&[]);
+        for level in 0..MAX_STACK_SIZE {
+            assert_eq!(chip8.op_2nnn(0x2400, &mut rng()), Ok(()));
+            assert_eq!(chip8.stack().len(), level + 1);
+        }
+        assert_eq!(chip8.stack()[0], 0x202);
+        assert_eq!(chip8.op_2nnn(0x2400, &mut rng()), Err(Error::StackOverflow));
+
+        for _ in 0..MAX_STACK_SIZE {
+            assert_eq!(chip8.op_00ee(0x00EE, &mut rng()), Ok(()));
+        }
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(
+            chip8.op_00ee(0x00EE, &mut rng()),
+            Err(Error::StackUnderflow)
+        );
+    }
+
+    #[test]
+    fn pc_wraps_around_the_address_space() {
+        let mut chip8 = with_registers(&[]);
+        chip8.set_memory_size(MAX_MEMORY_SIZE);
+        chip8.memory[0xFFFE..].copy_from_slice(&[0x60, 0x05]);
+        chip8.set_pc(0xFFFE);
+        assert_eq!(chip8.try_emulate_cycle(&mut rng()), Ok(()));
+        assert_eq!((chip8.pc(), chip8.v()[0]), (0x000, 5));
+
+        // skip of the last instruction
+        chip8.set_pc(0xFFFE);
+        chip8.op_3xnn(0x3005, &mut rng()).unwrap();
+        assert_eq!(chip8.pc(), 0x002);
+    }
+
+    #[test]
+    fn i_wraps_around_the_address_space() {
+        let mut chip8 = with_registers(&[1, 2]);
+        chip8.set_i(0xFFFF);
+        chip8.op_fx1e(0xF01E, &mut rng()).unwrap();
+        assert_eq!(chip8.i(), 0x000);
+
+        // the store wraps to the beginning of a 64KB memory
+        chip8.set_memory_size(MAX_MEMORY_SIZE);
+        chip8.set_memory_increment(true);
+        chip8.set_i(0xFFFF);
+        chip8.op_fx55(0xF155, &mut rng()).unwrap();
+        assert_eq!((chip8.memory()[0xFFFF], chip8.memory()[0x000]), (1, 2));
+        assert_eq!(chip8.i(), 0x001);
+    }
+
+    #[test]
+    fn accesses_past_the_end_of_memory_fail() {
+        let mut chip8 = with_registers(&[1, 2]);
+        chip8.set_i(0x0FFF);
+        assert_eq!(
+            chip8.op_fx55(0xF155, &mut rng()),
+            Err(Error::MemoryOutOfBounds(0x1000))
+        );
+        assert_eq!(
+            chip8.op_fx65(0xF165, &mut rng()),
+            Err(Error::MemoryOutOfBounds(0x1000))
+        );
+    }
+
+    #[test]
+    fn dxy0_draws_16x16_sprites() {
+        let mut chip8 = with_registers(&[4, 2]);
+        for row in 0..16 {
+            chip8.write_memory(0x300 + 2 * row, 0x80);
+            chip8.write_memory(0x301 + 2 * row, 0x01);
+        }
+        chip8.set_i(0x300);
+        chip8.op_00ff(0x00FF, &mut rng()).unwrap();
+
+        chip8.op_dxyn(0xD010, &mut rng()).unwrap();
+        assert_eq!(chip8.display[1], 0);
+        assert!(chip8.display[2..18]
+            .iter()
+            .all(|&row| row == columns(&[4, 19])));
+        assert_eq!(chip8.display[18], 0);
+    }
+
+    #[test]
+    fn dxy0_in_lores_follows_the_tall_sprites_quirk() {
+        let mut chip8 = with_registers(&[0, 0]);
+        for address in 0x300..0x320 {
+            chip8.write_memory(address, 0xFF);
+        }
+        chip8.set_i(0x300);
+
+        // 16x16 sprite (Octo)
+        chip8.set_lores_tall_sprites(false);
+        chip8.op_dxyn(0xD010, &mut rng()).unwrap();
+        assert_eq!(chip8.display[15], 0xFFFF << (HIRES_DISPLAY_WIDTH - 16));
+        chip8.op_00e0(0x00E0, &mut rng()).unwrap();
+
+        // 8x16 sprite (SUPER-CHIP 1.1)
+        chip8.set_lores_tall_sprites(true);
+        chip8.op_dxyn(0xD010, &mut rng()).unwrap();
+        assert_eq!(chip8.display[15], 0xFF << (HIRES_DISPLAY_WIDTH - 8));
+        assert_eq!(chip8.display[16], 0);
+    }
+
+    #[test]
+    fn fx30_points_to_the_hires_font() {
+        let mut chip8 = with_registers(&[0x1A]);
+        chip8.op_fx30(0xF030, &mut rng()).unwrap();
+
+        assert_eq!(chip8.i(), FONTSET_END + 0xA * 10);
+        let glyph = chip8.i() as usize;
+        assert_eq!(
+            chip8.memory()[glyph..glyph + 10],
+            chip8.hires_font()[100..110]
+        );
+    }
+
+    #[test]
+    fn register_ranges_are_stored_and_loaded_in_order() {
+        let mut chip8 = with_registers(&[0, 0, 2, 3, 4]);
+        chip8.set_i(0x300);
+        chip8.op_5xy2(0x5242, &mut rng()).unwrap();
+        assert_eq!(chip8.memory()[0x300..0x303], [2, 3, 4]);
+        // descending range: VX first
+        chip8.op_5xy2(0x5422, &mut rng()).unwrap();
+        assert_eq!(chip8.memory()[0x300..0x303], [4, 3, 2]);
+        assert_eq!(chip8.i(), 0x300);
+
+        chip8.op_5xy3(0x5013, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[..2], [4, 3]);
+        chip8.op_5xy3(0x5103, &mut rng()).unwrap();
+        assert_eq!(chip8.v()[..2], [3, 4]);
+        assert_eq!((chip8.i(), chip8.pc()), (0x300, 0x208));
+    }
+
+    #[test]
+    fn f000_loads_the_16_bit_address_after_it() {
+        let mut chip8 = with_registers(&[]);
+        chip8.memory[0x200..0x204].copy_from_slice(&[0xF0, 0x00, 0xAB, 0xCD]);
+        chip8.op_f000(0xF000, &mut rng()).unwrap();
+        assert_eq!((chip8.i(), chip8.pc()), (0xABCD, 0x204));
+
+        assert_eq!(
+            chip8.op_f000(0xF100, &mut rng()),
+            Err(Error::IllegalOpcode(0xF100))
+        );
+    }
+
+    #[test]
+    fn skips_skip_the_whole_f000_instruction() {
+        let mut chip8 = with_registers(&[5]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0xF0, 0x00]);
+        chip8.op_3xnn(0x3005, &mut rng()).unwrap();
+        assert_eq!(chip8.pc(), 0x206);
+
+        let mut chip8 = with_registers(&[5]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0x12, 0x00]);
+        chip8.op_3xnn(0x3005, &mut rng()).unwrap();
+        assert_eq!(chip8.pc(), 0x204);
+    }
+
+    #[test]
+    fn machine_code_calls_follow_the_policy() {
+        let mut chip8 = with_registers(&[]);
+        assert_eq!(
+            chip8.op_0nnn(0x0123, &mut rng()),
+            Err(Error::MachineCodeCall(0x0123))
+        );
+        assert_eq!(chip8.pc(), 0x200);
+
+        for policy in [MachineCodePolicy::Ignore, MachineCodePolicy::Warn] {
+            let mut chip8 = with_registers(&[]);
+            chip8.set_machine_code_policy(policy);
+            assert_eq!(chip8.op_0nnn(0x0123, &mut rng()), Ok(()));
+            assert_eq!(chip8.pc(), 0x202);
+        }
+    }
+}
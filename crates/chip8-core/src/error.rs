@@ -0,0 +1,46 @@
+//! Implementation of CHIP-8 (execution errors)
+
+use core::fmt::Display;
+
+/// Errors that stop the execution of an instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The opcode is not a CHIP-8 instruction (or it is not supported)
+    IllegalOpcode(u16),
+
+    /// Subroutine call with all the stack levels in use
+    StackOverflow,
+
+    /// Subroutine return with an empty stack
+    StackUnderflow,
+
+    /// Memory access beyond the end of memory (address of the first byte out of bounds)
+    MemoryOutOfBounds(usize),
+
+    /// Call of a machine code routine (`0NNN`) with the halt policy (opcode)
+    MachineCodeCall(u16),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::IllegalOpcode(opcode) => write!(f, "Illegal opcode: `{opcode:#06X}`"),
+            Self::StackOverflow => write!(f, "Stack overflow: subroutine call with a full stack"),
+            Self::StackUnderflow => {
+                write!(f, "Stack underflow: subroutine return with an empty stack")
+            }
+            Self::MemoryOutOfBounds(address) => {
+                write!(f, "Memory access out of bounds: `{address:#06X}`")
+            }
+            Self::MachineCodeCall(opcode) => {
+                write!(
+                    f,
+                    "Machine code routine call not supported: `{opcode:#06X}`"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
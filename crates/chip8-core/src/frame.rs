@@ -0,0 +1,225 @@
+//! Implementation of CHIP-8 (snapshots of the completed display frames)
+
+use super::{
+    ALL_DISPLAY_ROWS, DISPLAY_HEIGTH, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH,
+};
+use alloc::vec::Vec;
+
+/// Immutable snapshot of the CHIP-8 display: the display at the end of a 60Hz frame (see `Chip8::frame`),
+/// or at any instruction (see `Chip8::snapshot`).
+/// Frontends draw the snapshots instead of the live display, which changes while the program draws the next frame:
+/// a snapshot is a copy, so it can be kept or sent to another thread without tearing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Display rows: every bit holds the state of a pixel,
+    /// from the most significant bit (column 0) to the least significant bit (column 127).
+    /// In lores mode only the first 32 rows and the 64 most significant bits are used
+    pub(super) rows: [u128; HIRES_DISPLAY_HEIGTH],
+
+    /// Boolean set to true if the display was in the SUPER-CHIP hires mode (128x64)
+    pub(super) hires: bool,
+
+    /// Number of the frame: the number of 60Hz frames completed when the snapshot was taken
+    pub(super) number: u64,
+}
+
+impl Frame {
+    /// Returns the display as packed rows (as many rows as the display height): every bit holds the state
+    /// of a pixel, from the most significant bit (column 0) to the least significant bit (column 127).
+    /// In lores mode only the 64 most significant bits are used
+    pub fn rows(&self) -> &[u128] {
+        &self.rows[..self.size().1]
+    }
+
+    /// Returns the number of 60Hz frames completed when the snapshot was taken
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Returns true if the display was in the SUPER-CHIP hires mode
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Returns the size (width, height) of the display in pixels: 64x32 in lores mode, 128x64 in hires mode
+    pub fn size(&self) -> (usize, usize) {
+        display_size(self.hires)
+    }
+
+    /// Returns true if the pixel at coordinates (x, y) is on
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The pixel column (0-63, 0-127 in hires mode)
+    /// * `y` - The pixel row (0-31, 0-63 in hires mode)
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << (HIRES_DISPLAY_WIDTH - 1 - x)) != 0
+    }
+
+    /// Returns the display as unpacked pixels (row by row, true if the pixel is on),
+    /// width x height pixels of the size returned by `size`
+    pub fn pixels(&self) -> Vec<bool> {
+        let (width, height) = self.size();
+
+        (0..width * height)
+            .map(|i| self.pixel(i % width, i / width))
+            .collect()
+    }
+
+    /// Returns the mask of the rows that differ from another snapshot (bit n set if row n differs),
+    /// e.g. the rows to redraw since the last snapshot drawn. All the rows differ if the display
+    /// mode changed
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The snapshot to compare with
+    pub fn changed_rows(&self, other: &Frame) -> u64 {
+        if self.hires != other.hires {
+            return ALL_DISPLAY_ROWS;
+        }
+
+        self.rows()
+            .iter()
+            .zip(other.rows.iter())
+            .enumerate()
+            .filter(|(_, (row, other))| row != other)
+            .fold(0, |mask, (n, _)| mask | 1 << n)
+    }
+
+    /// Returns the pixels that differ from another snapshot as (x, y, on) tuples, row by row:
+    /// e.g. the pixels to redraw since the last snapshot drawn. All the pixels are returned
+    /// if the display mode changed
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The snapshot to compare with
+    pub fn changed_pixels(&self, other: &Frame) -> impl Iterator<Item = (usize, usize, bool)> {
+        // every pixel differs from the inverted display
+        let other = if self.hires == other.hires {
+            other.rows
+        } else {
+            self.rows.map(|row| !row)
+        };
+
+        changed_pixels(self.rows, other, self.hires)
+    }
+}
+
+// Default trait implementation for Frame: the blank lores display before the first frame
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            rows: [0; HIRES_DISPLAY_HEIGTH],
+            hires: false,
+            number: 0,
+        }
+    }
+}
+
+/// Returns the size (width, height) of the display in pixels
+///
+/// # Arguments
+///
+/// * `hires` - Boolean set to true for the SUPER-CHIP hires mode
+pub(super) fn display_size(hires: bool) -> (usize, usize) {
+    if hires {
+        (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH)
+    } else {
+        (DISPLAY_WIDTH, DISPLAY_HEIGTH)
+    }
+}
+
+/// Returns the pixels of display rows that differ from other rows as (x, y, on) tuples, row by row
+///
+/// # Arguments
+///
+/// * `rows` - The display rows
+/// * `other` - The display rows to compare with
+/// * `hires` - Boolean set to true for the SUPER-CHIP hires mode
+pub(super) fn changed_pixels(
+    rows: [u128; HIRES_DISPLAY_HEIGTH],
+    other: [u128; HIRES_DISPLAY_HEIGTH],
+    hires: bool,
+) -> impl Iterator<Item = (usize, usize, bool)> {
+    let (width, height) = display_size(hires);
+
+    rows.into_iter()
+        .zip(other)
+        .take(height)
+        .enumerate()
+        .filter(|(_, (row, other))| row != other)
+        .flat_map(move |(y, (row, other))| {
+            (0..width)
+                .filter(move |x| (row ^ other) & (1 << (HIRES_DISPLAY_WIDTH - 1 - x)) != 0)
+                .map(move |x| (x, y, row & (1 << (HIRES_DISPLAY_WIDTH - 1 - x)) != 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns a lores frame with the given pixels on
+    fn frame(pixels: &[(usize, usize)]) -> Frame {
+        let mut frame = Frame::default();
+        for &(x, y) in pixels {
+            frame.rows[y] |= 1 << (HIRES_DISPLAY_WIDTH - 1 - x);
+        }
+        frame
+    }
+
+    #[test]
+    fn frame_keeps_the_display_of_the_last_completed_frame() {
+        let mut chip8 = Chip8::new();
+        chip8.set_clock_frequency(120.0);
+        // I = glyph 0, draw it at (0, 0), erase it, jump to itself
+        chip8.load_rom_bytes(&[0xA0, 0x00, 0xD0, 0x05, 0xD0, 0x05, 0x12, 0x06]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        chip8.run_frame(&mut rng).unwrap();
+        assert!(chip8.frame().pixel(0, 0));
+        assert_eq!(chip8.frame().number(), 1);
+        // the sprite is erased in the live display, not in the frame
+        chip8.step(&mut rng).unwrap();
+        assert!(!chip8.snapshot().pixel(0, 0));
+        assert!(chip8.frame().pixel(0, 0));
+        chip8.step(&mut rng).unwrap();
+        assert!(!chip8.frame().pixel(0, 0));
+        assert_eq!(chip8.frame().number(), 2);
+    }
+
+    #[test]
+    fn changes_are_compared_row_by_row() {
+        let before = frame(&[(0, 0), (5, 3)]);
+        let after = frame(&[(0, 0), (6, 3), (63, 31)]);
+
+        assert_eq!(after.changed_rows(&before), 1 << 3 | 1 << 31);
+        assert_eq!(
+            after.changed_pixels(&before).collect::<Vec<_>>(),
+            [(5, 3, false), (6, 3, true), (63, 31, true)]
+        );
+        assert_eq!(after.changed_rows(&after), 0);
+    }
+
+    #[test]
+    fn display_mode_change_changes_every_pixel() {
+        let lores = frame(&[(0, 0)]);
+        let hires = Frame {
+            hires: true,
+            ..Frame::default()
+        };
+
+        assert_eq!(hires.changed_rows(&lores), ALL_DISPLAY_ROWS);
+        assert_eq!(
+            hires.changed_pixels(&lores).count(),
+            HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGTH
+        );
+        assert_eq!(
+            hires.pixels().len(),
+            HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGTH
+        );
+        assert_eq!(lores.pixels().len(), DISPLAY_WIDTH * DISPLAY_HEIGTH);
+    }
+}
@@ -0,0 +1,62 @@
+//! Implementation of CHIP-8 (fuzzing harness)
+
+use super::{Chip8, MAX_ROM_SIZE, MAX_STACK_SIZE, V_SIZE};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// max number of instructions executed by `fuzz_cycle`
+const FUZZ_MAX_CYCLES: usize = 1000;
+
+/// Executes arbitrary opcode sequences: the memory window is loaded as the ROM,
+/// the rest of the state (registers, I, stack, timers, keypad) is generated from the seed,
+/// then instructions are executed with `try_emulate_cycle` until an error is returned
+/// (at most 1000 instructions). Errors are expected, panics are bugs of the decoder or the executor
+///
+/// # Arguments
+///
+/// * `memory_window` - The bytes loaded at 0x200 (truncated to the max ROM size)
+/// * `state_seed` - The seed of the initial state and of the random number generator
+pub fn fuzz_cycle(memory_window: &[u8], state_seed: u64) {
+    let mut rng = StdRng::seed_from_u64(state_seed);
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom_bytes(&memory_window[..memory_window.len().min(MAX_ROM_SIZE)]);
+
+    rng.fill(&mut chip8.v[..V_SIZE]);
+    // I can be beyond the end of memory (FX1E)
+    chip8.i = rng.gen();
+    chip8.sp = rng.gen_range(0..=MAX_STACK_SIZE as u8);
+    rng.fill(&mut chip8.stack[..]);
+    chip8.timers.delay_timer = rng.gen();
+    chip8.timers.sound_timer = rng.gen();
+    chip8.keys = rng.gen();
+
+    for cycle in 0..FUZZ_MAX_CYCLES {
+        if chip8.try_emulate_cycle(&mut rng).is_err() {
+            break;
+        }
+        if cycle % 8 == 0 {
+            chip8.update_timers();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitrary_programs_do_not_panic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for seed in 0..200 {
+            let mut window = [0; 64];
+            rng.fill(&mut window[..]);
+            fuzz_cycle(&window, seed);
+        }
+    }
+
+    #[test]
+    fn oversized_and_empty_windows_are_accepted() {
+        fuzz_cycle(&[], 0);
+        fuzz_cycle(&[0xFF; MAX_ROM_SIZE + 16], 0);
+    }
+}
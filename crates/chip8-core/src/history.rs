@@ -0,0 +1,140 @@
+//! Implementation of CHIP-8 (history of the executed instructions)
+
+use super::{disassemble, Chip8};
+use alloc::{collections::VecDeque, format, string::String, vec::Vec};
+
+/// default number of executed instructions kept in the history
+pub const HISTORY_DEPTH: usize = 32;
+
+/// Ring buffer of the last executed instructions, printed when an instruction cannot be executed
+#[derive(Clone)]
+pub(super) struct InstructionHistory {
+    /// Number of instructions kept (0: the instructions are not recorded)
+    depth: usize,
+
+    /// Address and opcode of the executed instructions, from the oldest one
+    instructions: VecDeque<(u16, u16)>,
+}
+
+impl Default for InstructionHistory {
+    fn default() -> Self {
+        Self {
+            depth: HISTORY_DEPTH,
+            instructions: VecDeque::with_capacity(HISTORY_DEPTH),
+        }
+    }
+}
+
+impl Chip8 {
+    /// Keeps the address and opcode of the last `depth` executed instructions (`HISTORY_DEPTH` by default,
+    /// 0 disables the history): they are logged, disassembled, when an instruction cannot be executed
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of executed instructions kept
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.depth = depth;
+        while self.history.instructions.len() > depth {
+            self.history.instructions.pop_front();
+        }
+    }
+
+    /// Returns the number of executed instructions kept in the history
+    pub fn history_depth(&self) -> usize {
+        self.history.depth
+    }
+
+    /// Returns the address and opcode of the last executed instructions, from the oldest one
+    /// (the last one is the instruction that could not be executed, after an execution error)
+    pub fn last_instructions(&self) -> Vec<(u16, u16)> {
+        self.history.instructions.iter().copied().collect()
+    }
+
+    /// Adds the fetched instruction to the history
+    pub(super) fn record_instruction(&mut self) {
+        if self.history.depth == 0 {
+            return;
+        }
+
+        if self.history.instructions.len() == self.history.depth {
+            self.history.instructions.pop_front();
+        }
+        self.history.instructions.push_back((self.pc, self.opcode));
+    }
+
+    /// Clears the history of the executed instructions (e.g. after a reset)
+    pub(super) fn clear_history(&mut self) {
+        self.history.instructions.clear();
+    }
+
+    /// Returns the last executed instructions, disassembled: one line per instruction
+    /// with its address and opcode, from the oldest one
+    pub(super) fn dump_history(&self) -> String {
+        self.history
+            .instructions
+            .iter()
+            .map(|&(pc, opcode)| format!("{pc:#05X}: {opcode:04X}  {}", disassemble(opcode)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with a ROM loaded: V0 = 1, V0 += 1, V0 += 2, return with an empty stack
+    fn with_rom() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x60, 0x01, 0x70, 0x01, 0x70, 0x02, 0x00, 0xEE]);
+        chip8
+    }
+
+    /// Executes the instructions until an error
+    fn run(chip8: &mut Chip8) -> Error {
+        let mut rng = StdRng::seed_from_u64(0);
+        loop {
+            if let Err(e) = chip8.step(&mut rng) {
+                return e;
+            }
+        }
+    }
+
+    #[test]
+    fn history_ends_with_the_failed_instruction() {
+        let mut chip8 = with_rom();
+        assert_eq!(run(&mut chip8), Error::StackUnderflow);
+
+        assert_eq!(
+            chip8.last_instructions(),
+            [
+                (0x200, 0x6001),
+                (0x202, 0x7001),
+                (0x204, 0x7002),
+                (0x206, 0x00EE)
+            ]
+        );
+        assert_eq!(
+            chip8.dump_history().lines().last(),
+            Some("0x206: 00EE  RET")
+        );
+    }
+
+    #[test]
+    fn history_keeps_the_last_instructions() {
+        let mut chip8 = with_rom();
+        chip8.set_history_depth(2);
+        run(&mut chip8);
+        assert_eq!(
+            chip8.last_instructions(),
+            [(0x204, 0x7002), (0x206, 0x00EE)]
+        );
+
+        chip8.set_history_depth(1);
+        assert_eq!(chip8.last_instructions(), [(0x206, 0x00EE)]);
+        chip8.set_history_depth(0);
+        assert!(chip8.last_instructions().is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! Implementation of CHIP-8 (observer hooks of the emulation events)
+
+use super::Chip8;
+use alloc::{boxed::Box, vec::Vec};
+
+/// Hook called with the CHIP-8 instance after an emulation event
+type Hook = Box<dyn FnMut(&Chip8) + Send + Sync>;
+
+/// Hooks registered on a CHIP-8 instance, grouped by event
+#[derive(Default)]
+pub(super) struct Hooks {
+    /// Hooks called after an instruction modified the display (DXYN, 00E0, scroll and display mode instructions)
+    draw: Vec<Hook>,
+
+    /// Hooks called when the beeper starts sounding (the sound timer becomes greater than 0)
+    sound_start: Vec<Hook>,
+
+    /// Hooks called when the beeper stops sounding (the sound timer becomes 0)
+    sound_stop: Vec<Hook>,
+
+    /// Hooks called after every instruction
+    cycle: Vec<Hook>,
+
+    /// Hooks called at the end of every 60Hz frame, after the timers update
+    frame: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Returns true if no hook is registered
+    fn is_empty(&self) -> bool {
+        self.draw.is_empty()
+            && self.sound_start.is_empty()
+            && self.sound_stop.is_empty()
+            && self.cycle.is_empty()
+            && self.frame.is_empty()
+    }
+}
+
+impl Clone for Hooks {
+    /// Hooks cannot be cloned: the clone of a CHIP-8 instance has no hooks
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Emulation events of one step, passed to `Chip8::call_hooks`
+pub(super) struct StepEvents {
+    /// Boolean set to true if the instruction modified the display
+    pub(super) draw: bool,
+
+    /// Boolean set to true if the beeper was sounding before the step
+    pub(super) sound_before: bool,
+
+    /// Boolean set to true if the step ended a 60Hz frame
+    pub(super) frame_end: bool,
+}
+
+impl Chip8 {
+    /// Registers a hook called after every instruction that modified the display (DXYN, 00E0,
+    /// scroll and display mode instructions) executed with `step` or `run_frame`
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the CHIP-8 instance
+    pub fn on_draw(&mut self, hook: impl FnMut(&Chip8) + Send + Sync + 'static) {
+        self.hooks.draw.push(Box::new(hook));
+    }
+
+    /// Registers a hook called when the beeper starts sounding (the sound timer becomes greater than 0)
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the CHIP-8 instance
+    pub fn on_sound_start(&mut self, hook: impl FnMut(&Chip8) + Send + Sync + 'static) {
+        self.hooks.sound_start.push(Box::new(hook));
+    }
+
+    /// Registers a hook called when the beeper stops sounding (the sound timer becomes 0)
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the CHIP-8 instance
+    pub fn on_sound_stop(&mut self, hook: impl FnMut(&Chip8) + Send + Sync + 'static) {
+        self.hooks.sound_stop.push(Box::new(hook));
+    }
+
+    /// Registers a hook called after every instruction executed with `step` or `run_frame`
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the CHIP-8 instance
+    pub fn on_cycle(&mut self, hook: impl FnMut(&Chip8) + Send + Sync + 'static) {
+        self.hooks.cycle.push(Box::new(hook));
+    }
+
+    /// Registers a hook called at the end of every 60Hz frame of emulated time, after the timers update
+    /// (`beeping` returns the beeper state of the frame)
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the CHIP-8 instance
+    pub fn on_frame(&mut self, hook: impl FnMut(&Chip8) + Send + Sync + 'static) {
+        self.hooks.frame.push(Box::new(hook));
+    }
+
+    /// Calls the hooks of the events of the last step
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - The events of the last step
+    pub(super) fn call_hooks(&mut self, events: StepEvents) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        // the hooks are moved out of the instance, so that they can borrow it
+        let mut hooks = core::mem::take(&mut self.hooks);
+        let sound_after = self.timers.sound_timer > 0;
+
+        for hook in hooks.cycle.iter_mut() {
+            hook(self);
+        }
+        if events.draw {
+            for hook in hooks.draw.iter_mut() {
+                hook(self);
+            }
+        }
+        if !events.sound_before && sound_after {
+            for hook in hooks.sound_start.iter_mut() {
+                hook(self);
+            }
+        }
+        if events.sound_before && !sound_after {
+            for hook in hooks.sound_stop.iter_mut() {
+                hook(self);
+            }
+        }
+        if events.frame_end {
+            for hook in hooks.frame.iter_mut() {
+                hook(self);
+            }
+        }
+
+        self.hooks = hooks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns a counter and a hook that increments it
+    fn counter() -> (Arc<AtomicUsize>, impl FnMut(&Chip8) + Send + Sync + 'static) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let hook_count = count.clone();
+        (count, move |_: &Chip8| {
+            hook_count.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    #[test]
+    fn hooks_are_called_after_their_events() {
+        let mut chip8 = Chip8::new();
+        chip8.set_clock_frequency(120.0);
+        // clear the display, sound timer = 1, jump to itself
+        chip8.load_rom_bytes(&[0x00, 0xE0, 0x60, 0x01, 0xF0, 0x18, 0x12, 0x06]);
+        let (cycles, hook) = counter();
+        chip8.on_cycle(hook);
+        let (draws, hook) = counter();
+        chip8.on_draw(hook);
+        let (sound_starts, hook) = counter();
+        chip8.on_sound_start(hook);
+        let (sound_stops, hook) = counter();
+        chip8.on_sound_stop(hook);
+        let (frames, hook) = counter();
+        chip8.on_frame(hook);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = || {
+            chip8.step(&mut rng).unwrap();
+            [&cycles, &draws, &sound_starts, &sound_stops, &frames]
+                .map(|count| count.load(Ordering::Relaxed))
+        };
+        assert_eq!(counts(), [1, 1, 0, 0, 0]);
+        assert_eq!(counts(), [2, 1, 0, 0, 1]);
+        assert_eq!(counts(), [3, 1, 1, 0, 1]);
+        // the sound timer is decremented at the end of the frame
+        assert_eq!(counts(), [4, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn clones_have_no_hooks() {
+        let mut chip8 = Chip8::new();
+        let (_, hook) = counter();
+        chip8.on_cycle(hook);
+
+        assert!(!chip8.hooks.is_empty());
+        assert!(chip8.clone().hooks.is_empty());
+    }
+}
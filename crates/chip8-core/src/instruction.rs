@@ -0,0 +1,327 @@
+//! Implementation of CHIP-8 (decoded instructions)
+
+use super::RPL_FLAGS_SIZE;
+use core::fmt::Display;
+
+/// CHIP-8 instruction decoded from an opcode: the operands are extracted once, so that the decoded
+/// instructions can be cached per address (registers are indices 0x0-0xF, addresses are 12-bit)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// `00CN`: scroll the display down by N rows (SUPER-CHIP)
+    ScrollDown(u8),
+    /// `00E0`: clear the display
+    ClearScreen,
+    /// `00EE`: return from a subroutine
+    Return,
+    /// `00FB`: scroll the display right by 4 pixels (SUPER-CHIP)
+    ScrollRight,
+    /// `00FC`: scroll the display left by 4 pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// `00FD`: exit the interpreter (SUPER-CHIP)
+    Exit,
+    /// `00FE`: switch the display to lores mode, 64x32 pixels (SUPER-CHIP)
+    LowResolution,
+    /// `00FF`: switch the display to hires mode, 128x64 pixels (SUPER-CHIP)
+    HighResolution,
+    /// `0NNN`: call of a machine code routine at NNN
+    MachineCode(u16),
+    /// `1NNN`: jump to NNN
+    Jump(u16),
+    /// `2NNN`: call the subroutine at NNN
+    Call(u16),
+    /// `3XNN`: skip the next instruction if VX == NN
+    SkipEqual(u8, u8),
+    /// `4XNN`: skip the next instruction if VX != NN
+    SkipNotEqual(u8, u8),
+    /// `5XY0`: skip the next instruction if VX == VY
+    SkipEqualRegisters(u8, u8),
+    /// `5XY2`: store VX-VY in memory at I, I not modified (XO-CHIP)
+    StoreRange(u8, u8),
+    /// `5XY3`: load VX-VY from memory at I, I not modified (XO-CHIP)
+    LoadRange(u8, u8),
+    /// `6XNN`: set VX = NN
+    SetImmediate(u8, u8),
+    /// `7XNN`: set VX += NN (VF not affected)
+    AddImmediate(u8, u8),
+    /// `8XY0`: set VX = VY
+    Set(u8, u8),
+    /// `8XY1`: set VX |= VY
+    Or(u8, u8),
+    /// `8XY2`: set VX &= VY
+    And(u8, u8),
+    /// `8XY3`: set VX ^= VY
+    Xor(u8, u8),
+    /// `8XY4`: set VX += VY (VF affected)
+    Add(u8, u8),
+    /// `8XY5`: set VX = VX - VY (VF affected)
+    Sub(u8, u8),
+    /// `8XY6`: set VX = VY >> 1 (VF affected)
+    ShiftRight(u8, u8),
+    /// `8XY7`: set VX = VY - VX (VF affected)
+    SubReverse(u8, u8),
+    /// `8XYE`: set VX = VY << 1 (VF affected)
+    ShiftLeft(u8, u8),
+    /// `9XY0`: skip the next instruction if VX != VY
+    SkipNotEqualRegisters(u8, u8),
+    /// `ANNN`: set I = NNN
+    SetIndex(u16),
+    /// `BNNN`: jump to NNN + V0
+    JumpOffset(u16),
+    /// `CXNN`: set VX = random byte & NN
+    Random(u8, u8),
+    /// `DXYN`: draw the N rows sprite at I in (VX, VY), the 16x16 sprite if N is 0 (SUPER-CHIP)
+    Draw(u8, u8, u8),
+    /// `EX9E`: skip the next instruction if the key VX is pressed
+    SkipKeyPressed(u8),
+    /// `EXA1`: skip the next instruction if the key VX is not pressed
+    SkipKeyNotPressed(u8),
+    /// `F000 NNNN`: set I = NNNN, the 16-bit address in the next 2 bytes (XO-CHIP, 4 bytes long)
+    LongIndex,
+    /// `FX07`: set VX = delay timer
+    GetDelayTimer(u8),
+    /// `FX0A`: wait for a key press and release, then set VX = key
+    WaitKey(u8),
+    /// `FX15`: set delay timer = VX
+    SetDelayTimer(u8),
+    /// `FX18`: set sound timer = VX
+    SetSoundTimer(u8),
+    /// `FX1E`: set I += VX (VF not affected)
+    AddIndex(u8),
+    /// `FX29`: set I = location of the font sprite of digit VX
+    FontCharacter(u8),
+    /// `FX30`: set I = location of the hires font sprite of digit VX (SUPER-CHIP)
+    HiresFontCharacter(u8),
+    /// `FX33`: store the 3 decimal digits of VX in memory at I
+    Bcd(u8),
+    /// `FX55`: store V0-VX in memory at I
+    Store(u8),
+    /// `FX65`: load V0-VX from memory at I
+    Load(u8),
+    /// `FX75`: store V0-VX into the RPL user flags (SUPER-CHIP, X < 8)
+    StoreFlags(u8),
+    /// `FX85`: load V0-VX from the RPL user flags (SUPER-CHIP, X < 8)
+    LoadFlags(u8),
+    /// Opcode that is not a CHIP-8 instruction
+    Illegal(u16),
+}
+
+impl Instruction {
+    /// Decodes an opcode: CHIP-8 instructions are divided into broad categories by the first nibble
+    /// (half-byte), then by the last nibble or the last byte
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - The u16 opcode to decode
+    pub fn decode(opcode: u16) -> Self {
+        // second nibble: used to look up one of the 16 registers (VX) from V0-VF
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        // third nibble: used to look up one of the 16 registers (VY) from V0-VF
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        // fourth nibble: 4-bit number
+        let n = (opcode & 0x000F) as u8;
+        // second byte (third and fourth nibble): an 8-bit immediate number
+        let nn = (opcode & 0x00FF) as u8;
+        // second, third and fourth nibble: a 12-bit immediate number
+        let nnn = opcode & 0x0FFF;
+
+        match (opcode & 0xF000, n, nn) {
+            (0x0000, _, _) if nnn & 0xFF0 == 0x0C0 => Self::ScrollDown(n),
+            (0x0000, _, _) if nnn == 0x00E0 => Self::ClearScreen,
+            (0x0000, _, _) if nnn == 0x00EE => Self::Return,
+            (0x0000, _, _) if nnn == 0x00FB => Self::ScrollRight,
+            (0x0000, _, _) if nnn == 0x00FC => Self::ScrollLeft,
+            (0x0000, _, _) if nnn == 0x00FD => Self::Exit,
+            (0x0000, _, _) if nnn == 0x00FE => Self::LowResolution,
+            (0x0000, _, _) if nnn == 0x00FF => Self::HighResolution,
+            (0x0000, _, _) => Self::MachineCode(nnn),
+            (0x1000, _, _) => Self::Jump(nnn),
+            (0x2000, _, _) => Self::Call(nnn),
+            (0x3000, _, _) => Self::SkipEqual(x, nn),
+            (0x4000, _, _) => Self::SkipNotEqual(x, nn),
+            (0x5000, 0x0, _) => Self::SkipEqualRegisters(x, y),
+            (0x5000, 0x2, _) => Self::StoreRange(x, y),
+            (0x5000, 0x3, _) => Self::LoadRange(x, y),
+            (0x6000, _, _) => Self::SetImmediate(x, nn),
+            (0x7000, _, _) => Self::AddImmediate(x, nn),
+            (0x8000, 0x0, _) => Self::Set(x, y),
+            (0x8000, 0x1, _) => Self::Or(x, y),
+            (0x8000, 0x2, _) => Self::And(x, y),
+            (0x8000, 0x3, _) => Self::Xor(x, y),
+            (0x8000, 0x4, _) => Self::Add(x, y),
+            (0x8000, 0x5, _) => Self::Sub(x, y),
+            (0x8000, 0x6, _) => Self::ShiftRight(x, y),
+            (0x8000, 0x7, _) => Self::SubReverse(x, y),
+            (0x8000, 0xE, _) => Self::ShiftLeft(x, y),
+            (0x9000, 0x0, _) => Self::SkipNotEqualRegisters(x, y),
+            (0xA000, _, _) => Self::SetIndex(nnn),
+            (0xB000, _, _) => Self::JumpOffset(nnn),
+            (0xC000, _, _) => Self::Random(x, nn),
+            (0xD000, _, _) => Self::Draw(x, y, n),
+            (0xE000, _, 0x9E) => Self::SkipKeyPressed(x),
+            (0xE000, _, 0xA1) => Self::SkipKeyNotPressed(x),
+            (0xF000, _, _) if opcode == 0xF000 => Self::LongIndex,
+            (0xF000, _, 0x07) => Self::GetDelayTimer(x),
+            (0xF000, _, 0x0A) => Self::WaitKey(x),
+            (0xF000, _, 0x15) => Self::SetDelayTimer(x),
+            (0xF000, _, 0x18) => Self::SetSoundTimer(x),
+            (0xF000, _, 0x1E) => Self::AddIndex(x),
+            (0xF000, _, 0x29) => Self::FontCharacter(x),
+            (0xF000, _, 0x30) => Self::HiresFontCharacter(x),
+            (0xF000, _, 0x33) => Self::Bcd(x),
+            (0xF000, _, 0x55) => Self::Store(x),
+            (0xF000, _, 0x65) => Self::Load(x),
+            (0xF000, _, 0x75) if (x as usize) < RPL_FLAGS_SIZE => Self::StoreFlags(x),
+            (0xF000, _, 0x85) if (x as usize) < RPL_FLAGS_SIZE => Self::LoadFlags(x),
+            _ => Self::Illegal(opcode),
+        }
+    }
+
+    /// Returns the size of the instruction in bytes: 4 for `F000` (followed by its 16-bit address),
+    /// 2 for the other instructions. The skip instructions skip the whole next instruction
+    pub fn size(&self) -> u16 {
+        match self {
+            Self::LongIndex => 4,
+            _ => 2,
+        }
+    }
+
+    /// Returns true if the instruction ends a basic block: it may not fall through to the next
+    /// 2-byte instruction (jump, call, return, skip, key wait, exit, machine code routine, illegal opcode,
+    /// and `F000`, that is 4 bytes long)
+    pub(super) fn ends_block(&self) -> bool {
+        matches!(
+            self,
+            Self::Return
+                | Self::Exit
+                | Self::MachineCode(_)
+                | Self::Jump(_)
+                | Self::Call(_)
+                | Self::SkipEqual(_, _)
+                | Self::SkipNotEqual(_, _)
+                | Self::SkipEqualRegisters(_, _)
+                | Self::SkipNotEqualRegisters(_, _)
+                | Self::JumpOffset(_)
+                | Self::SkipKeyPressed(_)
+                | Self::SkipKeyNotPressed(_)
+                | Self::LongIndex
+                | Self::WaitKey(_)
+                | Self::Illegal(_)
+        )
+    }
+}
+
+// Display trait implementation for Instruction: the assembly mnemonic (e.g. `DRW V0, V1, 5`)
+impl Display for Instruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::ScrollDown(n) => write!(f, "SCD {n}"),
+            Self::ClearScreen => write!(f, "CLS"),
+            Self::Return => write!(f, "RET"),
+            Self::ScrollRight => write!(f, "SCR"),
+            Self::ScrollLeft => write!(f, "SCL"),
+            Self::Exit => write!(f, "EXIT"),
+            Self::LowResolution => write!(f, "LOW"),
+            Self::HighResolution => write!(f, "HIGH"),
+            Self::MachineCode(nnn) => write!(f, "SYS {nnn:#05X}"),
+            Self::Jump(nnn) => write!(f, "JP {nnn:#05X}"),
+            Self::Call(nnn) => write!(f, "CALL {nnn:#05X}"),
+            Self::SkipEqual(x, nn) => write!(f, "SE V{x:X}, {nn:#04X}"),
+            Self::SkipNotEqual(x, nn) => write!(f, "SNE V{x:X}, {nn:#04X}"),
+            Self::SkipEqualRegisters(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+            Self::StoreRange(x, y) => write!(f, "LD [I], V{x:X}-V{y:X}"),
+            Self::LoadRange(x, y) => write!(f, "LD V{x:X}-V{y:X}, [I]"),
+            Self::SetImmediate(x, nn) => write!(f, "LD V{x:X}, {nn:#04X}"),
+            Self::AddImmediate(x, nn) => write!(f, "ADD V{x:X}, {nn:#04X}"),
+            Self::Set(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+            Self::Or(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+            Self::And(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+            Self::Xor(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self::Add(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self::Sub(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self::ShiftRight(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self::SubReverse(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self::ShiftLeft(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self::SkipNotEqualRegisters(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self::SetIndex(nnn) => write!(f, "LD I, {nnn:#05X}"),
+            Self::JumpOffset(nnn) => write!(f, "JP V0, {nnn:#05X}"),
+            Self::Random(x, nn) => write!(f, "RND V{x:X}, {nn:#04X}"),
+            Self::Draw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self::SkipKeyPressed(x) => write!(f, "SKP V{x:X}"),
+            Self::SkipKeyNotPressed(x) => write!(f, "SKNP V{x:X}"),
+            Self::LongIndex => write!(f, "LD I, LONG"),
+            Self::GetDelayTimer(x) => write!(f, "LD V{x:X}, DT"),
+            Self::WaitKey(x) => write!(f, "LD V{x:X}, K"),
+            Self::SetDelayTimer(x) => write!(f, "LD DT, V{x:X}"),
+            Self::SetSoundTimer(x) => write!(f, "LD ST, V{x:X}"),
+            Self::AddIndex(x) => write!(f, "ADD I, V{x:X}"),
+            Self::FontCharacter(x) => write!(f, "LD F, V{x:X}"),
+            Self::HiresFontCharacter(x) => write!(f, "LD HF, V{x:X}"),
+            Self::Bcd(x) => write!(f, "LD B, V{x:X}"),
+            Self::Store(x) => write!(f, "LD [I], V{x:X}"),
+            Self::Load(x) => write!(f, "LD V{x:X}, [I]"),
+            Self::StoreFlags(x) => write!(f, "LD R, V{x:X}"),
+            Self::LoadFlags(x) => write!(f, "LD V{x:X}, R"),
+            Self::Illegal(opcode) => write!(f, "DW {opcode:#06X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn operands_are_extracted_from_the_nibbles() {
+        assert_eq!(Instruction::decode(0x00C7), Instruction::ScrollDown(7));
+        assert_eq!(Instruction::decode(0x0123), Instruction::MachineCode(0x123));
+        assert_eq!(Instruction::decode(0x2ABC), Instruction::Call(0xABC));
+        assert_eq!(
+            Instruction::decode(0x3A42),
+            Instruction::SkipEqual(0xA, 0x42)
+        );
+        assert_eq!(
+            Instruction::decode(0x5AB3),
+            Instruction::LoadRange(0xA, 0xB)
+        );
+        assert_eq!(
+            Instruction::decode(0x8ABE),
+            Instruction::ShiftLeft(0xA, 0xB)
+        );
+        assert_eq!(Instruction::decode(0xD120), Instruction::Draw(1, 2, 0));
+        assert_eq!(Instruction::decode(0xF000), Instruction::LongIndex);
+        assert_eq!(
+            Instruction::decode(0xF730),
+            Instruction::HiresFontCharacter(7)
+        );
+        assert_eq!(Instruction::decode(0xF775), Instruction::StoreFlags(7));
+    }
+
+    #[test]
+    fn unknown_opcodes_are_illegal() {
+        for opcode in [0x5AB1, 0x8AB8, 0x9AB1, 0xE1A0, 0xF1FF, 0xF875, 0xF885] {
+            assert_eq!(Instruction::decode(opcode), Instruction::Illegal(opcode));
+        }
+    }
+
+    #[test]
+    fn mnemonics_show_the_operands() {
+        let mnemonic = |opcode| Instruction::decode(opcode).to_string();
+        assert_eq!(mnemonic(0xD015), "DRW V0, V1, 5");
+        assert_eq!(mnemonic(0x1234), "JP 0x234");
+        assert_eq!(mnemonic(0x6A0F), "LD VA, 0x0F");
+        assert_eq!(mnemonic(0x5123), "LD V1-V2, [I]");
+        assert_eq!(mnemonic(0xFFFF), "DW 0xFFFF");
+    }
+
+    #[test]
+    fn control_flow_instructions_end_the_blocks() {
+        assert_eq!(Instruction::LongIndex.size(), 4);
+        assert_eq!(Instruction::ClearScreen.size(), 2);
+        assert!(Instruction::decode(0x1200).ends_block());
+        assert!(Instruction::decode(0xE09E).ends_block());
+        assert!(Instruction::decode(0xF000).ends_block());
+        assert!(!Instruction::decode(0xD015).ends_block());
+        assert!(!Instruction::decode(0x7001).ends_block());
+    }
+}
@@ -0,0 +1,1530 @@
+//! # chip8-core
+//!
+//! The CHIP-8 interpreter of `rust-chip-8`, for embedders: the pure interpreter without the emulation loop,
+//! the display renderers, the audio output and the command line frontend, and without their dependencies
+//! (clap, tracing-subscriber, wgpu...). The host owns the emulation loop and drives the interpreter
+//! with `step` or `run_frame`.
+//!
+//! Without the `std` feature the crate is `no_std` (it requires `alloc`): the host drives the interpreter
+//! with `step` (or `emulate_cycle` and `try_emulate_cycle`, that returns execution errors instead of panicking),
+//! `update_timers`, `load_rom_bytes` and the keypad functions. The `std` feature adds ROM files
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod access;
+mod builder;
+mod cache;
+mod disassembler;
+mod display;
+#[cfg(feature = "embedded-graphics")]
+pub mod embedded;
+mod emulation;
+mod error;
+mod frame;
+#[cfg(feature = "fuzzing")]
+mod fuzz;
+mod history;
+mod hooks;
+mod instruction;
+mod profile;
+mod random;
+mod rewind;
+mod smc;
+mod state;
+mod stepping;
+mod timing;
+mod tone;
+mod uninit;
+mod variants;
+
+pub use access::{AccessKind, MemoryAccess, ACCESS_LOG_SIZE};
+pub use builder::Chip8Builder;
+pub use disassembler::disassemble;
+pub use error::Error;
+pub use frame::Frame;
+#[cfg(feature = "fuzzing")]
+pub use fuzz::fuzz_cycle;
+pub use history::HISTORY_DEPTH;
+pub use instruction::Instruction;
+pub use profile::{Profile, Quirks};
+pub use random::{ConstantRng, CounterRng};
+pub use rewind::StateChange;
+pub use smc::SelfModifyingCodePolicy;
+pub use state::StateError;
+pub use stepping::SessionStats;
+pub use timing::InstructionTiming;
+pub use tone::{Beeper, Waveform, BEEP_FREQUENCY, MAX_VOLUME};
+pub use variants::Variants;
+
+use access::AccessLog;
+use alloc::{format, string::String, vec, vec::Vec};
+use cache::Block;
+use core::{fmt::Display, panic};
+use emulation::Handler;
+use history::InstructionHistory;
+use hooks::Hooks;
+use rewind::UndoLog;
+use smc::CodeTracker;
+#[cfg(feature = "std")]
+use std::{fs::File, io::Read, path::Path};
+
+use tracing::{debug, error, trace};
+
+/// default RAM memory size (COSMAC VIP): 4KB
+pub const MEMORY_SIZE: usize = 4096;
+
+/// min RAM memory size: 1KB
+pub const MIN_MEMORY_SIZE: usize = 1024;
+
+/// max RAM memory size (XO-CHIP): 64KB, the whole address space of the 16-bit I register
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+
+/// default program start address (COSMAC VIP): the ROM is loaded and executed from 0x200
+pub const PROGRAM_START: u16 = 0x200;
+
+/// program start address of the ETI-660 computer
+pub const ETI_660_PROGRAM_START: u16 = 0x600;
+
+/// max ROM size with the default memory size and program start address: the ROM is loaded from 0x200
+/// to the end of memory
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - PROGRAM_START as usize;
+
+/// fontset size: 16 glyphs (0-F) of 5 bytes (4x5 pixels)
+pub const FONTSET_SIZE: usize = 80;
+
+/// hires fontset size (SUPER-CHIP): 16 glyphs (0-F) of 10 bytes (8x10 pixels)
+pub const HIRES_FONTSET_SIZE: usize = 160;
+
+/// end of the fontset in memory: the lowest program start address (the hires fontset is loaded from it)
+const FONTSET_END: u16 = 0x50;
+
+/// end of the hires fontset in memory: the lowest program start address with an alternate hires font
+const HIRES_FONTSET_END: u16 = FONTSET_END + HIRES_FONTSET_SIZE as u16;
+
+/// display width (lores mode)
+pub const DISPLAY_WIDTH: usize = 64;
+
+/// display height (lores mode)
+pub const DISPLAY_HEIGTH: usize = 32;
+
+/// display width in the SUPER-CHIP hires mode
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+
+/// display height in the SUPER-CHIP hires mode
+pub const HIRES_DISPLAY_HEIGTH: usize = 64;
+
+/// max display size: (width x height) = (128 x 64) in hires mode
+pub const MAX_DISPLAY_SIZE: usize = HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGTH;
+
+/// default CHIP-8 clock frequency (Hz): instructions executed per second
+pub const CLOCK_FREQUENCY: f64 = 500.0;
+
+/// CHIP-8 delay and sound timers frequency (Hz)
+pub const TIMERS_FREQUENCY: f64 = 60.0;
+
+/// dirty rows mask with all the display rows set
+const ALL_DISPLAY_ROWS: u64 = u64::MAX;
+
+/// max stack levels
+const MAX_STACK_SIZE: usize = 16;
+
+/// max V size
+const V_SIZE: usize = 16;
+
+/// 64-bit FNV-1a hash offset basis
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// 64-bit FNV-1a hash prime
+const FNV_PRIME: u64 = 0x0100_0000_01B3;
+
+/// number of RPL user flags of SUPER-CHIP (FX75/FX85)
+pub const RPL_FLAGS_SIZE: usize = 8;
+
+/// number of keys of the hex keypad (0x0-0xF)
+pub const KEYPAD_SIZE: usize = 16;
+
+/// CHIP-8 fontset.
+/// Each font is 2 nibbles (or half-bytes) = 1 bytes = 8 bits
+const CHIP8_FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// SUPER-CHIP hires fontset (big digits drawn with FX30).
+/// Each font is 10 bytes (8x10 pixels), with the A-F glyphs of Octo
+const SCHIP_HIRES_FONTSET: [u8; HIRES_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Policy applied to the `0NNN` instructions (SYS: call of a machine code routine of the host computer),
+/// that cannot be executed by an interpreter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum MachineCodePolicy {
+    /// Skip the instruction (as a NOP)
+    Ignore,
+    /// Log a warning and skip the instruction
+    Warn,
+    /// Stop the execution with an error
+    #[default]
+    Halt,
+}
+
+impl Variants for MachineCodePolicy {
+    const VARIANTS: &'static [Self] = &[
+        MachineCodePolicy::Ignore,
+        MachineCodePolicy::Warn,
+        MachineCodePolicy::Halt,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MachineCodePolicy::Ignore => "ignore",
+            MachineCodePolicy::Warn => "warn",
+            MachineCodePolicy::Halt => "halt",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            MachineCodePolicy::Ignore => "Skip the instruction (as a NOP)",
+            MachineCodePolicy::Warn => "Log a warning and skip the instruction",
+            MachineCodePolicy::Halt => "Stop the execution with an error",
+        }
+    }
+}
+
+/// CHIP-8 representation
+#[derive(Clone)]
+pub struct Chip8 {
+    /// Boolean set to true if ROM has been loaded into memory, false otherwise
+    rom_loaded: bool,
+
+    /// Loaded ROM image (kept for soft resets)
+    rom: Vec<u8>,
+
+    /// Boolean set to true if the emulation is paused (the execution loop does not execute instructions)
+    paused: bool,
+
+    /// Boolean set to true if the program exited with `00FD` (the instance must be reset to run it again)
+    exited: bool,
+
+    /// CHIP-8 has 35 opcodes that are all 2 bytes = 16 bits long
+    opcode: u16,
+
+    /// CHIP-8 has 4KB = 4096 bytes of RAM memory in total (1KB to 64KB, see `set_memory_size`).
+    /// The fontset should be loaded at memory locations 0-80 (0x00-0x50).
+    /// The program should be loaded at memory 512-onwards (0x200-onwards, see `start_address`)
+    memory: Vec<u8>,
+
+    /// CHIP-8 has 15 8-bit general purpose CPU registers named V0-VE.
+    /// The 16th register (VF) is used for 'carry flag'.
+    /// every CPU register has 8 bit = 1 byte length
+    v: [u8; V_SIZE],
+
+    /// CHIP-8 has one 16-bit Index Register (I) that points at locations in memory
+    i: u16,
+
+    /// SUPER-CHIP RPL user flags (FX75/FX85): like the flags of the HP-48 calculator,
+    /// they are not cleared by reset, so that programs can keep data across runs
+    rpl: [u8; RPL_FLAGS_SIZE],
+
+    /// Program start address: the ROM is loaded and executed from it
+    start_address: u16,
+
+    /// Fontset loaded at 0x00-0x50 (the built-in one, or an alternate font)
+    font: [u8; FONTSET_SIZE],
+
+    /// Hires fontset loaded after the fontset (0x50-0xF0): the built-in one, or an alternate hires font
+    hires_font: [u8; HIRES_FONTSET_SIZE],
+
+    /// Boolean set to true if an alternate hires font is set: it must be loaded before the program start address
+    alternate_hires_font: bool,
+
+    /// CHIP-8 has one 16-bit Program Counter (PC) that points at the current instruction in memory
+    pc: u16,
+
+    /// CHIP-8 has a black and white graphics and the screen has a total of 2048 pixels (64 x 32),
+    /// 8192 pixels (128 x 64) in the SUPER-CHIP hires mode.
+    /// We can implement this with an array of 128-bit rows: every bit holds the state (1 or 0) of a pixel,
+    /// from the most significant bit (column 0) to the least significant bit (column 127).
+    /// In lores mode only the first 32 rows and the 64 most significant bits are used.
+    /// In this way, sprite drawing, collision detection and clear screen take a few bitwise operations per row
+    display: [u128; HIRES_DISPLAY_HEIGTH],
+
+    /// Boolean set to true in the SUPER-CHIP hires mode (128x64 display, `00FF`), false in lores mode
+    /// (64x32 display, `00FE`)
+    hires: bool,
+
+    /// Boolean set to true if the scroll instructions scroll the lores display by half the distance
+    /// (quirk of SUPER-CHIP 1.1, that scrolls by hires pixels, that is half lores pixels)
+    half_pixel_scroll: bool,
+
+    /// Snapshot of the display at the end of the last 60Hz frame (the completed frame shown by the frontends)
+    frame: Frame,
+
+    /// Boolean set to true if DXYN wraps the sprites around the screen edges (quirk of some platforms),
+    /// false if the sprites are clipped (as in COSMAC VIP)
+    wrap_sprites: bool,
+
+    /// Boolean set to true if DXY0 draws 8x16 sprites in lores mode (quirk of SUPER-CHIP 1.1),
+    /// false if it draws 16x16 sprites in both display modes (as in Octo)
+    lores_tall_sprites: bool,
+
+    /// Boolean set to true if DXYN sets VF to the number of sprite rows that collide or are clipped
+    /// in hires mode (quirk of SUPER-CHIP 1.1), false if it sets VF to 1 on collision
+    collision_rows: bool,
+
+    /// Boolean set to true if 8XY1, 8XY2 and 8XY3 reset VF (as in COSMAC VIP)
+    vf_reset: bool,
+
+    /// Boolean set to true if 8XY6 and 8XYE shift VY into VX (as in COSMAC VIP),
+    /// false if they shift VX in place (quirk of SUPER-CHIP 1.1)
+    shift_vy: bool,
+
+    /// Boolean set to true if FX55 and FX65 increment I (as in COSMAC VIP),
+    /// false if I is not modified (quirk of SUPER-CHIP 1.1)
+    memory_increment: bool,
+
+    /// Boolean set to true if BNNN jumps to XNN + VX (quirk of SUPER-CHIP 1.1),
+    /// false if it jumps to NNN + V0 (as in COSMAC VIP)
+    jump_vx: bool,
+
+    /// Policy applied to the `0NNN` machine code routine calls
+    machine_code: MachineCodePolicy,
+
+    /// CHIP-8 draw flag. If flag is set to true, redraw screen
+    draw: bool,
+
+    /// Mask of the display rows modified by the display instructions since the last redraw
+    /// (bit n set if row n was modified), so that the renderer can redraw only the affected rows
+    dirty_rows: u64,
+
+    /// CHIP-8 has a stack used to remember the current location
+    /// before a jump is performed.
+    /// (CHIP-8 instruction set has opcodes that allow the
+    /// program to jump to a certain address or call a subroutine)
+    /// So, anytime we perform a jump or call a subroutine, we
+    /// store the PC in the stack before proceeding.
+    /// the stack stores 16-bit addresses (2 bytes = 16 bits)
+    /// and has 16 levels of stack. In order to remember which level
+    /// of the stack is used, we need to implement a stack pointer (SP)
+    stack: [u16; MAX_STACK_SIZE],
+
+    /// CHIP-8 Stack Pointer (SP) used to remember which level of the stack is used (16 levels: 0-15)
+    sp: u8,
+
+    /// CHIP-8 has two 8-bit timer registers that count at 60Hz
+    /// when these registers are set with a value > 0, they
+    /// will count down until 0
+    timers: Timers,
+
+    /// CHIP-8 has a 16-key hexadecimal keypad (0x0-0xF).
+    /// Every bit holds the state of a key: bit n set if key n is pressed
+    keys: u16,
+
+    /// Keys pressed while waiting for a key with FX0A (the key is read when released)
+    waiting_keys: u16,
+
+    /// Key read by the last FX0A, if any
+    last_waited_key: Option<u8>,
+
+    /// Number of instructions executed with `step` since the instance was created or reset
+    cycles: u64,
+
+    /// Number of executed display instructions (00E0, DXYN, scroll and display mode instructions)
+    /// since the instance was created or reset
+    draws: u64,
+
+    /// Number of key presses and releases since the instance was created or reset
+    key_events: u64,
+
+    /// Clock frequency (Hz) of `step`: instructions executed per second of emulated time
+    clock_frequency: f64,
+
+    /// Timing model of the instructions executed with `step`
+    timing: InstructionTiming,
+
+    /// The events of one instruction every `trace_sample` cycles are logged (1: every instruction)
+    trace_sample: u64,
+
+    /// Boolean set to true if the events of the current instruction are logged
+    #[cfg(feature = "cycle-logging")]
+    log_cycle: bool,
+
+    /// Emulated time elapsed since the last timers update of `step` (seconds)
+    timers_elapsed: f64,
+
+    /// Boolean set to true if the beeper sounded in the last frame (sound timer > 0 before the timers update)
+    beeping: bool,
+
+    /// Boolean set to true if the beeper is muted (the audio outputs are silent)
+    muted: bool,
+
+    /// Generator of the beeper tone of `fill_audio`
+    beeper: Beeper,
+
+    /// Hooks of the emulation events, called by `step`
+    hooks: Hooks,
+
+    /// Memory accesses of the instructions within the traced address ranges
+    access_log: AccessLog,
+
+    /// Executed and modified addresses (None if self-modifying code is ignored)
+    code_tracker: Option<CodeTracker>,
+
+    /// Memory bytes ever written, one bit per address (None if uninitialized reads are not reported)
+    initialized: Option<Vec<u64>>,
+
+    /// State deltas of the last executed instructions, undone by `step_back`
+    undo_log: UndoLog,
+
+    /// Address and opcode of the last executed instructions, logged after an execution error
+    history: InstructionHistory,
+
+    /// Handlers of the decoded instructions, one per memory address (None if the opcode at the address
+    /// is not decoded yet)
+    decoded: Vec<Option<Handler>>,
+
+    /// Basic blocks starting at every memory address, executed by `run_block` (None if no block
+    /// starting at the address is cached)
+    blocks: Vec<Option<Block>>,
+}
+
+/// Structure that contains CHIP-8 delay_timer and sound_timer
+#[derive(Clone)]
+struct Timers {
+    /// 8-bit Delay Timer which is decremented at a rate of 60 Hz (60 times per second) until it reaches 0
+    delay_timer: u8,
+    /// 8-bit Sound Timer which functions like the delay timer, but which also gives off a beeping sound as long as it’s not 0
+    sound_timer: u8,
+}
+
+impl Chip8 {
+    /// Resets the CHIP-8 instance: memory, registers, stack, timers, keys and display are cleared
+    /// (the RPL user flags are kept).
+    /// If `keep_rom` is true the loaded ROM image is loaded again, so that the program restarts
+    /// from the beginning, otherwise the ROM is discarded and must be loaded again
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_rom` - Boolean set to true to load the current ROM image again
+    pub fn reset(&mut self, keep_rom: bool) {
+        trace!("Chip8::reset: start");
+        debug!("before reset: {}", self);
+
+        // clear rom_loaded flag
+        let reload = keep_rom && self.rom_loaded;
+        self.rom_loaded = false;
+
+        // clear exited flag
+        self.exited = false;
+
+        // reset current opcode
+        self.opcode = 0;
+
+        // clear memory
+        self.memory.fill(0);
+        self.clear_initialized();
+        self.clear_decoded();
+
+        // clear registers V0-VF
+        for i in 0..V_SIZE {
+            self.v[i] = 0;
+        }
+
+        // reset I
+        self.i = 0;
+
+        // PC starts at the program start address (0x200 by default)
+        self.pc = self.start_address;
+
+        // clear display (back to lores mode)
+        self.hires = false;
+        self.clear_display();
+        self.frame = Frame::default();
+
+        // the whole display must be redrawn
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+
+        // clear stack
+        for i in 0..MAX_STACK_SIZE {
+            self.stack[i] = 0;
+        }
+
+        // reset SP
+        self.sp = 0;
+
+        // reload fontset into memory (0x00-0x50)
+        self.load_fontset();
+
+        // reset timers
+        self.timers.delay_timer = 0;
+        self.timers.sound_timer = 0;
+
+        // release keys
+        self.keys = 0;
+        self.waiting_keys = 0;
+        self.last_waited_key = None;
+
+        // reset the emulated time
+        self.cycles = 0;
+        self.draws = 0;
+        self.key_events = 0;
+        self.timers_elapsed = 0.0;
+        self.beeping = false;
+
+        // the recorded memory accesses refer to the cycles before the reset
+        self.clear_memory_accesses();
+
+        // the program runs again from unmodified code
+        self.clear_code_tracker();
+        self.clear_undo_log();
+        self.clear_history();
+
+        // reload the ROM image, or discard it
+        let rom = core::mem::take(&mut self.rom);
+        if reload {
+            self.load_rom_bytes(&rom);
+        }
+
+        // the cleared display must be redrawn
+        self.draw = true;
+
+        debug!("after reset: {}", self);
+        trace!("Chip8::reset: exit");
+    }
+
+    /// Loads CHIP-8 fontset into memory at locations 0x00-0x50
+    fn load_fontset(&mut self) {
+        trace!("Chip8::load_fontset: start");
+
+        // load fontset into memory (0x00-0x50), and the hires fontset after it (0x50-0xF0)
+        // unless the program starts before its end
+        self.memory[..FONTSET_SIZE].copy_from_slice(&self.font);
+        if self.fontset_end() == HIRES_FONTSET_END {
+            self.memory[FONTSET_SIZE..HIRES_FONTSET_END as usize].copy_from_slice(&self.hires_font);
+        }
+        self.mark_initialized(0..self.fontset_end() as usize);
+        self.invalidate_decoded(0..self.fontset_end() as usize);
+
+        trace!("Chip8::load_fontset: exit");
+    }
+
+    /// Clears CHIP-8 display (set all display bits to 0)
+    fn clear_display(&mut self) {
+        trace!("Chip8::clear_screen: start");
+
+        self.display = [0; HIRES_DISPLAY_HEIGTH];
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+
+        trace!("Chip8::clear_screen: exit");
+    }
+
+    /// Returns a new CHIP-8 instance ready to load a new ROM file
+    pub fn new() -> Self {
+        trace!("Chip8::new: start");
+
+        // create new chip8 instance
+        let mut chip8 = Self {
+            rom_loaded: false,
+            rom: Vec::new(),
+            paused: false,
+            exited: false,
+            opcode: 0,
+            memory: vec![0; MEMORY_SIZE],
+            v: [0; V_SIZE],
+            i: 0,
+            rpl: [0; RPL_FLAGS_SIZE],
+            pc: PROGRAM_START,
+            start_address: PROGRAM_START,
+            font: CHIP8_FONTSET,
+            hires_font: SCHIP_HIRES_FONTSET,
+            alternate_hires_font: false,
+            display: [0; HIRES_DISPLAY_HEIGTH],
+            hires: false,
+            half_pixel_scroll: false,
+            frame: Frame::default(),
+            wrap_sprites: false,
+            lores_tall_sprites: false,
+            collision_rows: false,
+            vf_reset: true,
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: false,
+            machine_code: MachineCodePolicy::Halt,
+            draw: false,
+            dirty_rows: ALL_DISPLAY_ROWS,
+            stack: [0; MAX_STACK_SIZE],
+            sp: 0,
+            timers: Timers {
+                delay_timer: 0,
+                sound_timer: 0,
+            },
+            keys: 0,
+            waiting_keys: 0,
+            last_waited_key: None,
+            cycles: 0,
+            draws: 0,
+            key_events: 0,
+            clock_frequency: CLOCK_FREQUENCY,
+            timing: InstructionTiming::Uniform,
+            trace_sample: 1,
+            #[cfg(feature = "cycle-logging")]
+            log_cycle: false,
+            timers_elapsed: 0.0,
+            beeping: false,
+            muted: false,
+            beeper: Beeper::new(),
+            hooks: Hooks::default(),
+            access_log: AccessLog::default(),
+            code_tracker: None,
+            initialized: None,
+            undo_log: UndoLog::default(),
+            history: InstructionHistory::default(),
+            decoded: vec![None; MEMORY_SIZE],
+            blocks: vec![None; MEMORY_SIZE],
+        };
+        // load fontset
+        chip8.load_fontset();
+
+        debug!("new chip8 instance: {}", chip8);
+        trace!("Chip8::new: exit");
+
+        // return created instance
+        chip8
+    }
+
+    /// Loads a ROM file into the memory of the current CHIP-8 instance
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The Path reference that holds the path to the ROM file
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during opening and reading of the ROM file
+    /// or if the ROM does not fit into memory
+    #[cfg(feature = "std")]
+    pub fn load_rom(&mut self, file: &Path) {
+        trace!("Chip8::load_rom: start");
+
+        // opening file
+        let mut rom = match File::open(file) {
+            Ok(f) => f,
+            Err(e) => {
+                panic!("opening rom file: {e}")
+            }
+        };
+        // reading file
+        let mut contents = Vec::new();
+        if let Err(e) = rom.read_to_end(&mut contents) {
+            panic!("reading rom file: {e}")
+        }
+
+        self.load_rom_bytes(&contents);
+
+        trace!("Chip8::load_rom: exit");
+    }
+
+    /// Loads a ROM image into the memory of the current CHIP-8 instance
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM contents
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the ROM does not fit into memory
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) {
+        trace!("Chip8::load_rom_bytes: start");
+
+        // loading ROM into memory
+        // (we start filling memory from the program start address, 0x200 by default)
+        let start = self.start_address as usize;
+        if rom.len() > self.max_rom_size() {
+            panic!(
+                "ROM too big: {} bytes (max {} bytes)",
+                rom.len(),
+                self.max_rom_size()
+            );
+        }
+        self.memory[start..(start + rom.len())].copy_from_slice(rom);
+        self.mark_initialized(start..start + rom.len());
+        self.invalidate_decoded(start..start + rom.len());
+
+        // the loaded program has not been executed nor modified yet
+        self.clear_code_tracker();
+        self.clear_undo_log();
+
+        // keep the ROM image for soft resets
+        self.rom.clear();
+        self.rom.extend_from_slice(rom);
+
+        // set ROM loaded in memory flag
+        self.rom_loaded = true;
+
+        trace!("Chip8::load_rom_bytes: exit");
+    }
+
+    /// Sets the program start address (0x200 by default, 0x600 for ETI-660 programs): the address
+    /// the ROM is loaded at and PC starts from. The ROM must be loaded after setting the address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The program start address
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the address overlaps the fontset or is beyond the end of memory
+    pub fn set_start_address(&mut self, address: u16) {
+        if address < self.min_start_address() || address as usize >= self.memory.len() {
+            panic!(
+                "invalid start address {address:#05X}: must be between {:#05X} and {:#05X}",
+                self.min_start_address(),
+                self.memory.len() - 1
+            );
+        }
+
+        self.start_address = address;
+        self.pc = address;
+    }
+
+    /// Returns the program start address
+    pub fn start_address(&self) -> u16 {
+        self.start_address
+    }
+
+    /// Returns the max size of the ROM loaded at the program start address
+    pub fn max_rom_size(&self) -> usize {
+        self.memory
+            .len()
+            .saturating_sub(self.start_address as usize)
+    }
+
+    /// Sets an alternate font: 80 bytes replace the fontset (16 glyphs of 5 bytes at 0x00-0x50),
+    /// 160 bytes replace the hires fontset (16 glyphs of 10 bytes at 0x50-0xF0)
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The font data
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the font is not 80 or 160 bytes long,
+    /// or if the hires fontset overlaps the program start address
+    pub fn set_font(&mut self, font: &[u8]) {
+        if let Ok(font) = <[u8; FONTSET_SIZE]>::try_from(font) {
+            self.font = font;
+        } else if let Ok(font) = <[u8; HIRES_FONTSET_SIZE]>::try_from(font) {
+            self.hires_font = font;
+            self.alternate_hires_font = true;
+            if self.start_address < self.min_start_address() {
+                panic!(
+                    "invalid hires font: it overlaps the start address {:#05X}",
+                    self.start_address
+                );
+            }
+        } else {
+            panic!(
+                "invalid font: {} bytes (expected {FONTSET_SIZE} or {HIRES_FONTSET_SIZE} bytes)",
+                font.len()
+            );
+        }
+
+        self.load_fontset();
+    }
+
+    /// Returns the fontset (16 glyphs of 5 bytes)
+    pub fn font(&self) -> &[u8; FONTSET_SIZE] {
+        &self.font
+    }
+
+    /// Returns the hires fontset (16 glyphs of 10 bytes)
+    pub fn hires_font(&self) -> &[u8; HIRES_FONTSET_SIZE] {
+        &self.hires_font
+    }
+
+    /// Returns the lowest program start address: the end of the fontset,
+    /// or the end of the hires fontset if an alternate hires font is set
+    fn min_start_address(&self) -> u16 {
+        if self.alternate_hires_font {
+            HIRES_FONTSET_END
+        } else {
+            FONTSET_END
+        }
+    }
+
+    /// Returns the end of the fontsets in memory: the built-in hires fontset is not loaded
+    /// if the program starts before its end
+    fn fontset_end(&self) -> u16 {
+        if self.start_address >= HIRES_FONTSET_END {
+            HIRES_FONTSET_END
+        } else {
+            FONTSET_END
+        }
+    }
+
+    /// Sets the sprite wrapping quirk of DXYN: the sprite pixels beyond the right and bottom edges
+    /// of the screen are drawn on the opposite edges (as on some platforms, e.g. for scrolling effects)
+    /// instead of being clipped (as in COSMAC VIP, by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `wrap` - Boolean set to true to wrap the sprites around the screen edges
+    pub fn set_sprite_wrapping(&mut self, wrap: bool) {
+        self.wrap_sprites = wrap;
+    }
+
+    /// Returns true if DXYN wraps the sprites around the screen edges
+    pub fn sprite_wrapping(&self) -> bool {
+        self.wrap_sprites
+    }
+
+    /// Sets the lores quirk of DXY0: the instruction draws a 8x16 sprite from 16 bytes at I in lores mode
+    /// (as in SUPER-CHIP 1.1) instead of a 16x16 sprite from 32 bytes at I (as in hires mode and in Octo, by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `tall` - Boolean set to true to draw 8x16 sprites with DXY0 in lores mode
+    pub fn set_lores_tall_sprites(&mut self, tall: bool) {
+        self.lores_tall_sprites = tall;
+    }
+
+    /// Returns true if DXY0 draws 8x16 sprites in lores mode
+    pub fn lores_tall_sprites(&self) -> bool {
+        self.lores_tall_sprites
+    }
+
+    /// Sets the collision rows quirk of DXYN in hires mode: VF is set to the number of sprite rows
+    /// that collide with the display plus the rows clipped at the bottom edge (as in SUPER-CHIP 1.1)
+    /// instead of 1 if any pixel collides (as in lores mode, by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Boolean set to true to count the collision rows in hires mode
+    pub fn set_collision_rows(&mut self, count: bool) {
+        self.collision_rows = count;
+    }
+
+    /// Returns true if DXYN counts the collision rows in hires mode
+    pub fn collision_rows(&self) -> bool {
+        self.collision_rows
+    }
+
+    /// Sets the VF reset quirk of 8XY1, 8XY2 and 8XY3: the logical instructions set VF to 0
+    /// (as in COSMAC VIP, by default) instead of leaving it unchanged (as in SUPER-CHIP 1.1 and Octo)
+    ///
+    /// # Arguments
+    ///
+    /// * `reset` - Boolean set to true to reset VF with the logical instructions
+    pub fn set_vf_reset(&mut self, reset: bool) {
+        self.vf_reset = reset;
+    }
+
+    /// Returns true if 8XY1, 8XY2 and 8XY3 reset VF
+    pub fn vf_reset(&self) -> bool {
+        self.vf_reset
+    }
+
+    /// Sets the shift quirk of 8XY6 and 8XYE: the instructions shift VY and store the result in VX
+    /// (as in COSMAC VIP, by default) instead of shifting VX in place, ignoring VY (as in SUPER-CHIP 1.1)
+    ///
+    /// # Arguments
+    ///
+    /// * `shift_vy` - Boolean set to true to shift VY into VX
+    pub fn set_shift_vy(&mut self, shift_vy: bool) {
+        self.shift_vy = shift_vy;
+    }
+
+    /// Returns true if 8XY6 and 8XYE shift VY into VX
+    pub fn shift_vy(&self) -> bool {
+        self.shift_vy
+    }
+
+    /// Sets the memory quirk of FX55 and FX65: I is incremented by X + 1 after storing or loading
+    /// the registers (as in COSMAC VIP, by default) instead of being left unchanged (as in SUPER-CHIP 1.1)
+    ///
+    /// # Arguments
+    ///
+    /// * `increment` - Boolean set to true to increment I with FX55 and FX65
+    pub fn set_memory_increment(&mut self, increment: bool) {
+        self.memory_increment = increment;
+    }
+
+    /// Returns true if FX55 and FX65 increment I
+    pub fn memory_increment(&self) -> bool {
+        self.memory_increment
+    }
+
+    /// Sets the jump quirk of BNNN: the instruction jumps to XNN + VX, where X is the highest nibble
+    /// of the address (as in SUPER-CHIP 1.1), instead of NNN + V0 (as in COSMAC VIP, by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `jump_vx` - Boolean set to true to jump to XNN + VX
+    pub fn set_jump_vx(&mut self, jump_vx: bool) {
+        self.jump_vx = jump_vx;
+    }
+
+    /// Returns true if BNNN jumps to XNN + VX
+    pub fn jump_vx(&self) -> bool {
+        self.jump_vx
+    }
+
+    /// Sets the policy applied to the `0NNN` machine code routine calls, that many historical ROMs contain:
+    /// the instruction stops the execution by default, or it can be skipped
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy of the `0NNN` instructions
+    pub fn set_machine_code_policy(&mut self, policy: MachineCodePolicy) {
+        self.machine_code = policy;
+    }
+
+    /// Sets the RAM memory size (4KB by default, up to 64KB for XO-CHIP programs, down to 1KB for
+    /// smaller machines): the memory is cleared and the fontset is loaded again.
+    /// The ROM must be loaded after setting the size
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The memory size in bytes, a power of two
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the size is not a power of two between 1KB and 64KB
+    /// or if the program start address is beyond the end of memory
+    pub fn set_memory_size(&mut self, size: usize) {
+        if !size.is_power_of_two() || !(MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size) {
+            panic!(
+                "invalid memory size {size} bytes: must be a power of two between {MIN_MEMORY_SIZE} and {MAX_MEMORY_SIZE} bytes"
+            );
+        }
+        if self.start_address as usize >= size {
+            panic!(
+                "invalid memory size {size} bytes: the start address {:#05X} is beyond the end of memory",
+                self.start_address
+            );
+        }
+
+        self.memory = vec![0; size];
+        self.clear_initialized();
+        self.clear_decoded();
+        self.clear_undo_log();
+        self.load_fontset();
+        self.rom_loaded = false;
+    }
+
+    /// Returns the RAM memory size in bytes
+    pub fn memory_size(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Soft reset: resets the CHIP-8 instance and loads the current ROM again,
+    /// so that the program restarts from the beginning
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the ROM is not loaded
+    pub fn soft_reset(&mut self) {
+        trace!("Chip8::soft_reset: start");
+
+        if !self.rom_loaded {
+            panic!("ROM is not loaded");
+        }
+
+        self.reset(true);
+
+        trace!("Chip8::soft_reset: exit");
+    }
+
+    /// Pauses the emulation: the execution loop stops executing instructions until `resume` is called
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the emulation paused with `pause`
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Returns true if a ROM is loaded in memory
+    pub fn rom_loaded(&self) -> bool {
+        self.rom_loaded
+    }
+
+    /// Returns true if the emulation is paused
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns true if the program exited with `00FD` (SUPER-CHIP exit instruction):
+    /// stepping the instance executes the exit instruction again
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Decrements CHIP-8 delay and sound timers (if they are greater than 0).
+    /// This function must be called at a rate of 60Hz
+    pub fn update_timers(&mut self) {
+        trace!("Chip8::update_timers: start");
+
+        if self.timers.delay_timer > 0 {
+            self.timers.delay_timer -= 1;
+        }
+
+        if self.timers.sound_timer > 0 {
+            self.timers.sound_timer -= 1;
+        }
+
+        trace!("Chip8::update_timers: exit");
+    }
+
+    /// Returns the CHIP-8 display as packed rows (as many rows as the display height): every bit holds
+    /// the state of a pixel, from the most significant bit (column 0) to the least significant bit
+    /// (column 127). In lores mode only the 64 most significant bits are used
+    pub fn display_rows(&self) -> &[u128] {
+        &self.display[..self.display_size().1]
+    }
+
+    /// Returns a hash of the CHIP-8 display (64-bit FNV-1a of the packed rows: 64-bit rows in lores mode,
+    /// 128-bit rows in hires mode), to compare displays without storing them
+    pub fn display_hash(&self) -> u64 {
+        // the lores rows are hashed without the unused columns
+        let row_bytes = self.display_size().0 / 8;
+
+        self.display_rows()
+            .iter()
+            .flat_map(|row| row.to_be_bytes().into_iter().take(row_bytes))
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Returns true if the pixel at coordinates (x, y) of the CHIP-8 display is on
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The pixel column (0-63, 0-127 in hires mode)
+    /// * `y` - The pixel row (0-31, 0-63 in hires mode)
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.display[y] & (1 << (HIRES_DISPLAY_WIDTH - 1 - x)) != 0
+    }
+
+    /// Returns the CHIP-8 display as unpacked pixels (row by row, true if the pixel is on),
+    /// of the size returned by `display_size`
+    pub fn display_pixels(&self) -> Vec<bool> {
+        self.snapshot().pixels()
+    }
+
+    /// Returns the CHIP-8 display as typed pixels (row by row, true if the pixel is on),
+    /// of the size returned by `display_size`. The pixels are unpacked from the display
+    /// while iterating, without allocating
+    pub fn framebuffer(&self) -> impl Iterator<Item = bool> + '_ {
+        let (width, height) = self.display_size();
+        (0..height).flat_map(move |y| (0..width).map(move |x| self.pixel(x, y)))
+    }
+
+    /// Returns the size (width, height) of the display in pixels: 64x32 in the CHIP-8 display mode (lores),
+    /// 128x64 in the SUPER-CHIP hires mode
+    pub fn display_size(&self) -> (usize, usize) {
+        frame::display_size(self.hires)
+    }
+
+    /// Returns the pixels changed since the end of the last 60Hz frame as (x, y, on) tuples, row by row
+    /// (e.g. to redraw only the changed pixels while stepping). All the pixels are returned
+    /// if the display mode changed
+    pub fn changed_pixels(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        // every pixel differs from the inverted display
+        let other = if self.frame.hires == self.hires {
+            self.frame.rows
+        } else {
+            self.display.map(|row| !row)
+        };
+
+        frame::changed_pixels(self.display, other, self.hires)
+    }
+
+    /// Returns the snapshot of the display at the end of the last 60Hz frame: unlike the live display,
+    /// it never shows a frame the program is still drawing (e.g. sprites erased to be moved)
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Returns a snapshot of the live display, e.g. to show the effect of every instruction while stepping
+    pub fn snapshot(&self) -> Frame {
+        Frame {
+            rows: self.display,
+            hires: self.hires,
+            number: self.frame.number,
+        }
+    }
+
+    /// Presses a key of the hex keypad
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key (0x0-0xF)
+    pub fn key_down(&mut self, key: u8) {
+        self.set_keys(self.keys | 1 << (key & 0x0F));
+    }
+
+    /// Releases a key of the hex keypad
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key (0x0-0xF)
+    pub fn key_up(&mut self, key: u8) {
+        self.set_keys(self.keys & !(1 << (key & 0x0F)));
+    }
+
+    /// Sets the keys of the hex keypad held down, counting the presses and releases
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keys held down (bit n set if key n is pressed)
+    pub fn set_keys(&mut self, keys: u16) {
+        self.key_events += u64::from((self.keys ^ keys).count_ones());
+        self.keys = keys;
+    }
+
+    /// Returns true if a key of the hex keypad is pressed
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key (0x0-0xF, only the lowest nibble is used)
+    pub fn key_pressed(&self, key: u8) -> bool {
+        self.keys & (1 << (key & 0x0F)) != 0
+    }
+
+    /// Returns the keys of the hex keypad held down (bit n set if key n is pressed)
+    pub fn keys(&self) -> u16 {
+        self.keys
+    }
+
+    /// Returns the key read by the last `FX0A` instruction, if any
+    pub fn last_waited_key(&self) -> Option<u8> {
+        self.last_waited_key
+    }
+
+    /// Returns the opcode stored in memory at the given address (big-endian, 2 bytes)
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The u16 memory address of the opcode first byte
+    pub fn opcode_at(&self, address: u16) -> u16 {
+        let address = address as usize % self.memory.len();
+        (self.memory[address] as u16) << 8 | self.memory[(address + 1) % self.memory.len()] as u16
+    }
+
+    /// Writes a byte into memory (e.g. to patch a ROM or to preset a value read by the program)
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The u16 memory address (wrapped to the memory size)
+    /// * `value` - The byte to write
+    pub fn write_memory(&mut self, address: u16, value: u8) {
+        let address = address as usize % self.memory.len();
+        self.memory[address] = value;
+        self.mark_initialized(address..address + 1);
+        self.invalidate_decoded(address..address + 1);
+    }
+
+    /// Returns true if the next instruction is a jump to itself (`1NNN` with NNN = PC),
+    /// the common pattern used by programs to end: the state cannot change anymore
+    /// (except for the timers)
+    pub fn idle_loop(&self) -> bool {
+        self.opcode_at(self.pc) == 0x1000 | (self.pc & 0x0FFF)
+    }
+
+    /// Returns true if the next instruction waits for a key press (`FX0A`)
+    pub fn waiting_key(&self) -> bool {
+        self.opcode_at(self.pc) & 0xF0FF == 0xF00A
+    }
+
+    /// Returns a String that represents the current contents of the CHIP-8 RAM memory
+    fn dump_memory(&self) -> String {
+        trace!("Chip8::dump_memory: start");
+
+        let mut memory_str = String::from("[");
+        for i in 0..self.memory.len() {
+            if i == (self.memory.len() - 1) {
+                memory_str += &format!("{:#X}]", self.memory[i]);
+            } else {
+                memory_str += &format!("{:#X}, ", self.memory[i]);
+            }
+        }
+
+        trace!("Chip8::dump_memory: exit");
+
+        memory_str
+    }
+
+    /// Returns a String that represents the current contents of the CHIP-8 screen.
+    /// A CHIP-8 pixel can be white or black, so we have 1 if the pixel is white, 0 otherwise
+    #[cfg(feature = "std")]
+    pub fn dump_display(&self) -> String {
+        trace!("Chip8::dump_display: start");
+
+        // string representation of display (one line per display row)
+        let mut display_str = String::from("");
+        let width = self.display_size().0;
+        for row in self.display_rows() {
+            display_str += &format!("\n{:0width$b}", row >> (HIRES_DISPLAY_WIDTH - width));
+        }
+
+        trace!("Chip8::dump_display: exit");
+
+        display_str
+    }
+
+    /// Returns a String that represents the current contents of the CHIP-8 registers V0-VF
+    fn dump_v(&self) -> String {
+        trace!("Chip8::dump_v: start");
+
+        let mut v_str = String::from("[");
+        for i in 0..V_SIZE {
+            if i == (V_SIZE - 1) {
+                v_str += &format!("{:#X}]", self.v[i]);
+            } else {
+                v_str += &format!("{:#X}, ", self.v[i]);
+            }
+        }
+
+        trace!("Chip8::dump_v: exit");
+
+        v_str
+    }
+
+    /// Returns a String that represents the current contents of the CHIP-8 stack
+    fn dump_stack(&self) -> String {
+        trace!("Chip8::dump_stack: start");
+
+        let mut stack_str = String::from("[");
+        for i in 0..MAX_STACK_SIZE {
+            if i == (MAX_STACK_SIZE - 1) {
+                stack_str += &format!("{:#X}]", self.stack[i]);
+            } else {
+                stack_str += &format!("{:#X}, ", self.stack[i]);
+            }
+        }
+
+        trace!("Chip8::dump_stack: exit");
+
+        stack_str
+    }
+
+    /// Function that panics on an execution error, logging the state of the instance
+    ///
+    /// # Arguments
+    ///
+    /// * `e` - The execution error
+    fn panic_error(&self, e: Error) -> ! {
+        self.log_error_state(&e);
+        panic!("{e}");
+    }
+
+    /// Logs the execution error with the last executed instructions, and the state of the instance
+    ///
+    /// # Arguments
+    ///
+    /// * `e` - The execution error
+    pub fn log_error_state(&self, e: &Error) {
+        let history = self.dump_history();
+        if history.is_empty() {
+            error!("{e}");
+        } else {
+            error!("{e}, last executed instructions (oldest first):\n{history}");
+        }
+        debug!("chip8 state: {}", self);
+        trace!("chip8 memory dump: {}", self.dump_memory());
+    }
+}
+
+// read accessors of the CHIP-8 state
+impl Chip8 {
+    /// Returns the last executed opcode
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    /// Returns the loaded ROM image
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Returns the CHIP-8 RAM memory
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Returns the CHIP-8 registers V0-VF
+    pub fn v(&self) -> &[u8] {
+        &self.v
+    }
+
+    /// Returns the CHIP-8 Index Register (I)
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    /// Returns the SUPER-CHIP RPL user flags (FX75/FX85)
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAGS_SIZE] {
+        &self.rpl
+    }
+
+    /// Sets the SUPER-CHIP RPL user flags (FX75/FX85), e.g. to restore the flags saved by a previous run
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - The values of the flags
+    pub fn set_rpl_flags(&mut self, flags: &[u8; RPL_FLAGS_SIZE]) {
+        self.rpl = *flags;
+    }
+
+    /// Returns the CHIP-8 Program Counter (PC)
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Sets a CHIP-8 register V0-VF (e.g. to patch the state of a game in the debugger)
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The register index (0x0-0xF, only the lowest nibble is used)
+    /// * `value` - The new value of the register
+    pub fn set_v(&mut self, register: u8, value: u8) {
+        self.v[(register & 0x0F) as usize] = value;
+    }
+
+    /// Sets the CHIP-8 Index Register (I)
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new value of I
+    pub fn set_i(&mut self, value: u16) {
+        self.i = value;
+    }
+
+    /// Sets the CHIP-8 Program Counter (PC): the next instruction is executed from the address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the next instruction
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the address is beyond the end of memory
+    pub fn set_pc(&mut self, address: u16) {
+        if address as usize >= self.memory.len() {
+            panic!(
+                "invalid PC {address:#05X}: must be below {:#05X}",
+                self.memory.len()
+            );
+        }
+
+        self.pc = address;
+    }
+
+    /// Returns the CHIP-8 stack levels in use (from the bottom to the top of the stack)
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..(self.sp as usize).min(MAX_STACK_SIZE)]
+    }
+
+    /// Returns the CHIP-8 delay timer
+    pub fn delay_timer(&self) -> u8 {
+        self.timers.delay_timer
+    }
+
+    /// Returns the CHIP-8 sound timer
+    pub fn sound_timer(&self) -> u8 {
+        self.timers.sound_timer
+    }
+}
+
+// Default trait implementation for Chip8
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Display trait implementation for Chip8
+impl Display for Chip8 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // string representation of v
+        let v_str = self.dump_v();
+
+        // string representation of stack
+        let stack_str = self.dump_stack();
+
+        // chip8 string representation: avoided memory and display for excessive length
+        write!(f, "Chip8 {{ rom_loaded: {}, cycles: {}, current_opcode: {:#X}, memory: [...], V: {}, I: {:#X}, PC: {:#X}, display: [...], draw: {}, stack: {}, SP: {:#X}, timers.delay_timer: {:#X}, timers.sound_timer: {:#X} }}", self.rom_loaded, self.cycles, self.opcode, v_str, self.i, self.pc, self.draw, stack_str, self.sp, self.timers.delay_timer, self.timers.sound_timer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use rand::SeedableRng;
+
+    #[test]
+    fn framebuffer_unpacks_the_display_of_the_current_mode() {
+        let mut chip8 = Chip8::new();
+        chip8.display[0] = 1 << (HIRES_DISPLAY_WIDTH - 1);
+        chip8.display[31] = 1 << (HIRES_DISPLAY_WIDTH - 64);
+
+        let pixels: Vec<bool> = chip8.framebuffer().collect();
+        assert_eq!(pixels.len(), DISPLAY_WIDTH * DISPLAY_HEIGTH);
+        assert_eq!(pixels, chip8.display_pixels());
+        assert!(pixels[0] && pixels[DISPLAY_WIDTH * DISPLAY_HEIGTH - 1]);
+        assert_eq!(pixels.iter().filter(|&&on| on).count(), 2);
+
+        chip8.hires = true;
+        assert_eq!(
+            chip8.framebuffer().count(),
+            HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGTH
+        );
+        assert_eq!(
+            chip8.framebuffer().collect::<Vec<_>>(),
+            chip8.display_pixels()
+        );
+    }
+
+    #[test]
+    fn display_rows_are_packed_per_mode() {
+        let mut chip8 = Chip8::new();
+        chip8.display[1] = 1 << (HIRES_DISPLAY_WIDTH - 1 - 63);
+        assert_eq!(chip8.display_rows().len(), DISPLAY_HEIGTH);
+        assert!(chip8.pixel(63, 1));
+        assert!(!chip8.pixel(62, 1));
+
+        // the hash covers the pixels of the current mode only
+        let hash = chip8.display_hash();
+        chip8.display[1] |= 1;
+        assert_eq!(chip8.display_hash(), hash);
+        chip8.display[1] |= 1 << (HIRES_DISPLAY_WIDTH - 1);
+        assert_ne!(chip8.display_hash(), hash);
+
+        chip8.hires = true;
+        assert_eq!(chip8.display_rows().len(), HIRES_DISPLAY_HEIGTH);
+        assert!(chip8.pixel(127, 1));
+    }
+
+    #[test]
+    fn reset_restarts_the_program() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x00, 0xE0]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..3 {
+            chip8.step(&mut rng).unwrap();
+        }
+        chip8.set_rpl_flags(&[1; RPL_FLAGS_SIZE]);
+        chip8.key_down(3);
+
+        chip8.soft_reset();
+        assert!(chip8.rom_loaded());
+        assert_eq!((chip8.pc(), chip8.i(), chip8.v()[0]), (0x200, 0, 0));
+        assert_eq!(chip8.memory()[0x300], 0);
+        assert_eq!(chip8.memory()[0x200..0x208], *chip8.rom());
+        assert_eq!((chip8.keys(), chip8.cycles()), (0, 0));
+        // the user flags are kept
+        assert_eq!(chip8.rpl_flags(), &[1; RPL_FLAGS_SIZE]);
+
+        chip8.reset(false);
+        assert!(!chip8.rom_loaded());
+        assert!(chip8.rom().is_empty());
+        assert_eq!(chip8.memory()[0x200], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ROM is not loaded")]
+    fn soft_reset_needs_a_rom() {
+        Chip8::new().soft_reset();
+    }
+
+    #[test]
+    fn rom_is_loaded_at_the_start_address() {
+        let mut chip8 = Chip8::new();
+        chip8.set_start_address(0x600);
+        assert_eq!(chip8.pc(), 0x600);
+        assert_eq!(chip8.max_rom_size(), MEMORY_SIZE - 0x600);
+
+        chip8.load_rom_bytes(&[0x12, 0x34]);
+        assert_eq!(chip8.opcode_at(0x600), 0x1234);
+        assert_eq!(chip8.memory()[0x200], 0);
+        chip8.soft_reset();
+        assert_eq!(chip8.pc(), 0x600);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid start address")]
+    fn start_address_cannot_overlap_the_fontset() {
+        Chip8::new().set_start_address(0x010);
+    }
+
+    #[test]
+    #[should_panic(expected = "ROM too big")]
+    fn rom_must_fit_after_the_start_address() {
+        let mut chip8 = Chip8::new();
+        chip8.set_start_address(0xF00);
+        chip8.load_rom_bytes(&[0; 0x101]);
+    }
+
+    #[test]
+    fn fonts_replace_the_fontsets_in_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.set_font(&[0x11; FONTSET_SIZE]);
+        assert_eq!(chip8.memory()[..FONTSET_SIZE], [0x11; FONTSET_SIZE]);
+        assert_eq!(chip8.memory()[FONTSET_SIZE], chip8.hires_font()[0]);
+
+        chip8.set_font(&[0x22; HIRES_FONTSET_SIZE]);
+        assert_eq!(
+            chip8.memory()[FONTSET_SIZE..HIRES_FONTSET_END as usize],
+            [0x22; HIRES_FONTSET_SIZE]
+        );
+        // the fonts are kept across resets
+        chip8.reset(false);
+        assert_eq!(chip8.memory()[0], 0x11);
+        assert_eq!(chip8.memory()[FONTSET_SIZE], 0x22);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid font: 81 bytes")]
+    fn fonts_must_have_the_size_of_a_fontset() {
+        Chip8::new().set_font(&[0; FONTSET_SIZE + 1]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Implementation of CHIP-8 (compatibility profiles)
+
+use super::{Chip8, Variants};
+
+/// Compatibility profile: the interpreter whose quirks are emulated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum Profile {
+    /// CHIP-8 of COSMAC VIP: sprites clipped, 0/1 collision flag, VF reset by the logical instructions,
+    /// VY shifted into VX, I incremented by FX55/FX65, BNNN jumping to NNN + V0
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1: half pixel scroll and 8x16 DXY0 sprites in lores mode,
+    /// collision rows counted in hires mode, VX shifted in place, I not incremented by FX55/FX65,
+    /// BXNN jumping to XNN + VX
+    Schip,
+    /// XO-CHIP of Octo: sprites wrapped around the screen edges, VF not reset by the logical instructions
+    XoChip,
+}
+
+impl Variants for Profile {
+    const VARIANTS: &'static [Self] = &[Profile::Chip8, Profile::Schip, Profile::XoChip];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Profile::Chip8 => "chip8",
+            Profile::Schip => "schip",
+            Profile::XoChip => "xo-chip",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            Profile::Chip8 => "CHIP-8 of COSMAC VIP: sprites clipped, 0/1 collision flag, VF reset by the logical instructions, VY shifted into VX, I incremented by FX55/FX65, BNNN jumping to NNN + V0",
+            Profile::Schip => "SUPER-CHIP 1.1: half pixel scroll and 8x16 DXY0 sprites in lores mode, collision rows counted in hires mode, VX shifted in place, I not incremented by FX55/FX65, BXNN jumping to XNN + VX",
+            Profile::XoChip => "XO-CHIP of Octo: sprites wrapped around the screen edges, VF not reset by the logical instructions",
+        }
+    }
+}
+
+/// Quirks of the instructions that differ between interpreters (see the setters of `Chip8`).
+/// The default quirks are the ones of COSMAC VIP (`Profile::Chip8`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// DXYN wraps the sprites around the screen edges (see `Chip8::set_sprite_wrapping`)
+    pub sprite_wrapping: bool,
+    /// The scroll instructions scroll the lores display by half the distance
+    /// (see `Chip8::set_half_pixel_scroll`)
+    pub half_pixel_scroll: bool,
+    /// DXY0 draws 8x16 sprites in lores mode (see `Chip8::set_lores_tall_sprites`)
+    pub lores_tall_sprites: bool,
+    /// DXYN counts the collision rows in hires mode (see `Chip8::set_collision_rows`)
+    pub collision_rows: bool,
+    /// 8XY1, 8XY2 and 8XY3 reset VF (see `Chip8::set_vf_reset`)
+    pub vf_reset: bool,
+    /// 8XY6 and 8XYE shift VY into VX instead of shifting VX in place (see `Chip8::set_shift_vy`)
+    pub shift_vy: bool,
+    /// FX55 and FX65 increment I (see `Chip8::set_memory_increment`)
+    pub memory_increment: bool,
+    /// BNNN jumps to XNN + VX instead of NNN + V0 (see `Chip8::set_jump_vx`)
+    pub jump_vx: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            sprite_wrapping: false,
+            half_pixel_scroll: false,
+            lores_tall_sprites: false,
+            collision_rows: false,
+            vf_reset: true,
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: false,
+        }
+    }
+}
+
+impl Profile {
+    /// Returns the quirks emulated by the profile
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::default(),
+            Self::Schip => Quirks {
+                half_pixel_scroll: true,
+                lores_tall_sprites: true,
+                collision_rows: true,
+                vf_reset: false,
+                shift_vy: false,
+                memory_increment: false,
+                jump_vx: true,
+                ..Quirks::default()
+            },
+            Self::XoChip => Quirks {
+                sprite_wrapping: true,
+                vf_reset: false,
+                ..Quirks::default()
+            },
+        }
+    }
+}
+
+impl Chip8 {
+    /// Sets the quirks of the instructions
+    ///
+    /// # Arguments
+    ///
+    /// * `quirks` - The quirks
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.set_sprite_wrapping(quirks.sprite_wrapping);
+        self.set_half_pixel_scroll(quirks.half_pixel_scroll);
+        self.set_lores_tall_sprites(quirks.lores_tall_sprites);
+        self.set_collision_rows(quirks.collision_rows);
+        self.set_vf_reset(quirks.vf_reset);
+        self.set_shift_vy(quirks.shift_vy);
+        self.set_memory_increment(quirks.memory_increment);
+        self.set_jump_vx(quirks.jump_vx);
+    }
+
+    /// Returns the quirks of the instructions
+    pub fn quirks(&self) -> Quirks {
+        Quirks {
+            sprite_wrapping: self.wrap_sprites,
+            half_pixel_scroll: self.half_pixel_scroll,
+            lores_tall_sprites: self.lores_tall_sprites,
+            collision_rows: self.collision_rows,
+            vf_reset: self.vf_reset,
+            shift_vy: self.shift_vy,
+            memory_increment: self.memory_increment,
+            jump_vx: self.jump_vx,
+        }
+    }
+
+    /// Sets the quirks of a compatibility profile: the quirks not emulated by the interpreter
+    /// of the profile are disabled
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The compatibility profile
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.set_quirks(profile.quirks());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_quirks_are_set() {
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.quirks(), Profile::Chip8.quirks());
+
+        for &profile in Profile::VARIANTS {
+            chip8.set_profile(profile);
+            assert_eq!(chip8.quirks(), profile.quirks(), "{}", profile.name());
+        }
+    }
+
+    #[test]
+    fn profile_replaces_the_previous_quirks() {
+        let mut chip8 = Chip8::new();
+        chip8.set_quirks(Quirks {
+            sprite_wrapping: true,
+            half_pixel_scroll: true,
+            lores_tall_sprites: true,
+            collision_rows: true,
+            vf_reset: true,
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: true,
+        });
+
+        // the quirks not emulated by the interpreter of the profile are disabled
+        chip8.set_profile(Profile::XoChip);
+        assert!(chip8.quirks().sprite_wrapping);
+        assert!(!chip8.quirks().collision_rows);
+        assert!(!chip8.quirks().jump_vx);
+    }
+}
@@ -0,0 +1,120 @@
+//! Implementation of CHIP-8 (deterministic random number generators for CXNN)
+//!
+//! `step`, `run_frame` and `emulate_cycle` take any `Rng`: besides the seeded generators of `rand`,
+//! these generators make the results of `CXNN` fully predictable (e.g. in tests)
+
+use rand::{Error, RngCore};
+
+/// Random number generator that returns consecutive numbers, from a start value:
+/// every `CXNN` reads the next byte of the sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterRng {
+    /// Next number of the sequence
+    next: u64,
+}
+
+impl CounterRng {
+    /// Returns a generator that starts from a value
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first number returned
+    pub fn new(start: u64) -> Self {
+        Self { next: start }
+    }
+}
+
+impl RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.next;
+        self.next = self.next.wrapping_add(1);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.next_u64() as u8;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Random number generator that always returns the same value: every `CXNN` reads its lowest byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstantRng {
+    /// Value returned
+    value: u64,
+}
+
+impl ConstantRng {
+    /// Returns a generator of a constant value
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value returned
+    pub fn new(value: u64) -> Self {
+        Self { value }
+    }
+}
+
+impl RngCore for ConstantRng {
+    fn next_u32(&mut self) -> u32 {
+        self.value as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(self.value as u8);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+
+    /// Returns the values of V0-V2 after three `CXNN` with the given mask
+    fn random_bytes(rng: &mut impl RngCore, mask: u8) -> [u8; 3] {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0xC0, mask, 0xC1, mask, 0xC2, mask]);
+        for _ in 0..3 {
+            chip8.step(rng).unwrap();
+        }
+        chip8.v()[..3].try_into().unwrap()
+    }
+
+    #[test]
+    fn counter_returns_consecutive_bytes() {
+        assert_eq!(
+            random_bytes(&mut CounterRng::new(0xFE), 0xFF),
+            [0xFE, 0xFF, 0x00]
+        );
+        assert_eq!(random_bytes(&mut CounterRng::new(5), 0x0F), [5, 6, 7]);
+
+        let mut bytes = [0; 3];
+        CounterRng::new(u64::MAX).fill_bytes(&mut bytes);
+        assert_eq!(bytes, [0xFF, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn constant_returns_the_same_byte() {
+        assert_eq!(random_bytes(&mut ConstantRng::new(0x1A5), 0xFF), [0xA5; 3]);
+        assert_eq!(random_bytes(&mut ConstantRng::new(0xA5), 0x0F), [0x05; 3]);
+    }
+}
@@ -0,0 +1,400 @@
+//! Implementation of CHIP-8 (reverse stepping and state changes of the executed instructions)
+
+use super::{
+    Chip8, Frame, ALL_DISPLAY_ROWS, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH, MAX_STACK_SIZE,
+    V_SIZE,
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::fmt::Display;
+
+/// Change of the machine state made by an executed instruction (previous value, new value)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateChange {
+    /// General purpose register V0-VF (register, previous value, new value)
+    V(u8, u8, u8),
+    /// Index register
+    I(u16, u16),
+    /// Program counter
+    Pc(u16, u16),
+    /// Stack pointer
+    Sp(u8, u8),
+    /// Stack level (level, previous address, new address)
+    Stack(u8, u16, u16),
+    /// Delay timer
+    DelayTimer(u8, u8),
+    /// Sound timer
+    SoundTimer(u8, u8),
+    /// Memory byte (address, previous byte, new byte)
+    Memory(u16, u8, u8),
+    /// Display pixel (x, y, new state: true if on)
+    Pixel(u8, u8, bool),
+}
+
+/// State delta of an executed instruction: the state before the instruction, that is restored to undo it.
+/// Only the memory bytes and the display rows changed by the instruction are kept
+#[derive(Clone)]
+struct Delta {
+    /// Opcode of the previous instruction
+    opcode: u16,
+    /// Program counter
+    pc: u16,
+    /// Index register
+    i: u16,
+    /// Registers V0-VF
+    v: [u8; V_SIZE],
+    /// Stack
+    stack: [u16; MAX_STACK_SIZE],
+    /// Stack pointer
+    sp: u8,
+    /// Delay and sound timers
+    timers: (u8, u8),
+    /// Keys pressed while waiting for a key with FX0A, and the key read by the last FX0A
+    waiting_keys: (u16, Option<u8>),
+    /// Boolean set to true if the program exited
+    exited: bool,
+    /// Executed instructions and draw instructions
+    counters: (u64, u64),
+    /// Emulated time elapsed since the last timers update, and the beeper state
+    timers_elapsed: (f64, bool),
+    /// Completed frame shown by the frontends
+    frame: Frame,
+    /// Boolean set to true if the display was in hires mode
+    hires: bool,
+    /// Memory bytes written by the instruction (address, previous byte), in order of writing
+    memory: Vec<(u16, u8)>,
+    /// Display rows changed by the instruction (row, previous row)
+    display: Vec<(u8, u128)>,
+}
+
+/// History of the state deltas of the last executed instructions
+#[derive(Clone, Default)]
+pub(super) struct UndoLog {
+    /// Number of instructions that can be undone (0: the deltas are not recorded)
+    depth: usize,
+
+    /// State deltas of the executed instructions, from the oldest one
+    deltas: VecDeque<Delta>,
+
+    /// State delta of the instruction being executed, and the display before it
+    pending: Option<(Delta, [u128; HIRES_DISPLAY_HEIGTH])>,
+}
+
+impl Chip8 {
+    /// Keeps the state deltas of the last `depth` instructions executed with `step`, so that they can be
+    /// undone with `step_back` (0, the default, disables the history). The changes made outside of the
+    /// instructions (e.g. `write_memory`) and the memory access log are not undone
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of instructions that can be undone
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_log.depth = depth;
+        while self.undo_log.deltas.len() > depth {
+            self.undo_log.deltas.pop_front();
+        }
+    }
+
+    /// Returns the number of executed instructions that can be undone with `step_back`
+    pub fn undo_available(&self) -> usize {
+        self.undo_log.deltas.len()
+    }
+
+    /// Undoes the last executed instruction: the registers, stack, timers, memory and display are restored
+    /// to the state before it. Returns false if no instruction can be undone
+    pub fn step_back(&mut self) -> bool {
+        let Some(delta) = self.undo_log.deltas.pop_back() else {
+            return false;
+        };
+
+        self.opcode = delta.opcode;
+        self.pc = delta.pc;
+        self.i = delta.i;
+        self.v = delta.v;
+        self.stack = delta.stack;
+        self.sp = delta.sp;
+        (self.timers.delay_timer, self.timers.sound_timer) = delta.timers;
+        (self.waiting_keys, self.last_waited_key) = delta.waiting_keys;
+        self.exited = delta.exited;
+        (self.cycles, self.draws) = delta.counters;
+        (self.timers_elapsed, self.beeping) = delta.timers_elapsed;
+        self.frame = delta.frame;
+        if self.hires != delta.hires {
+            self.hires = delta.hires;
+            self.dirty_rows = ALL_DISPLAY_ROWS;
+            self.draw = true;
+        }
+        // the writes are undone from the last one, in case an address was written twice
+        for &(address, value) in delta.memory.iter().rev() {
+            self.memory[address as usize] = value;
+            self.invalidate_decoded(address as usize..address as usize + 1);
+        }
+        for &(row, value) in &delta.display {
+            self.display[row as usize] = value;
+            self.dirty_rows |= 1 << row;
+            self.draw = true;
+        }
+
+        true
+    }
+
+    /// Returns the changes of the machine state made by the last executed instruction (e.g. to show them
+    /// while stepping), from its state delta: the history must be enabled with `set_undo_depth`.
+    /// The timers are compared after their 60Hz update
+    pub fn last_changes(&self) -> Vec<StateChange> {
+        let Some(delta) = self.undo_log.deltas.back() else {
+            return Vec::new();
+        };
+
+        let mut changes = Vec::new();
+        for (register, (&before, &after)) in delta.v.iter().zip(self.v.iter()).enumerate() {
+            if before != after {
+                changes.push(StateChange::V(register as u8, before, after));
+            }
+        }
+        if delta.i != self.i {
+            changes.push(StateChange::I(delta.i, self.i));
+        }
+        if delta.pc != self.pc {
+            changes.push(StateChange::Pc(delta.pc, self.pc));
+        }
+        if delta.sp != self.sp {
+            changes.push(StateChange::Sp(delta.sp, self.sp));
+        }
+        for (level, (&before, &after)) in delta.stack.iter().zip(self.stack.iter()).enumerate() {
+            if before != after {
+                changes.push(StateChange::Stack(level as u8, before, after));
+            }
+        }
+        if delta.timers.0 != self.timers.delay_timer {
+            changes.push(StateChange::DelayTimer(
+                delta.timers.0,
+                self.timers.delay_timer,
+            ));
+        }
+        if delta.timers.1 != self.timers.sound_timer {
+            changes.push(StateChange::SoundTimer(
+                delta.timers.1,
+                self.timers.sound_timer,
+            ));
+        }
+        // an address written twice changed from its first previous byte
+        let mut written: Vec<(u16, u8)> = Vec::new();
+        for &(address, before) in &delta.memory {
+            if !written.iter().any(|&(written, _)| written == address) {
+                written.push((address, before));
+            }
+        }
+        for (address, before) in written {
+            let after = self.memory[address as usize];
+            if before != after {
+                changes.push(StateChange::Memory(address, before, after));
+            }
+        }
+        for &(y, before) in &delta.display {
+            let changed = before ^ self.display[y as usize];
+            for x in 0..self.display_size().0 {
+                let bit = 1 << (HIRES_DISPLAY_WIDTH - 1 - x);
+                if changed & bit != 0 {
+                    changes.push(StateChange::Pixel(
+                        x as u8,
+                        y,
+                        self.display[y as usize] & bit != 0,
+                    ));
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Forgets the executed instructions, e.g. when a new program is loaded
+    pub(super) fn clear_undo_log(&mut self) {
+        self.undo_log.deltas.clear();
+        self.undo_log.pending = None;
+    }
+
+    /// Records the state before the instruction executed by `step`
+    pub(super) fn begin_undo(&mut self) {
+        if self.undo_log.depth == 0 {
+            return;
+        }
+
+        let delta = Delta {
+            opcode: self.opcode,
+            pc: self.pc,
+            i: self.i,
+            v: self.v,
+            stack: self.stack,
+            sp: self.sp,
+            timers: (self.timers.delay_timer, self.timers.sound_timer),
+            waiting_keys: (self.waiting_keys, self.last_waited_key),
+            exited: self.exited,
+            counters: (self.cycles, self.draws),
+            timers_elapsed: (self.timers_elapsed, self.beeping),
+            frame: self.frame,
+            hires: self.hires,
+            memory: Vec::new(),
+            display: Vec::new(),
+        };
+        self.undo_log.pending = Some((delta, self.display));
+    }
+
+    /// Records the previous byte of a memory address written by the current instruction
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address, within memory
+    pub(super) fn record_undo_write(&mut self, address: usize) {
+        if let Some((delta, _)) = &mut self.undo_log.pending {
+            delta.memory.push((address as u16, self.memory[address]));
+        }
+    }
+
+    /// Adds the state delta of the instruction executed by `step` to the history
+    pub(super) fn end_undo(&mut self) {
+        let Some((mut delta, display)) = self.undo_log.pending.take() else {
+            return;
+        };
+
+        delta.display = display
+            .iter()
+            .zip(self.display.iter())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(row, (before, _))| (row as u8, *before))
+            .collect();
+        if self.undo_log.deltas.len() == self.undo_log.depth {
+            self.undo_log.deltas.pop_front();
+        }
+        self.undo_log.deltas.push_back(delta);
+    }
+}
+
+// Display trait implementation for StateChange
+impl Display for StateChange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::V(register, before, after) => {
+                write!(f, "V{register:X}: {before:#04X}→{after:#04X}")
+            }
+            Self::I(before, after) => write!(f, "I: {before:#05X}→{after:#05X}"),
+            Self::Pc(before, after) => write!(f, "PC: {before:#05X}→{after:#05X}"),
+            Self::Sp(before, after) => write!(f, "SP: {before}→{after}"),
+            Self::Stack(level, before, after) => {
+                write!(f, "stack[{level}]: {before:#05X}→{after:#05X}")
+            }
+            Self::DelayTimer(before, after) => write!(f, "DT: {before}→{after}"),
+            Self::SoundTimer(before, after) => write!(f, "ST: {before}→{after}"),
+            Self::Memory(address, before, after) => {
+                write!(f, "[{address:#05X}]: {before:#04X}→{after:#04X}")
+            }
+            Self::Pixel(x, y, on) => write!(f, "pixel({x},{y}) {}", if on { "on" } else { "off" }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// 6005 (V0 = 5), A300 (I = 0x300), F055 (store V0), 00FF (hires), A000 (I = glyph of 0),
+    /// D005 (draw at V0, V0), 7001 (V0 += 1)
+    const ROM: [u8; 14] = [
+        0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x05, 0x70, 0x01,
+    ];
+
+    /// Returns an instance with the ROM loaded and the history of the given depth,
+    /// after executing the given number of instructions
+    fn run(depth: usize, instructions: usize) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.set_undo_depth(depth);
+        chip8.load_rom_bytes(&ROM);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..instructions {
+            chip8.step(&mut rng).unwrap();
+        }
+        chip8
+    }
+
+    #[test]
+    fn step_back_restores_the_state_before_the_instructions() {
+        let initial = run(0, 0);
+        let mut chip8 = run(7, 7);
+        assert_eq!(chip8.undo_available(), 7);
+        assert_eq!(chip8.v()[0], 6);
+        assert!(chip8.hires());
+
+        while chip8.step_back() {}
+        assert_eq!(chip8.pc(), initial.pc());
+        assert_eq!(chip8.i(), initial.i());
+        assert_eq!(chip8.v(), initial.v());
+        assert_eq!(chip8.cycles(), 0);
+        assert_eq!(chip8.memory(), initial.memory());
+        assert_eq!(chip8.display, initial.display);
+        assert!(!chip8.hires());
+    }
+
+    #[test]
+    fn step_back_then_step_executes_the_same_instruction() {
+        let mut chip8 = run(7, 6);
+        let drawn = chip8.display;
+        assert_ne!(drawn, [0; HIRES_DISPLAY_HEIGTH]);
+        assert!(chip8.step_back());
+        assert_eq!(chip8.display, [0; HIRES_DISPLAY_HEIGTH]);
+
+        chip8.step(&mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!(chip8.display, drawn);
+        assert_eq!(chip8.pc(), 0x20C);
+    }
+
+    #[test]
+    fn history_keeps_the_last_instructions() {
+        let mut chip8 = run(2, 4);
+        assert_eq!(chip8.undo_available(), 2);
+        assert!(chip8.step_back());
+        assert!(chip8.step_back());
+        assert!(!chip8.step_back());
+        // F055 and 00FF undone, 6005 and A300 kept
+        assert_eq!(chip8.pc(), 0x204);
+        assert_eq!((chip8.v()[0], chip8.i()), (5, 0x300));
+        assert_eq!(chip8.memory()[0x300], 0);
+
+        // a new program forgets the history
+        let mut chip8 = run(2, 4);
+        chip8.load_rom_bytes(&ROM);
+        assert_eq!(chip8.undo_available(), 0);
+
+        // no history by default
+        let mut chip8 = run(0, 4);
+        assert_eq!(chip8.undo_available(), 0);
+        assert!(!chip8.step_back());
+    }
+
+    #[test]
+    fn last_changes_are_the_differences_of_the_instruction() {
+        let mut chip8 = run(7, 3);
+        assert_eq!(
+            chip8.last_changes(),
+            [
+                StateChange::I(0x300, 0x301),
+                StateChange::Pc(0x204, 0x206),
+                StateChange::Memory(0x300, 0, 5)
+            ]
+        );
+
+        for _ in 0..3 {
+            chip8.step(&mut StdRng::seed_from_u64(0)).unwrap();
+        }
+        let changes = chip8.last_changes();
+        assert!(changes.contains(&StateChange::Pixel(5, 5, true)));
+        assert_eq!(
+            StateChange::Memory(0x300, 0, 5).to_string(),
+            "[0x300]: 0x00→0x05"
+        );
+
+        // no history, no changes
+        assert!(run(0, 3).last_changes().is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Implementation of CHIP-8 (self-modifying code detection)
+
+use super::{Chip8, Variants, MAX_MEMORY_SIZE};
+use alloc::{vec, vec::Vec};
+use tracing::warn;
+
+/// Policy applied to self-modifying code: instructions that write to addresses already executed,
+/// and execution reaching bytes modified at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum SelfModifyingCodePolicy {
+    /// Do not track the executed and modified addresses
+    #[default]
+    Ignore,
+    /// Log a warning
+    Warn,
+    /// Log a warning and pause the emulation (e.g. to inspect the state in the debugger frontend)
+    Break,
+}
+
+impl Variants for SelfModifyingCodePolicy {
+    const VARIANTS: &'static [Self] = &[
+        SelfModifyingCodePolicy::Ignore,
+        SelfModifyingCodePolicy::Warn,
+        SelfModifyingCodePolicy::Break,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SelfModifyingCodePolicy::Ignore => "ignore",
+            SelfModifyingCodePolicy::Warn => "warn",
+            SelfModifyingCodePolicy::Break => "break",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            SelfModifyingCodePolicy::Ignore => "Do not track the executed and modified addresses",
+            SelfModifyingCodePolicy::Warn => "Log a warning",
+            SelfModifyingCodePolicy::Break => "Log a warning and pause the emulation (e.g. to inspect the state in the debugger frontend)",
+        }
+    }
+}
+
+/// Executed and modified addresses, tracked while the self-modifying code policy is not `Ignore`
+#[derive(Clone)]
+pub(super) struct CodeTracker {
+    /// Policy applied to self-modifying code
+    policy: SelfModifyingCodePolicy,
+
+    /// Addresses executed since the last write to them (bit n of word n / 64 set if address n was executed)
+    executed: Vec<u64>,
+
+    /// Addresses written at runtime since they were last executed
+    modified: Vec<u64>,
+}
+
+impl CodeTracker {
+    /// Returns a tracker with no executed and modified addresses
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy applied to self-modifying code
+    fn new(policy: SelfModifyingCodePolicy) -> Self {
+        Self {
+            policy,
+            executed: vec![0; MAX_MEMORY_SIZE / 64],
+            modified: vec![0; MAX_MEMORY_SIZE / 64],
+        }
+    }
+}
+
+/// Sets the bit of an address in an address set, returning its previous state
+///
+/// # Arguments
+///
+/// * `set` - The address set
+/// * `address` - The address
+/// * `value` - The new state of the bit
+pub(super) fn replace_bit(set: &mut [u64], address: usize, value: bool) -> bool {
+    let (word, bit) = (address / 64 % set.len(), 1 << (address % 64));
+    let previous = set[word] & bit != 0;
+    if value {
+        set[word] |= bit;
+    } else {
+        set[word] &= !bit;
+    }
+
+    previous
+}
+
+impl Chip8 {
+    /// Sets the policy applied to self-modifying code: with `Warn` or `Break` the executed addresses
+    /// and the addresses written at runtime are tracked, and a warning is logged when an instruction writes
+    /// to an address already executed and when the execution reaches bytes modified at runtime.
+    /// The addresses are not tracked by default (`Ignore`)
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy applied to self-modifying code
+    pub fn set_self_modifying_code_policy(&mut self, policy: SelfModifyingCodePolicy) {
+        match (&mut self.code_tracker, policy) {
+            (_, SelfModifyingCodePolicy::Ignore) => self.code_tracker = None,
+            (Some(tracker), _) => tracker.policy = policy,
+            (None, _) => self.code_tracker = Some(CodeTracker::new(policy)),
+        }
+    }
+
+    /// Forgets the executed and modified addresses, e.g. when a new program is loaded
+    pub(super) fn clear_code_tracker(&mut self) {
+        if let Some(tracker) = &mut self.code_tracker {
+            *tracker = CodeTracker::new(tracker.policy);
+        }
+    }
+
+    /// Marks the opcode at PC as executed
+    pub(super) fn track_fetch(&mut self) {
+        if let Some(tracker) = &mut self.code_tracker {
+            replace_bit(&mut tracker.executed, self.pc as usize, true);
+            replace_bit(&mut tracker.executed, self.pc as usize + 1, true);
+        }
+    }
+
+    /// Marks an address as modified by the current instruction, reporting the write if the address
+    /// was executed
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address written
+    pub(super) fn track_write(&mut self, address: usize) {
+        let Some(tracker) = &mut self.code_tracker else {
+            return;
+        };
+
+        replace_bit(&mut tracker.modified, address, true);
+        if replace_bit(&mut tracker.executed, address, false) {
+            warn!(
+                "self-modifying code: instruction at {:#05X} writes to {address:#05X}, already executed",
+                self.pc
+            );
+            self.break_on_self_modifying_code();
+        }
+    }
+
+    /// Reports the execution reaching bytes modified at runtime (the opcode at the new PC)
+    pub(super) fn track_modified_pc(&mut self) {
+        let Some(tracker) = &mut self.code_tracker else {
+            return;
+        };
+
+        let pc = self.pc as usize;
+        let modified = replace_bit(&mut tracker.modified, pc, false)
+            | replace_bit(&mut tracker.modified, pc + 1, false);
+        if modified {
+            warn!(
+                "self-modifying code: execution reached {pc:#05X}, modified at runtime (opcode {:#06X})",
+                self.opcode_at(self.pc)
+            );
+            self.break_on_self_modifying_code();
+        }
+    }
+
+    /// Pauses the emulation if the self-modifying code policy is `Break`
+    fn break_on_self_modifying_code(&mut self) {
+        if self
+            .code_tracker
+            .as_ref()
+            .is_some_and(|tracker| tracker.policy == SelfModifyingCodePolicy::Break)
+        {
+            self.paused = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with a ROM loaded, breaking on self-modifying code
+    fn with_rom(rom: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.set_self_modifying_code_policy(SelfModifyingCodePolicy::Break);
+        chip8.load_rom_bytes(rom);
+        chip8
+    }
+
+    /// Executes instructions, returning the pause state after each one
+    fn run(chip8: &mut Chip8, instructions: usize) -> Vec<bool> {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..instructions)
+            .map(|_| {
+                chip8.step(&mut rng).unwrap();
+                chip8.paused()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_to_executed_addresses_break() {
+        // I = 0x200, store V0 at 0x200
+        let mut chip8 = with_rom(&[0xA2, 0x00, 0xF0, 0x55]);
+
+        assert_eq!(run(&mut chip8, 2), [false, true]);
+    }
+
+    #[test]
+    fn reaching_modified_bytes_breaks() {
+        // I = 0x206, V0 = 0x12, store V0 at 0x206 (the next instruction)
+        let mut chip8 = with_rom(&[0xA2, 0x06, 0x60, 0x12, 0xF0, 0x55, 0x00, 0x00]);
+
+        assert_eq!(run(&mut chip8, 3), [false, false, true]);
+        assert_eq!(chip8.opcode_at(0x206), 0x1200);
+    }
+
+    #[test]
+    fn ignored_policy_does_not_track() {
+        let mut chip8 = with_rom(&[0xA2, 0x00, 0xF0, 0x55]);
+        chip8.set_self_modifying_code_policy(SelfModifyingCodePolicy::Ignore);
+        assert!(chip8.code_tracker.is_none());
+
+        assert_eq!(run(&mut chip8, 2), [false, false]);
+    }
+
+    #[test]
+    fn bits_are_replaced_in_the_address_set() {
+        let mut set = [0; 2];
+        assert!(!replace_bit(&mut set, 65, true));
+        assert_eq!(set, [0, 0b10]);
+        assert!(replace_bit(&mut set, 65, false));
+        // addresses wrap around the set
+        assert!(!replace_bit(&mut set, 128, true));
+        assert_eq!(set, [1, 0]);
+    }
+}
@@ -0,0 +1,431 @@
+//! Implementation of CHIP-8 (save states)
+//!
+//! A save state is a binary image of the machine state: the `C8ST` magic, the format version, the memory size,
+//! then memory, registers, stack, timers and display (multi-byte values are big-endian).
+//! Version 1 save states (4KB memory, without the memory size) and version 2 save states
+//! (lores display only) can still be loaded.
+//! The ROM image, the options (clock, trace sampling) and the hooks are not saved
+
+use super::{
+    Chip8, ALL_DISPLAY_ROWS, DISPLAY_HEIGTH, HIRES_DISPLAY_HEIGTH, MAX_MEMORY_SIZE, MAX_STACK_SIZE,
+    MEMORY_SIZE, MIN_MEMORY_SIZE, V_SIZE,
+};
+use alloc::vec::Vec;
+use core::fmt::Display;
+use tracing::{debug, trace};
+
+/// magic bytes at the start of a save state
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+
+/// version of the save state format
+const STATE_VERSION: u8 = 3;
+
+/// version of the save state format with the lores display only (64-bit rows, without the display mode)
+const STATE_VERSION_LORES: u8 = 2;
+
+/// version of the save state format without the memory size (4KB memory) and with the lores display only
+const STATE_VERSION_4KB: u8 = 1;
+
+/// size of the header of a save state in bytes
+const STATE_HEADER_SIZE: usize = STATE_MAGIC.len()
+    + 1 // version
+    + 4; // memory size
+
+/// size of the display of a save state in bytes
+const STATE_DISPLAY_SIZE: usize = 1 // display mode
+    + HIRES_DISPLAY_HEIGTH * 16;
+
+/// size of the lores display of the save states before version 3 in bytes
+const STATE_LORES_DISPLAY_SIZE: usize = DISPLAY_HEIGTH * 8;
+
+/// size of the registers, stack, timers and display of a save state in bytes (after memory)
+const STATE_MACHINE_SIZE: usize = V_SIZE
+    + 2 // I
+    + 2 // PC
+    + MAX_STACK_SIZE * 2
+    + 1 // SP
+    + 2 // delay and sound timers
+    + STATE_DISPLAY_SIZE
+    + 2 // keys waited by FX0A
+    + 8 // cycles
+    + 8 // emulated time since the last timers update
+    + 1; // beeping
+
+/// Errors of the save states that cannot be loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with the save state magic bytes
+    InvalidHeader,
+
+    /// The save state was written by an unsupported version of the format
+    UnsupportedVersion(u8),
+
+    /// The save state size is not the expected one (size of the data, expected size)
+    InvalidLength(usize, usize),
+
+    /// The memory size of the save state is not supported (memory size)
+    InvalidMemorySize(usize),
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "Invalid save state: missing header"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported save state version: {version}")
+            }
+            Self::InvalidLength(length, expected) => write!(
+                f,
+                "Invalid save state size: {length} bytes (expected {expected} bytes)"
+            ),
+            Self::InvalidMemorySize(size) => {
+                write!(f, "Invalid save state memory size: {size} bytes")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+/// Reader of the fields of a save state
+struct StateReader<'a> {
+    /// The save state data not read yet
+    data: &'a [u8],
+}
+
+impl StateReader<'_> {
+    /// Returns the next `N` bytes of the save state (the length is checked before reading)
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let (bytes, rest) = self.data.split_at(N);
+        self.data = rest;
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        array
+    }
+
+    /// Returns the next `length` bytes of the save state (the length is checked before reading)
+    fn slice(&mut self, length: usize) -> &[u8] {
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        bytes
+    }
+
+    /// Returns the next byte of the save state
+    fn u8(&mut self) -> u8 {
+        self.bytes::<1>()[0]
+    }
+
+    /// Returns the next big-endian u16 of the save state
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.bytes())
+    }
+
+    /// Returns the next big-endian u32 of the save state
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.bytes())
+    }
+
+    /// Returns the next big-endian u64 of the save state
+    fn u64(&mut self) -> u64 {
+        u64::from_be_bytes(self.bytes())
+    }
+
+    /// Returns the next big-endian u128 of the save state
+    fn u128(&mut self) -> u128 {
+        u128::from_be_bytes(self.bytes())
+    }
+}
+
+impl Chip8 {
+    /// Returns a save state of the CHIP-8 instance, to be restored with `load_state`
+    pub fn save_state(&self) -> Vec<u8> {
+        trace!("Chip8::save_state: start");
+
+        let mut state =
+            Vec::with_capacity(STATE_HEADER_SIZE + self.memory.len() + STATE_MACHINE_SIZE);
+        state.extend_from_slice(STATE_MAGIC);
+        state.push(STATE_VERSION);
+        state.extend_from_slice(&(self.memory.len() as u32).to_be_bytes());
+        state.extend_from_slice(&self.memory);
+        state.extend_from_slice(&self.v);
+        state.extend_from_slice(&self.i.to_be_bytes());
+        state.extend_from_slice(&self.pc.to_be_bytes());
+        for address in self.stack {
+            state.extend_from_slice(&address.to_be_bytes());
+        }
+        state.push(self.sp);
+        state.push(self.timers.delay_timer);
+        state.push(self.timers.sound_timer);
+        state.push(self.hires as u8);
+        for row in self.display {
+            state.extend_from_slice(&row.to_be_bytes());
+        }
+        state.extend_from_slice(&self.waiting_keys.to_be_bytes());
+        state.extend_from_slice(&self.cycles.to_be_bytes());
+        state.extend_from_slice(&self.timers_elapsed.to_bits().to_be_bytes());
+        state.push(self.beeping as u8);
+
+        trace!("Chip8::save_state: exit");
+
+        state
+    }
+
+    /// Restores a save state returned by `save_state`: the instance continues from the saved state
+    /// (the whole display must be redrawn) with the memory size of the save state
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The save state data
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        trace!("Chip8::load_state: start");
+
+        if !state.starts_with(STATE_MAGIC) {
+            return Err(StateError::InvalidHeader);
+        }
+        let mut reader = StateReader {
+            data: &state[STATE_MAGIC.len()..],
+        };
+        let version = match reader.data.first() {
+            Some(&version @ (STATE_VERSION | STATE_VERSION_LORES))
+                if reader.data.len() >= STATE_HEADER_SIZE - STATE_MAGIC.len() =>
+            {
+                version
+            }
+            Some(&STATE_VERSION_4KB) => STATE_VERSION_4KB,
+            Some(&(STATE_VERSION | STATE_VERSION_LORES)) | None => {
+                return Err(StateError::InvalidLength(state.len(), STATE_HEADER_SIZE))
+            }
+            Some(&version) => return Err(StateError::UnsupportedVersion(version)),
+        };
+        reader.u8();
+        let memory_size = match version {
+            STATE_VERSION_4KB => MEMORY_SIZE,
+            _ => reader.u32() as usize,
+        };
+        // the save states before version 3 have the lores display only
+        let machine_size = match version {
+            STATE_VERSION => STATE_MACHINE_SIZE,
+            _ => STATE_MACHINE_SIZE - STATE_DISPLAY_SIZE + STATE_LORES_DISPLAY_SIZE,
+        };
+        if !memory_size.is_power_of_two()
+            || !(MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&memory_size)
+        {
+            return Err(StateError::InvalidMemorySize(memory_size));
+        }
+        if reader.data.len() != memory_size + machine_size {
+            return Err(StateError::InvalidLength(
+                state.len(),
+                state.len() - reader.data.len() + memory_size + machine_size,
+            ));
+        }
+
+        self.memory = reader.slice(memory_size).to_vec();
+        self.clear_decoded();
+        self.v = reader.bytes();
+        self.i = reader.u16();
+        self.pc = reader.u16();
+        for address in self.stack.iter_mut() {
+            *address = reader.u16();
+        }
+        self.sp = reader.u8();
+        self.timers.delay_timer = reader.u8();
+        self.timers.sound_timer = reader.u8();
+        if version == STATE_VERSION {
+            self.hires = reader.u8() != 0;
+            for row in self.display.iter_mut() {
+                *row = reader.u128();
+            }
+        } else {
+            self.hires = false;
+            self.display = [0; HIRES_DISPLAY_HEIGTH];
+            for row in self.display[..DISPLAY_HEIGTH].iter_mut() {
+                *row = (reader.u64() as u128) << 64;
+            }
+        }
+        self.waiting_keys = reader.u16();
+        self.cycles = reader.u64();
+        self.timers_elapsed = f64::from_bits(reader.u64());
+        self.beeping = reader.u8() != 0;
+
+        // the program in memory can be executed, and the restored display must be redrawn
+        self.frame.rows = self.display;
+        self.frame.hires = self.hires;
+        self.rom_loaded = true;
+        self.draw = true;
+        self.dirty_rows = ALL_DISPLAY_ROWS;
+
+        // the restored memory has not been executed nor modified yet
+        self.clear_code_tracker();
+        self.clear_undo_log();
+        // what the restored memory was initialized by is unknown: no byte is reported
+        self.mark_initialized(0..self.memory.len());
+
+        debug!("state loaded: {}", self);
+        trace!("Chip8::load_state: exit");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance that drew a sprite and called a subroutine: 6005 (V0 = 5), A20E (I = 0x20E),
+    /// D005 (draw), 220A (call 0x20A), 0000, 7001 (V0 += 1), 120A (jump to 0x20A), sprite rows
+    fn running() -> (Chip8, StdRng) {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[
+            0x60, 0x05, 0xA2, 0x0E, 0xD0, 0x05, 0x22, 0x0A, 0x00, 0x00, 0x70, 0x01, 0x12, 0x0A,
+            0xF0, 0x90, 0xF0, 0x90, 0xF0,
+        ]);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..6 {
+            chip8.step(&mut rng).unwrap();
+        }
+
+        (chip8, rng)
+    }
+
+    #[test]
+    fn save_states_restore_the_machine() {
+        let (mut chip8, mut rng) = running();
+        let state = chip8.save_state();
+
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.memory(), chip8.memory());
+        assert_eq!(restored.v(), chip8.v());
+        assert_eq!((restored.i(), restored.pc()), (chip8.i(), chip8.pc()));
+        assert_eq!(restored.stack(), chip8.stack());
+        assert_eq!(restored.display_rows(), chip8.display_rows());
+        assert_eq!(restored.cycles(), chip8.cycles());
+
+        // the restored instance continues like the saved one
+        for _ in 0..10 {
+            chip8.step(&mut rng).unwrap();
+            restored.step(&mut rng).unwrap();
+        }
+        assert_eq!(restored.save_state(), chip8.save_state());
+    }
+
+    #[test]
+    fn invalid_save_states_are_rejected() {
+        let mut chip8 = Chip8::new();
+        let state = running().0.save_state();
+
+        assert_eq!(chip8.load_state(b"NOPE"), Err(StateError::InvalidHeader));
+        assert_eq!(
+            chip8.load_state(b"C8ST\x09"),
+            Err(StateError::UnsupportedVersion(9))
+        );
+        assert_eq!(
+            chip8.load_state(&state[..state.len() - 1]),
+            Err(StateError::InvalidLength(state.len() - 1, state.len()))
+        );
+
+        let mut odd_memory = state.clone();
+        odd_memory[5..9].copy_from_slice(&3000u32.to_be_bytes());
+        assert_eq!(
+            chip8.load_state(&odd_memory),
+            Err(StateError::InvalidMemorySize(3000))
+        );
+
+        // the instance is left untouched
+        assert_eq!(chip8.cycles(), 0);
+        assert!(!chip8.rom_loaded());
+    }
+
+    /// size of the end of a save state, after the display: keys waited by FX0A, cycles,
+    /// emulated time since the last timers update and beeping
+    const STATE_TAIL_SIZE: usize = 2 + 8 + 8 + 1;
+
+    /// Converts a save state of a lores display to a save state of an older version of the format
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The save state
+    /// * `version` - The version of the format (lores display only, without the memory size for version 1)
+    fn legacy(state: &[u8], version: u8) -> Vec<u8> {
+        let memory_size = u32::from_be_bytes(state[5..9].try_into().unwrap()) as usize;
+        let display =
+            STATE_HEADER_SIZE + memory_size + STATE_MACHINE_SIZE - STATE_DISPLAY_SIZE - 19;
+
+        let mut legacy = Vec::from(*STATE_MAGIC);
+        legacy.push(version);
+        if version != STATE_VERSION_4KB {
+            legacy.extend_from_slice(&state[5..9]);
+        }
+        legacy.extend_from_slice(&state[STATE_HEADER_SIZE..display]);
+        // the lores rows are the high halves of the first display rows, after the display mode
+        for row in state[display + 1..].chunks(16).take(DISPLAY_HEIGTH) {
+            legacy.extend_from_slice(&row[..8]);
+        }
+        legacy.extend_from_slice(&state[state.len() - STATE_TAIL_SIZE..]);
+
+        legacy
+    }
+
+    #[test]
+    fn save_states_keep_the_memory_size() {
+        let mut chip8 = Chip8::new();
+        chip8.set_memory_size(0x2000);
+        chip8.write_memory(0x1FFF, 0xAB);
+        let state = chip8.save_state();
+
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.memory_size(), 0x2000);
+        assert_eq!(restored.memory()[0x1FFF], 0xAB);
+    }
+
+    #[test]
+    fn save_states_without_the_memory_size_have_4kb() {
+        let (chip8, _) = running();
+        let state = legacy(&chip8.save_state(), STATE_VERSION_4KB);
+        assert_eq!(
+            state.len(),
+            STATE_HEADER_SIZE - 4 + MEMORY_SIZE + STATE_MACHINE_SIZE - STATE_DISPLAY_SIZE
+                + STATE_LORES_DISPLAY_SIZE
+        );
+
+        let mut restored = Chip8::new();
+        restored.set_memory_size(0x2000);
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.memory_size(), MEMORY_SIZE);
+        assert_eq!(restored.pc(), chip8.pc());
+        assert_eq!(restored.display_rows(), chip8.display_rows());
+    }
+
+    #[test]
+    fn save_states_keep_the_display_mode() {
+        let mut chip8 = Chip8::new();
+        // 00FF (hires mode), 6070 (V0 = 0x70), F029 (I = glyph of V0), D005 (draw at (0x70, 0x30): the
+        // start row wraps around the 64 rows)
+        chip8.load_rom_bytes(&[0x00, 0xFF, 0x60, 0x70, 0xF0, 0x29, 0xD0, 0x05]);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..4 {
+            chip8.step(&mut rng).unwrap();
+        }
+
+        let mut restored = Chip8::new();
+        restored.load_state(&chip8.save_state()).unwrap();
+        assert_eq!(restored.display_size(), (128, 64));
+        assert_eq!(restored.display_rows(), chip8.display_rows());
+        assert!(restored.pixel(0x70, 0x30));
+    }
+
+    #[test]
+    fn lores_save_states_are_loaded_in_lores_mode() {
+        let (chip8, _) = running();
+        let state = legacy(&chip8.save_state(), STATE_VERSION_LORES);
+
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.display_size(), (64, 32));
+        assert_eq!(restored.display_rows(), chip8.display_rows());
+        assert_eq!(restored.v(), chip8.v());
+    }
+}
@@ -0,0 +1,390 @@
+//! Implementation of CHIP-8 (host-driven stepping: instructions, frames and emulated time)
+
+use super::{hooks::StepEvents, Chip8, Error, Frame, TIMERS_FREQUENCY};
+use core::fmt::Display;
+use rand::Rng;
+#[cfg(feature = "cycle-logging")]
+use tracing::Span;
+use tracing::{debug_span, trace};
+
+/// Statistics of an emulation session, since the program was loaded or the instance was reset:
+/// e.g. to run for exactly N cycles, or to correlate the traces with the cycle numbers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Number of executed instructions (the cycle counter)
+    pub cycles: u64,
+    /// Number of completed 60Hz frames
+    pub frames: u64,
+    /// Number of executed display instructions (00E0, DXYN, scroll and display mode instructions)
+    pub draws: u64,
+    /// Number of key presses and releases
+    pub key_events: u64,
+}
+
+impl Chip8 {
+    /// Executes one instruction and advances the emulated time by one CHIP-8 clock cycle (1/500 s by default),
+    /// or by the time of the instruction in the COSMAC VIP timing model (see `set_instruction_timing`):
+    /// the timers are updated every 1/60 s of emulated time, then the hooks of the events are called.
+    /// Returns true if the timers were updated, that is the instruction ended a 60Hz frame.
+    ///
+    /// The function never blocks: the host owns the loop and decides when to step
+    /// (e.g. at 500Hz, as fast as possible or one instruction at a time)
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Mutable reference to a struct that implements the Rng trait used to generate random numbers
+    pub fn step<R: Rng>(&mut self, rng: &mut R) -> Result<bool, Error> {
+        trace!("Chip8::step: start");
+
+        // the events of the instruction are logged with its cycle, PC and opcode
+        #[cfg(feature = "cycle-logging")]
+        let span = if self.cycles.is_multiple_of(self.trace_sample) {
+            debug_span!(
+                "step",
+                cycle = self.cycles,
+                pc = %format_args!("{:#05X}", self.pc),
+                opcode = %format_args!("{:#06X}", self.opcode_at(self.pc))
+            )
+        } else {
+            Span::none()
+        };
+        #[cfg(feature = "cycle-logging")]
+        let _enter = span.enter();
+
+        let sound_before = self.timers.sound_timer > 0;
+        self.begin_undo();
+        self.try_emulate_cycle(rng)?;
+        self.cycles += 1;
+
+        // timers are decremented at 60Hz of emulated time (not host time)
+        let mut frame_end = false;
+        self.timers_elapsed += self.instruction_time(self.opcode);
+        while self.timers_elapsed >= 1.0 / TIMERS_FREQUENCY {
+            self.timers_elapsed -= 1.0 / TIMERS_FREQUENCY;
+            // the beeper sounds for the elapsed frame if the sound timer is set before the update
+            self.beeping = self.timers.sound_timer > 0;
+            self.update_timers();
+            frame_end = true;
+        }
+        // the completed frame is swapped in for the frontends
+        if frame_end {
+            self.frame = Frame {
+                rows: self.display,
+                hires: self.hires,
+                number: self.frame.number() + 1,
+            };
+        }
+
+        // display instructions: scroll, clear, display mode, DXYN
+        let draw = matches!(
+            self.opcode,
+            0x00C0..=0x00CF | 0x00E0 | 0x00FB | 0x00FC | 0x00FE | 0x00FF
+        ) || self.opcode & 0xF000 == 0xD000;
+        if draw {
+            self.draws += 1;
+        }
+        self.end_undo();
+
+        self.call_hooks(StepEvents {
+            draw,
+            sound_before,
+            frame_end,
+        });
+
+        trace!("Chip8::step: exit");
+
+        Ok(frame_end)
+    }
+
+    /// Executes the instructions of one 60Hz frame (about 8 instructions at 500Hz),
+    /// until the timers are updated. The function never blocks: hosts driven by
+    /// a 60Hz refresh (e.g. libretro, browsers) call it once per refresh
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Mutable reference to a struct that implements the Rng trait used to generate random numbers
+    pub fn run_frame<R: Rng>(&mut self, rng: &mut R) -> Result<(), Error> {
+        trace!("Chip8::run_frame: start");
+
+        // the events of the frame instructions are logged in the frame span
+        let span = debug_span!("frame", cycle = self.cycles);
+        let _enter = span.enter();
+        while !self.step(rng)? {}
+
+        trace!("Chip8::run_frame: exit");
+
+        Ok(())
+    }
+
+    /// Executes a basic block with `step`: the instruction at PC and the following straight-line instructions,
+    /// back to back, up to the next instruction that may change the control flow (jump, call, return, skip,
+    /// key wait, exit), so that the host loop is entered once per block instead of once per instruction
+    /// (e.g. uncapped runs). The blocks are built once per start address and cached: a memory write
+    /// in a block invalidates it (self-modifying code). The block is also interrupted at the end of
+    /// a 60Hz frame, when the emulation is paused, when PC leaves the block (e.g. an instruction of the block
+    /// overwritten while it runs), after `max_instructions` instructions, or when `proceed` returns false
+    /// before an instruction after the first one (e.g. a breakpoint). Returns true if the last instruction
+    /// ended a 60Hz frame
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - Mutable reference to a struct that implements the Rng trait used to generate random numbers
+    /// * `max_instructions` - The maximum number of instructions executed (at least one is executed)
+    /// * `proceed` - The function called before every instruction after the first one, with mutable access
+    ///   to the instance (e.g. to update the keys): the block stops if it returns false
+    pub fn run_block<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        max_instructions: u64,
+        mut proceed: impl FnMut(&mut Chip8) -> bool,
+    ) -> Result<bool, Error> {
+        trace!("Chip8::run_block: start");
+
+        let start = self.pc as usize % self.memory.len();
+        let instructions = (self.block_at(start).instructions as u64).min(max_instructions.max(1));
+        let mut executed = 0;
+        let frame_end = loop {
+            let frame_end = self.step(rng)?;
+            executed += 1;
+
+            // the instructions before the last one of the block fall through to the next one
+            if executed >= instructions
+                || frame_end
+                || self.paused
+                || !self.block_cached(start)
+                || self.pc as usize != start + 2 * executed as usize
+                || !proceed(self)
+            {
+                break frame_end;
+            }
+        };
+
+        trace!("Chip8::run_block: exit");
+
+        Ok(frame_end)
+    }
+
+    /// Returns the mask of the display rows modified since the last call
+    /// (bit n set if row n was modified), or None if the display has not changed:
+    /// the host redraws the display only when needed
+    pub fn take_draw(&mut self) -> Option<u64> {
+        if !self.draw {
+            return None;
+        }
+
+        self.draw = false;
+        Some(core::mem::take(&mut self.dirty_rows))
+    }
+
+    /// Returns the number of instructions executed with `step` and `run_frame`
+    /// since the instance was created or reset
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Returns the statistics of the session: executed instructions, frames and draw instructions,
+    /// and key events since the instance was created or reset
+    pub fn session_stats(&self) -> SessionStats {
+        SessionStats {
+            cycles: self.cycles,
+            frames: self.frame.number(),
+            draws: self.draws,
+            key_events: self.key_events,
+        }
+    }
+
+    /// Sets the clock frequency (Hz) of `step`, that is the instructions executed per second
+    /// of emulated time (500Hz by default): many programs expect faster clocks (e.g. 700Hz or 1000Hz)
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The instructions per second
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frequency is not greater than 0
+    pub fn set_clock_frequency(&mut self, frequency: f64) {
+        if !frequency.is_finite() || frequency <= 0.0 {
+            panic!("invalid clock frequency {frequency}: must be greater than 0");
+        }
+
+        self.clock_frequency = frequency;
+    }
+
+    /// Sets the sampling of the per-instruction logging: the events of one instruction every `sample`
+    /// instructions executed with `step` are logged (1 by default: every instruction), so that long
+    /// debug and trace sessions stay fast. The events are logged only with the `cycle-logging` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - The number of instructions between two logged instructions
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the sample is 0
+    pub fn set_trace_sample(&mut self, sample: u64) {
+        if sample == 0 {
+            panic!("invalid trace sample 0: must be greater than 0");
+        }
+
+        self.trace_sample = sample;
+    }
+
+    /// Returns the clock frequency (Hz) of `step`
+    pub fn clock_frequency(&self) -> f64 {
+        self.clock_frequency
+    }
+
+    /// Returns true if the beeper sounded in the last frame executed with `step` and `run_frame`
+    pub fn beeping(&self) -> bool {
+        self.beeping
+    }
+
+    /// Mutes or unmutes the beeper: the state of the sound timer is not affected, the audio outputs
+    /// (e.g. the WAV recording) are silent while muted
+    ///
+    /// # Arguments
+    ///
+    /// * `muted` - Boolean set to true to mute the beeper
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Returns true if the beeper is muted
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+}
+
+// Display trait implementation for SessionStats
+impl Display for SessionStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} cycles, {} frames, {} draws, {} key events",
+            self.cycles, self.frames, self.draws, self.key_events
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ALL_DISPLAY_ROWS;
+    use alloc::string::ToString;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns an instance with a ROM loaded, clocked at the given frequency
+    fn with_rom(rom: &[u8], frequency: f64) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.set_clock_frequency(frequency);
+        chip8.load_rom_bytes(rom);
+        chip8
+    }
+
+    /// Returns the random number generator of the steps
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    #[test]
+    fn step_executes_one_instruction() {
+        // V0 = 5, V1 = 6, jump to itself
+        let mut chip8 = with_rom(&[0x60, 0x05, 0x61, 0x06, 0x12, 0x04], 500.0);
+        let mut rng = rng();
+
+        assert_eq!(chip8.step(&mut rng), Ok(false));
+        assert_eq!((chip8.pc(), chip8.v()[0], chip8.v()[1]), (0x202, 5, 0));
+        assert_eq!(chip8.step(&mut rng), Ok(false));
+        assert_eq!((chip8.pc(), chip8.v()[1]), (0x204, 6));
+        assert_eq!(chip8.cycles(), 2);
+    }
+
+    #[test]
+    fn step_returns_true_at_the_end_of_a_frame() {
+        // delay timer = 2, jump to itself
+        let mut chip8 = with_rom(&[0x60, 0x02, 0xF0, 0x15, 0x12, 0x04], 120.0);
+        let mut rng = rng();
+
+        assert_eq!(chip8.step(&mut rng), Ok(false));
+        assert_eq!(chip8.step(&mut rng), Ok(true));
+        assert_eq!(chip8.delay_timer(), 1);
+        assert_eq!(chip8.frame().number(), 1);
+        assert_eq!(chip8.step(&mut rng), Ok(false));
+        assert_eq!(chip8.step(&mut rng), Ok(true));
+        assert_eq!(chip8.delay_timer(), 0);
+    }
+
+    #[test]
+    fn run_frame_executes_the_instructions_of_a_frame() {
+        let mut chip8 = with_rom(&[0x12, 0x00], 500.0);
+        let mut rng = rng();
+
+        // 500Hz: 8.33 instructions per frame, the fraction is carried to the next frame
+        chip8.run_frame(&mut rng).unwrap();
+        assert_eq!(chip8.cycles(), 9);
+        chip8.run_frame(&mut rng).unwrap();
+        assert_eq!(chip8.cycles(), 17);
+        chip8.run_frame(&mut rng).unwrap();
+        assert_eq!(chip8.cycles(), 25);
+        assert_eq!(chip8.frame().number(), 3);
+    }
+
+    #[test]
+    fn step_errors_leave_the_cycles_unchanged() {
+        // return with an empty stack
+        let mut chip8 = with_rom(&[0x00, 0xEE], 500.0);
+
+        assert_eq!(chip8.step(&mut rng()), Err(Error::StackUnderflow));
+        assert_eq!(chip8.cycles(), 0);
+    }
+
+    #[test]
+    fn take_draw_returns_the_rows_modified_since_the_last_call() {
+        // I = glyph 0, V1 = 3, draw 2 rows at (0, 3) twice, clear the display
+        let mut chip8 = with_rom(
+            &[0xA0, 0x00, 0x61, 0x03, 0xD0, 0x12, 0xD0, 0x12, 0x00, 0xE0],
+            500.0,
+        );
+        let mut rng = rng();
+
+        chip8.step(&mut rng).unwrap();
+        chip8.step(&mut rng).unwrap();
+        assert_eq!(chip8.take_draw(), None);
+        // the first draw redraws the whole new display
+        chip8.step(&mut rng).unwrap();
+        assert_eq!(chip8.take_draw(), Some(ALL_DISPLAY_ROWS));
+        assert_eq!(chip8.take_draw(), None);
+        chip8.step(&mut rng).unwrap();
+        assert_eq!(chip8.take_draw(), Some(0b11000));
+        chip8.step(&mut rng).unwrap();
+        assert_eq!(chip8.take_draw(), Some(ALL_DISPLAY_ROWS));
+    }
+
+    #[test]
+    fn session_stats_count_the_events_since_the_reset() {
+        // clear the display, jump to itself
+        let mut chip8 = with_rom(&[0x00, 0xE0, 0x12, 0x02], 120.0);
+        let mut rng = rng();
+
+        chip8.run_frame(&mut rng).unwrap();
+        chip8.key_down(0xA);
+        chip8.key_up(0xA);
+        chip8.set_keys(0b11);
+        chip8.run_frame(&mut rng).unwrap();
+        let stats = SessionStats {
+            cycles: 4,
+            frames: 2,
+            draws: 1,
+            key_events: 4,
+        };
+        assert_eq!(chip8.session_stats(), stats);
+        assert_eq!(
+            stats.to_string(),
+            "4 cycles, 2 frames, 1 draws, 4 key events"
+        );
+
+        chip8.soft_reset();
+        assert_eq!(chip8.session_stats(), SessionStats::default());
+    }
+}
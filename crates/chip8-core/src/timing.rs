@@ -0,0 +1,144 @@
+//! Implementation of CHIP-8 (instruction timing models)
+//!
+//! By default every instruction takes one clock cycle (1/500s at the default clock). The COSMAC VIP
+//! timing model gives every instruction the machine cycles taken by the original interpreter:
+//! the programs that count instructions to measure time (e.g. music and timing demos) run as on the VIP
+
+use super::{Chip8, Variants};
+
+/// duration of a COSMAC VIP machine cycle in seconds: 8 clock periods of the 1.76064MHz CDP1802 clock
+const VIP_MACHINE_CYCLE: f64 = 8.0 / 1_760_640.0;
+
+/// Timing model of the instructions executed with `step`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum InstructionTiming {
+    /// Every instruction takes one cycle of the clock frequency
+    #[default]
+    Uniform,
+    /// Every instruction takes the machine cycles of the COSMAC VIP interpreter
+    /// (the clock frequency is not used)
+    CosmacVip,
+}
+
+impl Variants for InstructionTiming {
+    const VARIANTS: &'static [Self] = &[InstructionTiming::Uniform, InstructionTiming::CosmacVip];
+
+    fn name(&self) -> &'static str {
+        match self {
+            InstructionTiming::Uniform => "uniform",
+            InstructionTiming::CosmacVip => "cosmac-vip",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            InstructionTiming::Uniform => "Every instruction takes one cycle of the clock frequency",
+            InstructionTiming::CosmacVip => "Every instruction takes the machine cycles of the COSMAC VIP interpreter (the clock frequency is not used)",
+        }
+    }
+}
+
+/// Returns the machine cycles taken by an instruction of the COSMAC VIP interpreter,
+/// or None if the opcode is not a CHIP-8 instruction of the VIP (e.g. SUPER-CHIP instructions)
+///
+/// # Arguments
+///
+/// * `opcode` - The u16 opcode
+fn vip_machine_cycles(opcode: u16) -> Option<u32> {
+    let cycles = match (opcode & 0xF000, opcode & 0x00FF) {
+        (0x0000, 0xE0) if opcode & 0x0F00 == 0 => 24,
+        (0x0000, 0xEE) if opcode & 0x0F00 == 0 => 23,
+        (0x1000, _) | (0x2000, _) | (0xB000, _) => 23,
+        (0x3000, _) | (0x4000, _) | (0xA000, _) => 12,
+        (0x5000, _) | (0x9000, _) if opcode & 0x000F == 0 => 16,
+        (0x6000, _) => 6,
+        (0x7000, _) => 10,
+        (0x8000, _) => 44,
+        (0xC000, _) => 36,
+        // the sprite is drawn after the vertical blank interrupt
+        (0xD000, _) => 5007,
+        (0xE000, 0x9E) | (0xE000, 0xA1) => 16,
+        // the key wait is timed at every check
+        (0xF000, 0x07) | (0xF000, 0x0A) | (0xF000, 0x15) | (0xF000, 0x18) => 10,
+        (0xF000, 0x1E) => 19,
+        (0xF000, 0x29) => 20,
+        (0xF000, 0x33) => 204,
+        (0xF000, 0x55) | (0xF000, 0x65) => 133,
+        _ => return None,
+    };
+
+    Some(cycles)
+}
+
+impl Chip8 {
+    /// Sets the timing model of the instructions executed with `step`: uniform (one cycle of the clock
+    /// frequency, by default) or COSMAC VIP (the machine cycles of the original interpreter)
+    ///
+    /// # Arguments
+    ///
+    /// * `timing` - The timing model
+    pub fn set_instruction_timing(&mut self, timing: InstructionTiming) {
+        self.timing = timing;
+    }
+
+    /// Returns the timing model of the instructions executed with `step`
+    pub fn instruction_timing(&self) -> InstructionTiming {
+        self.timing
+    }
+
+    /// Returns the emulated time of an instruction in seconds, in the timing model of the instance.
+    /// In the COSMAC VIP timing model, the instructions of the later platforms take one cycle of the
+    /// clock frequency
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - The u16 opcode
+    pub fn instruction_time(&self, opcode: u16) -> f64 {
+        match (self.timing, vip_machine_cycles(opcode)) {
+            (InstructionTiming::CosmacVip, Some(cycles)) => cycles as f64 * VIP_MACHINE_CYCLE,
+            _ => 1.0 / self.clock_frequency,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_instructions_take_one_clock_cycle() {
+        let mut chip8 = Chip8::new();
+        chip8.set_clock_frequency(1000.0);
+
+        assert_eq!(chip8.instruction_time(0xD015), 0.001);
+        assert_eq!(chip8.instruction_time(0x6005), 0.001);
+    }
+
+    #[test]
+    fn vip_instructions_take_their_machine_cycles() {
+        let mut chip8 = Chip8::new();
+        chip8.set_instruction_timing(InstructionTiming::CosmacVip);
+
+        assert_eq!(chip8.instruction_time(0x6005), 6.0 * VIP_MACHINE_CYCLE);
+        assert_eq!(chip8.instruction_time(0x00E0), 24.0 * VIP_MACHINE_CYCLE);
+        assert_eq!(chip8.instruction_time(0xF533), 204.0 * VIP_MACHINE_CYCLE);
+        // a sprite takes most of a frame
+        assert!(chip8.instruction_time(0xD015) > 0.8 / 60.0);
+    }
+
+    #[test]
+    fn later_instructions_take_one_clock_cycle_in_the_vip_model() {
+        let mut chip8 = Chip8::new();
+        chip8.set_instruction_timing(InstructionTiming::CosmacVip);
+
+        for opcode in [0x00FF, 0x00C4, 0x5122, 0xF030, 0xF075] {
+            assert_eq!(vip_machine_cycles(opcode), None);
+            assert_eq!(
+                chip8.instruction_time(opcode),
+                1.0 / chip8.clock_frequency()
+            );
+        }
+    }
+}
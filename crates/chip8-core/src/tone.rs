@@ -0,0 +1,360 @@
+//! Implementation of CHIP-8 (beeper tone generation and pull-based audio stream)
+//!
+//! The tone is generated without the standard library (the sine wave is a polynomial approximation),
+//! so that embedders render the beeper audio in their own audio callbacks with `fill_audio`
+
+use super::{Chip8, Variants};
+use core::f64::consts::{FRAC_PI_2, PI, TAU};
+
+/// default beeper tone frequency (Hz)
+pub const BEEP_FREQUENCY: f64 = 440.0;
+
+/// maximum volume of the beeper (full volume)
+pub const MAX_VOLUME: u8 = 100;
+
+/// beeper tone amplitude at full volume (a quarter of the full scale)
+const BEEP_AMPLITUDE: f64 = 0.25;
+
+/// Waveform of the beeper tone
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum Waveform {
+    /// Square wave: the buzzer of the original computers (harsh)
+    #[default]
+    Square,
+    /// Sine wave: a soft pure tone
+    Sine,
+    /// Triangle wave: between the square and the sine wave
+    Triangle,
+}
+
+impl Variants for Waveform {
+    const VARIANTS: &'static [Self] = &[Waveform::Square, Waveform::Sine, Waveform::Triangle];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            Waveform::Square => "Square wave: the buzzer of the original computers (harsh)",
+            Waveform::Sine => "Sine wave: a soft pure tone",
+            Waveform::Triangle => "Triangle wave: between the square and the sine wave",
+        }
+    }
+}
+
+impl Waveform {
+    /// Returns the value of the waveform, in [-1, 1], at a phase of its period
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The phase in [0, 1)
+    fn value(self, phase: f64) -> f64 {
+        match self {
+            // high on the first half of the period, low on the second half
+            Self::Square if phase < 0.5 => 1.0,
+            Self::Square => -1.0,
+            Self::Sine => sine(phase * TAU),
+            // rising on the first half of the period, falling on the second half
+            Self::Triangle if phase < 0.5 => 4.0 * phase - 1.0,
+            Self::Triangle => 3.0 - 4.0 * phase,
+        }
+    }
+}
+
+/// Returns the sine of an angle in [0, 2π), approximated with its Taylor polynomial of degree 9
+/// (error below 0.00001, inaudible)
+///
+/// # Arguments
+///
+/// * `angle` - The angle in radians
+fn sine(angle: f64) -> f64 {
+    // fold the angle in [-π/2, π/2], where the polynomial is accurate: sin(π - x) = sin(x)
+    let x = if angle < FRAC_PI_2 {
+        angle
+    } else if angle < 3.0 * FRAC_PI_2 {
+        PI - angle
+    } else {
+        angle - TAU
+    };
+    let x2 = x * x;
+
+    x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0))))
+}
+
+/// Generator of the CHIP-8 beeper tone
+#[derive(Clone, Debug)]
+pub struct Beeper {
+    /// wave phase in [0, 1), kept between calls to avoid clicks
+    phase: f64,
+
+    /// waveform of the tone
+    waveform: Waveform,
+
+    /// frequency of the tone (Hz)
+    frequency: f64,
+
+    /// volume of the tone, from 0 to 100
+    volume: u8,
+}
+
+impl Beeper {
+    /// Returns a new beeper tone generator (square wave at 440Hz, full volume)
+    pub fn new() -> Self {
+        Self {
+            phase: 0.0,
+            waveform: Waveform::default(),
+            frequency: BEEP_FREQUENCY,
+            volume: MAX_VOLUME,
+        }
+    }
+
+    /// Sets the waveform and the frequency of the tone
+    ///
+    /// # Arguments
+    ///
+    /// * `waveform` - The waveform of the tone
+    /// * `frequency` - The frequency of the tone (Hz)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frequency is not greater than 0
+    pub fn set_tone(&mut self, waveform: Waveform, frequency: f64) {
+        if !frequency.is_finite() || frequency <= 0.0 {
+            panic!("invalid beep frequency {frequency}: must be greater than 0");
+        }
+
+        self.waveform = waveform;
+        self.frequency = frequency;
+    }
+
+    /// Sets the volume of the tone
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - The volume, from 0 (silent) to 100 (full volume)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the volume is greater than 100
+    pub fn set_volume(&mut self, volume: u8) {
+        if volume > MAX_VOLUME {
+            panic!("invalid volume {volume}: must be between 0 and {MAX_VOLUME}");
+        }
+
+        self.volume = volume;
+    }
+
+    /// Returns the volume of the tone, from 0 (silent) to 100 (full volume)
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Returns the next audio sample of the beeper, in [-1, 1]
+    ///
+    /// # Arguments
+    ///
+    /// * `beeping` - Boolean that is true if the beeper is sounding (sound timer > 0)
+    /// * `sample_rate` - The audio sample rate (samples per second)
+    pub fn sample(&mut self, beeping: bool, sample_rate: u32) -> f64 {
+        let sample = if beeping {
+            self.waveform.value(self.phase) * BEEP_AMPLITUDE * self.volume as f64
+                / MAX_VOLUME as f64
+        } else {
+            0.0
+        };
+        // the phase wraps around at the end of the period
+        self.phase += self.frequency / sample_rate as f64;
+        self.phase -= self.phase as u64 as f64;
+
+        sample
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chip8 {
+    /// Sets the waveform and the frequency of the beeper tone of `fill_audio`
+    /// (square wave at 440Hz by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `waveform` - The waveform of the tone
+    /// * `frequency` - The frequency of the tone (Hz)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frequency is not greater than 0
+    pub fn set_beep_tone(&mut self, waveform: Waveform, frequency: f64) {
+        self.beeper.set_tone(waveform, frequency);
+    }
+
+    /// Sets the volume of the beeper tone of `fill_audio` (full volume by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - The volume, from 0 (silent) to 100 (full volume)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the volume is greater than 100
+    pub fn set_volume(&mut self, volume: u8) {
+        self.beeper.set_volume(volume);
+    }
+
+    /// Returns the volume of the beeper tone of `fill_audio`, from 0 (silent) to 100 (full volume)
+    pub fn volume(&self) -> u8 {
+        self.beeper.volume()
+    }
+
+    /// Fills an audio buffer (mono samples in [-1, 1]) with the beeper tone: the tone sounds if the beeper
+    /// sounded in the last frame (see `beeping`) and is not muted, silence otherwise. The phase of the tone
+    /// is kept between calls, so that hosts call it from their audio callbacks with buffers of any size
+    /// (e.g. WASM audio worklets, libretro)
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The audio buffer to fill
+    /// * `sample_rate` - The audio sample rate of the host (samples per second)
+    pub fn fill_audio(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let beeping = self.beeping && !self.muted;
+        for sample in buffer {
+            *sample = self.beeper.sample(beeping, sample_rate) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the next samples of a beeper
+    fn samples<const N: usize>(beeper: &mut Beeper, beeping: bool, sample_rate: u32) -> [f64; N] {
+        let mut samples = [0.0; N];
+        for sample in &mut samples {
+            *sample = beeper.sample(beeping, sample_rate);
+        }
+        samples
+    }
+
+    /// Returns true if two values are equal within the error of the sine approximation
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.00001
+    }
+
+    #[test]
+    fn waveforms_have_the_expected_values() {
+        let phases = [0.0, 0.25, 0.5, 0.75];
+        let values = [
+            (Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+            (Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+            (Waveform::Triangle, [-1.0, 0.0, 1.0, 0.0]),
+        ];
+        for (waveform, values) in values {
+            for (phase, value) in phases.into_iter().zip(values) {
+                assert!(
+                    close(waveform.value(phase), value),
+                    "{waveform:?} at {phase}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sine_is_accurate_on_the_whole_period() {
+        // sin(π/6) = 0.5, sin(5π/6) = 0.5, sin(7π/6) = -0.5, sin(11π/6) = -0.5
+        for (twelfths, value) in [(1.0, 0.5), (5.0, 0.5), (7.0, -0.5), (11.0, -0.5)] {
+            assert!(close(sine(twelfths * TAU / 12.0), value), "{twelfths}/12");
+        }
+    }
+
+    #[test]
+    fn tone_follows_the_frequency() {
+        // 4 samples per period
+        let mut beeper = Beeper::new();
+        beeper.set_tone(Waveform::Square, 1000.0);
+        let amplitude = BEEP_AMPLITUDE;
+        assert_eq!(
+            samples(&mut beeper, true, 4000),
+            [amplitude, amplitude, -amplitude, -amplitude, amplitude, amplitude]
+        );
+
+        // 2 samples per period, from the phase kept (half of the period)
+        beeper.set_tone(Waveform::Square, 2000.0);
+        assert_eq!(
+            samples(&mut beeper, true, 4000),
+            [-amplitude, amplitude, -amplitude, amplitude]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid beep frequency 0: must be greater than 0")]
+    fn zero_frequency_panics() {
+        Beeper::new().set_tone(Waveform::Sine, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid beep frequency NaN")]
+    fn nan_frequency_panics() {
+        Beeper::new().set_tone(Waveform::Sine, f64::NAN);
+    }
+
+    #[test]
+    fn volume_scales_the_tone() {
+        let mut beeper = Beeper::new();
+        beeper.set_tone(Waveform::Square, 1000.0);
+        beeper.set_volume(50);
+        assert_eq!(beeper.volume(), 50);
+        assert_eq!(
+            samples(&mut beeper, true, 4000),
+            [0.125, 0.125, -0.125, -0.125]
+        );
+
+        beeper.set_volume(0);
+        assert_eq!(samples(&mut beeper, true, 4000), [0.0; 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid volume 101: must be between 0 and 100")]
+    fn volume_above_100_panics() {
+        Chip8::new().set_volume(101);
+    }
+
+    #[test]
+    fn fill_audio_sounds_while_beeping() {
+        let mut chip8 = Chip8::new();
+        chip8.set_beep_tone(Waveform::Square, 1000.0);
+        let mut buffer = [1.0; 6];
+        chip8.fill_audio(&mut buffer, 4000);
+        assert_eq!(buffer, [0.0; 6]);
+
+        // the phase advanced while silent (half of the period)
+        chip8.beeping = true;
+        chip8.fill_audio(&mut buffer, 4000);
+        assert_eq!(buffer, [-0.25, -0.25, 0.25, 0.25, -0.25, -0.25]);
+    }
+
+    #[test]
+    fn fill_audio_is_silent_while_muted() {
+        let mut chip8 = Chip8::new();
+        chip8.beeping = true;
+        chip8.set_muted(true);
+        let mut buffer = [1.0; 16];
+        chip8.fill_audio(&mut buffer, 44100);
+        assert_eq!(buffer, [0.0; 16]);
+
+        chip8.set_muted(false);
+        chip8.fill_audio(&mut buffer, 44100);
+        assert!(buffer.iter().all(|&sample| sample == 0.25));
+    }
+}
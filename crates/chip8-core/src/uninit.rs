@@ -0,0 +1,119 @@
+//! Implementation of CHIP-8 (uninitialized memory reads detection)
+
+use super::{smc::replace_bit, Chip8, MAX_MEMORY_SIZE};
+use alloc::vec;
+use core::ops::Range;
+use tracing::warn;
+
+impl Chip8 {
+    /// Enables the warnings of the reads of uninitialized memory: the bytes written by the fontset
+    /// and ROM loading and by the instructions are tracked, and a warning is logged when an instruction
+    /// reads a byte that was never written (e.g. an off-by-one address beyond the ROM image).
+    /// Every uninitialized byte is reported once
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Boolean set to true to enable the warnings
+    pub fn set_uninitialized_read_warnings(&mut self, enabled: bool) {
+        if !enabled {
+            self.initialized = None;
+        } else if self.initialized.is_none() {
+            self.initialized = Some(vec![0; MAX_MEMORY_SIZE / 64]);
+            // the fontsets and the ROM already loaded are initialized
+            self.mark_initialized(0..self.fontset_end() as usize);
+            let start = self.start_address as usize;
+            self.mark_initialized(start..start + self.rom.len());
+        }
+    }
+
+    /// Forgets the initialized bytes when memory is cleared
+    pub(super) fn clear_initialized(&mut self) {
+        if let Some(initialized) = &mut self.initialized {
+            initialized.fill(0);
+        }
+    }
+
+    /// Marks a range of memory addresses as initialized
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - The memory addresses written
+    pub(super) fn mark_initialized(&mut self, addresses: Range<usize>) {
+        if let Some(initialized) = &mut self.initialized {
+            for address in addresses {
+                replace_bit(initialized, address, true);
+            }
+        }
+    }
+
+    /// Reports the read of a byte that was never written by the current instruction
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The memory address read, within memory
+    pub(super) fn check_initialized(&mut self, address: usize) {
+        let Some(initialized) = &mut self.initialized else {
+            return;
+        };
+
+        // the byte is reported once: it is marked as initialized
+        if !replace_bit(initialized, address, true) {
+            warn!(
+                "uninitialized memory read: instruction at {:#05X} ({:#06X}) reads {address:#05X}, never written",
+                self.pc, self.opcode
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FONTSET_SIZE;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Returns true if an address is tracked as initialized
+    fn initialized(chip8: &Chip8, address: usize) -> bool {
+        let mut set = chip8.initialized.clone().unwrap();
+        replace_bit(&mut set, address, true)
+    }
+
+    #[test]
+    fn loaded_bytes_are_initialized() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x12, 0x00]);
+        chip8.set_uninitialized_read_warnings(true);
+
+        assert!(initialized(&chip8, 0));
+        assert!(initialized(&chip8, FONTSET_SIZE - 1));
+        assert!(initialized(&chip8, 0x201));
+        assert!(!initialized(&chip8, 0x202));
+    }
+
+    #[test]
+    fn reads_and_writes_initialize_the_bytes() {
+        let mut chip8 = Chip8::new();
+        chip8.set_uninitialized_read_warnings(true);
+        // I = 0x300, store V0 at 0x300, read V0-V1 from 0x301
+        chip8.load_rom_bytes(&[0xA3, 0x00, 0xF0, 0x55, 0xF1, 0x65]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        chip8.step(&mut rng).unwrap();
+        chip8.step(&mut rng).unwrap();
+        assert!(initialized(&chip8, 0x300));
+        assert!(!initialized(&chip8, 0x301));
+        // the uninitialized bytes are reported once
+        chip8.step(&mut rng).unwrap();
+        assert!(initialized(&chip8, 0x301) && initialized(&chip8, 0x302));
+    }
+
+    #[test]
+    fn warnings_are_disabled_by_default() {
+        let mut chip8 = Chip8::new();
+        assert!(chip8.initialized.is_none());
+
+        chip8.set_uninitialized_read_warnings(true);
+        chip8.set_uninitialized_read_warnings(false);
+        assert!(chip8.initialized.is_none());
+    }
+}
@@ -0,0 +1,43 @@
+//! Implementation of CHIP-8 (names of the variants of the option enums)
+
+/// Enum of options whose variants are named in kebab-case (e.g. `xo-chip`, `cosmac-vip`), the names used
+/// by the configuration files: frontends parse them (e.g. as command line values) without the interpreter
+/// depending on a command line parser
+pub trait Variants: Sized + Copy + 'static {
+    /// The variants, in the order they are listed
+    const VARIANTS: &'static [Self];
+
+    /// Returns the name of the variant
+    fn name(&self) -> &'static str;
+
+    /// Returns the description of the variant (e.g. for the help of the command line)
+    fn help(&self) -> &'static str;
+
+    /// Returns the variant with a name, or None if no variant has it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variant
+    fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .iter()
+            .copied()
+            .find(|variant| variant.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Profile, Waveform};
+
+    #[test]
+    fn variants_are_found_by_name() {
+        for profile in Profile::VARIANTS {
+            assert_eq!(Profile::from_name(profile.name()), Some(*profile));
+        }
+        assert_eq!(Waveform::from_name("square"), Some(Waveform::Square));
+        assert_eq!(Profile::from_name("Chip-8"), None);
+        assert_eq!(Profile::from_name(""), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Implementation of CHIP-8 (beeper audio recording)
+
+use chip8_core::{Beeper, Waveform};
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use tracing::{debug, trace};
+
+/// WAV sample rate (samples per second)
+const WAV_SAMPLE_RATE: u32 = 44100;
+
+/// WAV bits per sample (signed 16-bit PCM)
+const WAV_BITS_PER_SAMPLE: u16 = 16;
+
+/// WAV number of channels (mono)
+const WAV_CHANNELS: u16 = 1;
+
+/// WAV header size in bytes (RIFF header + fmt chunk + data chunk header)
+const WAV_HEADER_SIZE: u32 = 44;
+
+/// WAV file recorder of the CHIP-8 beeper.
+/// The recorder is fed once per timer tick (60Hz) of the emulation timeline (e.g. by an `on_frame` hook),
+/// so the recorded audio stays synchronized with the emulated time
+/// regardless of the host speed
+pub struct WavRecorder {
+    /// WAV output file
+    file: File,
+
+    /// number of samples written so far
+    samples: u32,
+
+    /// beeper tone generator
+    beeper: Beeper,
+
+    /// fractional samples carried between ticks
+    remainder: f64,
+}
+
+impl WavRecorder {
+    /// Returns a new WAV recorder that writes into the given file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The Path reference that holds the path to the WAV file
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during creation of the WAV file
+    pub fn new(path: &Path) -> Self {
+        trace!("WavRecorder::new: start");
+
+        let file = match File::create(path) {
+            Ok(f) => f,
+            Err(e) => {
+                panic!("creating wav file: {e}")
+            }
+        };
+
+        let mut recorder = Self {
+            file,
+            samples: 0,
+            beeper: Beeper::new(),
+            remainder: 0.0,
+        };
+        // write header of an empty WAV file
+        recorder.write_header();
+
+        debug!("recording beeper audio into `{}`", path.display());
+        trace!("WavRecorder::new: exit");
+
+        recorder
+    }
+
+    /// Writes the WAV header at the beginning of the file, using the current number of samples
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during writing of the WAV file
+    fn write_header(&mut self) {
+        let block_align = WAV_CHANNELS * WAV_BITS_PER_SAMPLE / 8;
+        let byte_rate = WAV_SAMPLE_RATE * block_align as u32;
+        let data_size = self.samples * block_align as u32;
+
+        let mut header = Vec::with_capacity(WAV_HEADER_SIZE as usize);
+        // RIFF header
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(WAV_HEADER_SIZE - 8 + data_size).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+        // fmt chunk (PCM)
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&WAV_CHANNELS.to_le_bytes());
+        header.extend_from_slice(&WAV_SAMPLE_RATE.to_le_bytes());
+        header.extend_from_slice(&byte_rate.to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&WAV_BITS_PER_SAMPLE.to_le_bytes());
+        // data chunk header
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+
+        let result = self
+            .file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.write_all(&header))
+            .and_then(|_| self.file.seek(SeekFrom::End(0)));
+        if let Err(e) = result {
+            panic!("writing wav file: {e}")
+        }
+    }
+
+    /// Sets the waveform and the frequency of the recorded beeper tone (square wave at 440Hz by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `waveform` - The waveform of the tone
+    /// * `frequency` - The frequency of the tone (Hz)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frequency is not greater than 0
+    pub fn set_tone(&mut self, waveform: Waveform, frequency: f64) {
+        self.beeper.set_tone(waveform, frequency);
+    }
+
+    /// Sets the volume of the recorded beeper tone (full volume by default)
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - The volume, from 0 (silent) to 100 (full volume)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the volume is greater than 100
+    pub fn set_volume(&mut self, volume: u8) {
+        self.beeper.set_volume(volume);
+    }
+
+    /// Records one timer tick (1/60 s) of beeper audio.
+    /// The header is updated after every tick, so the WAV file is always valid
+    /// even if the emulation is terminated abruptly
+    ///
+    /// # Arguments
+    ///
+    /// * `beeping` - Boolean that is true if the beeper was sounding during the tick (sound timer > 0)
+    /// * `seconds` - The duration of the tick in seconds of emulated time
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during writing of the WAV file
+    pub fn record(&mut self, beeping: bool, seconds: f64) {
+        trace!("WavRecorder::record: start");
+
+        // number of samples of this tick (carrying the fractional part to the next tick)
+        let exact = seconds * WAV_SAMPLE_RATE as f64 + self.remainder;
+        let count = exact as u32;
+        self.remainder = exact - count as f64;
+
+        let mut data = Vec::with_capacity(count as usize * 2);
+        for _ in 0..count {
+            let sample = (self.beeper.sample(beeping, WAV_SAMPLE_RATE) * i16::MAX as f64) as i16;
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        if let Err(e) = self.file.write_all(&data) {
+            panic!("writing wav file: {e}")
+        }
+        self.samples += count;
+
+        // keep header sizes up to date
+        self.write_header();
+
+        trace!("WavRecorder::record: exit");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    /// Returns the path of a temporary WAV file for a test
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("rust-chip-8-{name}-{}.wav", std::process::id()))
+    }
+
+    /// Returns the little-endian u32 at an offset of a WAV file
+    fn u32_at(wav: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(wav[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Returns the samples of the data chunk of a WAV file
+    fn samples(wav: &[u8]) -> Vec<i16> {
+        wav[WAV_HEADER_SIZE as usize..]
+            .chunks(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn empty_recording_is_a_valid_wav_file() {
+        let path = temp_file("wav-empty");
+        WavRecorder::new(&path);
+
+        let wav = fs::read(&path).unwrap();
+        assert_eq!(wav.len(), WAV_HEADER_SIZE as usize);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), WAV_HEADER_SIZE - 8);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&wav, 24), WAV_SAMPLE_RATE);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), 0);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn ticks_follow_the_emulated_time() {
+        let path = temp_file("wav-ticks");
+        let mut recorder = WavRecorder::new(&path);
+        // 735 samples per tick at 44100 Hz, the fractional samples are carried
+        recorder.record(false, 1.0 / 60.0);
+        recorder.record(true, 1.0 / 60.0);
+        recorder.record(true, 0.5 / WAV_SAMPLE_RATE as f64);
+        recorder.record(true, 0.5 / WAV_SAMPLE_RATE as f64);
+
+        let wav = fs::read(&path).unwrap();
+        let samples = samples(&wav);
+        assert_eq!(samples.len(), 735 * 2 + 1);
+        assert_eq!(u32_at(&wav, 40), samples.len() as u32 * 2);
+        assert_eq!(u32_at(&wav, 4), wav.len() as u32 - 8);
+        assert!(samples[..735].iter().all(|&sample| sample == 0));
+        assert!(samples[735..].iter().all(|&sample| sample != 0));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1341 @@
+//! Emulation loop (pacing of the instructions, hotkeys and stepping execution)
+
+use crate::{Hotkey, Renderer};
+use chip8_core::{
+    disassemble, Chip8, ConstantRng, CounterRng, Error, Frame, SessionStats, StateChange, Variants,
+    TIMERS_FREQUENCY,
+};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde::Serialize;
+use std::{
+    fmt::Display,
+    fs, io, thread,
+    time::{Duration, Instant},
+};
+use tracing::{debug, debug_span, info, warn};
+
+/// speed multiplier applied while the fast-forward key is held
+const FAST_FORWARD_SPEED: f64 = 8.0;
+
+/// speed multiplier applied while the slow-motion is enabled
+const SLOW_MOTION_SPEED: f64 = 0.25;
+
+/// time before a cycle deadline spent spin-waiting instead of sleeping
+/// (the sleeps of the host can last much longer than requested)
+const SPIN_TIME: Duration = Duration::from_micros(500);
+
+/// max delay on the cycle deadlines recovered executing the instructions faster:
+/// beyond it (e.g. after a pause or a stall of the host) the deadlines restart from the present
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+/// number of executed instructions that can be undone in stepping execution
+const STEP_BACK_DEPTH: usize = 1000;
+
+/// number of changed pixels listed after an instruction in stepping execution (e.g. not the whole display cleared)
+const STEP_CHANGED_PIXELS: usize = 8;
+
+/// Options of the CHIP-8 emulation
+#[derive(Clone, Copy, Debug)]
+pub struct RunOptions {
+    /// Boolean that enables stepping execution (one cycle at time)
+    pub stepping: bool,
+    /// Seed for the random number generator
+    pub seed: u64,
+    /// Random number generator of the `CXNN` instructions
+    pub random: RandomMode,
+    /// Emulation speed multiplier (1.0 is the CHIP-8 clock): instructions and timers are scaled together
+    pub speed: f64,
+    /// Boolean that disables all the sleeps, so that instructions are executed as fast as possible
+    /// (the frontend is updated at most 60 times per second and the instructions per second are reported)
+    pub uncapped: bool,
+    /// Optional number of instructions after which the emulation stops
+    pub max_cycles: Option<u64>,
+    /// Optional host time (seconds) after which the emulation stops
+    pub max_seconds: Option<f64>,
+    /// What to do when the program gets stuck in an idle loop
+    pub on_idle: IdlePolicy,
+}
+
+/// Policy applied when the program gets stuck in an idle loop: a jump to itself,
+/// or a wait for a key press (`FX0A`) when the frontend has no keypad input
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum IdlePolicy {
+    /// Keep running at the CHIP-8 clock, also in uncapped mode (no busy loop)
+    #[default]
+    Sleep,
+    /// Stop the emulation
+    Halt,
+}
+
+impl Variants for IdlePolicy {
+    const VARIANTS: &'static [Self] = &[IdlePolicy::Sleep, IdlePolicy::Halt];
+
+    fn name(&self) -> &'static str {
+        match self {
+            IdlePolicy::Sleep => "sleep",
+            IdlePolicy::Halt => "halt",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            IdlePolicy::Sleep => {
+                "Keep running at the CHIP-8 clock, also in uncapped mode (no busy loop)"
+            }
+            IdlePolicy::Halt => "Stop the emulation",
+        }
+    }
+}
+
+/// Random number generator of the `CXNN` instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RandomMode {
+    /// Generator seeded with the random seed: the runs are reproducible
+    #[default]
+    Seeded,
+    /// Generator seeded from the OS entropy: every run is different
+    #[cfg(feature = "std")]
+    Entropy,
+    /// Consecutive numbers from the random seed (e.g. tests)
+    Counter,
+    /// Always the random seed (e.g. tests)
+    Constant,
+}
+
+impl Variants for RandomMode {
+    const VARIANTS: &'static [Self] = &[
+        RandomMode::Seeded,
+        #[cfg(feature = "std")]
+        RandomMode::Entropy,
+        RandomMode::Counter,
+        RandomMode::Constant,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            RandomMode::Seeded => "seeded",
+            #[cfg(feature = "std")]
+            RandomMode::Entropy => "entropy",
+            RandomMode::Counter => "counter",
+            RandomMode::Constant => "constant",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            RandomMode::Seeded => {
+                "Generator seeded with the random seed: the runs are reproducible"
+            }
+            #[cfg(feature = "std")]
+            RandomMode::Entropy => "Generator seeded from the OS entropy: every run is different",
+            RandomMode::Counter => "Consecutive numbers from the random seed (e.g. tests)",
+            RandomMode::Constant => "Always the random seed (e.g. tests)",
+        }
+    }
+}
+
+impl RandomMode {
+    /// Returns the random number generator of the mode
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The random seed (the start value of the counter, the value of the constant)
+    pub fn rng(self, seed: u64) -> Box<dyn RngCore> {
+        match self {
+            Self::Seeded => Box::new(StdRng::seed_from_u64(seed)),
+            #[cfg(feature = "std")]
+            Self::Entropy => Box::new(StdRng::from_entropy()),
+            Self::Counter => Box::new(CounterRng::new(seed)),
+            Self::Constant => Box::new(ConstantRng::new(seed)),
+        }
+    }
+}
+
+/// Reason why the emulation stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StopReason {
+    /// The user asked to quit
+    Quit,
+    /// The maximum number of instructions has been executed
+    MaxCycles,
+    /// The maximum host time has elapsed
+    MaxSeconds,
+    /// The program jumps to itself (halt idle policy)
+    IdleLoop,
+    /// The program waits for a key press and the frontend has no keypad input (halt idle policy)
+    KeyWait,
+    /// The program exited with the `00FD` instruction
+    ProgramExit,
+    /// An instruction could not be executed (illegal opcode, stack overflow or underflow, memory out of bounds)
+    ExecutionError,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Quit => write!(f, "quit"),
+            StopReason::MaxCycles => write!(f, "max cycles reached"),
+            StopReason::MaxSeconds => write!(f, "max seconds reached"),
+            StopReason::IdleLoop => write!(f, "idle loop"),
+            StopReason::KeyWait => write!(f, "waiting for a key without keypad input"),
+            StopReason::ProgramExit => write!(f, "program exit"),
+            StopReason::ExecutionError => write!(f, "execution error"),
+        }
+    }
+}
+
+/// Summary of a finished emulation
+#[derive(Clone, Copy, Debug)]
+pub struct RunSummary {
+    /// Number of executed instructions
+    pub cycles: u64,
+    /// Host time of the emulation
+    pub elapsed: Duration,
+    /// Reason why the emulation stopped
+    pub stop_reason: StopReason,
+    /// Pacing statistics of the emulation
+    pub timing: TimingStats,
+    /// The error of the instruction that could not be executed (execution error stop reason)
+    pub error: Option<Error>,
+    /// Statistics of the session (since the program was loaded or reset), at the end of the emulation
+    pub session: SessionStats,
+}
+
+/// Pacing statistics of an emulation, to diagnose pacing problems
+/// (e.g. a slow frontend or an inaccurate sleep of the host)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimingStats {
+    /// Number of executed 60Hz frames
+    pub frames: u64,
+    /// Total host time of the frames (the time while paused is not counted)
+    pub frame_time: Duration,
+    /// Max host time of a frame
+    pub max_frame_time: Duration,
+    /// Number of sleeps that paced the instructions
+    pub sleeps: u64,
+    /// Total time the sleeps were requested for
+    pub sleep_requested: Duration,
+    /// Total time the sleeps actually lasted
+    pub sleep_actual: Duration,
+    /// Number of instructions that took longer than a clock cycle (the emulation fell behind)
+    pub overruns: u64,
+}
+
+impl TimingStats {
+    /// Records the host time of a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The host time of the frame
+    fn record_frame(&mut self, time: Duration) {
+        self.frames += 1;
+        self.frame_time += time;
+        self.max_frame_time = self.max_frame_time.max(time);
+    }
+
+    /// Records a sleep that paced the instructions
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - The time the sleep was requested for
+    /// * `actual` - The time the sleep actually lasted
+    fn record_sleep(&mut self, requested: Duration, actual: Duration) {
+        self.sleeps += 1;
+        self.sleep_requested += requested;
+        self.sleep_actual += actual;
+    }
+
+    /// Returns the average host time of a frame (1/60 s at normal speed)
+    pub fn average_frame_time(&self) -> Duration {
+        match self.frames {
+            0 => Duration::ZERO,
+            frames => self.frame_time / frames as u32,
+        }
+    }
+
+    /// Returns the average time (seconds) a sleep lasted more than requested (less if negative)
+    pub fn average_sleep_error(&self) -> f64 {
+        match self.sleeps {
+            0 => 0.0,
+            sleeps => {
+                (self.sleep_actual.as_secs_f64() - self.sleep_requested.as_secs_f64())
+                    / sleeps as f64
+            }
+        }
+    }
+}
+
+impl Display for TimingStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} frames, average frame time {:.2} ms (max {:.2} ms), sleep error {:+.1} us on average over {} sleeps, {} overruns",
+            self.frames,
+            self.average_frame_time().as_secs_f64() * 1000.0,
+            self.max_frame_time.as_secs_f64() * 1000.0,
+            self.average_sleep_error() * 1_000_000.0,
+            self.sleeps,
+            self.overruns
+        )
+    }
+}
+
+/// Emulation status shown by the frontends (e.g. in a status bar), updated at every timer tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmulationStatus {
+    /// Frontend frames (timer ticks) per second of host time, over the last second
+    pub fps: f64,
+    /// Instructions executed per second of host time, over the last second
+    pub ips: f64,
+    /// Boolean set to true if the emulation is paused
+    pub paused: bool,
+    /// Boolean set to true while the sound timer is active (the beeper sounds)
+    pub beeping: bool,
+    /// Instructions executed since the program was loaded or reset (the cycle counter)
+    pub cycles: u64,
+}
+
+impl Display for EmulationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} fps | {:.0} ips | cycle {} | {}",
+            self.fps,
+            self.ips,
+            self.cycles,
+            if self.paused { "paused" } else { "running" }
+        )?;
+        if self.beeping {
+            write!(f, " | ♪ beep")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Meter of the frames and instructions per second of the emulation status
+struct StatusMeter {
+    /// Start of the current measurement (one second)
+    started: Instant,
+    /// Frames of the current measurement
+    frames: u64,
+    /// Executed instructions at the start of the current measurement
+    cycles: u64,
+    /// Last status
+    status: EmulationStatus,
+}
+
+impl StatusMeter {
+    /// Returns a new meter, starting the first measurement
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            frames: 0,
+            cycles: 0,
+            status: EmulationStatus::default(),
+        }
+    }
+
+    /// Records a frame and returns the status (the rates are updated every second)
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    /// * `cycles` - The instructions executed since the emulation started
+    fn tick(&mut self, chip8: &Chip8, cycles: u64) -> EmulationStatus {
+        self.frames += 1;
+        let elapsed = self.started.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.status.fps = self.frames as f64 / elapsed.as_secs_f64();
+            self.status.ips = (cycles - self.cycles) as f64 / elapsed.as_secs_f64();
+            self.started = Instant::now();
+            self.frames = 0;
+            self.cycles = cycles;
+        }
+        self.status.paused = chip8.paused();
+        self.status.beeping = chip8.sound_timer() > 0;
+        self.status.cycles = chip8.cycles();
+
+        self.status
+    }
+}
+
+/// Pacer of the instructions: every cycle ends at a deadline on the monotonic clock, one clock cycle after
+/// the deadline of the previous one, so that the sleep errors do not accumulate (the clock does not drift)
+struct Pacer {
+    /// Deadline of the last cycle
+    deadline: Instant,
+}
+
+impl Pacer {
+    /// Returns a new pacer, with the deadlines starting from the present
+    fn new() -> Self {
+        Self {
+            deadline: Instant::now(),
+        }
+    }
+
+    /// Restarts the deadlines from the present (e.g. after a pause, or while not pacing)
+    fn resync(&mut self) {
+        self.deadline = Instant::now();
+    }
+
+    /// Waits for the deadline of the cycle: sleeps most of the time left, then spin-waits the rest.
+    /// Returns false if the deadline had already passed (the emulation fell behind)
+    ///
+    /// # Arguments
+    ///
+    /// * `cycle_time` - The host time of a clock cycle
+    /// * `timing` - Mutable reference to the pacing statistics, that record the sleep
+    fn wait(&mut self, cycle_time: Duration, timing: &mut TimingStats) -> bool {
+        self.deadline += cycle_time;
+
+        let now = Instant::now();
+        if now >= self.deadline {
+            // small delays are recovered by the next cycles, large ones are dropped
+            if now - self.deadline > MAX_LAG {
+                self.deadline = now;
+            }
+            return false;
+        }
+
+        let requested = self.deadline - now;
+        if requested > SPIN_TIME {
+            thread::sleep(requested - SPIN_TIME);
+        }
+        while Instant::now() < self.deadline {
+            std::hint::spin_loop();
+        }
+        timing.record_sleep(requested, now.elapsed());
+
+        true
+    }
+}
+
+/// Emulation loop of a CHIP-8 instance: the instructions are paced at the clock frequency, the display
+/// is drawn by a renderer and the hotkeys of the frontend control the emulation
+pub trait Run {
+    /// Function that starts the CHIP-8 emulation and returns its summary when it stops
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The emulation options
+    /// * `renderer` - Mutable reference to the renderer that draws the display
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the ROM is not loaded, in case of illegal input during the stepping execution.
+    /// Execution errors of the program stop the emulation (see `RunSummary::error`)
+    fn run(&mut self, options: RunOptions, renderer: &mut dyn Renderer) -> RunSummary;
+
+    /// Function that starts the CHIP-8 emulation with a random number generator of the `CXNN` instructions
+    /// provided by the caller (the seed and the random mode of the options are not used), and returns
+    /// its summary when it stops
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The emulation options
+    /// * `renderer` - Mutable reference to the renderer that draws the display
+    /// * `rng` - Mutable reference to the random number generator
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the ROM is not loaded, in case of illegal input during the stepping execution
+    fn run_with_rng(
+        &mut self,
+        options: RunOptions,
+        renderer: &mut dyn Renderer,
+        rng: &mut dyn RngCore,
+    ) -> RunSummary;
+}
+
+impl Run for Chip8 {
+    fn run(&mut self, options: RunOptions, renderer: &mut dyn Renderer) -> RunSummary {
+        let mut rng = options.random.rng(options.seed);
+        self.run_with_rng(options, renderer, &mut *rng)
+    }
+
+    fn run_with_rng(
+        &mut self,
+        options: RunOptions,
+        renderer: &mut dyn Renderer,
+        mut rng: &mut dyn RngCore,
+    ) -> RunSummary {
+        let RunOptions {
+            stepping,
+            speed,
+            uncapped,
+            max_cycles,
+            max_seconds,
+            on_idle,
+            ..
+        } = options;
+
+        if !self.rom_loaded() {
+            panic!("ROM is not loaded");
+        }
+
+        // CHIP-8 clock is 500Hz by default, 500 heartbeats per second
+        // an iteration of the game loop is called frame or tick
+        // frame per second (fps) is how many loop iteration we have in 1 second
+        // clock = frequency = cycles/seconds
+        // seconds = cycles/clock
+        let chip8_clock_time_seconds = 1.0 / self.clock_frequency();
+        // timers are decremented at 60Hz of emulated time (not host time) by step
+        let timers_time_seconds = 1.0 / TIMERS_FREQUENCY;
+        // set to true if the instructions until the next timer tick must be executed while paused
+        let mut frame_advance = false;
+        // set to true if the slow-motion is enabled
+        let mut slow_motion = false;
+        // set to true if the emulation has been paused because the window lost the focus
+        let mut focus_paused = false;
+        // the display is rendered at 60Hz of host time, whatever the clock, sampling the latest display
+        // (in uncapped mode the frontend is also ticked at that rate);
+        // in uncapped mode the instructions per second are reported every second
+        let refresh_time = Duration::from_secs_f64(timers_time_seconds);
+        let mut last_refresh = Instant::now();
+        let started = Instant::now();
+        let mut cycles: u64 = 0;
+        let mut report_instant = Instant::now();
+        let mut report_cycles: u64 = 0;
+        // pacing statistics, and the span of the current frame (the instructions until the next timer tick)
+        let mut timing = TimingStats::default();
+        let mut frame_started = Instant::now();
+        let mut frame_span = debug_span!("frame", frame = timing.frames);
+        let mut status = StatusMeter::new();
+        let mut pacer = Pacer::new();
+        // display snapshot shown by the frontend: the completed frames are shown while running,
+        // the live display while paused or stepping (every instruction can change it)
+        let mut shown = Frame::default();
+        let mut error = None;
+        // in stepping execution the last instructions can be undone
+        if stepping {
+            self.set_undo_depth(STEP_BACK_DEPTH);
+        }
+        let stop_reason = 'run: loop {
+            // run limits (e.g. scripted testing)
+            if max_cycles.is_some_and(|max_cycles| cycles >= max_cycles) {
+                break StopReason::MaxCycles;
+            }
+            if max_seconds.is_some_and(|max_seconds| started.elapsed().as_secs_f64() >= max_seconds)
+            {
+                break StopReason::MaxSeconds;
+            }
+
+            // external controllers (e.g. the control socket) can act on the emulation
+            renderer.control(self);
+
+            for hotkey in renderer.hotkeys() {
+                match hotkey {
+                    Hotkey::TogglePause if self.paused() => {
+                        info!("emulation resumed");
+                        self.resume();
+                        focus_paused = false;
+                    }
+                    Hotkey::TogglePause => {
+                        info!("emulation paused");
+                        self.pause();
+                    }
+                    Hotkey::FrameAdvance => frame_advance = self.paused(),
+                    Hotkey::ToggleSlowMotion => {
+                        slow_motion = !slow_motion;
+                        info!("slow-motion: {}", if slow_motion { "on" } else { "off" });
+                    }
+                    Hotkey::Reset => {
+                        info!("emulation reset");
+                        self.soft_reset();
+                    }
+                    Hotkey::ToggleMute => {
+                        self.set_muted(!self.muted());
+                        info!("audio: {}", if self.muted() { "muted" } else { "unmuted" });
+                    }
+                    Hotkey::Unfocused if !self.paused() => {
+                        info!("emulation paused: window unfocused");
+                        self.pause();
+                        focus_paused = true;
+                    }
+                    Hotkey::Focused if focus_paused => {
+                        focus_paused = false;
+                        if self.paused() {
+                            info!("emulation resumed: window focused");
+                            self.resume();
+                        }
+                    }
+                    Hotkey::Unfocused | Hotkey::Focused => {}
+                }
+            }
+
+            // while paused the frontend keeps ticking, so that it can handle its events
+            if self.paused() && !frame_advance {
+                if renderer.quit_requested() {
+                    break StopReason::Quit;
+                }
+                show(renderer, self.snapshot(), &mut shown);
+                renderer.tick(&shown.pixels());
+                renderer.status(&status.tick(self, cycles));
+                thread::sleep(Duration::from_secs_f64(timers_time_seconds));
+                frame_started = Instant::now();
+                pacer.resync();
+                continue;
+            }
+
+            // debugger frontends can hold the execution of the next instruction (pause, breakpoints)
+            if !renderer.debug(self) {
+                if renderer.quit_requested() {
+                    break StopReason::Quit;
+                }
+                // the instructions stepped while held are drawn at once
+                show(renderer, self.snapshot(), &mut shown);
+                thread::sleep(Duration::from_secs_f64(chip8_clock_time_seconds));
+                pacer.resync();
+                continue;
+            }
+
+            // idle loops cannot change the state anymore: stop, or do not busy loop
+            let idle = if self.idle_loop() {
+                Some(StopReason::IdleLoop)
+            } else if self.waiting_key() && !renderer.has_keypad() {
+                Some(StopReason::KeyWait)
+            } else {
+                None
+            };
+            if let (Some(reason), IdlePolicy::Halt) = (idle, on_idle) {
+                break reason;
+            }
+
+            self.set_keys(renderer.keypad());
+            let executed_before = self.cycles();
+            let frame_end = {
+                let _frame = frame_span.enter();
+                // in uncapped mode the straight-line instructions are executed back to back, asking the
+                // frontend before every instruction (breakpoints, keys); paced and stepped instructions one at a time
+                let result = if uncapped && !stepping && idle.is_none() {
+                    let max_instructions =
+                        max_cycles.map_or(u64::MAX, |max_cycles| max_cycles - cycles);
+                    self.run_block(&mut rng, max_instructions, |chip8| {
+                        if !renderer.debug(chip8) {
+                            return false;
+                        }
+                        chip8.set_keys(renderer.keypad());
+                        true
+                    })
+                } else {
+                    self.step(&mut rng)
+                };
+                match result {
+                    Ok(frame_end) => frame_end,
+                    Err(e) => {
+                        self.log_error_state(&e);
+                        error = Some(e);
+                        break StopReason::ExecutionError;
+                    }
+                }
+            };
+            cycles += self.cycles() - executed_before;
+
+            if frame_end {
+                timing.record_frame(frame_started.elapsed());
+                frame_started = Instant::now();
+                frame_span = debug_span!("frame", frame = timing.frames);
+                if !uncapped {
+                    renderer.tick(&shown.pixels());
+                    renderer.status(&status.tick(self, cycles));
+                }
+                // the advanced frame ends with the timer tick
+                frame_advance = false;
+            }
+            // while stepping every instruction is drawn
+            if stepping || last_refresh.elapsed() >= refresh_time {
+                last_refresh = Instant::now();
+                if uncapped {
+                    renderer.tick(&shown.pixels());
+                    renderer.status(&status.tick(self, cycles));
+                }
+                let frame = if stepping {
+                    self.snapshot()
+                } else {
+                    *self.frame()
+                };
+                if show(renderer, frame, &mut shown) {
+                    debug!("display: {}", self.dump_display());
+                }
+            }
+
+            // the user asked to quit from the frontend
+            if renderer.quit_requested() {
+                break StopReason::Quit;
+            }
+
+            // the program asked to exit
+            if self.exited() {
+                break StopReason::ProgramExit;
+            }
+
+            // the host time of a cycle is scaled by the speed multiplier, while the emulated time
+            // (that drives the timers) is not: instructions and timers keep their proportion
+            let mut speed = speed;
+            if renderer.fast_forward() {
+                speed *= FAST_FORWARD_SPEED;
+            }
+            if slow_motion {
+                speed *= SLOW_MOTION_SPEED;
+            }
+            // the instruction takes one clock cycle, or its own time in the COSMAC VIP timing model
+            let cycle_time_seconds = self.instruction_time(self.opcode()) / speed;
+
+            if uncapped {
+                let elapsed = report_instant.elapsed();
+                if elapsed >= Duration::from_secs(1) {
+                    info!(
+                        "{:.0} instructions per second",
+                        (cycles - report_cycles) as f64 / elapsed.as_secs_f64()
+                    );
+                    report_instant = Instant::now();
+                    report_cycles = cycles;
+                }
+            }
+
+            // wait for the end of the clock cycle (idle loops are paced also in uncapped mode)
+            if !uncapped || idle.is_some() {
+                if !pacer.wait(Duration::from_secs_f64(cycle_time_seconds), &mut timing)
+                    && !uncapped
+                {
+                    // the instruction (with the frontend updates) took longer than a clock cycle
+                    timing.overruns += 1;
+                }
+            } else {
+                pacer.resync();
+            }
+
+            if stepping {
+                info!("changes: {}", format_changes(&self.last_changes()));
+                // the prompt is shown again after undoing instructions
+                loop {
+                    let mut next = String::new();
+                    let opcode = self.opcode_at(self.pc());
+                    info!(
+                        "next instruction: {:#05X}: {:04X} {}",
+                        self.pc(),
+                        opcode,
+                        disassemble(opcode)
+                    );
+                    info!("[n] next, [b [N]] back N instructions, [r] reset, [l <FILE>] load ROM, [q] quit");
+                    io::stdin().read_line(&mut next).unwrap();
+
+                    if next.trim() == "n" {
+                        break;
+                    } else if let Some(count) = next
+                        .trim()
+                        .strip_prefix('b')
+                        .filter(|count| count.is_empty() || count.starts_with(' '))
+                    {
+                        let count = match count.trim() {
+                            "" => 1,
+                            count => count.parse().unwrap_or_else(|_| panic!("illegal input")),
+                        };
+                        let undone = (0..count).take_while(|_| self.step_back()).count();
+                        if undone < count {
+                            info!(
+                                "{undone} instructions undone: no more instructions in the history"
+                            );
+                        } else {
+                            info!("{undone} instructions undone");
+                        }
+                        show(renderer, self.snapshot(), &mut shown);
+                    } else if next.trim() == "r" {
+                        info!("emulation reset");
+                        self.reset(true);
+                        break;
+                    } else if let Some(path) = next.trim().strip_prefix("l ") {
+                        // the ROM is read and checked before the reset, so that a wrong path
+                        // is reported at the prompt and the current program is kept
+                        let path = path.trim();
+                        match fs::read(path) {
+                            Ok(rom) if rom.len() > self.max_rom_size() => {
+                                warn!(
+                                    "ROM too big: {} bytes (max {} bytes)",
+                                    rom.len(),
+                                    self.max_rom_size()
+                                );
+                            }
+                            Ok(rom) => {
+                                self.reset(false);
+                                self.load_rom_bytes(&rom);
+                                info!("rom loaded: {path}");
+                                break;
+                            }
+                            Err(e) => warn!("reading rom file: {e}"),
+                        }
+                    } else if next.trim() == "q" {
+                        break 'run StopReason::Quit;
+                    } else {
+                        panic!("illegal input");
+                    }
+                }
+            }
+        };
+
+        // the changes since the last refresh are drawn, so that the frontend shows the final display
+        show(renderer, self.snapshot(), &mut shown);
+
+        RunSummary {
+            cycles,
+            elapsed: started.elapsed(),
+            stop_reason,
+            timing,
+            error,
+            session: self.session_stats(),
+        }
+    }
+}
+
+/// Returns the changes of an instruction as a list (e.g. `V3: 0x02→0x0A, PC: 0x200→0x202, pixel(12,5) on`):
+/// beyond `STEP_CHANGED_PIXELS` the changed pixels are counted
+///
+/// # Arguments
+///
+/// * `changes` - The state changes of the instruction
+fn format_changes(changes: &[StateChange]) -> String {
+    if changes.is_empty() {
+        return String::from("none");
+    }
+
+    let pixels = changes
+        .iter()
+        .filter(|change| matches!(change, StateChange::Pixel(..)))
+        .count();
+    let mut list: Vec<String> = changes
+        .iter()
+        .filter(|change| !matches!(change, StateChange::Pixel(..)) || pixels <= STEP_CHANGED_PIXELS)
+        .map(ToString::to_string)
+        .collect();
+    if pixels > STEP_CHANGED_PIXELS {
+        list.push(format!("{pixels} pixels"));
+    }
+
+    list.join(", ")
+}
+
+/// Draws a display snapshot, redrawing the rows that differ from the snapshot shown by the frontend.
+/// Returns true if some rows were redrawn
+///
+/// # Arguments
+///
+/// * `renderer` - Mutable reference to the display renderer
+/// * `frame` - The display snapshot to show
+/// * `shown` - Mutable reference to the snapshot shown by the frontend, replaced by the new one
+fn show(renderer: &mut dyn Renderer, frame: Frame, shown: &mut Frame) -> bool {
+    let dirty_rows = frame.changed_rows(shown);
+    if dirty_rows != 0 {
+        renderer.render(&frame.pixels(), dirty_rows);
+    }
+    *shown = frame;
+
+    dirty_rows != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renderer of the tests: returns the scripted hotkeys and records the calls of the emulation loop
+    #[derive(Default)]
+    struct TestRenderer {
+        /// hotkeys returned by the calls of `hotkeys`, by call number (from 1)
+        hotkeys: Vec<(usize, Hotkey)>,
+        /// number of calls of `hotkeys`
+        hotkey_calls: usize,
+        /// number of timer ticks after which the quit is requested
+        quit_after_ticks: Option<usize>,
+        /// number of timer ticks
+        ticks: usize,
+        /// number of rendered frames
+        renders: usize,
+        /// emulation statuses received
+        statuses: Vec<EmulationStatus>,
+        /// value of `fast_forward`
+        fast_forward: bool,
+        /// value of `has_keypad`
+        has_keypad: bool,
+    }
+
+    impl Renderer for TestRenderer {
+        fn render(&mut self, _display: &[bool], _dirty_rows: u64) {
+            self.renders += 1;
+        }
+
+        fn tick(&mut self, _display: &[bool]) {
+            self.ticks += 1;
+        }
+
+        fn status(&mut self, status: &EmulationStatus) {
+            self.statuses.push(*status);
+        }
+
+        fn hotkeys(&mut self) -> Vec<Hotkey> {
+            self.hotkey_calls += 1;
+            self.hotkeys
+                .iter()
+                .filter(|(call, _)| *call == self.hotkey_calls)
+                .map(|(_, hotkey)| *hotkey)
+                .collect()
+        }
+
+        fn fast_forward(&self) -> bool {
+            self.fast_forward
+        }
+
+        fn has_keypad(&self) -> bool {
+            self.has_keypad
+        }
+
+        fn quit_requested(&self) -> bool {
+            self.quit_after_ticks
+                .is_some_and(|ticks| self.ticks >= ticks)
+        }
+    }
+
+    /// Returns the options of a paced run stopped after a number of instructions
+    fn options(speed: f64, max_cycles: u64) -> RunOptions {
+        RunOptions {
+            stepping: false,
+            seed: 0,
+            random: RandomMode::Seeded,
+            speed,
+            uncapped: false,
+            max_cycles: Some(max_cycles),
+            max_seconds: None,
+            on_idle: IdlePolicy::Sleep,
+        }
+    }
+
+    /// Returns an instance with a ROM loaded
+    fn chip8(rom: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom);
+        chip8
+    }
+
+    /// ROM that increments V0 in a loop: 7001 (V0 += 1), 1200 (jump to 0x200)
+    const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+    #[test]
+    fn pause_hotkey_stops_the_instructions() {
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::TogglePause)],
+            quit_after_ticks: Some(3),
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+
+        assert_eq!(summary.stop_reason, StopReason::Quit);
+        assert_eq!(summary.cycles, 0);
+        assert!(chip8.paused());
+
+        // resumed on the next press
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::TogglePause)],
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+        assert!(!chip8.paused());
+    }
+
+    #[test]
+    fn reset_hotkey_restarts_the_program() {
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(10, Hotkey::Reset)],
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+
+        // 9 instructions before the reset, 11 after it
+        assert_eq!(summary.cycles, 20);
+        assert_eq!(summary.session.cycles, 11);
+        assert_eq!(chip8.v()[0], 6);
+    }
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        let mut first = RandomMode::Seeded.rng(10);
+        let mut second = RandomMode::Seeded.rng(10);
+        assert_eq!(first.next_u64(), second.next_u64());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn entropy_rng_differs_between_runs() {
+        // the seed is ignored: two generators from the OS entropy collide with probability 2^-64
+        let mut first = RandomMode::Entropy.rng(10);
+        let mut second = RandomMode::Entropy.rng(10);
+        assert_ne!(first.next_u64(), second.next_u64());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn entropy_mode_is_a_variant() {
+        assert!(RandomMode::VARIANTS.contains(&RandomMode::Entropy));
+        assert_eq!(RandomMode::Entropy.name(), "entropy");
+    }
+
+    #[test]
+    fn counter_and_constant_rngs_start_from_the_seed() {
+        let mut counter = RandomMode::Counter.rng(10);
+        let mut constant = RandomMode::Constant.rng(10);
+        assert_eq!([counter.next_u64(), counter.next_u64()], [10, 11]);
+        assert_eq!([constant.next_u64(), constant.next_u64()], [10, 10]);
+    }
+
+    #[test]
+    fn frame_advance_executes_one_frame() {
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::TogglePause), (3, Hotkey::FrameAdvance)],
+            quit_after_ticks: Some(5),
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1.0, 100), &mut renderer);
+
+        // the instructions of 1/60 s at 500Hz, then paused again
+        assert_eq!(summary.stop_reason, StopReason::Quit);
+        assert_eq!(summary.cycles, 9);
+        assert!(chip8.paused());
+    }
+
+    #[test]
+    fn fast_forward_speeds_up_the_instructions() {
+        // 30 instructions at 500Hz last 60 ms, 7.5 ms while fast-forwarding
+        let mut chip8 = chip8(&COUNTER);
+        let summary = chip8.run(options(1.0, 30), &mut TestRenderer::default());
+        assert!(summary.elapsed >= Duration::from_millis(58));
+
+        let mut chip8 = self::chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            fast_forward: true,
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1.0, 30), &mut renderer);
+        assert!(summary.elapsed < Duration::from_millis(58));
+    }
+
+    #[test]
+    fn speed_scales_the_instructions_and_not_the_timers() {
+        // 60 instructions at twice the speed last 60 ms of host time (120 ms at normal speed),
+        // the timers still tick every 1/60 s of emulated time (120 ms)
+        let mut chip8 = chip8(&COUNTER);
+        let summary = chip8.run(options(2.0, 60), &mut TestRenderer::default());
+        assert!(summary.elapsed >= Duration::from_millis(58));
+        assert!(summary.elapsed < Duration::from_millis(120));
+        assert_eq!(summary.session.frames, 7);
+    }
+
+    #[test]
+    fn slow_motion_slows_down_the_instructions() {
+        // 10 instructions at a quarter of 500Hz last 80 ms
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::ToggleSlowMotion)],
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1.0, 10), &mut renderer);
+        assert!(summary.elapsed >= Duration::from_millis(78));
+
+        // toggled off on the next press
+        let mut chip8 = self::chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::ToggleSlowMotion), (2, Hotkey::ToggleSlowMotion)],
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1.0, 10), &mut renderer);
+        assert!(summary.elapsed < Duration::from_millis(78));
+    }
+
+    #[test]
+    fn uncapped_mode_does_not_pace_the_instructions() {
+        // 50000 instructions at 500Hz would last 100 s
+        let mut chip8 = chip8(&COUNTER);
+        let options = RunOptions {
+            uncapped: true,
+            ..options(1.0, 50000)
+        };
+        let summary = chip8.run(options, &mut TestRenderer::default());
+
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+        assert_eq!(summary.cycles, 50000);
+        assert!(summary.elapsed < Duration::from_secs(10));
+        assert_eq!(summary.timing.sleeps, 0);
+    }
+
+    #[test]
+    fn run_stops_at_the_limits() {
+        let mut chip8 = chip8(&COUNTER);
+        let summary = chip8.run(options(1000.0, 25), &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+        assert_eq!(summary.cycles, 25);
+        assert_eq!(chip8.v()[0], 13);
+
+        let mut chip8 = self::chip8(&COUNTER);
+        let options = RunOptions {
+            max_cycles: None,
+            max_seconds: Some(0.05),
+            ..options(1.0, 0)
+        };
+        let summary = chip8.run(options, &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::MaxSeconds);
+        assert!(summary.elapsed >= Duration::from_millis(50));
+        assert!(summary.cycles > 0);
+    }
+
+    #[test]
+    fn uncapped_run_stops_at_the_max_cycles() {
+        // the instructions executed back to back do not pass the limit
+        let mut chip8 = chip8(&COUNTER);
+        let options = RunOptions {
+            uncapped: true,
+            ..options(1.0, 1001)
+        };
+        let summary = chip8.run(options, &mut TestRenderer::default());
+        assert_eq!(summary.cycles, 1001);
+        // 501 increments, wrapped around
+        assert_eq!(chip8.v()[0], 245);
+    }
+
+    #[test]
+    fn halt_policy_stops_on_idle_loops() {
+        let halt = |max_cycles| RunOptions {
+            on_idle: IdlePolicy::Halt,
+            ..options(1000.0, max_cycles)
+        };
+
+        // 7001, 1202 (jump to itself)
+        let mut chip8 = chip8(&[0x70, 0x01, 0x12, 0x02]);
+        let summary = chip8.run(halt(100), &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::IdleLoop);
+        assert_eq!(summary.cycles, 1);
+
+        // F00A without keypad input
+        let mut chip8 = self::chip8(&[0xF0, 0x0A]);
+        let summary = chip8.run(halt(100), &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::KeyWait);
+
+        // F00A with keypad input: the key can still be pressed
+        let mut chip8 = self::chip8(&[0xF0, 0x0A]);
+        let mut renderer = TestRenderer {
+            has_keypad: true,
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(halt(10), &mut renderer);
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+    }
+
+    #[test]
+    fn sleep_policy_paces_idle_loops_in_uncapped_mode() {
+        // 10 instructions of an idle loop at 500Hz last 20 ms
+        let mut chip8 = chip8(&[0x12, 0x00]);
+        let options = RunOptions {
+            uncapped: true,
+            ..options(1.0, 10)
+        };
+        let summary = chip8.run(options, &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+        assert!(summary.elapsed >= Duration::from_millis(18));
+    }
+
+    #[test]
+    fn run_stops_on_program_exit_and_errors() {
+        // 00FD (exit)
+        let mut chip8 = chip8(&[0x00, 0xFD]);
+        let summary = chip8.run(options(1000.0, 100), &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::ProgramExit);
+        assert_eq!(summary.error, None);
+
+        // 00EE with an empty stack
+        let mut chip8 = self::chip8(&[0x00, 0xEE]);
+        let summary = chip8.run(options(1000.0, 100), &mut TestRenderer::default());
+        assert_eq!(summary.stop_reason, StopReason::ExecutionError);
+        assert_eq!(summary.error, Some(Error::StackUnderflow));
+        assert_eq!(summary.cycles, 0);
+    }
+
+    #[test]
+    fn status_reports_the_pause_the_beeper_and_the_cycles() {
+        // 6010 (V0 = 0x10), F018 (sound timer = V0), 7101 (V1 += 1), 1204 (jump to 0x204)
+        let mut chip8 = chip8(&[0x60, 0x10, 0xF0, 0x18, 0x71, 0x01, 0x12, 0x04]);
+        let mut renderer = TestRenderer::default();
+        chip8.run(options(1000.0, 20), &mut renderer);
+
+        // a status per timer tick (every 8.33 instructions at 500Hz)
+        let cycles: Vec<u64> = renderer
+            .statuses
+            .iter()
+            .map(|status| status.cycles)
+            .collect();
+        assert_eq!(cycles, [9, 17]);
+        assert!(renderer.statuses[0].beeping);
+        assert!(!renderer.statuses[0].paused);
+
+        let status = EmulationStatus {
+            fps: 59.9,
+            ips: 700.2,
+            paused: true,
+            beeping: true,
+            cycles: 42,
+        };
+        assert_eq!(
+            status.to_string(),
+            "60 fps | 700 ips | cycle 42 | paused | ♪ beep"
+        );
+        let status = EmulationStatus {
+            paused: false,
+            beeping: false,
+            ..status
+        };
+        assert_eq!(status.to_string(), "60 fps | 700 ips | cycle 42 | running");
+    }
+
+    #[test]
+    fn timing_stats_average_the_frames_and_the_sleeps() {
+        let mut timing = TimingStats::default();
+        assert_eq!(timing.average_frame_time(), Duration::ZERO);
+        assert_eq!(timing.average_sleep_error(), 0.0);
+
+        timing.record_frame(Duration::from_millis(10));
+        timing.record_frame(Duration::from_millis(20));
+        timing.record_sleep(Duration::from_micros(100), Duration::from_micros(150));
+        timing.record_sleep(Duration::from_micros(100), Duration::from_micros(110));
+        timing.overruns = 1;
+
+        assert_eq!(timing.average_frame_time(), Duration::from_millis(15));
+        assert_eq!(timing.max_frame_time, Duration::from_millis(20));
+        assert!((timing.average_sleep_error() - 0.000_030).abs() < 1e-9);
+        assert_eq!(
+            timing.to_string(),
+            "2 frames, average frame time 15.00 ms (max 20.00 ms), sleep error +30.0 us on average over 2 sleeps, 1 overruns"
+        );
+    }
+
+    #[test]
+    fn paced_run_records_the_frames() {
+        let mut chip8 = chip8(&COUNTER);
+        let summary = chip8.run(options(1.0, 18), &mut TestRenderer::default());
+
+        assert_eq!(summary.timing.frames, 2);
+        assert!(summary.timing.sleeps > 0);
+    }
+
+    #[test]
+    fn unfocused_window_pauses_until_focused() {
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::Unfocused)],
+            quit_after_ticks: Some(3),
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+        assert_eq!(summary.stop_reason, StopReason::Quit);
+        assert_eq!(summary.cycles, 0);
+        assert!(chip8.paused());
+
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::Unfocused), (2, Hotkey::Focused)],
+            ..TestRenderer::default()
+        };
+        let mut chip8 = self::chip8(&COUNTER);
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+        assert_eq!(summary.stop_reason, StopReason::MaxCycles);
+        assert!(!chip8.paused());
+    }
+
+    #[test]
+    fn focus_does_not_resume_a_paused_emulation() {
+        // paused by the user before losing the focus
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![
+                (1, Hotkey::TogglePause),
+                (2, Hotkey::Unfocused),
+                (3, Hotkey::Focused),
+            ],
+            quit_after_ticks: Some(5),
+            ..TestRenderer::default()
+        };
+        let summary = chip8.run(options(1000.0, 20), &mut renderer);
+        assert_eq!(summary.stop_reason, StopReason::Quit);
+        assert!(chip8.paused());
+    }
+
+    #[test]
+    fn mute_hotkey_toggles_the_beeper() {
+        let mut chip8 = chip8(&COUNTER);
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::ToggleMute)],
+            ..TestRenderer::default()
+        };
+        chip8.run(options(1000.0, 10), &mut renderer);
+        assert!(chip8.muted());
+
+        let mut renderer = TestRenderer {
+            hotkeys: vec![(1, Hotkey::ToggleMute)],
+            ..TestRenderer::default()
+        };
+        chip8.run(options(1000.0, 10), &mut renderer);
+        assert!(!chip8.muted());
+    }
+
+    #[test]
+    fn display_is_rendered_at_60hz_of_host_time() {
+        // F029 (I = glyph of V0), D015 (draw it), 1202 (jump to 0x202): the display changes at every draw
+        let mut chip8 = chip8(&[0xF0, 0x29, 0xD0, 0x15, 0x12, 0x02]);
+        let options = RunOptions {
+            uncapped: true,
+            ..options(1.0, 300_000)
+        };
+        let mut renderer = TestRenderer::default();
+        let summary = chip8.run(options, &mut renderer);
+
+        // far more frames of emulated time than of host time
+        let host_frames = (summary.elapsed.as_secs_f64() * TIMERS_FREQUENCY) as usize + 1;
+        assert!(summary.timing.frames as usize > host_frames);
+        assert!(renderer.renders > 0 && renderer.renders <= host_frames);
+        assert!(renderer.ticks <= host_frames);
+    }
+
+    #[test]
+    fn pacer_waits_for_the_deadlines_without_drifting() {
+        let mut timing = TimingStats::default();
+        let mut pacer = Pacer::new();
+        let started = Instant::now();
+        // the cycles that start late (e.g. the thread was preempted) are not waited for
+        let late = (0..50)
+            .filter(|_| !pacer.wait(Duration::from_millis(2), &mut timing))
+            .count();
+
+        // the deadlines are one cycle apart from the start, whatever the sleep errors
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_millis(150));
+        assert_eq!(timing.sleeps as usize + late, 50);
+        assert!(timing.sleeps > 0);
+    }
+
+    #[test]
+    fn pacer_recovers_small_delays_and_drops_large_ones() {
+        let mut timing = TimingStats::default();
+        let mut pacer = Pacer::new();
+
+        // 10 ms behind: the next 1 ms cycles are not waited for until the delay is recovered
+        thread::sleep(Duration::from_millis(10));
+        assert!(!pacer.wait(Duration::from_millis(1), &mut timing));
+        assert!(!pacer.wait(Duration::from_millis(1), &mut timing));
+
+        // beyond the max lag the deadlines restart from the present
+        thread::sleep(MAX_LAG + Duration::from_millis(10));
+        assert!(!pacer.wait(Duration::from_millis(1), &mut timing));
+        assert!(pacer.wait(Duration::from_millis(5), &mut timing));
+        assert_eq!(timing.sleeps, 1);
+    }
+}
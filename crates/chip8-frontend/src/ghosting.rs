@@ -0,0 +1,135 @@
+//! Phosphor persistence (anti-flicker ghosting) filter
+
+use crate::display_size;
+use chip8_core::MAX_DISPLAY_SIZE;
+
+/// Filter that fades pixels out over a number of frames instead of turning them off instantly,
+/// reducing the flicker of sprites that are XOR-erased and redrawn every frame
+pub struct Ghosting {
+    /// number of frames a pixel takes to fade out (0 = filter disabled)
+    frames: u8,
+
+    /// persistence level of each pixel (from `frames`, just turned off, to 0, completely faded out)
+    levels: [u8; MAX_DISPLAY_SIZE],
+
+    /// number of pixels of the display of the levels (the levels are reset when the display mode changes)
+    pixels: usize,
+}
+
+impl Ghosting {
+    /// Returns a new ghosting filter
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - The number of frames a pixel takes to fade out (0 disables the filter)
+    pub fn new(frames: u8) -> Self {
+        Self {
+            frames,
+            levels: [0; MAX_DISPLAY_SIZE],
+            pixels: 0,
+        }
+    }
+
+    /// Updates the persistence levels with a new display: pixels that are on get the maximum level
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    pub fn update(&mut self, display: &[bool]) {
+        // the pixels of the previous display mode do not fade out in the new one
+        if self.pixels != display.len() {
+            self.pixels = display.len();
+            self.levels = [0; MAX_DISPLAY_SIZE];
+        }
+        for (level, on) in self.levels.iter_mut().zip(display) {
+            if *on {
+                *level = self.frames;
+            }
+        }
+    }
+
+    /// Fades out by one frame the pixels that are off.
+    /// Returns the mask of the display rows that have pixels fading out (bit n set if row n is fading out)
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    pub fn fade(&mut self, display: &[bool]) -> u64 {
+        let width = display_size(display).0;
+        let mut fading_rows = 0;
+        for (index, (level, on)) in self.levels.iter_mut().zip(display).enumerate() {
+            if !*on && *level > 0 {
+                *level -= 1;
+                fading_rows |= 1 << (index / width);
+            }
+        }
+
+        fading_rows
+    }
+
+    /// Returns the intensity of a pixel, from 1.0 (on) to 0.0 (off and completely faded out)
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `index` - The index of the pixel in the display
+    pub fn intensity(&self, display: &[bool], index: usize) -> f64 {
+        if display[index] {
+            1.0
+        } else {
+            self.levels[index] as f64 / (self.frames as f64 + 1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::{DISPLAY_HEIGTH, DISPLAY_WIDTH};
+
+    /// Returns a lores display with the given pixels on
+    fn display(on: &[usize]) -> Vec<bool> {
+        let mut display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        for &index in on {
+            display[index] = true;
+        }
+        display
+    }
+
+    #[test]
+    fn pixels_fade_out_over_the_frames() {
+        let mut ghosting = Ghosting::new(2);
+        ghosting.update(&display(&[DISPLAY_WIDTH * 3]));
+
+        let off = display(&[]);
+        assert_eq!(ghosting.intensity(&off, DISPLAY_WIDTH * 3), 2.0 / 3.0);
+        assert_eq!(ghosting.fade(&off), 1 << 3);
+        assert_eq!(ghosting.intensity(&off, DISPLAY_WIDTH * 3), 1.0 / 3.0);
+        assert_eq!(ghosting.fade(&off), 1 << 3);
+        assert_eq!(ghosting.intensity(&off, DISPLAY_WIDTH * 3), 0.0);
+        assert_eq!(ghosting.fade(&off), 0);
+    }
+
+    #[test]
+    fn disabled_filter_turns_pixels_off_instantly() {
+        let mut ghosting = Ghosting::new(0);
+        let on = display(&[0]);
+        ghosting.update(&on);
+        assert_eq!(ghosting.intensity(&on, 0), 1.0);
+
+        let off = display(&[]);
+        assert_eq!(ghosting.fade(&off), 0);
+        assert_eq!(ghosting.intensity(&off, 0), 0.0);
+    }
+
+    #[test]
+    fn display_mode_change_resets_the_levels() {
+        let mut ghosting = Ghosting::new(4);
+        ghosting.update(&display(&[0]));
+
+        let hires = vec![false; MAX_DISPLAY_SIZE];
+        ghosting.update(&hires);
+        assert_eq!(ghosting.intensity(&hires, 0), 0.0);
+        assert_eq!(ghosting.fade(&hires), 0);
+    }
+}
@@ -0,0 +1,154 @@
+//! Mapping of the CHIP-8 hex keypad to the keyboard keys
+
+use chip8_core::KEYPAD_SIZE;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// CHIP-8 hex keypad layout (COSMAC VIP), row by row: keymaps list their keys in this order
+const KEYPAD_LAYOUT: [u8; KEYPAD_SIZE] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF,
+];
+
+/// built-in keymaps: the characters of the left 4x4 block of keys of the layout
+const KEYMAP_PRESETS: [(&str, &str); 3] = [
+    ("qwerty", "1234qwerasdfzxcv"),
+    ("qwertz", "1234qwerasdfyxcv"),
+    ("azerty", "&é\"'azerqsdfwxcv"),
+];
+
+/// Mapping of the CHIP-8 hex keypad to the characters typed by the keyboard keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Keymap {
+    /// Character of every CHIP-8 key (index 0x0-0xF)
+    keys: [char; KEYPAD_SIZE],
+}
+
+impl Keymap {
+    /// Returns the CHIP-8 key mapped to the character typed by a keyboard key, if any
+    /// (letters are matched case insensitively)
+    ///
+    /// # Arguments
+    ///
+    /// * `character` - The character typed by the keyboard key
+    pub fn key(&self, character: &str) -> Option<u8> {
+        let mut chars = character.chars().flat_map(char::to_lowercase);
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return None,
+        };
+
+        self.keys
+            .iter()
+            .position(|key| *key == c)
+            .map(|key| key as u8)
+    }
+}
+
+impl Default for Keymap {
+    /// Returns the QWERTY keymap (1234/QWER/ASDF/ZXCV)
+    fn default() -> Self {
+        KEYMAP_PRESETS[0].1.parse().unwrap()
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    /// Parses a keymap from a built-in keymap name (`qwerty`, `qwertz`, `azerty`)
+    /// or from the 16 characters of the keys mapped to the CHIP-8 keypad, row by row
+    /// (e.g. `1234qwerasdfzxcv`: `1` is mapped to 1, `4` to C, `q` to 4 and so on)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let chars = match KEYMAP_PRESETS.iter().find(|(name, _)| *name == s) {
+            Some((_, chars)) => *chars,
+            None => s.as_str(),
+        };
+
+        let chars: Vec<char> = chars.chars().collect();
+        if chars.len() != KEYPAD_SIZE {
+            let names: Vec<&str> = KEYMAP_PRESETS.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "invalid keymap `{s}`: expected {KEYPAD_SIZE} characters or one of: {}",
+                names.join(", ")
+            ));
+        }
+
+        let mut keys = [' '; KEYPAD_SIZE];
+        for (i, c) in chars.iter().enumerate() {
+            if chars[..i].contains(c) {
+                return Err(format!("invalid keymap `{s}`: character `{c}` is repeated"));
+            }
+            keys[KEYPAD_LAYOUT[i] as usize] = *c;
+        }
+
+        Ok(Self { keys })
+    }
+}
+
+impl TryFrom<String> for Keymap {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_follow_the_keypad_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.key("1"), Some(0x1));
+        assert_eq!(keymap.key("4"), Some(0xC));
+        assert_eq!(keymap.key("q"), Some(0x4));
+        assert_eq!(keymap.key("x"), Some(0x0));
+        assert_eq!(keymap.key("v"), Some(0xF));
+        assert_eq!(keymap.key("p"), None);
+    }
+
+    #[test]
+    fn letters_are_matched_case_insensitively() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.key("Q"), Some(0x4));
+        assert_eq!(keymap.key("qq"), None);
+        assert_eq!(keymap.key(""), None);
+    }
+
+    #[test]
+    fn presets_are_parsed_by_name() {
+        let azerty: Keymap = " AZERTY ".parse().unwrap();
+        assert_eq!(azerty.key("é"), Some(0x2));
+        assert_eq!(azerty.key("a"), Some(0x4));
+        assert_eq!(azerty.key("w"), Some(0xA));
+
+        let qwertz: Keymap = "qwertz".parse().unwrap();
+        assert_eq!(qwertz.key("y"), Some(0xA));
+        assert_eq!(qwertz.key("z"), None);
+    }
+
+    #[test]
+    fn custom_keymaps_are_validated() {
+        let keymap: Keymap = "7890uiopjklmnbhg".parse().unwrap();
+        assert_eq!(keymap.key("7"), Some(0x1));
+        assert_eq!(keymap.key("g"), Some(0xF));
+
+        assert_eq!(
+            "dvorak".parse::<Keymap>(),
+            Err(String::from(
+                "invalid keymap `dvorak`: expected 16 characters or one of: qwerty, qwertz, azerty"
+            ))
+        );
+        assert_eq!(
+            "1234qwerasdfzxcc".parse::<Keymap>(),
+            Err(String::from(
+                "invalid keymap `1234qwerasdfzxcc`: character `c` is repeated"
+            ))
+        );
+    }
+}
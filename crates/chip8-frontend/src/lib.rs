@@ -0,0 +1,405 @@
+//! # chip8-frontend
+//!
+//! The host side of `rust-chip-8`, shared by its frontends: the emulation loop (pacing of the instructions,
+//! hotkeys, stepping prompt), the `Renderer` trait implemented by the display renderers with their options
+//! (palettes, keymap, ghosting, keypad overlay) and the beeper audio (WAV recording and live output)
+
+mod audio;
+mod execution;
+mod ghosting;
+mod keymap;
+mod overlay;
+#[cfg(feature = "audio")]
+mod speaker;
+
+pub use audio::WavRecorder;
+pub use execution::{
+    EmulationStatus, IdlePolicy, RandomMode, Run, RunOptions, RunSummary, StopReason, TimingStats,
+};
+pub use ghosting::Ghosting;
+pub use keymap::Keymap;
+pub use overlay::{KeypadOverlay, KEYPAD_LAYOUT};
+#[cfg(feature = "audio")]
+pub use speaker::AudioOutput;
+
+use chip8_core::{
+    Chip8, Variants, DISPLAY_HEIGTH, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH,
+    MAX_DISPLAY_SIZE,
+};
+use serde::Deserialize;
+use std::{path::PathBuf, str::FromStr};
+
+/// ANSI color names, in order of ANSI color index (0-15)
+const ANSI_COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+/// maximum integer scaling factor of the display
+pub const MAX_SCALE: u32 = 32;
+
+/// RGB values of the ANSI colors (0-15), as in the default xterm palette
+const ANSI_COLOR_RGB: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xCD, 0x00, 0x00),
+    (0x00, 0xCD, 0x00),
+    (0xCD, 0xCD, 0x00),
+    (0x00, 0x00, 0xEE),
+    (0xCD, 0x00, 0xCD),
+    (0x00, 0xCD, 0xCD),
+    (0xE5, 0xE5, 0xE5),
+    (0x7F, 0x7F, 0x7F),
+    (0xFF, 0x00, 0x00),
+    (0x00, 0xFF, 0x00),
+    (0xFF, 0xFF, 0x00),
+    (0x5C, 0x5C, 0xFF),
+    (0xFF, 0x00, 0xFF),
+    (0x00, 0xFF, 0xFF),
+    (0xFF, 0xFF, 0xFF),
+];
+
+/// Returns the size (width, height) of a CHIP-8 display from its pixels: 64x32 in lores mode,
+/// 128x64 in the SUPER-CHIP hires mode
+///
+/// # Arguments
+///
+/// * `display` - The CHIP-8 display pixels (row by row)
+pub fn display_size(display: &[bool]) -> (usize, usize) {
+    if display.len() == MAX_DISPLAY_SIZE {
+        (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH)
+    } else {
+        (DISPLAY_WIDTH, DISPLAY_HEIGTH)
+    }
+}
+
+/// Trait implemented by every CHIP-8 display renderer
+pub trait Renderer {
+    /// Renders the CHIP-8 display
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels (row by row, true if the pixel is on): 64x32 pixels in lores mode,
+    ///   128x64 pixels in the SUPER-CHIP hires mode (see `display_size`)
+    /// * `dirty_rows` - The mask of the display rows modified since the last render (bit n set if row n was modified)
+    fn render(&mut self, display: &[bool], dirty_rows: u64);
+
+    /// Called at every timer tick (60Hz), even if the display did not change,
+    /// so that renderers can animate the display (e.g. fading out pixels)
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels (row by row, true if the pixel is on)
+    fn tick(&mut self, _display: &[bool]) {}
+
+    /// Called at every timer tick (also while paused) with the emulation status,
+    /// so that frontends can show it (e.g. in a status bar)
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The emulation status
+    fn status(&mut self, _status: &EmulationStatus) {}
+
+    /// Called before every CHIP-8 cycle with the machine state, so that debugger frontends can inspect it.
+    /// Returns false if the next instruction must not be executed yet (e.g. emulation paused on a breakpoint)
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance, before the execution of the next instruction
+    fn debug(&mut self, _chip8: &Chip8) -> bool {
+        true
+    }
+
+    /// Called at every iteration of the execution loop (also while paused) with mutable access
+    /// to the CHIP-8 instance, so that external controllers can act on the emulation
+    /// (e.g. pause, reset or restore a save state)
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - Mutable reference to the CHIP-8 instance
+    fn control(&mut self, _chip8: &mut Chip8) {}
+
+    /// Returns the hotkeys pressed in the frontend since the last call
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        Vec::new()
+    }
+
+    /// Returns true while the fast-forward key is held down
+    fn fast_forward(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the frontend has a keypad input (the keys returned by `keypad` can change)
+    fn has_keypad(&self) -> bool {
+        false
+    }
+
+    /// Returns the CHIP-8 keys held down in the frontend (bit n set if key n is pressed)
+    fn keypad(&self) -> u16 {
+        0
+    }
+
+    /// Returns true if the user asked to quit the emulation (e.g. closing the window)
+    fn quit_requested(&self) -> bool {
+        false
+    }
+}
+
+/// Emulation control hotkeys of the window frontends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    /// Pauses the emulation, or resumes it if paused (F5)
+    TogglePause,
+    /// Advances one frame while paused: the instructions of 1/60 s and one timer tick (F6)
+    FrameAdvance,
+    /// Slows the emulation down, or restores its speed if slowed down (F7)
+    ToggleSlowMotion,
+    /// Soft reset: restarts the loaded ROM from the beginning (F8)
+    Reset,
+    /// Mutes the beeper, or unmutes it if muted (F9)
+    ToggleMute,
+    /// The window lost the focus: pauses the emulation, if running (pause when unfocused enabled)
+    Unfocused,
+    /// The window got the focus back: resumes the emulation, if paused by `Unfocused`
+    Focused,
+}
+
+/// Options of the display renderers
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Colors of the pixels
+    pub palette: Palette,
+    /// Number of frames a pixel takes to fade out (0 disables ghosting)
+    pub ghosting: u8,
+    /// Boolean that enables CRT post-processing effects at start (gpu frontend only)
+    pub crt: bool,
+    /// Mapping of the CHIP-8 keypad to the keyboard (window frontends only)
+    pub keymap: Keymap,
+    /// Boolean that enables the keypad overlay (terminal and gpu frontends)
+    pub keypad_overlay: bool,
+    /// Boolean that enables the status bar (terminal, gpu and debugger frontends)
+    pub status_bar: bool,
+    /// Integer scaling factor of the display (sixel, kitty and gpu frontends), None for the frontend default
+    pub scale: Option<u32>,
+    /// Boolean that opens the window in fullscreen (gpu and debugger frontends)
+    pub fullscreen: bool,
+    /// Boolean that pauses the emulation while the window is not focused (gpu and debugger frontends)
+    pub pause_unfocused: bool,
+    /// Directory of the debugger session files, where the breakpoints, the event breaks and the watches
+    /// are saved per ROM (debugger frontend only), None to not keep them across sessions
+    pub session_dir: Option<PathBuf>,
+}
+
+/// Pixel color
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Color {
+    /// Terminal ANSI color (index 0-15, the actual color depends on the terminal palette)
+    Ansi(u8),
+    /// RGB color
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Returns the RGB values of the color
+    /// (ANSI colors are converted using the default xterm palette)
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Ansi(index) => ANSI_COLOR_RGB[index as usize],
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// Parses a color from an ANSI color name (e.g. `green`, `bright-white`) or a RGB hex string (e.g. `#33FF66`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 {
+                return Err(format!("invalid RGB color `#{hex}`: expected `#RRGGBB`"));
+            }
+            let value = u32::from_str_radix(hex, 16)
+                .map_err(|e| format!("invalid RGB color `#{hex}`: {e}"))?;
+            return Ok(Color::Rgb(
+                (value >> 16) as u8,
+                (value >> 8) as u8,
+                value as u8,
+            ));
+        }
+
+        match ANSI_COLOR_NAMES.iter().position(|name| *name == s) {
+            Some(index) => Ok(Color::Ansi(index as u8)),
+            None => Err(format!(
+                "invalid color `{s}`: expected `#RRGGBB` or one of: {}",
+                ANSI_COLOR_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Built-in color themes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// White pixels on black background (terminal colors)
+    Mono,
+    /// Classic green phosphor monitor
+    Classic,
+    /// Amber monochrome monitor
+    Amber,
+    /// Black ink on white paper
+    PaperWhite,
+}
+
+impl Variants for Theme {
+    const VARIANTS: &'static [Self] =
+        &[Theme::Mono, Theme::Classic, Theme::Amber, Theme::PaperWhite];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Theme::Mono => "mono",
+            Theme::Classic => "classic",
+            Theme::Amber => "amber",
+            Theme::PaperWhite => "paper-white",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            Theme::Mono => "White pixels on black background (terminal colors)",
+            Theme::Classic => "Classic green phosphor monitor",
+            Theme::Amber => "Amber monochrome monitor",
+            Theme::PaperWhite => "Black ink on white paper",
+        }
+    }
+}
+
+impl Theme {
+    /// Returns the palette of the theme
+    pub fn palette(&self) -> Palette {
+        match self {
+            Theme::Mono => Palette {
+                foreground: Color::Ansi(15),
+                background: Color::Ansi(0),
+            },
+            Theme::Classic => Palette {
+                foreground: Color::Rgb(0x33, 0xFF, 0x66),
+                background: Color::Rgb(0x0A, 0x1A, 0x0F),
+            },
+            Theme::Amber => Palette {
+                foreground: Color::Rgb(0xFF, 0xB0, 0x00),
+                background: Color::Rgb(0x1A, 0x10, 0x00),
+            },
+            Theme::PaperWhite => Palette {
+                foreground: Color::Rgb(0x20, 0x20, 0x20),
+                background: Color::Rgb(0xF5, 0xF5, 0xEE),
+            },
+        }
+    }
+}
+
+/// Colors used to render the CHIP-8 display
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    /// Color of the pixels that are on
+    pub foreground: Color,
+    /// Color of the pixels that are off
+    pub background: Color,
+}
+
+impl Palette {
+    /// Returns the palette of a theme, with optional foreground and background overrides
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - The base theme
+    /// * `foreground` - Optional color that replaces the theme foreground color
+    /// * `background` - Optional color that replaces the theme background color
+    pub fn new(theme: Theme, foreground: Option<Color>, background: Option<Color>) -> Self {
+        let palette = theme.palette();
+
+        Self {
+            foreground: foreground.unwrap_or(palette.foreground),
+            background: background.unwrap_or(palette.background),
+        }
+    }
+
+    /// Returns the color of a pixel with the given intensity, blending background and foreground colors
+    ///
+    /// # Arguments
+    ///
+    /// * `intensity` - The pixel intensity, from 0.0 (background) to 1.0 (foreground)
+    pub fn blend(&self, intensity: f64) -> Color {
+        if intensity >= 1.0 {
+            return self.foreground;
+        }
+        if intensity <= 0.0 {
+            return self.background;
+        }
+
+        let (fr, fg, fb) = self.foreground.to_rgb();
+        let (br, bg, bb) = self.background.to_rgb();
+        let mix = |f: u8, b: u8| (b as f64 + (f as f64 - b as f64) * intensity).round() as u8;
+
+        Color::Rgb(mix(fr, br), mix(fg, bg), mix(fb, bb))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_are_parsed_from_names_and_hex_strings() {
+        assert_eq!("green".parse(), Ok(Color::Ansi(2)));
+        assert_eq!(" Bright-White ".parse(), Ok(Color::Ansi(15)));
+        assert_eq!("#33FF66".parse(), Ok(Color::Rgb(0x33, 0xFF, 0x66)));
+        assert!("#33FF6".parse::<Color>().is_err());
+        assert!("#33FFGG".parse::<Color>().is_err());
+        assert!("teal".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn palette_overrides_the_theme_colors() {
+        let palette = Palette::new(Theme::Amber, None, Some(Color::Ansi(0)));
+        assert_eq!(palette.foreground, Theme::Amber.palette().foreground);
+        assert_eq!(palette.background, Color::Ansi(0));
+        assert_eq!(Color::Ansi(0).to_rgb(), (0x00, 0x00, 0x00));
+    }
+
+    #[test]
+    fn blend_mixes_the_background_and_the_foreground() {
+        let palette = Palette {
+            foreground: Color::Rgb(0xFF, 0x80, 0x00),
+            background: Color::Rgb(0x00, 0x00, 0x00),
+        };
+        assert_eq!(palette.blend(1.5), palette.foreground);
+        assert_eq!(palette.blend(0.0), palette.background);
+        assert_eq!(palette.blend(0.5), Color::Rgb(0x80, 0x40, 0x00));
+    }
+}
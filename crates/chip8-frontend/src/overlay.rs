@@ -0,0 +1,148 @@
+//! Keypad overlay of the frontends: the keys of the hex keypad held down and the key read by the last FX0A
+
+use chip8_core::Chip8;
+
+/// CHIP-8 hex keypad layout (COSMAC VIP)
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// State of the keypad overlay, updated before every instruction
+#[derive(Default)]
+pub struct KeypadOverlay {
+    /// CHIP-8 keys held down (bit n set if key n is pressed)
+    keys: u16,
+
+    /// Key read by the last FX0A, if any
+    waited: Option<u8>,
+
+    /// Boolean set to true if the state changed since the last draw
+    changed: bool,
+}
+
+impl KeypadOverlay {
+    /// Returns a new keypad overlay, to be drawn at the first frame
+    pub fn new() -> Self {
+        Self {
+            changed: true,
+            ..Self::default()
+        }
+    }
+
+    /// Updates the overlay with the keypad state of the CHIP-8 instance
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    pub fn update(&mut self, chip8: &Chip8) {
+        if self.keys != chip8.keys() || self.waited != chip8.last_waited_key() {
+            self.keys = chip8.keys();
+            self.waited = chip8.last_waited_key();
+            self.changed = true;
+        }
+    }
+
+    /// Marks the overlay as changed, so that it is drawn again (e.g. after the screen was cleared)
+    pub fn invalidate(&mut self) {
+        self.changed = true;
+    }
+
+    /// Returns true if the state changed since the last draw, and clears the flag
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Returns true if the key is held down
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key (0x0-0xF)
+    pub fn pressed(&self, key: u8) -> bool {
+        self.keys & (1 << key) != 0
+    }
+
+    /// Returns true if the key was read by the last FX0A
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key (0x0-0xF)
+    pub fn waited(&self, key: u8) -> bool {
+        self.waited == Some(key)
+    }
+
+    /// Returns a one-line description of the keypad state (e.g. `keys: 5 A | FX0A: 5`)
+    pub fn describe(&self) -> String {
+        let keys: Vec<String> = (0..16)
+            .filter(|key| self.pressed(*key))
+            .map(|key| format!("{key:X}"))
+            .collect();
+        let keys = if keys.is_empty() {
+            String::from("-")
+        } else {
+            keys.join(" ")
+        };
+        let waited = self
+            .waited
+            .map_or(String::from("-"), |key| format!("{key:X}"));
+
+        format!("keys: {keys} | FX0A: {waited}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn changes_are_reported_once() {
+        let mut chip8 = Chip8::new();
+        let mut overlay = KeypadOverlay::new();
+        assert!(overlay.take_changed());
+        assert!(!overlay.take_changed());
+
+        overlay.update(&chip8);
+        assert!(!overlay.take_changed());
+
+        chip8.key_down(0x5);
+        overlay.update(&chip8);
+        assert!(overlay.take_changed());
+        assert!(overlay.pressed(0x5));
+        assert!(!overlay.pressed(0x6));
+
+        overlay.invalidate();
+        assert!(overlay.take_changed());
+    }
+
+    #[test]
+    fn keypad_state_is_described() {
+        let mut chip8 = Chip8::new();
+        let mut overlay = KeypadOverlay::new();
+        assert_eq!(overlay.describe(), "keys: - | FX0A: -");
+
+        chip8.set_keys(1 << 0x5 | 1 << 0xA);
+        overlay.update(&chip8);
+        assert_eq!(overlay.describe(), "keys: 5 A | FX0A: -");
+    }
+
+    #[test]
+    fn key_read_by_fx0a_is_shown() {
+        // F00A: waits for a key (read when released)
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0xF0, 0x0A]);
+        let mut overlay = KeypadOverlay::new();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        chip8.key_down(0xB);
+        chip8.emulate_cycle(&mut rng);
+        chip8.key_up(0xB);
+        chip8.emulate_cycle(&mut rng);
+        overlay.update(&chip8);
+        assert!(overlay.waited(0xB));
+        assert!(!overlay.waited(0x0));
+        assert_eq!(overlay.describe(), "keys: - | FX0A: B");
+    }
+}
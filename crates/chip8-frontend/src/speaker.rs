@@ -0,0 +1,255 @@
+//! Implementation of CHIP-8 (live beeper audio output)
+
+use chip8_core::{Beeper, Chip8, Waveform, TIMERS_FREQUENCY};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, Stream, StreamConfig,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+};
+use tracing::{debug, trace, warn};
+
+/// max number of frames of emulated time waiting to be played (100ms of latency): the oldest frames
+/// are dropped when the emulation runs faster than the audio device (e.g. fast-forward)
+const MAX_QUEUED_FRAMES: usize = 6;
+
+/// Beeper states of the frames of emulated time, turned into the samples of the audio device
+/// by the tone generator of `fill_audio`
+struct ToneQueue {
+    /// beeper states of the frames waiting to be played (true if the beeper sounds)
+    frames: VecDeque<bool>,
+
+    /// beeper tone generator
+    beeper: Beeper,
+
+    /// sample rate of the audio device (samples per second)
+    sample_rate: u32,
+
+    /// beeper state of the frame being played
+    beeping: bool,
+
+    /// samples left of the frame being played (the fractional part is carried to the next frame)
+    left: f64,
+}
+
+impl ToneQueue {
+    /// Returns an empty queue
+    ///
+    /// # Arguments
+    ///
+    /// * `beeper` - The beeper tone generator
+    /// * `sample_rate` - The sample rate of the audio device (samples per second)
+    fn new(beeper: Beeper, sample_rate: u32) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(MAX_QUEUED_FRAMES),
+            beeper,
+            sample_rate,
+            beeping: false,
+            left: 0.0,
+        }
+    }
+
+    /// Queues the beeper state of a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `beeping` - Boolean that is true if the beeper sounded in the frame
+    fn push(&mut self, beeping: bool) {
+        if self.frames.len() == MAX_QUEUED_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(beeping);
+    }
+
+    /// Fills an audio buffer of interleaved samples with the tone of the queued frames
+    /// (silence when the queue is empty, e.g. while the emulation is paused)
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The audio buffer to fill
+    /// * `channels` - The number of channels of the buffer (every channel gets the same sample)
+    fn fill(&mut self, buffer: &mut [f32], channels: usize) {
+        let frame_samples = self.sample_rate as f64 / TIMERS_FREQUENCY;
+        for frame in buffer.chunks_mut(channels) {
+            if self.left < 1.0 {
+                self.beeping = self.frames.pop_front().unwrap_or(false);
+                self.left += frame_samples;
+            }
+            self.left -= 1.0;
+            frame.fill(self.beeper.sample(self.beeping, self.sample_rate) as f32);
+        }
+    }
+}
+
+/// Live audio output of the CHIP-8 beeper on the default audio device of the host (cpal).
+/// The beeper state of every frame of emulated time is queued by an `on_frame` hook and played
+/// by the audio callback, so the output stays silent while the emulation is paused or muted
+pub struct AudioOutput {
+    /// audio stream of the device, playing while the output is alive
+    _stream: Stream,
+
+    /// frames shared with the audio callback
+    queue: Arc<Mutex<ToneQueue>>,
+}
+
+impl AudioOutput {
+    /// Returns an audio output playing on the default audio device of the host
+    ///
+    /// # Arguments
+    ///
+    /// * `waveform` - The waveform of the tone
+    /// * `frequency` - The frequency of the tone (Hz)
+    /// * `volume` - The volume, from 0 (silent) to 100 (full volume)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the frequency is not greater than 0 or the volume is greater than 100
+    pub fn new(waveform: Waveform, frequency: f64, volume: u8) -> Result<Self, String> {
+        trace!("AudioOutput::new: start");
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| String::from("no audio output device"))?;
+        let supported = device
+            .default_output_config()
+            .map_err(|e| format!("audio output configuration: {e}"))?;
+        let config = supported.config();
+
+        let mut beeper = Beeper::new();
+        beeper.set_tone(waveform, frequency);
+        beeper.set_volume(volume);
+        let queue = Arc::new(Mutex::new(ToneQueue::new(beeper, config.sample_rate.0)));
+
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, queue.clone()),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, queue.clone()),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, queue.clone()),
+            format => Err(format!("unsupported audio sample format {format}")),
+        }?;
+        stream
+            .play()
+            .map_err(|e| format!("starting audio output: {e}"))?;
+
+        debug!(
+            "audio output: {} Hz, {} channels, {}",
+            config.sample_rate.0,
+            config.channels,
+            supported.sample_format()
+        );
+        trace!("AudioOutput::new: exit");
+
+        Ok(Self {
+            _stream: stream,
+            queue,
+        })
+    }
+
+    /// Plays the beeper of a CHIP-8 instance: registers the `on_frame` hook queuing its beeper state
+    /// (silent while muted, see `set_muted`)
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    pub fn play(&self, chip8: &mut Chip8) {
+        let queue = self.queue.clone();
+        chip8.on_frame(move |chip8| {
+            queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(chip8.beeping() && !chip8.muted())
+        });
+    }
+}
+
+/// Returns the audio stream of a device playing the queued frames, with samples of type T
+///
+/// # Arguments
+///
+/// * `device` - The audio device
+/// * `config` - The configuration of the stream
+/// * `queue` - The frames shared with the emulation
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    queue: Arc<Mutex<ToneQueue>>,
+) -> Result<Stream, String> {
+    let channels = config.channels as usize;
+    let mut samples = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                samples.resize(data.len(), 0.0);
+                queue
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .fill(&mut samples, channels);
+                for (output, sample) in data.iter_mut().zip(&samples) {
+                    *output = T::from_sample(*sample);
+                }
+            },
+            |e| warn!("audio output: {e}"),
+            None,
+        )
+        .map_err(|e| format!("opening audio output: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// sample rate of the tests: 800 samples per frame
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn queue() -> ToneQueue {
+        ToneQueue::new(Beeper::new(), SAMPLE_RATE)
+    }
+
+    #[test]
+    fn frames_are_played_in_order() {
+        let mut queue = queue();
+        queue.push(false);
+        queue.push(true);
+
+        let mut buffer = vec![1.0; 1600];
+        queue.fill(&mut buffer, 1);
+        assert!(buffer[..800].iter().all(|&sample| sample == 0.0));
+        assert!(buffer[800..].iter().any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn empty_queue_is_silent() {
+        let mut queue = queue();
+
+        let mut buffer = vec![1.0; 1024];
+        queue.fill(&mut buffer, 2);
+        assert!(buffer.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn channels_get_the_same_sample() {
+        let mut queue = queue();
+        queue.push(true);
+
+        let mut buffer = vec![0.0; 800];
+        queue.fill(&mut buffer, 2);
+        assert!(buffer.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert!(buffer.iter().any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn oldest_frames_are_dropped_when_full() {
+        let mut queue = queue();
+        queue.push(true);
+        for _ in 0..MAX_QUEUED_FRAMES {
+            queue.push(false);
+        }
+
+        assert_eq!(queue.frames.len(), MAX_QUEUED_FRAMES);
+        let mut buffer = vec![1.0; 800];
+        queue.fill(&mut buffer, 1);
+        assert!(buffer.iter().all(|&sample| sample == 0.0));
+    }
+}
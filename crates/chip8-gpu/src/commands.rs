@@ -0,0 +1,320 @@
+//! Commands of the debugger console: the state of the running program can be patched from the console panel
+//! (e.g. `set V3 0x1F`, `set I 0x300`, `set pc 0x200`, `poke 0x400 0xAA BB`) and memory can be searched
+//! for a value (e.g. `find 03`, then `refine 02` after losing a life, to locate the lives variable).
+//! Numbers are hex digits, with or without the `0x` prefix
+
+use chip8_core::Chip8;
+use std::str::FromStr;
+
+/// Register patched by the `set` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Register {
+    /// General purpose register V0-VF
+    V(u8),
+    /// Index register
+    I,
+    /// Program counter
+    Pc,
+}
+
+/// number of addresses printed by the search commands
+const FOUND_LINES: usize = 16;
+
+/// Command of the debugger console
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Sets a register
+    Set(Register, u16),
+    /// Writes bytes into memory from an address
+    Poke(u16, Vec<u8>),
+    /// Searches memory for a byte pattern
+    Find(Vec<u8>),
+    /// Searches the byte pattern among the addresses found by the last search
+    Refine(Vec<u8>),
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    /// Parses a register name (`V0`-`VF`, `I` or `PC`, case insensitive)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_uppercase();
+        match name.as_str() {
+            "I" => Ok(Self::I),
+            "PC" => Ok(Self::Pc),
+            _ => name
+                .strip_prefix('V')
+                .filter(|index| index.len() == 1)
+                .and_then(|index| u8::from_str_radix(index, 16).ok())
+                .map(Self::V)
+                .ok_or(format!("unknown register `{s}`: expected V0-VF, I or PC")),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses a command line of the debugger console (e.g. `set V3 0x1F`, `poke 0x400 0xAA BB`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let arguments: Vec<&str> = words.collect();
+
+        match (name, arguments.as_slice()) {
+            ("set", [register, value]) => Ok(Self::Set(register.parse()?, parse_number(value)?)),
+            ("set", _) => Err(String::from("usage: set <V0-VF|I|PC> <VALUE>")),
+            ("poke", [address, bytes @ ..]) if !bytes.is_empty() => Ok(Self::Poke(
+                parse_number(address)?,
+                bytes
+                    .iter()
+                    .map(|byte| {
+                        u8::try_from(parse_number(byte)?)
+                            .map_err(|_| format!("invalid byte `{byte}`: must be at most 0xFF"))
+                    })
+                    .collect::<Result<_, _>>()?,
+            )),
+            ("poke", _) => Err(String::from("usage: poke <ADDRESS> <BYTE>...")),
+            ("find", values) if !values.is_empty() => Ok(Self::Find(parse_pattern(values)?)),
+            ("refine", values) if !values.is_empty() => Ok(Self::Refine(parse_pattern(values)?)),
+            ("find" | "refine", _) => Err(format!("usage: {name} <VALUE>...")),
+            _ => Err(format!(
+                "unknown command `{name}`: expected set <REGISTER> <VALUE>, poke <ADDRESS> <BYTE>..., \
+                 find <VALUE>... or refine <VALUE>..."
+            )),
+        }
+    }
+}
+
+impl Command {
+    /// Executes the command on the CHIP-8 instance and returns its output
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - Mutable reference to the CHIP-8 instance
+    /// * `found` - Mutable reference to the addresses found by the last search
+    pub(crate) fn execute(
+        &self,
+        chip8: &mut Chip8,
+        found: &mut Vec<u16>,
+    ) -> Result<String, String> {
+        match *self {
+            Self::Set(Register::V(index), value) => {
+                let value = u8::try_from(value)
+                    .map_err(|_| format!("invalid value {value:#X}: V{index:X} is 8 bits"))?;
+                chip8.set_v(index, value);
+                Ok(format!("V{index:X} = {value:#04X}"))
+            }
+            Self::Set(Register::I, value) => {
+                chip8.set_i(value);
+                Ok(format!("I = {value:#05X}"))
+            }
+            Self::Set(Register::Pc, address) => {
+                if address as usize >= chip8.memory_size() {
+                    return Err(format!(
+                        "invalid PC {address:#05X}: beyond the end of memory"
+                    ));
+                }
+                chip8.set_pc(address);
+                Ok(format!("PC = {address:#05X}"))
+            }
+            Self::Poke(address, ref bytes) => {
+                if address as usize + bytes.len() > chip8.memory_size() {
+                    return Err(format!(
+                        "invalid address {address:#05X}: {} bytes do not fit into memory",
+                        bytes.len()
+                    ));
+                }
+                for (offset, byte) in bytes.iter().enumerate() {
+                    chip8.write_memory(address + offset as u16, *byte);
+                }
+                Ok(format!("{} bytes written at {address:#05X}", bytes.len()))
+            }
+            Self::Find(ref pattern) => {
+                *found = chip8
+                    .memory()
+                    .windows(pattern.len())
+                    .enumerate()
+                    .filter(|(_, bytes)| bytes == pattern)
+                    .map(|(address, _)| address as u16)
+                    .collect();
+                Ok(format_found(found))
+            }
+            Self::Refine(ref pattern) => {
+                let memory = chip8.memory();
+                found.retain(|&address| {
+                    memory
+                        .get(address as usize..address as usize + pattern.len())
+                        .is_some_and(|bytes| bytes == pattern.as_slice())
+                });
+                Ok(format_found(found))
+            }
+        }
+    }
+}
+
+/// Returns the output of a search: the number of addresses found and the first ones
+///
+/// # Arguments
+///
+/// * `found` - The addresses found
+fn format_found(found: &[u16]) -> String {
+    let addresses: Vec<String> = found
+        .iter()
+        .take(FOUND_LINES)
+        .map(|address| format!("{address:#05X}"))
+        .collect();
+    let more = if found.len() > FOUND_LINES {
+        " ..."
+    } else {
+        ""
+    };
+
+    format!("{} found: {}{more}", found.len(), addresses.join(" "))
+}
+
+/// Parses the values of a search pattern: values of 1-2 hex digits are bytes (the size of V0-VF),
+/// values of 3-4 hex digits are big-endian 16-bit words (the size of I)
+///
+/// # Arguments
+///
+/// * `values` - The value strings
+fn parse_pattern(values: &[&str]) -> Result<Vec<u8>, String> {
+    let mut pattern = Vec::new();
+    for value in values {
+        let number = parse_number(value)?;
+        let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+        if digits.len() > 2 {
+            pattern.extend_from_slice(&number.to_be_bytes());
+        } else {
+            pattern.push(number as u8);
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// Parses a number in hex digits (e.g. `0x1F`, `1F`)
+///
+/// # Arguments
+///
+/// * `s` - The number string
+fn parse_number(s: &str) -> Result<u16, String> {
+    let digits = s.strip_prefix("0x").or(s.strip_prefix("0X")).unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|e| format!("invalid number `{s}`: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_poke_commands_are_parsed() {
+        assert_eq!(
+            "set V3 0x1F".parse(),
+            Ok(Command::Set(Register::V(3), 0x1F))
+        );
+        assert_eq!("set i 300".parse(), Ok(Command::Set(Register::I, 0x300)));
+        assert_eq!(
+            "set PC 0x200".parse(),
+            Ok(Command::Set(Register::Pc, 0x200))
+        );
+        assert_eq!(
+            "poke 0x400 0xAA BB".parse(),
+            Ok(Command::Poke(0x400, vec![0xAA, 0xBB]))
+        );
+
+        assert!("set V3".parse::<Command>().is_err());
+        assert!("set VG 1".parse::<Command>().is_err());
+        assert!("set V10 1".parse::<Command>().is_err());
+        assert!("poke 0x400".parse::<Command>().is_err());
+        assert!("poke 0x400 0x100".parse::<Command>().is_err());
+        assert!("peek 0x400".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn set_and_poke_commands_patch_the_state() {
+        let mut chip8 = Chip8::new();
+        let mut found = Vec::new();
+
+        assert_eq!(
+            Command::Set(Register::V(3), 0x1F).execute(&mut chip8, &mut found),
+            Ok(String::from("V3 = 0x1F"))
+        );
+        assert_eq!(chip8.v()[3], 0x1F);
+        Command::Set(Register::I, 0x300)
+            .execute(&mut chip8, &mut found)
+            .unwrap();
+        assert_eq!(chip8.i(), 0x300);
+        Command::Set(Register::Pc, 0x204)
+            .execute(&mut chip8, &mut found)
+            .unwrap();
+        assert_eq!(chip8.pc(), 0x204);
+        assert_eq!(
+            Command::Poke(0x400, vec![0xAA, 0xBB]).execute(&mut chip8, &mut found),
+            Ok(String::from("2 bytes written at 0x400"))
+        );
+        assert_eq!(chip8.memory()[0x400..0x402], [0xAA, 0xBB]);
+
+        // out of range values are rejected
+        assert!(Command::Set(Register::V(3), 0x100)
+            .execute(&mut chip8, &mut found)
+            .is_err());
+        assert!(Command::Set(Register::Pc, 0x1000)
+            .execute(&mut chip8, &mut found)
+            .is_err());
+        assert!(Command::Poke(0xFFF, vec![0xAA, 0xBB])
+            .execute(&mut chip8, &mut found)
+            .is_err());
+        assert_eq!(chip8.v()[3], 0x1F);
+        assert_eq!(chip8.pc(), 0x204);
+    }
+
+    #[test]
+    fn search_patterns_are_bytes_or_words() {
+        assert_eq!("find 03".parse(), Ok(Command::Find(vec![0x03])));
+        assert_eq!("find 0x0300".parse(), Ok(Command::Find(vec![0x03, 0x00])));
+        assert_eq!(
+            "refine 1 0x2A4".parse(),
+            Ok(Command::Refine(vec![0x01, 0x02, 0xA4]))
+        );
+        assert!("find".parse::<Command>().is_err());
+        assert!("refine xyz".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn searches_are_refined_among_the_last_results() {
+        let mut chip8 = Chip8::new();
+        let mut found = Vec::new();
+        for address in [0x300, 0x310, 0x320] {
+            chip8.write_memory(address, 0xA7);
+        }
+        chip8.write_memory(0x311, 0x01);
+
+        assert_eq!(
+            Command::Find(vec![0xA7]).execute(&mut chip8, &mut found),
+            Ok(String::from("3 found: 0x300 0x310 0x320"))
+        );
+        assert_eq!(found, [0x300, 0x310, 0x320]);
+
+        // the values changed: only the addresses of the last search are searched again
+        chip8.write_memory(0x300, 0xA6);
+        chip8.write_memory(0x330, 0xA7);
+        assert_eq!(
+            Command::Refine(vec![0xA7]).execute(&mut chip8, &mut found),
+            Ok(String::from("2 found: 0x310 0x320"))
+        );
+        Command::Refine(vec![0xA7, 0x01])
+            .execute(&mut chip8, &mut found)
+            .unwrap();
+        assert_eq!(found, [0x310]);
+    }
+
+    #[test]
+    fn only_the_first_addresses_found_are_printed() {
+        let found: Vec<u16> = (0..20).collect();
+        let output = format_found(&found);
+        assert!(output.starts_with("20 found: 0x000 0x001"));
+        assert!(output.ends_with("0x00F ..."));
+    }
+}
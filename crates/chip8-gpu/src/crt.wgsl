@@ -0,0 +1,68 @@
+// CHIP-8 display shader: draws the display texture on a fullscreen triangle,
+// with optional CRT post-processing effects (scanlines, curvature, bloom)
+
+struct Params {
+    // x: scanlines, y: curvature, z: bloom (effect enabled if > 0.5)
+    effects: vec4<f32>,
+    // xy: display texture size (pixels)
+    size: vec4<f32>,
+};
+
+@group(0) @binding(0) var display_texture: texture_2d<f32>;
+@group(0) @binding(1) var display_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // fullscreen triangle: (0, 0), (2, 0), (0, 2)
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var uv = in.uv;
+
+    // curvature: push the coordinates outwards, more at the corners
+    if params.effects.y > 0.5 {
+        let centered = uv * 2.0 - 1.0;
+        let distortion = centered.yx * centered.yx * 0.06;
+        uv = (centered + centered * distortion) * 0.5 + 0.5;
+        if uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 {
+            return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+
+    var color = textureSampleLevel(display_texture, display_sampler, uv, 0.0).rgb;
+
+    // bloom: add the blurred neighbourhood of the pixel
+    if params.effects.z > 0.5 {
+        let texel = 1.0 / params.size.xy;
+        var glow = vec3<f32>(0.0);
+        for (var dx = -2; dx <= 2; dx++) {
+            for (var dy = -2; dy <= 2; dy++) {
+                let offset = vec2<f32>(f32(dx), f32(dy)) * texel;
+                glow += textureSampleLevel(display_texture, display_sampler, uv + offset, 0.0).rgb;
+            }
+        }
+        color += glow / 25.0 * 0.5;
+    }
+
+    // scanlines: darken the lower part of every display row
+    if params.effects.x > 0.5 {
+        if fract(uv.y * params.size.y) > 0.65 {
+            color *= 0.55;
+        }
+    }
+
+    return vec4<f32>(color, 1.0);
+}
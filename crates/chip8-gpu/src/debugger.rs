@@ -0,0 +1,1619 @@
+//! Graphical debugger frontend (egui window with the display and the debugger panels)
+
+use crate::commands::Command;
+use crate::gpu::{self, GpuWindow};
+use chip8_core::{
+    disassemble, AccessKind, Chip8, Instruction, DISPLAY_HEIGTH, DISPLAY_WIDTH, MAX_DISPLAY_SIZE,
+};
+use chip8_frontend::{EmulationStatus, Ghosting, Hotkey, Keymap, Palette, Renderer, KEYPAD_LAYOUT};
+use egui::{Color32, ColorImage, RichText, TextureHandle, TextureOptions};
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, trace, warn};
+use winit::{
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    platform::pump_events::EventLoopExtPumpEvents,
+};
+
+/// initial window width
+const WINDOW_WIDTH: u32 = 1280;
+
+/// initial window height
+const WINDOW_HEIGHT: u32 = 800;
+
+/// minimum time between two window redraws while the emulation is paused (60 fps)
+const PAUSED_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+/// number of instructions shown in the disassembly panel before the current one
+const DISASSEMBLY_BEFORE: u16 = 8;
+
+/// number of instructions shown in the disassembly panel
+const DISASSEMBLY_LINES: u16 = 32;
+
+/// number of bytes shown in every row of the memory panel
+const MEMORY_ROW_SIZE: usize = 16;
+
+/// number of rows shown in the sprite panel when the current instruction is not a DXYN
+const SPRITE_ROWS: usize = 15;
+
+/// number of memory accesses shown in the accesses panel (the most recent ones)
+const ACCESS_LINES: usize = 256;
+
+/// number of lines kept in the console panel
+const CONSOLE_LINES: usize = 100;
+
+/// size of the pixels of the sprite panel (points)
+const SPRITE_PIXEL_SIZE: f32 = 12.0;
+
+/// highlight color of the current instruction and of the keys checked by it
+const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(0xFF, 0xC0, 0x40);
+
+/// color of the breakpoint markers
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(0xE0, 0x40, 0x40);
+
+/// Visibility of the debugger panels
+struct Panels {
+    registers: bool,
+    memory: bool,
+    disassembly: bool,
+    breakpoints: bool,
+    sprite: bool,
+    keypad: bool,
+    accesses: bool,
+    watches: bool,
+    console: bool,
+}
+
+/// Events that pause the emulation before the instruction that triggers them
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct EventBreaks {
+    /// Sprite drawing (DXYN)
+    draw: bool,
+    /// Display clearing (00E0)
+    clear: bool,
+    /// Sound timer setting (FX18)
+    sound: bool,
+    /// Keypad checks (EX9E, EXA1, FX0A)
+    keys: bool,
+}
+
+impl EventBreaks {
+    /// Returns the name of the event of the opcode, if the emulation must pause on it
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - The opcode of the next instruction
+    fn event(&self, opcode: u16) -> Option<&'static str> {
+        match opcode {
+            _ if self.draw && opcode & 0xF000 == 0xD000 => Some("draw"),
+            0x00E0 if self.clear => Some("clear"),
+            _ if self.sound && opcode & 0xF0FF == 0xF018 => Some("sound"),
+            _ if self.keys && matches!(opcode & 0xF0FF, 0xE09E | 0xE0A1 | 0xF00A) => Some("key"),
+            _ => None,
+        }
+    }
+}
+
+/// Registers watched for changes: the emulation pauses after an instruction that changes their value
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Watches {
+    /// Watched registers V0-VF
+    v: [bool; 16],
+    /// Boolean set to true if I is watched
+    i: bool,
+}
+
+impl Watches {
+    /// Returns the changes of the watched registers made by an instruction (e.g. `V3: 0x05 -> 0x06`)
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - The state before the instruction
+    /// * `after` - The state after the instruction
+    fn changes(&self, before: &Chip8, after: &Chip8) -> Vec<String> {
+        let mut changes: Vec<_> = (0..16)
+            .filter(|&index| self.v[index] && before.v()[index] != after.v()[index])
+            .map(|index| {
+                format!(
+                    "V{index:X}: {:#04X} -> {:#04X}",
+                    before.v()[index],
+                    after.v()[index]
+                )
+            })
+            .collect();
+        if self.i && before.i() != after.i() {
+            changes.push(format!("I: {:#05X} -> {:#05X}", before.i(), after.i()));
+        }
+
+        changes
+    }
+}
+
+/// Debugger setup of a ROM, kept across debugging sessions in a TOML file
+/// (`<sha1>.toml` in the session directory, keyed by the SHA-1 hash of the ROM)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Session {
+    /// Breakpoint addresses
+    breakpoints: BTreeSet<u16>,
+    /// Events that pause the emulation
+    break_on: EventBreaks,
+    /// Registers watched for changes
+    watches: Watches,
+}
+
+impl Session {
+    /// Returns the path of the session file of a ROM (`<sha1>.toml` in the session directory)
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory of the session files
+    /// * `rom` - The ROM bytes
+    fn path(dir: &Path, rom: &[u8]) -> PathBuf {
+        dir.join(format!("{}.toml", Sha1::from(rom).digest()))
+    }
+
+    /// Reads a session file: returns None if the file does not exist
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the session file
+    fn read(path: &Path) -> Result<Option<Self>, String> {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| format!("parsing debugger session file `{}`: {e}", path.display()))
+    }
+
+    /// Writes the session to a file, creating its directory if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the session file
+    fn write(&self, path: &Path) -> Result<(), String> {
+        let contents =
+            toml::to_string(self).map_err(|e| format!("serializing debugger session: {e}"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                format!(
+                    "creating debugger session directory `{}`: {e}",
+                    dir.display()
+                )
+            })?;
+        }
+        fs::write(path, contents)
+            .map_err(|e| format!("writing debugger session file `{}`: {e}", path.display()))
+    }
+}
+
+/// Renderer that draws the CHIP-8 display in a window together with the debugger panels
+/// (registers, memory hex view, disassembly, breakpoints, keypad, memory accesses, console).
+/// The registers and memory can be patched with the commands of the console (see `commands`).
+/// The emulation can be paused (F5), stepped one instruction or one frame (F6) at a time,
+/// stopped on breakpoints, on events (draw, clear, sound, keys) or on changes of watched registers, slowed down (F7), reset (F8), muted (F9) and fast-forwarded holding Tab.
+/// Fullscreen is toggled with F11
+pub struct DebuggerRenderer {
+    /// Mapping of the CHIP-8 keypad to the keyboard
+    keymap: Keymap,
+
+    /// CHIP-8 keys held down (bit n set if key n is pressed)
+    keys: u16,
+
+    /// Boolean set to true while the fast-forward key is held down
+    fast_forward: bool,
+
+    /// Hotkeys pressed since the last call of `hotkeys` (the pause is handled by the debugger)
+    hotkeys: Vec<Hotkey>,
+
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// Window and GPU device
+    gpu: GpuWindow,
+
+    /// egui context
+    context: egui::Context,
+
+    /// egui window integration (input events)
+    state: egui_winit::State,
+
+    /// egui GPU renderer
+    egui_renderer: egui_wgpu::Renderer,
+
+    /// Display texture (allocated at the first frame)
+    texture: Option<TextureHandle>,
+
+    /// Last rendered display pixels
+    display: [bool; MAX_DISPLAY_SIZE],
+
+    /// CHIP-8 state before the next instruction (None until the emulation starts)
+    machine: Option<Chip8>,
+
+    /// Boolean set to true if the emulation is paused
+    paused: bool,
+
+    /// Boolean set to true to pause the emulation while the window is not focused
+    pause_unfocused: bool,
+
+    /// Boolean set to true if the emulation has been paused because the window lost the focus
+    focus_paused: bool,
+
+    /// Boolean set to true if the next instruction must be executed while paused
+    step: bool,
+
+    /// Boolean set to true if the instructions until the next timer tick must be executed while paused
+    frame_advance: bool,
+
+    /// Address the execution has just been resumed from (its breakpoint must not stop the execution again)
+    resumed_at: Option<u16>,
+
+    /// Breakpoint addresses
+    breakpoints: BTreeSet<u16>,
+
+    /// Events that pause the emulation
+    event_breaks: EventBreaks,
+
+    /// Registers watched for changes
+    watches: Watches,
+
+    /// Last change of the watched registers, with the instruction responsible for it
+    watch_hit: Option<String>,
+
+    /// Contents of the console command field
+    command_input: String,
+
+    /// Console commands to execute on the machine by `control`, with their command lines
+    commands: Vec<(String, Command)>,
+
+    /// Lines of the console panel: the commands and their output, from the oldest one
+    console: VecDeque<String>,
+
+    /// Addresses found by the last search of the console (narrowed by `refine`)
+    found: Vec<u16>,
+
+    /// Contents of the new breakpoint address field
+    breakpoint_input: String,
+
+    /// Address of the sprite panel (None: the address in I)
+    sprite_address: Option<u16>,
+
+    /// Contents of the sprite address field
+    sprite_input: String,
+
+    /// Traced address ranges to add (true) or remove (false), applied to the machine by `control`
+    trace_changes: Vec<(RangeInclusive<u16>, bool)>,
+
+    /// Boolean set to true if the recorded memory accesses must be cleared by `control`
+    clear_accesses: bool,
+
+    /// Contents of the new traced address range field
+    trace_input: String,
+
+    /// Address range of the accesses shown in the accesses panel (None: all the accesses)
+    access_filter: Option<RangeInclusive<u16>>,
+
+    /// Contents of the accesses filter field
+    access_filter_input: String,
+
+    /// Visibility of the debugger panels
+    panels: Panels,
+
+    /// Directory of the session files (None: the debugger setup is not kept across sessions)
+    session_dir: Option<PathBuf>,
+
+    /// Session file of the loaded ROM (None until the ROM is known)
+    session_path: Option<PathBuf>,
+
+    /// Debugger setup last loaded from or saved to the session file
+    saved_session: Session,
+
+    /// Last emulation status shown in the toolbar (None if the status bar is disabled)
+    status: Option<EmulationStatus>,
+
+    /// Instant of the last window redraw
+    last_frame: Instant,
+
+    /// Boolean set to true if the window has been closed
+    quit: bool,
+}
+
+impl DebuggerRenderer {
+    /// Returns a new debugger renderer, opening its window
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The colors used to draw the pixels
+    /// * `ghosting` - The number of frames a pixel takes to fade out (0 disables ghosting)
+    /// * `keymap` - The mapping of the CHIP-8 keypad to the keyboard
+    /// * `status_bar` - Boolean set to true to show the emulation status in the toolbar
+    /// * `fullscreen` - Boolean set to true to open the window in fullscreen
+    /// * `pause_unfocused` - Boolean set to true to pause the emulation while the window is not focused
+    /// * `session_dir` - The directory of the session files, where the breakpoints, the event breaks
+    ///   and the watches are saved per ROM (None to not keep them across sessions)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the window or the GPU device cannot be created
+    pub fn new(
+        palette: Palette,
+        ghosting: u8,
+        keymap: Keymap,
+        status_bar: bool,
+        fullscreen: bool,
+        pause_unfocused: bool,
+        session_dir: Option<PathBuf>,
+    ) -> Self {
+        trace!("DebuggerRenderer::new: start");
+
+        let gpu = GpuWindow::new(WINDOW_WIDTH, WINDOW_HEIGHT, fullscreen);
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            &gpu.window,
+            Some(gpu.window.scale_factor() as f32),
+            None,
+        );
+        let egui_renderer = egui_wgpu::Renderer::new(&gpu.device, gpu.config.format, None, 1);
+
+        trace!("DebuggerRenderer::new: exit");
+
+        Self {
+            keymap,
+            keys: 0,
+            fast_forward: false,
+            hotkeys: Vec::new(),
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            gpu,
+            context,
+            state,
+            egui_renderer,
+            texture: None,
+            display: [false; MAX_DISPLAY_SIZE],
+            machine: None,
+            paused: false,
+            pause_unfocused,
+            focus_paused: false,
+            step: false,
+            frame_advance: false,
+            resumed_at: None,
+            breakpoints: BTreeSet::new(),
+            event_breaks: EventBreaks::default(),
+            watches: Watches::default(),
+            watch_hit: None,
+            command_input: String::new(),
+            commands: Vec::new(),
+            console: VecDeque::with_capacity(CONSOLE_LINES),
+            found: Vec::new(),
+            breakpoint_input: String::new(),
+            sprite_address: None,
+            sprite_input: String::new(),
+            trace_changes: Vec::new(),
+            clear_accesses: false,
+            trace_input: String::new(),
+            access_filter: None,
+            access_filter_input: String::new(),
+            panels: Panels {
+                registers: true,
+                memory: true,
+                disassembly: true,
+                breakpoints: true,
+                sprite: true,
+                keypad: true,
+                accesses: true,
+                watches: true,
+                console: true,
+            },
+            session_dir,
+            session_path: None,
+            saved_session: Session::default(),
+            status: status_bar.then(EmulationStatus::default),
+            last_frame: Instant::now(),
+            quit: false,
+        }
+    }
+
+    /// Handles the pending window events, forwarding them to egui
+    fn handle_events(&mut self) {
+        let mut resized = None;
+        let mut pressed = Vec::new();
+        let mut focused = None;
+        let mut quit = false;
+
+        let GpuWindow {
+            event_loop, window, ..
+        } = &mut self.gpu;
+        let state = &mut self.state;
+        let (keymap, keys, fast_forward) = (&self.keymap, &mut self.keys, &mut self.fast_forward);
+        event_loop.pump_events(Some(Duration::ZERO), |event, _| {
+            if let Event::WindowEvent {
+                window_id: id,
+                event,
+            } = event
+            {
+                if id != window.id() {
+                    return;
+                }
+                let response = state.on_window_event(window, &event);
+                match event {
+                    WindowEvent::CloseRequested => quit = true,
+                    WindowEvent::Resized(size) => resized = Some(size),
+                    WindowEvent::Focused(focus) => focused = Some(focus),
+                    // keys typed in the text fields of the panels are not sent to the keypad
+                    // (releases are, so that no key stays held down)
+                    WindowEvent::KeyboardInput { event, .. }
+                        if !response.consumed || event.state == ElementState::Released =>
+                    {
+                        gpu::update_keypad(keys, keymap, &event);
+                        if event.physical_key == PhysicalKey::Code(KeyCode::Tab) {
+                            *fast_forward = event.state == ElementState::Pressed;
+                        }
+                        if let KeyEvent {
+                            physical_key: PhysicalKey::Code(code),
+                            state: ElementState::Pressed,
+                            repeat: false,
+                            ..
+                        } = event
+                        {
+                            pressed.push(code);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        for code in pressed {
+            match code {
+                KeyCode::F5 => self.toggle_pause(),
+                KeyCode::F6 if self.paused => self.frame_advance = true,
+                KeyCode::F7 => self.hotkeys.push(Hotkey::ToggleSlowMotion),
+                KeyCode::F8 => self.hotkeys.push(Hotkey::Reset),
+                KeyCode::F9 => self.hotkeys.push(Hotkey::ToggleMute),
+                KeyCode::F11 => self.gpu.toggle_fullscreen(),
+                _ => {}
+            }
+        }
+
+        if let Some(size) = resized {
+            self.gpu.resize(size);
+        }
+
+        // the pause when unfocused does not resume an emulation paused by the user
+        match focused {
+            Some(false) if self.pause_unfocused && !self.paused => {
+                info!("emulation paused: window unfocused");
+                self.paused = true;
+                self.focus_paused = true;
+            }
+            Some(true) if self.focus_paused => {
+                info!("emulation resumed: window focused");
+                self.toggle_pause();
+            }
+            _ => {}
+        }
+
+        self.quit |= quit;
+    }
+
+    /// Builds the debugger user interface and draws it in the window
+    fn draw(&mut self) {
+        self.handle_events();
+        self.last_frame = Instant::now();
+
+        let input = self.state.take_egui_input(&self.gpu.window);
+        let context = self.context.clone();
+        let output = context.run(input, |ctx| self.ui(ctx));
+        self.state
+            .handle_platform_output(&self.gpu.window, output.platform_output);
+        let jobs = context.tessellate(output.shapes, output.pixels_per_point);
+
+        // textures are updated even if the frame is not drawn, so that no update is lost
+        for (id, delta) in &output.textures_delta.set {
+            self.egui_renderer
+                .update_texture(&self.gpu.device, &self.gpu.queue, *id, delta);
+        }
+        if let Some(frame) = self.gpu.frame() {
+            self.draw_frame(frame, &jobs, output.pixels_per_point);
+        }
+        for id in &output.textures_delta.free {
+            self.egui_renderer.free_texture(id);
+        }
+
+        self.save_session();
+    }
+
+    /// Returns the debugger setup to keep across sessions
+    fn session(&self) -> Session {
+        Session {
+            breakpoints: self.breakpoints.clone(),
+            break_on: self.event_breaks.clone(),
+            watches: self.watches.clone(),
+        }
+    }
+
+    /// Restores the debugger setup saved for the loaded ROM, if any
+    /// (errors are logged: they do not stop the emulator)
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    fn load_session(&mut self, chip8: &Chip8) {
+        let Some(dir) = self.session_dir.as_ref() else {
+            return;
+        };
+        let path = Session::path(dir, chip8.rom());
+
+        match Session::read(&path) {
+            Ok(Some(session)) => {
+                info!(
+                    "debugger session restored from `{}`: {} breakpoints",
+                    path.display(),
+                    session.breakpoints.len()
+                );
+                self.breakpoints = session.breakpoints.clone();
+                self.event_breaks = session.break_on.clone();
+                self.watches = session.watches.clone();
+                self.saved_session = session;
+            }
+            Ok(None) => debug!("debugger session file not found: {}", path.display()),
+            Err(e) => warn!("{e}"),
+        }
+        self.session_path = Some(path);
+    }
+
+    /// Writes the debugger setup to the session file of the loaded ROM, if it changed
+    /// (errors are logged: they do not stop the emulator)
+    fn save_session(&mut self) {
+        let Some(path) = self.session_path.as_ref() else {
+            return;
+        };
+        let session = self.session();
+        if session == self.saved_session {
+            return;
+        }
+
+        match session.write(path) {
+            Ok(()) => debug!("debugger session saved to `{}`", path.display()),
+            Err(e) => warn!("{e}"),
+        }
+        // not retried at every frame if the write failed
+        self.saved_session = session;
+    }
+
+    /// Draws the tessellated user interface on a window surface texture
+    fn draw_frame(
+        &mut self,
+        frame: wgpu::SurfaceTexture,
+        jobs: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) {
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.gpu.config.width, self.gpu.config.height],
+            pixels_per_point,
+        };
+
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let commands = self.egui_renderer.update_buffers(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &mut encoder,
+            jobs,
+            &screen,
+        );
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("debugger"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.egui_renderer.render(&mut pass, jobs, &screen);
+        }
+        self.gpu
+            .queue
+            .submit(commands.into_iter().chain(Some(encoder.finish())));
+        frame.present();
+    }
+
+    /// Builds the debugger user interface: toolbar, display and panels
+    fn ui(&mut self, ctx: &egui::Context) {
+        self.toolbar_ui(ctx);
+        self.display_ui(ctx);
+
+        let Some(machine) = self.machine.take() else {
+            return;
+        };
+
+        let mut open = self.panels.registers;
+        egui::Window::new("Registers")
+            .open(&mut open)
+            .show(ctx, |ui| registers_ui(ui, &machine));
+        self.panels.registers = open;
+
+        let mut open = self.panels.disassembly;
+        egui::Window::new("Disassembly")
+            .open(&mut open)
+            .show(ctx, |ui| self.disassembly_ui(ui, &machine));
+        self.panels.disassembly = open;
+
+        let mut open = self.panels.memory;
+        egui::Window::new("Memory")
+            .open(&mut open)
+            .show(ctx, |ui| memory_ui(ui, &machine));
+        self.panels.memory = open;
+
+        let mut open = self.panels.breakpoints;
+        egui::Window::new("Breakpoints")
+            .open(&mut open)
+            .show(ctx, |ui| self.breakpoints_ui(ui));
+        self.panels.breakpoints = open;
+
+        let mut open = self.panels.sprite;
+        egui::Window::new("Sprite")
+            .open(&mut open)
+            .show(ctx, |ui| self.sprite_ui(ui, &machine));
+        self.panels.sprite = open;
+
+        let mut open = self.panels.keypad;
+        egui::Window::new("Keypad")
+            .open(&mut open)
+            .show(ctx, |ui| keypad_ui(ui, &machine));
+        self.panels.keypad = open;
+
+        let mut open = self.panels.accesses;
+        egui::Window::new("Accesses")
+            .open(&mut open)
+            .show(ctx, |ui| self.accesses_ui(ui, &machine));
+        self.panels.accesses = open;
+
+        let mut open = self.panels.watches;
+        egui::Window::new("Watches")
+            .open(&mut open)
+            .show(ctx, |ui| self.watches_ui(ui));
+        self.panels.watches = open;
+
+        let mut open = self.panels.console;
+        egui::Window::new("Console")
+            .open(&mut open)
+            .show(ctx, |ui| self.console_ui(ui));
+        self.panels.console = open;
+
+        self.machine = Some(machine);
+    }
+
+    /// Pauses the emulation, or resumes it from the current instruction if paused
+    fn toggle_pause(&mut self) {
+        self.focus_paused = false;
+        if self.paused {
+            self.paused = false;
+            self.resumed_at = self.machine.as_ref().map(Chip8::pc);
+        } else {
+            self.paused = true;
+        }
+    }
+
+    /// Builds the toolbar: execution controls and panels visibility
+    fn toolbar_ui(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.panels.registers, "Registers");
+                    ui.checkbox(&mut self.panels.disassembly, "Disassembly");
+                    ui.checkbox(&mut self.panels.memory, "Memory");
+                    ui.checkbox(&mut self.panels.breakpoints, "Breakpoints");
+                    ui.checkbox(&mut self.panels.sprite, "Sprite");
+                    ui.checkbox(&mut self.panels.keypad, "Keypad");
+                    ui.checkbox(&mut self.panels.accesses, "Accesses");
+                    ui.checkbox(&mut self.panels.watches, "Watches");
+                    ui.checkbox(&mut self.panels.console, "Console");
+                });
+                ui.separator();
+
+                if self.paused {
+                    if ui.button("▶ Continue").clicked() {
+                        self.toggle_pause();
+                    }
+                    if ui.button("⏭ Step").clicked() {
+                        self.step = true;
+                    }
+                    if ui.button("⏩ Frame").clicked() {
+                        self.frame_advance = true;
+                    }
+                } else if ui.button("⏸ Pause").clicked() {
+                    self.toggle_pause();
+                }
+                if ui.button("⟲ Reset").clicked() {
+                    self.hotkeys.push(Hotkey::Reset);
+                }
+                match self.status {
+                    // the rates are not updated while the debugger holds the execution
+                    Some(status) => ui.label(
+                        EmulationStatus {
+                            paused: self.paused,
+                            ..status
+                        }
+                        .to_string(),
+                    ),
+                    None => ui.label(if self.paused { "paused" } else { "running" }),
+                };
+            });
+        });
+    }
+
+    /// Builds the display panel: the display is scaled by the largest integer factor that fits the panel
+    fn display_ui(&mut self, ctx: &egui::Context) {
+        let mut image = ColorImage::new([DISPLAY_WIDTH, DISPLAY_HEIGTH], Color32::BLACK);
+        for (index, pixel) in image.pixels.iter_mut().enumerate() {
+            let (r, g, b) = self
+                .palette
+                .blend(self.ghosting.intensity(&self.display, index))
+                .to_rgb();
+            *pixel = Color32::from_rgb(r, g, b);
+        }
+        let texture = match self.texture.as_mut() {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => {
+                self.texture
+                    .insert(ctx.load_texture("display", image, TextureOptions::NEAREST))
+            }
+        };
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let available = ui.available_size();
+            let scale = (available.x / DISPLAY_WIDTH as f32)
+                .min(available.y / DISPLAY_HEIGTH as f32)
+                .floor()
+                .max(1.0);
+            let size = egui::vec2(DISPLAY_WIDTH as f32, DISPLAY_HEIGTH as f32) * scale;
+            ui.centered_and_justified(|ui| {
+                ui.add(egui::Image::new(egui::load::SizedTexture::new(
+                    texture.id(),
+                    size,
+                )));
+            });
+        });
+    }
+
+    /// Builds the disassembly panel: the instructions around the PC, with breakpoint toggles
+    fn disassembly_ui(&mut self, ui: &mut egui::Ui, machine: &Chip8) {
+        let pc = machine.pc();
+        let start = pc.saturating_sub(DISASSEMBLY_BEFORE * 2);
+
+        egui::Grid::new("disassembly").show(ui, |ui| {
+            let mut address = start;
+            for _ in 0..DISASSEMBLY_LINES {
+                let breakpoint = self.breakpoints.contains(&address);
+                let marker =
+                    RichText::new(if breakpoint { "●" } else { "○" }).color(if breakpoint {
+                        BREAKPOINT_COLOR
+                    } else {
+                        Color32::GRAY
+                    });
+                if ui.small_button(marker).clicked() {
+                    self.toggle_breakpoint(address);
+                }
+
+                // F000 (XO-CHIP) is followed by its 16-bit address
+                let opcode = machine.opcode_at(address);
+                let size = Instruction::decode(opcode).size();
+                let operand = if size == 4 {
+                    format!(" {:04X}", machine.opcode_at(address.wrapping_add(2)))
+                } else {
+                    String::new()
+                };
+                let mut line = RichText::new(format!(
+                    "{address:#05X}  {opcode:04X}{operand}  {}",
+                    disassemble(opcode)
+                ))
+                .monospace();
+                if address == pc {
+                    line = line.color(HIGHLIGHT_COLOR);
+                }
+                ui.label(line);
+                ui.end_row();
+
+                address = address.wrapping_add(size);
+            }
+        });
+    }
+
+    /// Builds the breakpoints panel: the breakpoints list, a field to add a new breakpoint
+    /// and the toggles of the events that pause the emulation
+    fn breakpoints_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Break on");
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.event_breaks.draw, "draw (DXYN)");
+            ui.checkbox(&mut self.event_breaks.clear, "clear (00E0)");
+            ui.checkbox(&mut self.event_breaks.sound, "sound (FX18)");
+            ui.checkbox(&mut self.event_breaks.keys, "keys (EX9E/EXA1/FX0A)");
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.breakpoint_input)
+                    .hint_text("address (hex)")
+                    .desired_width(100.0),
+            );
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || submitted {
+                let address = self.breakpoint_input.trim();
+                let address = address
+                    .strip_prefix("0x")
+                    .or(address.strip_prefix("0X"))
+                    .unwrap_or(address);
+                if let Ok(address) = u16::from_str_radix(address, 16) {
+                    self.breakpoints.insert(address);
+                    self.breakpoint_input.clear();
+                }
+            }
+        });
+        ui.separator();
+
+        if self.breakpoints.is_empty() {
+            ui.label("no breakpoints");
+        }
+        let mut removed = None;
+        for address in &self.breakpoints {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("{address:#05X}"));
+                if ui.small_button("✖").clicked() {
+                    removed = Some(*address);
+                }
+            });
+        }
+        if let Some(address) = removed {
+            self.breakpoints.remove(&address);
+        }
+    }
+
+    /// Builds the sprite panel: the bytes at I (or at the address of the field) drawn as a sprite,
+    /// with the rows that the DXYN at PC is about to draw
+    fn sprite_ui(&mut self, ui: &mut egui::Ui, machine: &Chip8) {
+        ui.horizontal(|ui| {
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.sprite_input)
+                    .hint_text("address (hex)")
+                    .desired_width(100.0),
+            );
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Show").clicked() || submitted {
+                let address = self.sprite_input.trim();
+                let address = address
+                    .strip_prefix("0x")
+                    .or(address.strip_prefix("0X"))
+                    .unwrap_or(address);
+                self.sprite_address = u16::from_str_radix(address, 16).ok();
+            }
+            if ui.button("Follow I").clicked() {
+                self.sprite_address = None;
+                self.sprite_input.clear();
+            }
+        });
+
+        let (address, rows, description) = sprite_source(machine, self.sprite_address);
+        ui.label(description);
+        ui.separator();
+
+        let memory = machine.memory();
+        for row in 0..rows {
+            let row_address = (address as usize + row) % memory.len();
+            let byte = memory[row_address];
+            ui.horizontal(|ui| {
+                ui.monospace(format!("{row_address:#05X}  {byte:02X}"));
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(8.0 * SPRITE_PIXEL_SIZE, SPRITE_PIXEL_SIZE),
+                    egui::Sense::hover(),
+                );
+                let painter = ui.painter_at(rect);
+                painter.rect_filled(rect, 0.0, Color32::BLACK);
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        let pixel = egui::Rect::from_min_size(
+                            rect.min + egui::vec2(bit as f32 * SPRITE_PIXEL_SIZE, 0.0),
+                            egui::vec2(SPRITE_PIXEL_SIZE - 1.0, SPRITE_PIXEL_SIZE - 1.0),
+                        );
+                        painter.rect_filled(pixel, 0.0, Color32::WHITE);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Builds the accesses panel: the traced address ranges, a field to trace a new range
+    /// and the most recent memory accesses recorded (filtered by an address range)
+    fn accesses_ui(&mut self, ui: &mut egui::Ui, machine: &Chip8) {
+        ui.horizontal(|ui| {
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.trace_input)
+                    .hint_text("range (hex)")
+                    .desired_width(100.0),
+            );
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Trace").clicked() || submitted {
+                if let Some(range) = parse_range(&self.trace_input) {
+                    self.trace_changes.push((range, true));
+                    self.trace_input.clear();
+                }
+            }
+        });
+        if machine.traced_memory().is_empty() {
+            ui.label("no traced ranges");
+        }
+        for range in machine.traced_memory() {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("{:#05X}-{:#05X}", range.start(), range.end()));
+                if ui.small_button("✖").clicked() {
+                    self.trace_changes.push((range.clone(), false));
+                }
+            });
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.access_filter_input)
+                    .hint_text("filter (hex)")
+                    .desired_width(100.0),
+            );
+            if input.changed() {
+                self.access_filter = parse_range(&self.access_filter_input);
+            }
+            if ui.button("Clear").clicked() {
+                self.clear_accesses = true;
+            }
+        });
+
+        let accesses: Vec<_> = machine
+            .memory_accesses()
+            .rev()
+            .filter(|access| {
+                self.access_filter
+                    .as_ref()
+                    .is_none_or(|range| range.contains(&access.address))
+            })
+            .take(ACCESS_LINES)
+            .collect();
+        if accesses.is_empty() {
+            ui.label("no accesses");
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("accesses").striped(true).show(ui, |ui| {
+                for access in accesses {
+                    ui.monospace(access.cycle.to_string());
+                    ui.monospace(format!("{:#05X}", access.pc));
+                    ui.label(match access.kind {
+                        AccessKind::Read => RichText::new("R").monospace(),
+                        AccessKind::Write => RichText::new("W").monospace().color(HIGHLIGHT_COLOR),
+                    });
+                    ui.monospace(format!("{:#05X}", access.address));
+                    ui.monospace(format!("{:#04X}", access.value));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Builds the watches panel: the toggles of the watched registers and the last change of their values
+    fn watches_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("watches").show(ui, |ui| {
+            for (index, watched) in self.watches.v.iter_mut().enumerate() {
+                ui.checkbox(watched, format!("V{index:X}"));
+                if index % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+            ui.checkbox(&mut self.watches.i, "I");
+            ui.end_row();
+        });
+        ui.separator();
+
+        ui.label(match &self.watch_hit {
+            Some(hit) => RichText::new(hit).monospace().color(HIGHLIGHT_COLOR),
+            None => RichText::new("no changes"),
+        });
+    }
+
+    /// Builds the console panel: the commands executed with their output, and the command field
+    fn console_ui(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &self.console {
+                    ui.monospace(line);
+                }
+            });
+        ui.separator();
+
+        let input = ui.add(
+            egui::TextEdit::singleline(&mut self.command_input)
+                .hint_text("command (e.g. set V3 0x1F, poke 0x400 0xAA BB, find 03)")
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY),
+        );
+        if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            let line = std::mem::take(&mut self.command_input);
+            match line.parse() {
+                Ok(command) => self.commands.push((line, command)),
+                Err(e) => {
+                    self.print(format!("> {line}"));
+                    self.print(format!("error: {e}"));
+                }
+            }
+            input.request_focus();
+        }
+    }
+
+    /// Adds a line to the console panel, discarding the oldest one if full
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to add
+    fn print(&mut self, line: String) {
+        if self.console.len() == CONSOLE_LINES {
+            self.console.pop_front();
+        }
+        self.console.push_back(line);
+    }
+
+    /// Adds a breakpoint at the given address, or removes it if already set
+    fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+}
+
+impl Renderer for DebuggerRenderer {
+    fn render(&mut self, display: &[bool], _dirty_rows: u64) {
+        trace!("DebuggerRenderer::render: start");
+
+        // the window is redrawn at the next tick (at most 60 times per second)
+        self.ghosting.update(display);
+        self.display.copy_from_slice(display);
+
+        trace!("DebuggerRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        // the advanced frame ends with the timer tick
+        self.frame_advance = false;
+
+        self.ghosting.fade(display);
+        self.display.copy_from_slice(display);
+        self.draw();
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        if let Some(shown) = self.status.as_mut() {
+            *shown = *status;
+        }
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        let pc = chip8.pc();
+
+        // the changes of the watched registers are made by the previous instruction
+        if let Some(before) = self.machine.as_ref() {
+            let changes = self.watches.changes(before, chip8);
+            if !changes.is_empty() {
+                let opcode = before.opcode_at(before.pc());
+                let hit = format!(
+                    "{} by {:#05X}: {opcode:04X} {}",
+                    changes.join(", "),
+                    before.pc(),
+                    disassemble(opcode)
+                );
+                info!("watch: {hit}");
+                self.watch_hit = Some(hit);
+                self.paused = true;
+            }
+        }
+
+        match self.machine.as_mut() {
+            Some(machine) => machine.clone_from(chip8),
+            None => self.machine = Some(chip8.clone()),
+        }
+
+        // stop on breakpoints and events, unless the execution has just been resumed from the same address
+        if !self.paused && self.resumed_at != Some(pc) {
+            if let Some(hit) = break_hit(&self.breakpoints, &self.event_breaks, chip8) {
+                info!("{hit}");
+                self.paused = true;
+            }
+        }
+        self.resumed_at = None;
+
+        if !self.paused {
+            return true;
+        }
+
+        // while paused, the window is redrawn here (timers do not tick)
+        if self.last_frame.elapsed() >= PAUSED_FRAME_TIME {
+            self.draw();
+        }
+
+        if self.step {
+            self.step = false;
+            self.resumed_at = Some(pc);
+            return true;
+        }
+
+        !self.paused || self.frame_advance
+    }
+
+    fn control(&mut self, chip8: &mut Chip8) {
+        // the debugger setup of the ROM is restored before the first instruction
+        if self.session_path.is_none() && !chip8.rom().is_empty() {
+            self.load_session(chip8);
+        }
+
+        // the pause of the machine (e.g. restored crash dump, control socket) becomes a pause of the debugger,
+        // so that the state can be inspected and stepped
+        if chip8.paused() {
+            chip8.resume();
+            self.paused = true;
+        }
+
+        // the traced ranges changed in the accesses panel
+        for (range, traced) in self.trace_changes.drain(..) {
+            if traced {
+                chip8.trace_memory(range);
+            } else {
+                chip8.untrace_memory(&range);
+            }
+        }
+        if std::mem::take(&mut self.clear_accesses) {
+            chip8.clear_memory_accesses();
+        }
+
+        // the commands of the console patch the machine
+        let commands = std::mem::take(&mut self.commands);
+        for (line, command) in &commands {
+            self.print(format!("> {line}"));
+            match command.execute(chip8, &mut self.found) {
+                Ok(output) => {
+                    info!("console: {line}: {output}");
+                    self.print(output);
+                }
+                Err(e) => self.print(format!("error: {e}")),
+            }
+        }
+        // the patched values are not changes made by the instructions (watches)
+        if !commands.is_empty() {
+            if let Some(machine) = self.machine.as_mut() {
+                machine.clone_from(chip8);
+            }
+        }
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        std::mem::take(&mut self.hotkeys)
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    fn has_keypad(&self) -> bool {
+        true
+    }
+
+    fn keypad(&self) -> u16 {
+        self.keys
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.quit
+    }
+}
+
+/// Returns the reason to stop before the instruction at PC: a breakpoint at PC,
+/// or an instruction of an event that breaks the execution. Returns None if the execution goes on
+///
+/// # Arguments
+///
+/// * `breakpoints` - The breakpoint addresses
+/// * `event_breaks` - The events that break the execution
+/// * `machine` - The CHIP-8 instance
+fn break_hit(
+    breakpoints: &BTreeSet<u16>,
+    event_breaks: &EventBreaks,
+    machine: &Chip8,
+) -> Option<String> {
+    let pc = machine.pc();
+    if breakpoints.contains(&pc) {
+        Some(format!("breakpoint hit at {pc:#05X}"))
+    } else {
+        event_breaks
+            .event(machine.opcode_at(pc))
+            .map(|event| format!("{event} event at {pc:#05X}"))
+    }
+}
+
+/// Builds the registers panel: V0-VF, I, PC, timers, last executed opcode and call stack
+/// (subroutine entry address and return address of every level, from the innermost one)
+fn registers_ui(ui: &mut egui::Ui, machine: &Chip8) {
+    egui::Grid::new("registers").striped(true).show(ui, |ui| {
+        for (index, value) in machine.v().iter().enumerate() {
+            ui.monospace(format!("V{index:X}"));
+            ui.monospace(format!("{value:#04X}"));
+            if index % 2 == 1 {
+                ui.end_row();
+            }
+        }
+        ui.monospace("I");
+        ui.monospace(format!("{:#05X}", machine.i()));
+        ui.monospace("PC");
+        ui.monospace(format!("{:#05X}", machine.pc()));
+        ui.end_row();
+        ui.monospace("DT");
+        ui.monospace(format!("{:#04X}", machine.delay_timer()));
+        ui.monospace("ST");
+        ui.monospace(format!("{:#04X}", machine.sound_timer()));
+        ui.end_row();
+        ui.monospace("SP");
+        ui.monospace(format!("{:#04X}", machine.stack().len()));
+        ui.monospace("OP");
+        ui.monospace(format!("{:04X}", machine.opcode()));
+        ui.end_row();
+    });
+    ui.separator();
+
+    ui.label("Call stack");
+    egui::Grid::new("call stack").striped(true).show(ui, |ui| {
+        ui.label("level");
+        ui.label("subroutine");
+        ui.label("return");
+        ui.end_row();
+        for (level, (subroutine, address)) in call_stack(machine).into_iter().enumerate().rev() {
+            ui.monospace(format!("{level:2}"));
+            ui.monospace(format!("{subroutine:#05X}"));
+            ui.monospace(format!("{address:#05X}"));
+            ui.end_row();
+        }
+    });
+    if machine.stack().is_empty() {
+        ui.label("no calls");
+    }
+}
+
+/// Returns the subroutine entry address and the return address of every level of the call stack,
+/// from the outermost one: every level holds the return address of a 2NNN call, the instruction after it
+///
+/// # Arguments
+///
+/// * `machine` - The CHIP-8 instance
+fn call_stack(machine: &Chip8) -> Vec<(u16, u16)> {
+    machine
+        .stack()
+        .iter()
+        .map(|&address| (machine.opcode_at(address.wrapping_sub(2)) & 0x0FFF, address))
+        .collect()
+}
+
+/// Returns the address, the number of rows and the description of the sprite shown by the sprite panel:
+/// the rows that the DXYN at PC is about to draw from I, or the default number of rows from the address
+///
+/// # Arguments
+///
+/// * `machine` - The CHIP-8 instance
+/// * `sprite_address` - The address of the sprite field, or None to follow I
+fn sprite_source(machine: &Chip8, sprite_address: Option<u16>) -> (u16, usize, String) {
+    // DXYN at PC draws N rows from I, with the top-left corner at (VX, VY)
+    let opcode = machine.opcode_at(machine.pc());
+    let draw = (opcode & 0xF000 == 0xD000 && opcode & 0x000F != 0).then(|| {
+        let x = machine.v()[((opcode & 0x0F00) >> 8) as usize];
+        let y = machine.v()[((opcode & 0x00F0) >> 4) as usize];
+        (x, y, (opcode & 0x000F) as usize)
+    });
+    let address = sprite_address.unwrap_or(machine.i());
+    match (sprite_address, draw) {
+        (None, Some((x, y, rows))) => (
+            address,
+            rows,
+            format!("{opcode:04X} draws {rows} rows from I at ({x}, {y})"),
+        ),
+        _ => (
+            address,
+            SPRITE_ROWS,
+            format!("{SPRITE_ROWS} rows from {address:#05X}"),
+        ),
+    }
+}
+
+/// Builds the memory panel: hex view of the whole RAM, with the bytes at PC and I highlighted
+fn memory_ui(ui: &mut egui::Ui, machine: &Chip8) {
+    let memory = machine.memory();
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let pc = machine.pc() as usize;
+    let i = machine.i() as usize;
+
+    egui::ScrollArea::vertical().show_rows(
+        ui,
+        row_height,
+        memory.len() / MEMORY_ROW_SIZE,
+        |ui, rows| {
+            for row in rows {
+                let start = row * MEMORY_ROW_SIZE;
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.monospace(format!("{start:#05X}"));
+                    for (offset, byte) in memory[start..start + MEMORY_ROW_SIZE].iter().enumerate()
+                    {
+                        let address = start + offset;
+                        let mut text = RichText::new(format!("{byte:02X}")).monospace();
+                        if address == pc || address == pc + 1 {
+                            text = text.color(HIGHLIGHT_COLOR);
+                        } else if address == i {
+                            text = text.color(BREAKPOINT_COLOR);
+                        }
+                        ui.label(text);
+                    }
+                });
+            }
+        },
+    );
+}
+
+/// Parses an inclusive address range in hex (e.g. `300-3FF`), or a single address (e.g. `300`)
+fn parse_range(s: &str) -> Option<RangeInclusive<u16>> {
+    let parse = |address: &str| {
+        let address = address.trim();
+        let digits = address
+            .strip_prefix("0x")
+            .or(address.strip_prefix("0X"))
+            .unwrap_or(address);
+        u16::from_str_radix(digits, 16).ok()
+    };
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => (parse(s)?, parse(s)?),
+    };
+
+    (start <= end).then_some(start..=end)
+}
+
+/// Builds the keypad panel: the hex keypad, with the keys held down drawn in reverse colors
+/// and the keys checked by the current instruction and read by the last FX0A highlighted
+fn keypad_ui(ui: &mut egui::Ui, machine: &Chip8) {
+    let opcode = machine.opcode_at(machine.pc());
+    // EX9E/EXA1 check the key in VX, FX0A waits for any key
+    let checked = match opcode & 0xF0FF {
+        0xE09E | 0xE0A1 => Some(machine.v()[((opcode & 0x0F00) >> 8) as usize] & 0x0F),
+        _ => None,
+    };
+    let waiting = opcode & 0xF0FF == 0xF00A;
+
+    egui::Grid::new("keypad").show(ui, |ui| {
+        for row in KEYPAD_LAYOUT {
+            for key in row {
+                let mut text = RichText::new(format!(" {key:X} ")).monospace().size(18.0);
+                if checked == Some(key) || waiting {
+                    text = text.color(HIGHLIGHT_COLOR);
+                } else if machine.last_waited_key() == Some(key) {
+                    text = text.color(BREAKPOINT_COLOR);
+                }
+                if machine.key_pressed(key) {
+                    text = text.background_color(Color32::DARK_GRAY);
+                }
+                ui.label(text);
+            }
+            ui.end_row();
+        }
+    });
+    ui.label(match machine.last_waited_key() {
+        Some(key) => format!("last FX0A key: {key:X}"),
+        None => String::from("last FX0A key: -"),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Returns an empty temporary directory for a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust-chip-8-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn session() -> Session {
+        let mut session = Session {
+            breakpoints: BTreeSet::from([0x200, 0x2A4]),
+            ..Session::default()
+        };
+        session.break_on.draw = true;
+        session.break_on.keys = true;
+        session.watches.v[3] = true;
+        session.watches.i = true;
+        session
+    }
+
+    #[test]
+    fn sessions_are_kept_per_rom() {
+        let dir = temp_dir("debugger-sessions");
+        let path = Session::path(&dir, &[0x00, 0xE0]);
+        assert_eq!(
+            path,
+            dir.join(format!("{}.toml", Sha1::from([0x00, 0xE0]).digest()))
+        );
+        assert_ne!(path, Session::path(&dir, &[0x00, 0xEE]));
+
+        assert_eq!(Session::read(&path), Ok(None));
+        session().write(&path).unwrap();
+        assert_eq!(Session::read(&path), Ok(Some(session())));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn session_files_can_be_partial() {
+        let dir = temp_dir("debugger-partial");
+        let path = dir.join("partial.toml");
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(&path, "breakpoints = [512]\n").unwrap();
+        let expected = Session {
+            breakpoints: BTreeSet::from([0x200]),
+            ..Session::default()
+        };
+        assert_eq!(Session::read(&path), Ok(Some(expected)));
+
+        fs::write(&path, "breakpoints = \"none\"\n").unwrap();
+        assert!(Session::read(&path).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn events_pause_only_when_enabled() {
+        let none = EventBreaks::default();
+        assert_eq!(none.event(0xD015), None);
+
+        let all = EventBreaks {
+            draw: true,
+            clear: true,
+            sound: true,
+            keys: true,
+        };
+        assert_eq!(all.event(0xD015), Some("draw"));
+        assert_eq!(all.event(0x00E0), Some("clear"));
+        assert_eq!(all.event(0xF318), Some("sound"));
+        assert_eq!(all.event(0xE19E), Some("key"));
+        assert_eq!(all.event(0xE2A1), Some("key"));
+        assert_eq!(all.event(0xF40A), Some("key"));
+        assert_eq!(all.event(0x6005), None);
+    }
+
+    #[test]
+    fn watches_report_the_changes_of_the_watched_registers() {
+        let before = Chip8::new();
+        let mut after = before.clone();
+        after.set_v(3, 0x06);
+        after.set_v(4, 0x07);
+        after.set_i(0x300);
+
+        let mut watches = Watches::default();
+        assert!(watches.changes(&before, &after).is_empty());
+
+        watches.v[3] = true;
+        watches.i = true;
+        assert_eq!(
+            watches.changes(&before, &after),
+            ["V3: 0x00 -> 0x06", "I: 0x000 -> 0x300"]
+        );
+    }
+
+    #[test]
+    fn call_stack_has_the_subroutine_and_the_return_address_of_every_level() {
+        let (mut chip8, mut rng) = Chip8::builder().build_with_rng();
+        // 2204 (call 0x204), 0000, 2208 (call 0x208), 0000, 1208 (jump to itself)
+        chip8.load_rom_bytes(&[0x22, 0x04, 0x00, 0x00, 0x22, 0x08, 0x00, 0x00, 0x12, 0x08]);
+        assert!(call_stack(&chip8).is_empty());
+
+        chip8.step(&mut rng).unwrap();
+        chip8.step(&mut rng).unwrap();
+        assert_eq!(call_stack(&chip8), [(0x204, 0x202), (0x208, 0x206)]);
+    }
+
+    #[test]
+    fn sprite_panel_shows_the_rows_drawn_at_pc() {
+        let mut chip8 = Chip8::new();
+        // D125 (draw 5 rows at (V1, V2))
+        chip8.load_rom_bytes(&[0xD1, 0x25]);
+        chip8.set_v(1, 3);
+        chip8.set_v(2, 4);
+        chip8.set_i(0x300);
+
+        assert_eq!(
+            sprite_source(&chip8, None),
+            (0x300, 5, String::from("D125 draws 5 rows from I at (3, 4)"))
+        );
+        assert_eq!(
+            sprite_source(&chip8, Some(0x400)),
+            (0x400, SPRITE_ROWS, String::from("15 rows from 0x400"))
+        );
+
+        // not a draw instruction: the default number of rows from I
+        chip8.load_rom_bytes(&[0x00, 0xE0]);
+        assert_eq!(
+            sprite_source(&chip8, None),
+            (0x300, SPRITE_ROWS, String::from("15 rows from 0x300"))
+        );
+    }
+
+    #[test]
+    fn execution_breaks_on_breakpoints_and_events() {
+        let mut chip8 = Chip8::new();
+        // 6005 (V0 = 5), D015 (draw)
+        chip8.load_rom_bytes(&[0x60, 0x05, 0xD0, 0x15]);
+        let draw = EventBreaks {
+            draw: true,
+            ..EventBreaks::default()
+        };
+
+        assert_eq!(break_hit(&BTreeSet::new(), &draw, &chip8), None);
+        assert_eq!(
+            break_hit(&BTreeSet::from([0x200]), &draw, &chip8),
+            Some(String::from("breakpoint hit at 0x200"))
+        );
+
+        chip8.set_pc(0x202);
+        assert_eq!(
+            break_hit(&BTreeSet::from([0x200]), &draw, &chip8),
+            Some(String::from("draw event at 0x202"))
+        );
+        assert_eq!(
+            break_hit(&BTreeSet::new(), &EventBreaks::default(), &chip8),
+            None
+        );
+    }
+
+    #[test]
+    fn traced_ranges_are_parsed() {
+        assert_eq!(parse_range("300-3FF"), Some(0x300..=0x3FF));
+        assert_eq!(parse_range("0x300 - 0x30F"), Some(0x300..=0x30F));
+        assert_eq!(parse_range("300"), Some(0x300..=0x300));
+        assert_eq!(parse_range("3FF-300"), None);
+        assert_eq!(parse_range("300-"), None);
+        assert_eq!(parse_range("xyz"), None);
+    }
+}
@@ -0,0 +1,849 @@
+//! GPU display renderer (wgpu window with optional CRT post-processing)
+
+use chip8_core::{Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH, MAX_DISPLAY_SIZE};
+use chip8_frontend::{
+    EmulationStatus, Ghosting, Hotkey, Keymap, KeypadOverlay, Palette, RenderOptions, Renderer,
+    MAX_SCALE,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::{info, trace};
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::EventLoop,
+    keyboard::{Key, KeyCode, PhysicalKey},
+    platform::pump_events::EventLoopExtPumpEvents,
+    window::{Fullscreen, Window, WindowBuilder},
+};
+
+/// window title
+const WINDOW_TITLE: &str = "rust-chip-8";
+
+/// initial window scaling factor, if the scale is not set
+const WINDOW_SCALE: u32 = 10;
+
+/// size of the shader parameters uniform buffer (2 x vec4<f32>)
+const PARAMS_SIZE: u64 = 32;
+
+/// CRT post-processing effects
+#[derive(Clone, Copy, Debug)]
+pub struct CrtEffects {
+    /// darken the lower part of every display row
+    pub scanlines: bool,
+    /// curved screen distortion
+    pub curvature: bool,
+    /// glow around the pixels that are on
+    pub bloom: bool,
+}
+
+impl CrtEffects {
+    /// Toggles the effect of a toggle key: F1 (scanlines), F2 (curvature), F3 (bloom).
+    /// Returns the name of the effect and its new state, or None if the key toggles no effect
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code of the key pressed
+    fn toggle(&mut self, code: KeyCode) -> Option<(&'static str, bool)> {
+        let (name, enabled) = match code {
+            KeyCode::F1 => ("scanlines", &mut self.scanlines),
+            KeyCode::F2 => ("curvature", &mut self.curvature),
+            KeyCode::F3 => ("bloom", &mut self.bloom),
+            _ => return None,
+        };
+        *enabled = !*enabled;
+
+        Some((name, *enabled))
+    }
+
+    /// Returns the shader parameters uniform buffer: the effect flags (1.0 if enabled)
+    /// and the display size
+    fn params(&self) -> Vec<u8> {
+        let flag = |enabled: bool| if enabled { 1.0f32 } else { 0.0 };
+        let params = [
+            flag(self.scanlines),
+            flag(self.curvature),
+            flag(self.bloom),
+            0.0,
+            DISPLAY_WIDTH as f32,
+            DISPLAY_HEIGTH as f32,
+            0.0,
+            0.0,
+        ];
+
+        params.iter().flat_map(|p| p.to_le_bytes()).collect()
+    }
+}
+
+/// Renderer that draws the CHIP-8 display in a window using the GPU (wgpu),
+/// with integer scaling and optional CRT post-processing effects.
+/// The effects can be toggled at runtime: F1 (scanlines), F2 (curvature), F3 (bloom).
+/// The emulation can be paused and resumed with F5, advanced one frame while paused with F6,
+/// slowed down with F7, reset with F8, muted with F9 and fast-forwarded holding Tab.
+/// The integer scaling factor can be changed at runtime with + and -, fullscreen is toggled with F11
+pub struct GpuRenderer {
+    /// Mapping of the CHIP-8 keypad to the keyboard
+    keymap: Keymap,
+
+    /// CHIP-8 keys held down (bit n set if key n is pressed)
+    keys: u16,
+
+    /// Boolean set to true while the fast-forward key is held down
+    fast_forward: bool,
+
+    /// Hotkeys pressed since the last call of `hotkeys`
+    hotkeys: Vec<Hotkey>,
+
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// CRT post-processing effects
+    effects: CrtEffects,
+
+    /// Integer scaling factor of the display (logical pixels), None for the largest one that fits the window
+    scale: Option<u32>,
+
+    /// Window and GPU device
+    gpu: GpuWindow,
+
+    /// Display texture (one texel per CHIP-8 pixel)
+    texture: wgpu::Texture,
+
+    /// Shader parameters uniform buffer
+    params: wgpu::Buffer,
+
+    /// Bind group of texture, sampler and parameters
+    bind_group: wgpu::BindGroup,
+
+    /// Render pipeline
+    pipeline: wgpu::RenderPipeline,
+
+    /// Last rendered display pixels
+    display: [bool; MAX_DISPLAY_SIZE],
+
+    /// Boolean set to true if the window must be redrawn
+    redraw: bool,
+
+    /// Keypad overlay shown in the window title, if enabled
+    keypad: Option<KeypadOverlay>,
+
+    /// Status shown in the window title (None if disabled, empty until the first status)
+    status_bar: Option<String>,
+
+    /// Boolean set to true to pause the emulation while the window is not focused
+    pause_unfocused: bool,
+
+    /// Boolean set to true if the window has been closed
+    quit: bool,
+}
+
+impl GpuRenderer {
+    /// Returns a new GPU renderer, opening its window
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The display options: colors, ghosting, CRT effects enabled at start, keymap,
+    ///   keypad overlay and status bar (shown in the window title), scaling factor, fullscreen
+    ///   and pause when unfocused
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the window or the GPU device cannot be created
+    pub fn new(options: &RenderOptions) -> Self {
+        trace!("GpuRenderer::new: start");
+
+        let RenderOptions {
+            palette,
+            ghosting,
+            keymap,
+            scale,
+            ..
+        } = *options;
+        let effects = CrtEffects {
+            scanlines: options.crt,
+            curvature: options.crt,
+            bloom: options.crt,
+        };
+
+        let window_scale = scale.unwrap_or(WINDOW_SCALE);
+        let gpu = GpuWindow::new(
+            DISPLAY_WIDTH as u32 * window_scale,
+            DISPLAY_HEIGTH as u32 * window_scale,
+            options.fullscreen,
+        );
+        let GpuWindow { device, config, .. } = &gpu;
+
+        // display texture: colors are sRGB, as the surface ones
+        let texture_format = if config.format.is_srgb() {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("display"),
+            size: texture_size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        // nearest filtering keeps the pixels crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: PARAMS_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("display"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("display"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("crt"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("crt.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("display"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("display"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        trace!("GpuRenderer::new: exit");
+
+        Self {
+            keymap,
+            keys: 0,
+            fast_forward: false,
+            hotkeys: Vec::new(),
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            effects,
+            scale,
+            gpu,
+            texture,
+            params,
+            bind_group,
+            pipeline,
+            display: [false; MAX_DISPLAY_SIZE],
+            redraw: true,
+            keypad: options.keypad_overlay.then(KeypadOverlay::new),
+            status_bar: options.status_bar.then(String::new),
+            pause_unfocused: options.pause_unfocused,
+            quit: false,
+        }
+    }
+
+    /// Handles the pending window events (close, resize, keypad, hotkeys and effects toggle keys)
+    fn handle_events(&mut self) {
+        let mut resized = None;
+        let mut toggled = Vec::new();
+        let mut focused = None;
+        let mut quit = false;
+
+        let window_id = self.gpu.window.id();
+        let (keymap, keys, fast_forward) = (&self.keymap, &mut self.keys, &mut self.fast_forward);
+        self.gpu
+            .event_loop
+            .pump_events(Some(Duration::ZERO), |event, _| {
+                if let Event::WindowEvent {
+                    window_id: id,
+                    event,
+                } = event
+                {
+                    if id != window_id {
+                        return;
+                    }
+                    match event {
+                        WindowEvent::CloseRequested => quit = true,
+                        WindowEvent::Resized(size) => resized = Some(size),
+                        WindowEvent::Focused(focus) => focused = Some(focus),
+                        WindowEvent::KeyboardInput { event, .. } => {
+                            update_keypad(keys, keymap, &event);
+                            if event.physical_key == PhysicalKey::Code(KeyCode::Tab) {
+                                *fast_forward = event.state == ElementState::Pressed;
+                            }
+                            if let KeyEvent {
+                                physical_key: PhysicalKey::Code(code),
+                                state: ElementState::Pressed,
+                                repeat: false,
+                                ..
+                            } = event
+                            {
+                                toggled.push(code);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            });
+
+        if let Some(size) = resized {
+            if self.gpu.resize(size) {
+                self.redraw = true;
+            }
+        }
+
+        match focused {
+            Some(false) if self.pause_unfocused => self.hotkeys.push(Hotkey::Unfocused),
+            Some(true) if self.pause_unfocused => self.hotkeys.push(Hotkey::Focused),
+            _ => {}
+        }
+
+        for code in toggled {
+            if let Some((name, enabled)) = self.effects.toggle(code) {
+                info!("crt {name}: {}", if enabled { "on" } else { "off" });
+                self.redraw = true;
+                continue;
+            }
+            match code {
+                KeyCode::F5 => self.hotkeys.push(Hotkey::TogglePause),
+                KeyCode::F6 => self.hotkeys.push(Hotkey::FrameAdvance),
+                KeyCode::F7 => self.hotkeys.push(Hotkey::ToggleSlowMotion),
+                KeyCode::F8 => self.hotkeys.push(Hotkey::Reset),
+                KeyCode::F9 => self.hotkeys.push(Hotkey::ToggleMute),
+                KeyCode::F11 => self.gpu.toggle_fullscreen(),
+                KeyCode::Equal | KeyCode::NumpadAdd => self.rescale(1),
+                KeyCode::Minus | KeyCode::NumpadSubtract => self.rescale(-1),
+                _ => {}
+            }
+        }
+
+        self.quit |= quit;
+    }
+
+    /// Changes the integer scaling factor of the display and resizes the window to fit it
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The change of the scaling factor (+1 or -1)
+    fn rescale(&mut self, step: i32) {
+        let current = self.scale.unwrap_or_else(|| {
+            (self.fit_scale() as f64 / self.gpu.window.scale_factor()).max(1.0) as u32
+        });
+        let scale = current.saturating_add_signed(step).clamp(1, MAX_SCALE);
+        self.scale = Some(scale);
+        let _ = self.gpu.window.request_inner_size(LogicalSize::new(
+            DISPLAY_WIDTH as u32 * scale,
+            DISPLAY_HEIGTH as u32 * scale,
+        ));
+        info!("display scale: {scale}x");
+        self.redraw = true;
+    }
+
+    /// Returns the largest integer scaling factor of the display that fits the window (physical pixels)
+    fn fit_scale(&self) -> u32 {
+        fit_scale(self.gpu.config.width, self.gpu.config.height)
+    }
+
+    /// Sets the window title: the status and the keypad overlay follow the name of the emulator, if enabled
+    fn update_title(&self) {
+        let mut title = String::from(WINDOW_TITLE);
+        if let Some(status_bar) = self.status_bar.as_ref() {
+            title.push_str(&format!(" | {status_bar}"));
+        }
+        if let Some(keypad) = self.keypad.as_ref() {
+            title.push_str(&format!(" | {}", keypad.describe()));
+        }
+        self.gpu.window.set_title(&title);
+    }
+
+    /// Draws the display in the window
+    fn draw(&mut self) {
+        // upload display colors
+        let mut texels = Vec::with_capacity(MAX_DISPLAY_SIZE * 4);
+        for index in 0..MAX_DISPLAY_SIZE {
+            let (r, g, b) = self
+                .palette
+                .blend(self.ghosting.intensity(&self.display, index))
+                .to_rgb();
+            texels.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+        self.gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(DISPLAY_WIDTH as u32 * 4),
+                rows_per_image: Some(DISPLAY_HEIGTH as u32),
+            },
+            texture_size(),
+        );
+
+        // upload shader parameters
+        self.gpu
+            .queue
+            .write_buffer(&self.params, 0, &self.effects.params());
+
+        let Some(frame) = self.gpu.frame() else {
+            return;
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let (x, y, width, height) = viewport(
+            self.gpu.config.width,
+            self.gpu.config.height,
+            self.scale,
+            self.gpu.window.scale_factor(),
+        );
+
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("display"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            pass.draw(0..3, 0..1);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        frame.present();
+
+        self.redraw = false;
+    }
+}
+
+impl Renderer for GpuRenderer {
+    fn render(&mut self, display: &[bool], _dirty_rows: u64) {
+        trace!("GpuRenderer::render: start");
+
+        // the window is redrawn at the next tick (at most 60 times per second)
+        self.ghosting.update(display);
+        self.display.copy_from_slice(display);
+        self.redraw = true;
+
+        trace!("GpuRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        self.handle_events();
+
+        if self.ghosting.fade(display) != 0 {
+            self.redraw = true;
+        }
+        if self.redraw {
+            self.display.copy_from_slice(display);
+            self.draw();
+        }
+        if self
+            .keypad
+            .as_mut()
+            .is_some_and(KeypadOverlay::take_changed)
+        {
+            self.update_title();
+        }
+    }
+
+    fn status(&mut self, status: &EmulationStatus) {
+        let Some(status_bar) = self.status_bar.as_mut() else {
+            return;
+        };
+        let text = status.to_string();
+        if *status_bar != text {
+            *status_bar = text;
+            self.update_title();
+        }
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        if let Some(keypad) = self.keypad.as_mut() {
+            keypad.update(chip8);
+        }
+
+        true
+    }
+
+    fn hotkeys(&mut self) -> Vec<Hotkey> {
+        std::mem::take(&mut self.hotkeys)
+    }
+
+    fn fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    fn has_keypad(&self) -> bool {
+        true
+    }
+
+    fn keypad(&self) -> u16 {
+        self.keys
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.quit
+    }
+}
+
+/// Returns the largest integer scaling factor of the display that fits a window
+///
+/// # Arguments
+///
+/// * `width` - The window width (physical pixels)
+/// * `height` - The window height (physical pixels)
+fn fit_scale(width: u32, height: u32) -> u32 {
+    (width / DISPLAY_WIDTH as u32)
+        .min(height / DISPLAY_HEIGTH as u32)
+        .max(1)
+}
+
+/// Returns the viewport of the display in a window (x, y, width and height in physical pixels).
+/// Integer scaling: the scale set (shrunk if the window is smaller) or the largest one that fits
+/// the window, centered (letterboxing)
+///
+/// # Arguments
+///
+/// * `width` - The window width (physical pixels)
+/// * `height` - The window height (physical pixels)
+/// * `scale` - The scaling factor set (logical pixels), or None to fit the window
+/// * `scale_factor` - The scale factor of the monitor (physical pixels per logical pixel)
+fn viewport(
+    width: u32,
+    height: u32,
+    scale: Option<u32>,
+    scale_factor: f64,
+) -> (f32, f32, f32, f32) {
+    let fit = fit_scale(width, height);
+    let scale = match scale {
+        Some(scale) => ((scale as f64 * scale_factor).round() as u32).clamp(1, fit),
+        None => fit,
+    };
+    let display_width = (DISPLAY_WIDTH as u32 * scale) as f32;
+    let display_height = (DISPLAY_HEIGTH as u32 * scale) as f32;
+    let x = ((width as f32 - display_width) / 2.0).max(0.0);
+    let y = ((height as f32 - display_height) / 2.0).max(0.0);
+
+    (x, y, display_width, display_height)
+}
+
+/// Updates the CHIP-8 keys held down with a keyboard event of a window frontend
+///
+/// # Arguments
+///
+/// * `keys` - The CHIP-8 keys held down (bit n set if key n is pressed)
+/// * `keymap` - The mapping of the CHIP-8 keypad to the keyboard
+/// * `event` - The keyboard event
+pub(crate) fn update_keypad(keys: &mut u16, keymap: &Keymap, event: &KeyEvent) {
+    // keys are matched by the typed character, so that the keymap follows the keyboard layout
+    let key = match &event.logical_key {
+        Key::Character(character) => keymap.key(character),
+        _ => None,
+    };
+
+    if let Some(key) = key {
+        match event.state {
+            ElementState::Pressed => *keys |= 1 << key,
+            ElementState::Released => *keys &= !(1 << key),
+        }
+    }
+}
+
+/// Window with its GPU surface and device, shared by the window frontends
+pub(crate) struct GpuWindow {
+    /// Window event loop (pumped by the frontend)
+    pub(crate) event_loop: EventLoop<()>,
+
+    /// Window
+    pub(crate) window: Arc<Window>,
+
+    /// Window surface
+    pub(crate) surface: wgpu::Surface<'static>,
+
+    /// Window surface configuration
+    pub(crate) config: wgpu::SurfaceConfiguration,
+
+    /// GPU device
+    pub(crate) device: wgpu::Device,
+
+    /// GPU command queue
+    pub(crate) queue: wgpu::Queue,
+}
+
+impl GpuWindow {
+    /// Opens a new window and initializes the GPU device that draws on it
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The initial window width (logical pixels)
+    /// * `height` - The initial window height (logical pixels)
+    /// * `fullscreen` - Boolean set to true to open the window in fullscreen
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the window or the GPU device cannot be created
+    pub(crate) fn new(width: u32, height: u32, fullscreen: bool) -> Self {
+        trace!("GpuWindow::new: start");
+
+        let event_loop = match EventLoop::new() {
+            Ok(event_loop) => event_loop,
+            Err(e) => panic!("creating window event loop: {e}"),
+        };
+        let window = match WindowBuilder::new()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(LogicalSize::new(width, height))
+            .with_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)))
+            .build(&event_loop)
+        {
+            Ok(window) => Arc::new(window),
+            Err(e) => panic!("creating window: {e}"),
+        };
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let surface = match instance.create_surface(window.clone()) {
+            Ok(surface) => surface,
+            Err(e) => panic!("creating window surface: {e}"),
+        };
+        let adapter =
+            match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })) {
+                Some(adapter) => adapter,
+                None => panic!("no GPU adapter found"),
+            };
+        let (device, queue) = match pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        )) {
+            Ok(device) => device,
+            Err(e) => panic!("creating GPU device: {e}"),
+        };
+
+        let size = window.inner_size();
+        let mut config = match surface.get_default_config(&adapter, size.width, size.height) {
+            Some(config) => config,
+            None => panic!("window surface not supported by the GPU adapter"),
+        };
+        // never block the emulation waiting for the vertical sync
+        config.present_mode = wgpu::PresentMode::AutoNoVsync;
+        surface.configure(&device, &config);
+
+        trace!("GpuWindow::new: exit");
+
+        Self {
+            event_loop,
+            window,
+            surface,
+            config,
+            device,
+            queue,
+        }
+    }
+
+    /// Reconfigures the window surface after a resize.
+    /// Returns false if the window has no area (e.g. minimized) and the surface was left untouched
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new window size (physical pixels)
+    pub(crate) fn resize(&mut self, size: PhysicalSize<u32>) -> bool {
+        if size.width == 0 || size.height == 0 {
+            return false;
+        }
+
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+
+        true
+    }
+
+    /// Switches the window to borderless fullscreen on its monitor, or back to windowed mode
+    pub(crate) fn toggle_fullscreen(&self) {
+        let fullscreen = self.window.fullscreen().is_none();
+        self.window
+            .set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+        info!("fullscreen: {}", if fullscreen { "on" } else { "off" });
+    }
+
+    /// Returns the next surface texture to draw on,
+    /// or None if the surface had to be reconfigured (the frame must be drawn at the next tick)
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the surface texture cannot be acquired
+    pub(crate) fn frame(&mut self) -> Option<wgpu::SurfaceTexture> {
+        match self.surface.get_current_texture() {
+            Ok(frame) => Some(frame),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                None
+            }
+            Err(e) => panic!("drawing window: {e}"),
+        }
+    }
+}
+
+/// Returns the size of the display texture
+fn texture_size() -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: DISPLAY_WIDTH as u32,
+        height: DISPLAY_HEIGTH as u32,
+        depth_or_array_layers: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_are_toggled_by_the_function_keys() {
+        let mut effects = CrtEffects {
+            scanlines: false,
+            curvature: false,
+            bloom: false,
+        };
+        assert_eq!(effects.toggle(KeyCode::F1), Some(("scanlines", true)));
+        assert_eq!(effects.toggle(KeyCode::F3), Some(("bloom", true)));
+        assert_eq!(effects.toggle(KeyCode::F1), Some(("scanlines", false)));
+        assert_eq!(effects.toggle(KeyCode::F5), None);
+        assert!(!effects.scanlines && !effects.curvature && effects.bloom);
+    }
+
+    #[test]
+    fn shader_parameters_hold_the_effects_and_the_display_size() {
+        let effects = CrtEffects {
+            scanlines: true,
+            curvature: false,
+            bloom: true,
+        };
+        let params = effects.params();
+        assert_eq!(params.len() as u64, PARAMS_SIZE);
+
+        let floats: Vec<f32> = params
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats, [1.0, 0.0, 1.0, 0.0, 64.0, 32.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn display_is_scaled_by_integer_factors() {
+        // the largest scale that fits the window, centered
+        assert_eq!(viewport(640, 400, None, 1.0), (0.0, 40.0, 640.0, 320.0));
+        assert_eq!(viewport(700, 320, None, 1.0), (30.0, 0.0, 640.0, 320.0));
+
+        // the scale set, in logical pixels, shrunk if the window is smaller
+        assert_eq!(
+            viewport(640, 320, Some(5), 1.0),
+            (160.0, 80.0, 320.0, 160.0)
+        );
+        assert_eq!(viewport(640, 320, Some(4), 2.0), (64.0, 32.0, 512.0, 256.0));
+        assert_eq!(viewport(640, 320, Some(20), 1.0), (0.0, 0.0, 640.0, 320.0));
+
+        // a window smaller than the display gets the display at scale 1
+        assert_eq!(fit_scale(32, 16), 1);
+    }
+
+    #[test]
+    fn fullscreen_display_is_letterboxed() {
+        // 1920x1080 monitor: scale 30, black bars above and below
+        assert_eq!(viewport(1920, 1080, None, 1.0), (0.0, 60.0, 1920.0, 960.0));
+        // 2560x1440 monitor at 1.5x with the scale 10 set: 15 physical pixels per CHIP-8 pixel
+        assert_eq!(
+            viewport(2560, 1440, Some(10), 1.5),
+            (800.0, 480.0, 960.0, 480.0)
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! # chip8-gpu
+//!
+//! The window frontends of `rust-chip-8`: the GPU display renderer (wgpu and winit, with optional CRT
+//! post-processing) and, with the `debugger` feature, the graphical debugger (egui).
+//! They implement the `Renderer` trait of `chip8-frontend`
+
+#[cfg(feature = "debugger")]
+mod commands;
+#[cfg(feature = "debugger")]
+mod debugger;
+mod gpu;
+
+#[cfg(feature = "debugger")]
+pub use debugger::DebuggerRenderer;
+pub use gpu::{CrtEffects, GpuRenderer};
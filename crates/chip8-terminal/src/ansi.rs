@@ -0,0 +1,219 @@
+//! ANSI escape sequences shared by the text terminal renderers
+
+use chip8_frontend::Color;
+use std::io::{self, Write};
+
+/// clears the terminal and hides the cursor
+pub(crate) const SETUP: &str = "\x1b[2J\x1b[?25l";
+
+/// resets colors and shows the cursor
+pub(crate) const RESTORE: &str = "\x1b[0m\x1b[?25h";
+
+/// moves the cursor to the top left corner
+pub(crate) const CURSOR_HOME: &str = "\x1b[H";
+
+/// resets colors
+const RESET_COLORS: &str = "\x1b[0m";
+
+/// Returns the ANSI escape sequence that sets the foreground color
+pub(crate) fn foreground(color: Color) -> String {
+    match color {
+        Color::Ansi(index) => format!("\x1b[38;5;{index}m"),
+        Color::Rgb(r, g, b) => format!("\x1b[38;2;{r};{g};{b}m"),
+    }
+}
+
+/// Returns the ANSI escape sequence that sets the background color
+pub(crate) fn background(color: Color) -> String {
+    match color {
+        Color::Ansi(index) => format!("\x1b[48;5;{index}m"),
+        Color::Rgb(r, g, b) => format!("\x1b[48;2;{r};{g};{b}m"),
+    }
+}
+
+/// Writes a frame on the terminal (stdout)
+///
+/// # Panics
+///
+/// The function panics in case of errors during writing to the terminal
+pub(crate) fn write(frame: &str) {
+    let mut stdout = io::stdout().lock();
+    if let Err(e) = stdout
+        .write_all(frame.as_bytes())
+        .and_then(|_| stdout.flush())
+    {
+        panic!("writing to terminal: {e}")
+    }
+}
+
+/// Terminal cell: a character with its foreground and background colors
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cell {
+    /// character
+    pub(crate) c: char,
+    /// foreground color
+    pub(crate) foreground: Color,
+    /// background color
+    pub(crate) background: Color,
+}
+
+/// Terminal screen that remembers the cells written in the previous frame,
+/// so that only the changed cells are written in the next one
+pub(crate) struct Screen {
+    /// screen width (cells)
+    width: usize,
+
+    /// cells of the next frame (None if the cell has never been set)
+    cells: Vec<Option<Cell>>,
+
+    /// cells of the previous frame (None if the cell has never been written)
+    written: Vec<Option<Cell>>,
+}
+
+impl Screen {
+    /// Returns a new (never written) screen
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The screen width (cells)
+    /// * `height` - The screen height (cells)
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            cells: vec![None; width * height],
+            written: vec![None; width * height],
+        }
+    }
+
+    /// Sets a cell of the next frame
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The cell row
+    /// * `col` - The cell column
+    /// * `cell` - The cell contents
+    pub(crate) fn set(&mut self, row: usize, col: usize, cell: Cell) {
+        self.cells[row * self.width + col] = Some(cell);
+    }
+
+    /// Writes the next frame on the terminal, emitting only cursor moves and changed cells
+    ///
+    /// # Panics
+    ///
+    /// The function panics in case of errors during writing to the terminal
+    pub(crate) fn flush(&mut self) {
+        let frame = self.frame();
+        if !frame.is_empty() {
+            write(&frame);
+        }
+    }
+
+    /// Returns the escape sequences that draw the next frame over the previous one
+    /// (empty if no cell changed): only cursor moves and changed cells are emitted
+    fn frame(&mut self) -> String {
+        let mut frame = String::new();
+        // position of the terminal cursor and current colors, if known
+        let mut cursor = None;
+        let mut foreground = None;
+        let mut background = None;
+
+        for (index, cell) in self.cells.iter().enumerate() {
+            let Some(cell) = cell else {
+                continue;
+            };
+            if self.written[index] == Some(*cell) {
+                continue;
+            }
+
+            let position = (index / self.width, index % self.width);
+            if cursor != Some(position) {
+                // cursor positions are 1-based
+                frame += &format!("\x1b[{};{}H", position.0 + 1, position.1 + 1);
+            }
+            if foreground != Some(cell.foreground) {
+                frame += &self::foreground(cell.foreground);
+                foreground = Some(cell.foreground);
+            }
+            if background != Some(cell.background) {
+                frame += &self::background(cell.background);
+                background = Some(cell.background);
+            }
+            frame.push(cell.c);
+
+            cursor = Some((position.0, position.1 + 1));
+            self.written[index] = Some(*cell);
+        }
+
+        if !frame.is_empty() {
+            // reset colors and move cursor below the display
+            let height = self.cells.len() / self.width;
+            frame += &format!("{RESET_COLORS}\x1b[{};1H", height + 1);
+        }
+
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a cell with white foreground and black background
+    fn cell(c: char) -> Cell {
+        Cell {
+            c,
+            foreground: Color::Ansi(15),
+            background: Color::Ansi(0),
+        }
+    }
+
+    #[test]
+    fn first_frame_writes_every_cell() {
+        let mut screen = Screen::new(2, 1);
+        screen.set(0, 0, cell('a'));
+        screen.set(0, 1, cell('b'));
+
+        assert_eq!(
+            screen.frame(),
+            "\x1b[1;1H\x1b[38;5;15m\x1b[48;5;0mab\x1b[0m\x1b[2;1H"
+        );
+    }
+
+    #[test]
+    fn next_frames_write_only_the_changed_cells() {
+        let mut screen = Screen::new(3, 2);
+        for row in 0..2 {
+            for col in 0..3 {
+                screen.set(row, col, cell(' '));
+            }
+        }
+        screen.frame();
+
+        assert_eq!(screen.frame(), "");
+        screen.set(1, 2, cell('x'));
+        screen.set(0, 0, cell(' '));
+        assert_eq!(
+            screen.frame(),
+            "\x1b[2;3H\x1b[38;5;15m\x1b[48;5;0mx\x1b[0m\x1b[3;1H"
+        );
+    }
+
+    #[test]
+    fn colors_are_written_only_when_they_change() {
+        let mut screen = Screen::new(2, 1);
+        screen.set(0, 0, cell('a'));
+        screen.set(
+            0,
+            1,
+            Cell {
+                foreground: Color::Rgb(1, 2, 3),
+                ..cell('b')
+            },
+        );
+
+        assert_eq!(
+            screen.frame(),
+            "\x1b[1;1H\x1b[38;5;15m\x1b[48;5;0ma\x1b[38;2;1;2;3mb\x1b[0m\x1b[2;1H"
+        );
+    }
+}
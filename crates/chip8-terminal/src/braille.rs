@@ -0,0 +1,213 @@
+//! Braille terminal display renderer (Unicode braille patterns)
+
+use crate::ansi::{self, Cell, Screen};
+use chip8_core::{DISPLAY_HEIGTH, DISPLAY_WIDTH};
+use chip8_frontend::{display_size, Ghosting, Palette, Renderer};
+use tracing::trace;
+
+/// braille cell width (pixels)
+const CELL_WIDTH: usize = 2;
+
+/// braille cell height (pixels)
+const CELL_HEIGHT: usize = 4;
+
+/// first Unicode braille pattern (blank cell)
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// braille dot bits, indexed by [row][column] of the pixel in the cell
+const BRAILLE_DOTS: [[u32; CELL_WIDTH]; CELL_HEIGHT] =
+    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Renderer that draws the CHIP-8 display on the terminal (stdout) using Unicode braille patterns.
+/// Every terminal cell holds 2 x 4 pixels, so the display takes only 32 x 8 cells
+/// (64 x 16 cells in the SUPER-CHIP hires mode)
+pub struct BrailleRenderer {
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// Terminal screen (only the changed cells are redrawn)
+    screen: Screen,
+
+    /// Size (width, height) of the display drawn in pixels: the terminal is cleared when it changes
+    size: (usize, usize),
+}
+
+impl BrailleRenderer {
+    /// Returns a new braille renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The colors used to draw the pixels
+    /// * `ghosting` - The number of frames a pixel takes to fade out (0 disables ghosting)
+    pub fn new(palette: Palette, ghosting: u8) -> Self {
+        ansi::write(ansi::SETUP);
+
+        Self {
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            screen: Screen::new(DISPLAY_WIDTH / CELL_WIDTH, DISPLAY_HEIGTH / CELL_HEIGHT),
+            size: (DISPLAY_WIDTH, DISPLAY_HEIGTH),
+        }
+    }
+
+    /// Resizes the terminal screen to the size of the display, if changed (e.g. the program switched
+    /// to the SUPER-CHIP hires mode): the terminal is cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size (width, height) of the display in pixels
+    fn resize(&mut self, size: (usize, usize)) {
+        if self.size == size {
+            return;
+        }
+
+        ansi::write(ansi::SETUP);
+        self.size = size;
+        self.screen = Screen::new(size.0 / CELL_WIDTH, size.1 / CELL_HEIGHT);
+    }
+
+    /// Returns the terminal cell of the 2 x 4 pixels at the given position: a dot is drawn if the pixel
+    /// is on (or still fading out), the cell is colored with the intensity of its brightest pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `cell_row` - The display row of the top pixels of the cell
+    /// * `cell_col` - The display column of the left pixels of the cell
+    fn cell(&self, display: &[bool], cell_row: usize, cell_col: usize) -> Cell {
+        let width = self.size.0;
+        let mut pattern = BRAILLE_BLANK;
+        let mut intensity: f64 = 0.0;
+        for (row, dots) in BRAILLE_DOTS.iter().enumerate() {
+            for (col, dot) in dots.iter().enumerate() {
+                let index = (cell_row + row) * width + cell_col + col;
+                let pixel_intensity = self.ghosting.intensity(display, index);
+                if pixel_intensity > 0.0 {
+                    pattern |= dot;
+                    intensity = intensity.max(pixel_intensity);
+                }
+            }
+        }
+
+        Cell {
+            // braille patterns are all valid chars
+            c: char::from_u32(pattern).unwrap(),
+            foreground: self.palette.blend(intensity),
+            background: self.palette.background,
+        }
+    }
+
+    /// Draws the display on the terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `dirty_rows` - The mask of the display rows to redraw
+    fn draw(&mut self, display: &[bool], dirty_rows: u64) {
+        let (width, height) = self.size;
+        for cell_row in (0..height).step_by(CELL_HEIGHT) {
+            // skip terminal rows whose display rows did not change
+            if dirty_rows & (0b1111 << cell_row) == 0 {
+                continue;
+            }
+            for cell_col in (0..width).step_by(CELL_WIDTH) {
+                let cell = self.cell(display, cell_row, cell_col);
+                self.screen
+                    .set(cell_row / CELL_HEIGHT, cell_col / CELL_WIDTH, cell);
+            }
+        }
+
+        self.screen.flush();
+    }
+}
+
+impl Renderer for BrailleRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        trace!("BrailleRenderer::render: start");
+
+        self.resize(display_size(display));
+        self.ghosting.update(display);
+        self.draw(display, dirty_rows);
+
+        trace!("BrailleRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        // redraw only the rows with pixels fading out
+        let fading_rows = self.ghosting.fade(display);
+        if fading_rows != 0 && display_size(display) == self.size {
+            self.draw(display, fading_rows);
+        }
+    }
+}
+
+impl Drop for BrailleRenderer {
+    fn drop(&mut self) {
+        ansi::write(ansi::RESTORE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::{HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH, MAX_DISPLAY_SIZE};
+    use chip8_frontend::Color;
+
+    /// Returns a renderer for a display of the given size, without writing to the terminal
+    fn renderer(size: (usize, usize), ghosting: u8) -> BrailleRenderer {
+        BrailleRenderer {
+            palette: Palette {
+                foreground: Color::Rgb(0xFF, 0xFF, 0xFF),
+                background: Color::Rgb(0x00, 0x00, 0x00),
+            },
+            ghosting: Ghosting::new(ghosting),
+            screen: Screen::new(size.0 / CELL_WIDTH, size.1 / CELL_HEIGHT),
+            size,
+        }
+    }
+
+    #[test]
+    fn cells_hold_the_dots_of_2_by_4_pixels() {
+        let renderer = renderer((DISPLAY_WIDTH, DISPLAY_HEIGTH), 0);
+        let mut display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        assert_eq!(renderer.cell(&display, 0, 0).c, '⠀');
+
+        // top left and bottom right pixels of the second cell of the second row
+        display[CELL_HEIGHT * DISPLAY_WIDTH + CELL_WIDTH] = true;
+        display[(2 * CELL_HEIGHT - 1) * DISPLAY_WIDTH + 2 * CELL_WIDTH - 1] = true;
+        let cell = renderer.cell(&display, CELL_HEIGHT, CELL_WIDTH);
+        assert_eq!(cell.c, '⢁');
+        assert_eq!(cell.foreground, Color::Rgb(0xFF, 0xFF, 0xFF));
+        assert_eq!(renderer.cell(&display, 0, 0).c, '⠀');
+    }
+
+    #[test]
+    fn fading_pixels_are_drawn_dimmed() {
+        let mut renderer = renderer((DISPLAY_WIDTH, DISPLAY_HEIGTH), 1);
+        let mut display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        display[1] = true;
+        renderer.ghosting.update(&display);
+        display[1] = false;
+
+        let cell = renderer.cell(&display, 0, 0);
+        assert_eq!(cell.c, '⠈');
+        assert_eq!(cell.foreground, Color::Rgb(0x80, 0x80, 0x80));
+    }
+
+    #[test]
+    fn hires_cells_use_the_hires_width() {
+        let renderer = renderer((HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH), 0);
+        let mut display = vec![false; MAX_DISPLAY_SIZE];
+        display[HIRES_DISPLAY_WIDTH + HIRES_DISPLAY_WIDTH - 1] = true;
+
+        assert_eq!(
+            renderer
+                .cell(&display, 0, HIRES_DISPLAY_WIDTH - CELL_WIDTH)
+                .c,
+            '⠐'
+        );
+    }
+}
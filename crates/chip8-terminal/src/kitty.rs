@@ -0,0 +1,231 @@
+//! Kitty graphics protocol display renderer (kitty, WezTerm, ...)
+
+use crate::ansi;
+use chip8_core::{DISPLAY_HEIGTH, DISPLAY_WIDTH};
+use chip8_frontend::{display_size, Ghosting, Palette, Renderer};
+use std::env;
+use tracing::trace;
+
+/// max size of the base64 payload of one graphics command
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// id of the image that holds the CHIP-8 display (transmitting an image with the same id replaces it)
+const KITTY_IMAGE_ID: u32 = 1;
+
+/// base64 alphabet
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Renderer that draws the CHIP-8 display on the terminal (stdout) as a scaled bitmap,
+/// using the kitty terminal graphics protocol. The bitmap keeps its size in the SUPER-CHIP hires mode
+/// (the hires pixels are half the size of the lores pixels)
+pub struct KittyRenderer {
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// Number of screen pixels drawn for every CHIP-8 pixel (in both directions)
+    scale: usize,
+}
+
+impl KittyRenderer {
+    /// Returns a new kitty renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The colors used to draw the pixels
+    /// * `ghosting` - The number of frames a pixel takes to fade out (0 disables ghosting)
+    /// * `scale` - The number of screen pixels drawn for every CHIP-8 pixel (in both directions)
+    pub fn new(palette: Palette, ghosting: u8, scale: usize) -> Self {
+        ansi::write(ansi::SETUP);
+
+        Self {
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            scale,
+        }
+    }
+
+    /// Returns true if the terminal supports the kitty graphics protocol.
+    /// The protocol support is detected from the environment variables set by the terminals
+    pub fn supported() -> bool {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+
+        env::var_os("KITTY_WINDOW_ID").is_some()
+            || var("TERM") == "xterm-kitty"
+            || var("TERM") == "xterm-ghostty"
+            || var("TERM_PROGRAM") == "WezTerm"
+            || var("TERM_PROGRAM") == "ghostty"
+    }
+
+    /// Draws the display on the terminal.
+    /// The bitmap is always redrawn as a whole, if some display rows changed
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `dirty_rows` - The mask of the display rows to redraw
+    fn draw(&self, display: &[bool], dirty_rows: u64) {
+        if dirty_rows != 0 {
+            ansi::write(&self.frame(display));
+        }
+    }
+
+    /// Returns the graphics commands that transmit and display the scaled bitmap of the display
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    fn frame(&self, display: &[bool]) -> String {
+        let width = DISPLAY_WIDTH * self.scale;
+        let height = DISPLAY_HEIGTH * self.scale;
+        let (display_width, display_height) = display_size(display);
+
+        // scaled RGB bitmap
+        let mut bitmap = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let index =
+                    (y * display_height / height) * display_width + x * display_width / width;
+                let (r, g, b) = self
+                    .palette
+                    .blend(self.ghosting.intensity(display, index))
+                    .to_rgb();
+                bitmap.extend_from_slice(&[r, g, b]);
+            }
+        }
+
+        let payload = base64(&bitmap);
+        let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+        let mut frame = String::from(ansi::CURSOR_HOME);
+        for (i, chunk) in chunks.iter().enumerate() {
+            // m=1 if more chunks follow
+            let more = if i + 1 < chunks.len() { 1 } else { 0 };
+            if i == 0 {
+                // transmit and display RGB image, without responses (q=2) and without moving the cursor (C=1)
+                frame += &format!(
+                    "\x1b_Ga=T,f=24,s={width},v={height},i={KITTY_IMAGE_ID},q=2,C=1,m={more};"
+                );
+            } else {
+                frame += &format!("\x1b_Gm={more};");
+            }
+            // base64 is ASCII
+            frame += std::str::from_utf8(chunk).unwrap();
+            frame += "\x1b\\";
+        }
+
+        frame
+    }
+}
+
+impl Renderer for KittyRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        trace!("KittyRenderer::render: start");
+
+        self.ghosting.update(display);
+        self.draw(display, dirty_rows);
+
+        trace!("KittyRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        // redraw only the rows with pixels fading out
+        let fading_rows = self.ghosting.fade(display);
+        if fading_rows != 0 {
+            self.draw(display, fading_rows);
+        }
+    }
+}
+
+impl Drop for KittyRenderer {
+    fn drop(&mut self) {
+        // delete the image
+        ansi::write(&format!("\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\"));
+        ansi::write(ansi::RESTORE);
+    }
+}
+
+/// Returns the base64 encoding (with padding) of the given bytes
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::{HIRES_DISPLAY_WIDTH, MAX_DISPLAY_SIZE};
+    use chip8_frontend::Color;
+
+    /// Returns a renderer with black and white pixels, without writing to the terminal
+    fn renderer(scale: usize) -> KittyRenderer {
+        KittyRenderer {
+            palette: Palette {
+                foreground: Color::Rgb(0xFF, 0xFF, 0xFF),
+                background: Color::Rgb(0x00, 0x00, 0x00),
+            },
+            ghosting: Ghosting::new(0),
+            scale,
+        }
+    }
+
+    #[test]
+    fn base64_pads_the_last_group() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xFB, 0xFF]), "+/8=");
+    }
+
+    #[test]
+    fn bitmap_is_sent_in_chunks() {
+        let display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        let frame = renderer(2).frame(&display);
+
+        // 128 x 64 RGB pixels: 24576 bytes, 32768 base64 characters
+        let commands: Vec<&str> = frame
+            .strip_prefix(ansi::CURSOR_HOME)
+            .unwrap()
+            .split_terminator("\x1b\\")
+            .collect();
+        assert_eq!(commands.len(), 32768 / KITTY_CHUNK_SIZE);
+        assert!(commands[0].starts_with("\x1b_Ga=T,f=24,s=128,v=64,i=1,q=2,C=1,m=1;AAAA"));
+        assert!(commands[1..7]
+            .iter()
+            .all(|command| command.starts_with("\x1b_Gm=1;")));
+        assert!(commands[7].starts_with("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn hires_bitmap_keeps_the_lores_size() {
+        let mut display = vec![false; MAX_DISPLAY_SIZE];
+        display[0] = true;
+        display[HIRES_DISPLAY_WIDTH + 1] = true;
+        let frame = renderer(2).frame(&display);
+
+        // the hires pixels are drawn at scale 1: white, black on the first row
+        assert!(frame.contains("a=T,f=24,s=128,v=64,"));
+        assert!(frame.contains(";////AAAA"));
+    }
+}
@@ -0,0 +1,16 @@
+//! # chip8-terminal
+//!
+//! The terminal frontends of `rust-chip-8`: renderers of the CHIP-8 display that write ANSI escape sequences
+//! to the standard output (colored half blocks, braille patterns, sixel and kitty graphics).
+//! They implement the `Renderer` trait of `chip8-frontend`, without window or GPU dependencies
+
+mod ansi;
+mod braille;
+mod kitty;
+mod sixel;
+mod terminal;
+
+pub use braille::BrailleRenderer;
+pub use kitty::KittyRenderer;
+pub use sixel::SixelRenderer;
+pub use terminal::TerminalRenderer;
@@ -0,0 +1,269 @@
+//! Sixel graphics display renderer (xterm, mlterm, foot, ...)
+
+use crate::ansi;
+use chip8_core::{DISPLAY_HEIGTH, DISPLAY_WIDTH, MAX_DISPLAY_SIZE};
+use chip8_frontend::{display_size, Ghosting, Palette, Renderer};
+use tracing::trace;
+
+/// sixel band height (every sixel character encodes a column of 6 pixels)
+const SIXEL_BAND_HEIGHT: usize = 6;
+
+/// sixel image start (DCS sequence with sixel mode)
+const SIXEL_START: &str = "\x1bPq";
+
+/// sixel image end (ST sequence)
+const SIXEL_END: &str = "\x1b\\";
+
+/// Renderer that draws the CHIP-8 display on the terminal (stdout) as a scaled sixel image.
+/// The image keeps its size in the SUPER-CHIP hires mode (the hires pixels are half the size of the lores pixels)
+pub struct SixelRenderer {
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// Number of screen pixels drawn for every CHIP-8 pixel (in both directions)
+    scale: usize,
+}
+
+impl SixelRenderer {
+    /// Returns a new sixel renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The colors used to draw the pixels
+    /// * `ghosting` - The number of frames a pixel takes to fade out (0 disables ghosting)
+    /// * `scale` - The number of screen pixels drawn for every CHIP-8 pixel (in both directions)
+    pub fn new(palette: Palette, ghosting: u8, scale: usize) -> Self {
+        ansi::write(ansi::SETUP);
+
+        Self {
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            scale,
+        }
+    }
+
+    /// Draws the display on the terminal.
+    /// The bitmap is always redrawn as a whole, if some display rows changed
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `dirty_rows` - The mask of the display rows to redraw
+    fn draw(&self, display: &[bool], dirty_rows: u64) {
+        if dirty_rows != 0 {
+            ansi::write(&self.frame(display));
+        }
+    }
+
+    /// Returns the escape sequences that draw the display as a sixel image
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    fn frame(&self, display: &[bool]) -> String {
+        // sixel color registers used by the image and color register of each CHIP-8 pixel
+        let mut colors: Vec<(u8, u8, u8)> = Vec::new();
+        let mut pixel_colors = [0; MAX_DISPLAY_SIZE];
+        for (index, pixel_color) in pixel_colors[..display.len()].iter_mut().enumerate() {
+            let rgb = self
+                .palette
+                .blend(self.ghosting.intensity(display, index))
+                .to_rgb();
+            *pixel_color = match colors.iter().position(|color| *color == rgb) {
+                Some(register) => register,
+                None => {
+                    colors.push(rgb);
+                    colors.len() - 1
+                }
+            };
+        }
+
+        let width = DISPLAY_WIDTH * self.scale;
+        let height = DISPLAY_HEIGTH * self.scale;
+        let (display_width, display_height) = display_size(display);
+
+        // raster attributes: 1:1 pixel aspect ratio, image size
+        let mut frame = format!("{}{SIXEL_START}\"1;1;{width};{height}", ansi::CURSOR_HOME);
+
+        // color registers definition (RGB values are percentages)
+        for (register, (r, g, b)) in colors.iter().enumerate() {
+            let percent = |value: u8| value as usize * 100 / 255;
+            frame += &format!(
+                "#{register};2;{};{};{}",
+                percent(*r),
+                percent(*g),
+                percent(*b)
+            );
+        }
+
+        for band in (0..height).step_by(SIXEL_BAND_HEIGHT) {
+            // every band is drawn once per color, returning to the beginning of the band ($)
+            for register in 0..colors.len() {
+                let mut sixels = Rle::default();
+                let mut used = false;
+                for x in 0..width {
+                    let mut bits = 0;
+                    for bit in 0..SIXEL_BAND_HEIGHT {
+                        let y = band + bit;
+                        let index = (y * display_height / height) * display_width
+                            + x * display_width / width;
+                        if y < height && pixel_colors[index] == register {
+                            bits |= 1 << bit;
+                        }
+                    }
+                    used |= bits != 0;
+                    sixels.push((b'?' + bits) as char);
+                }
+                // skip colors that are not used in the band
+                if used {
+                    frame += &format!("#{register}{}$", sixels.finish());
+                }
+            }
+            // next band
+            frame += "-";
+        }
+        frame += SIXEL_END;
+
+        frame
+    }
+}
+
+impl Renderer for SixelRenderer {
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        trace!("SixelRenderer::render: start");
+
+        self.ghosting.update(display);
+        self.draw(display, dirty_rows);
+
+        trace!("SixelRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        // redraw only the rows with pixels fading out
+        let fading_rows = self.ghosting.fade(display);
+        if fading_rows != 0 {
+            self.draw(display, fading_rows);
+        }
+    }
+}
+
+impl Drop for SixelRenderer {
+    fn drop(&mut self) {
+        ansi::write(ansi::RESTORE);
+    }
+}
+
+/// Run-length encoder of sixel characters (`!<count><char>`)
+#[derive(Default)]
+struct Rle {
+    /// encoded sixel characters
+    encoded: String,
+
+    /// current repeated character and its count
+    run: Option<(char, usize)>,
+}
+
+impl Rle {
+    /// Appends a sixel character
+    fn push(&mut self, c: char) {
+        match self.run {
+            Some((run_char, count)) if run_char == c => self.run = Some((c, count + 1)),
+            _ => {
+                self.flush();
+                self.run = Some((c, 1));
+            }
+        }
+    }
+
+    /// Encodes the current run
+    fn flush(&mut self) {
+        if let Some((c, count)) = self.run.take() {
+            if count > 3 {
+                self.encoded += &format!("!{count}{c}");
+            } else {
+                self.encoded.extend(std::iter::repeat_n(c, count));
+            }
+        }
+    }
+
+    /// Returns the encoded sixel characters
+    fn finish(mut self) -> String {
+        self.flush();
+        self.encoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::HIRES_DISPLAY_WIDTH;
+    use chip8_frontend::Color;
+
+    /// Returns a renderer with black and white pixels, without writing to the terminal
+    fn renderer(scale: usize) -> SixelRenderer {
+        SixelRenderer {
+            palette: Palette {
+                foreground: Color::Rgb(0xFF, 0xFF, 0xFF),
+                background: Color::Rgb(0x00, 0x00, 0x00),
+            },
+            ghosting: Ghosting::new(0),
+            scale,
+        }
+    }
+
+    /// Returns the sixel image of a frame (without the cursor move and the DCS sequences)
+    fn image(frame: &str) -> &str {
+        frame
+            .strip_prefix(ansi::CURSOR_HOME)
+            .and_then(|frame| frame.strip_prefix(SIXEL_START))
+            .and_then(|frame| frame.strip_suffix(SIXEL_END))
+            .unwrap()
+    }
+
+    #[test]
+    fn runs_longer_than_3_are_encoded() {
+        let mut rle = Rle::default();
+        for c in "~~~~~??@@@".chars() {
+            rle.push(c);
+        }
+        assert_eq!(rle.finish(), "!5~??@@@");
+    }
+
+    #[test]
+    fn blank_display_is_drawn_in_bands_of_6_rows() {
+        let display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        let frame = renderer(1).frame(&display);
+
+        assert_eq!(
+            image(&frame),
+            format!("\"1;1;64;32#0;2;0;0;0{}#0!64B$-", "#0!64~$-".repeat(5))
+        );
+    }
+
+    #[test]
+    fn pixels_are_scaled() {
+        let mut display = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGTH];
+        display[0] = true;
+        let frame = renderer(2).frame(&display);
+
+        let image = image(&frame);
+        assert!(image.starts_with("\"1;1;128;64#0;2;100;100;100#1;2;0;0;0"));
+        // first band: 2x2 foreground pixels in the top left corner
+        assert!(image.contains("#0BB!126?$#1{{!126~$-"));
+    }
+
+    #[test]
+    fn hires_image_keeps_the_lores_size() {
+        let mut display = vec![false; MAX_DISPLAY_SIZE];
+        display[HIRES_DISPLAY_WIDTH] = true;
+        let frame = renderer(2).frame(&display);
+
+        let image = image(&frame);
+        assert!(image.starts_with("\"1;1;128;64#0;2;0;0;0#1;2;100;100;100"));
+        // first band: the hires pixel of the second row has the size of the lores pixels at scale 1
+        assert!(image.contains("#0|!127~$#1A!127?$-"));
+    }
+}
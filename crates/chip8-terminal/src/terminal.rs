@@ -0,0 +1,229 @@
+//! Terminal display renderer (ANSI escape sequences)
+
+use crate::ansi::{self, Cell, Screen};
+use chip8_core::{Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH};
+use chip8_frontend::{
+    display_size, Color, EmulationStatus, Ghosting, KeypadOverlay, Palette, Renderer, KEYPAD_LAYOUT,
+};
+use tracing::trace;
+
+/// Unicode upper half block: the top half of the cell is drawn with the foreground color,
+/// the bottom half with the background color
+const UPPER_HALF_BLOCK: char = '▀';
+
+/// width of a key of the keypad overlay (cells)
+const KEY_WIDTH: usize = 3;
+
+/// color of the key read by the last FX0A in the keypad overlay (bright yellow)
+const WAITED_KEY_COLOR: Color = Color::Ansi(11);
+
+/// Renderer that draws the CHIP-8 display on the terminal (stdout).
+/// Every terminal cell holds two vertical pixels, so the display takes 64 x 16 cells
+/// (128 x 32 cells in the SUPER-CHIP hires mode)
+pub struct TerminalRenderer {
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Phosphor persistence filter
+    ghosting: Ghosting,
+
+    /// Terminal screen (only the changed cells are redrawn)
+    screen: Screen,
+
+    /// Keypad overlay drawn on the right of the display, if enabled
+    keypad: Option<KeypadOverlay>,
+
+    /// Status bar drawn below the display (None if disabled, empty until the first status)
+    status_bar: Option<String>,
+
+    /// Size (width, height) of the display drawn in pixels: the terminal is cleared when it changes
+    size: (usize, usize),
+}
+
+impl TerminalRenderer {
+    /// Returns a new terminal renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The colors used to draw the pixels
+    /// * `ghosting` - The number of frames a pixel takes to fade out (0 disables ghosting)
+    /// * `keypad_overlay` - Boolean set to true to draw the keypad overlay on the right of the display
+    /// * `status_bar` - Boolean set to true to draw the status bar below the display
+    pub fn new(palette: Palette, ghosting: u8, keypad_overlay: bool, status_bar: bool) -> Self {
+        ansi::write(ansi::SETUP);
+
+        let mut renderer = Self {
+            palette,
+            ghosting: Ghosting::new(ghosting),
+            screen: Screen::new(0, 0),
+            keypad: keypad_overlay.then(KeypadOverlay::new),
+            status_bar: status_bar.then(String::new),
+            size: (0, 0),
+        };
+        renderer.resize((DISPLAY_WIDTH, DISPLAY_HEIGTH));
+
+        renderer
+    }
+
+    /// Returns the first column of the keypad overlay (on the right of the display, after a blank column)
+    fn keypad_column(&self) -> usize {
+        self.size.0 + 1
+    }
+
+    /// Resizes the terminal screen to the size of the display, if changed (e.g. the program switched
+    /// to the SUPER-CHIP hires mode): the terminal is cleared, the keypad overlay and the status bar
+    /// are drawn again
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size (width, height) of the display in pixels
+    fn resize(&mut self, size: (usize, usize)) {
+        if self.size == size {
+            return;
+        }
+        if self.size != (0, 0) {
+            ansi::write(ansi::SETUP);
+        }
+
+        self.size = size;
+        let width = if self.keypad.is_some() {
+            self.keypad_column() + KEY_WIDTH * KEYPAD_LAYOUT[0].len()
+        } else {
+            size.0
+        };
+        let height = size.1 / 2 + self.status_bar.is_some() as usize;
+        self.screen = Screen::new(width, height);
+        if let Some(keypad) = self.keypad.as_mut() {
+            keypad.invalidate();
+        }
+        if let Some(status_bar) = self.status_bar.as_mut() {
+            status_bar.clear();
+        }
+    }
+
+    /// Returns the color of a pixel
+    fn color(&self, display: &[bool], index: usize) -> Color {
+        self.palette.blend(self.ghosting.intensity(display, index))
+    }
+
+    /// Draws the display on the terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The CHIP-8 display pixels
+    /// * `dirty_rows` - The mask of the display rows to redraw
+    fn draw(&mut self, display: &[bool], dirty_rows: u64) {
+        let (width, height) = self.size;
+        for row in (0..height).step_by(2) {
+            // skip terminal rows whose display rows did not change
+            if dirty_rows & (0b11 << row) == 0 {
+                continue;
+            }
+            for col in 0..width {
+                let cell = Cell {
+                    c: UPPER_HALF_BLOCK,
+                    foreground: self.color(display, row * width + col),
+                    background: self.color(display, (row + 1) * width + col),
+                };
+                self.screen.set(row / 2, col, cell);
+            }
+        }
+
+        self.screen.flush();
+    }
+
+    /// Draws the keypad overlay, if enabled and changed: the keys held down are drawn in reverse colors,
+    /// the key read by the last FX0A is highlighted
+    fn draw_keypad(&mut self) {
+        let keypad_column = self.keypad_column();
+        let Some(keypad) = self.keypad.as_mut() else {
+            return;
+        };
+        if !keypad.take_changed() {
+            return;
+        }
+
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (n, key) in keys.iter().enumerate() {
+                let (foreground, background) = if keypad.pressed(*key) {
+                    (self.palette.background, self.palette.foreground)
+                } else if keypad.waited(*key) {
+                    (WAITED_KEY_COLOR, self.palette.background)
+                } else {
+                    (self.palette.foreground, self.palette.background)
+                };
+                let label = format!(" {key:X} ");
+                for (offset, c) in label.chars().enumerate() {
+                    let cell = Cell {
+                        c,
+                        foreground,
+                        background,
+                    };
+                    self.screen
+                        .set(row, keypad_column + n * KEY_WIDTH + offset, cell);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn status(&mut self, status: &EmulationStatus) {
+        let Some(status_bar) = self.status_bar.as_mut() else {
+            return;
+        };
+        let text = status.to_string();
+        if *status_bar == text {
+            return;
+        }
+
+        // the status bar is the row below the display, padded to the display width
+        let (width, height) = self.size;
+        let chars: Vec<char> = text.chars().collect();
+        for col in 0..width {
+            let cell = Cell {
+                c: chars.get(col).copied().unwrap_or(' '),
+                foreground: self.palette.foreground,
+                background: self.palette.background,
+            };
+            self.screen.set(height / 2, col, cell);
+        }
+        self.screen.flush();
+        *status_bar = text;
+    }
+
+    fn render(&mut self, display: &[bool], dirty_rows: u64) {
+        trace!("TerminalRenderer::render: start");
+
+        self.resize(display_size(display));
+        self.ghosting.update(display);
+        self.draw_keypad();
+        self.draw(display, dirty_rows);
+
+        trace!("TerminalRenderer::render: exit");
+    }
+
+    fn tick(&mut self, display: &[bool]) {
+        // redraw only the rows with pixels fading out, and the keypad overlay if changed
+        if display_size(display) != self.size {
+            return;
+        }
+        self.draw_keypad();
+        let fading_rows = self.ghosting.fade(display);
+        self.draw(display, fading_rows);
+    }
+
+    fn debug(&mut self, chip8: &Chip8) -> bool {
+        if let Some(keypad) = self.keypad.as_mut() {
+            keypad.update(chip8);
+        }
+
+        true
+    }
+}
+
+impl Drop for TerminalRenderer {
+    fn drop(&mut self) {
+        ansi::write(ansi::RESTORE);
+    }
+}
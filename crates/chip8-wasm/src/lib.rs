@@ -0,0 +1,317 @@
+//! WebAssembly frontend: the CHIP-8 display is drawn on a HTML `<canvas>`,
+//! the keypad is driven by the browser keyboard events.
+//!
+//! The emulation loop is owned by the web page (`requestAnimationFrame`), see `web/index.html`
+
+use chip8_core::{Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH, MAX_DISPLAY_SIZE};
+use chip8_frontend::{Color, Palette, Theme};
+use rand::{rngs::StdRng, SeedableRng};
+use std::str::FromStr;
+use wasm_bindgen::{prelude::*, Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+/// max emulated time of a single `run` call (seconds): avoids running a burst of cycles
+/// after the page has been in background
+const MAX_RUN_TIME: f64 = 0.1;
+
+/// Keyboard to hex keypad mapping: `KeyboardEvent.code` values (physical keys, independent of the layout)
+///
+/// ```text
+/// 1 2 3 4        1 2 3 C
+/// Q W E R   ->   4 5 6 D
+/// A S D F        7 8 9 E
+/// Z X C V        A 0 B F
+/// ```
+const KEYMAP: [(&str, u8); 16] = [
+    ("Digit1", 0x1),
+    ("Digit2", 0x2),
+    ("Digit3", 0x3),
+    ("Digit4", 0xC),
+    ("KeyQ", 0x4),
+    ("KeyW", 0x5),
+    ("KeyE", 0x6),
+    ("KeyR", 0xD),
+    ("KeyA", 0x7),
+    ("KeyS", 0x8),
+    ("KeyD", 0x9),
+    ("KeyF", 0xE),
+    ("KeyZ", 0xA),
+    ("KeyX", 0x0),
+    ("KeyC", 0xB),
+    ("KeyV", 0xF),
+];
+
+/// CHIP-8 emulator running in a web page
+#[wasm_bindgen]
+pub struct WebChip8 {
+    /// CHIP-8 instance (None until a ROM is loaded)
+    chip8: Option<Chip8>,
+
+    /// Random number generator
+    rng: StdRng,
+
+    /// Canvas element (the canvas has the size of the display, 64 x 32 or 128 x 64 pixels, scaled with CSS)
+    canvas: HtmlCanvasElement,
+
+    /// Canvas 2D context
+    context: CanvasRenderingContext2d,
+
+    /// Colors of the pixels
+    palette: Palette,
+
+    /// Emulated time not yet executed (seconds)
+    elapsed: f64,
+}
+
+#[wasm_bindgen]
+impl WebChip8 {
+    /// Returns a new emulator drawing on the given canvas
+    ///
+    /// # Arguments
+    ///
+    /// * `canvas` - The canvas element
+    /// * `seed` - The seed for the random number generator
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement, seed: u32) -> Result<WebChip8, JsValue> {
+        canvas.set_width(DISPLAY_WIDTH as u32);
+        canvas.set_height(DISPLAY_HEIGTH as u32);
+        let context = canvas
+            .get_context("2d")?
+            .ok_or("canvas 2D context not available")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        Ok(Self {
+            chip8: None,
+            rng: StdRng::seed_from_u64(seed as u64),
+            canvas,
+            context,
+            palette: Palette::new(Theme::Mono, None, None),
+            elapsed: 0.0,
+        })
+    }
+
+    /// Sets the colors of the pixels, as in the command line options (e.g. `#33FF66`, `bright-white`)
+    ///
+    /// # Arguments
+    ///
+    /// * `foreground` - The color of the pixels that are on
+    /// * `background` - The color of the pixels that are off
+    pub fn set_colors(&mut self, foreground: &str, background: &str) -> Result<(), JsValue> {
+        self.palette = Palette::new(
+            Theme::Mono,
+            Some(Color::from_str(foreground)?),
+            Some(Color::from_str(background)?),
+        );
+
+        Ok(())
+    }
+
+    /// Loads a ROM, restarting the emulation. Returns an error if the ROM does not fit into memory
+    /// (the emulation of the previous ROM goes on)
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM contents
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsValue> {
+        let chip8 = load_chip8(rom).map_err(|e| JsValue::from_str(&e))?;
+
+        self.chip8 = Some(chip8);
+        self.elapsed = 0.0;
+
+        Ok(())
+    }
+
+    /// Runs the emulation for the time elapsed since the last call and draws the display.
+    /// Must be called at every animation frame
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed_ms` - The time elapsed since the last call (milliseconds)
+    pub fn run(&mut self, elapsed_ms: f64) -> Result<(), JsValue> {
+        let Some(chip8) = self.chip8.as_mut() else {
+            return Ok(());
+        };
+
+        let clock_time_seconds = 1.0 / chip8.clock_frequency();
+        self.elapsed += (elapsed_ms / 1000.0).clamp(0.0, MAX_RUN_TIME);
+        while self.elapsed >= clock_time_seconds {
+            self.elapsed -= clock_time_seconds;
+            chip8
+                .step(&mut self.rng)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        }
+
+        self.draw()
+    }
+
+    /// Returns true if the beeper is on (sound timer > 0)
+    pub fn beeping(&self) -> bool {
+        self.chip8
+            .as_ref()
+            .is_some_and(|chip8| chip8.sound_timer() > 0)
+    }
+
+    /// Fills an audio buffer (mono samples in [-1, 1]) with the beeper tone, e.g. from the audio
+    /// callback of a `ScriptProcessorNode` (silence if no ROM is loaded)
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The audio buffer to fill
+    /// * `sample_rate` - The sample rate of the audio context (samples per second)
+    pub fn fill_audio(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        match self.chip8.as_mut() {
+            Some(chip8) => chip8.fill_audio(buffer, sample_rate),
+            None => buffer.fill(0.0),
+        }
+    }
+
+    /// Presses the keypad key mapped to a keyboard key.
+    /// Returns true if the keyboard key is mapped to a keypad key
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The `KeyboardEvent.code` of the keyboard key
+    pub fn key_down(&mut self, code: &str) -> bool {
+        match (keypad_key(code), self.chip8.as_mut()) {
+            (Some(key), Some(chip8)) => {
+                chip8.key_down(key);
+                true
+            }
+            (key, _) => key.is_some(),
+        }
+    }
+
+    /// Releases the keypad key mapped to a keyboard key.
+    /// Returns true if the keyboard key is mapped to a keypad key
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The `KeyboardEvent.code` of the keyboard key
+    pub fn key_up(&mut self, code: &str) -> bool {
+        match (keypad_key(code), self.chip8.as_mut()) {
+            (Some(key), Some(chip8)) => {
+                chip8.key_up(key);
+                true
+            }
+            (key, _) => key.is_some(),
+        }
+    }
+}
+
+impl WebChip8 {
+    /// Draws the display on the canvas
+    fn draw(&self) -> Result<(), JsValue> {
+        let Some(chip8) = self.chip8.as_ref() else {
+            return Ok(());
+        };
+
+        // the canvas is resized when the display mode changes
+        let (width, height) = chip8.display_size();
+        if self.canvas.width() != width as u32 || self.canvas.height() != height as u32 {
+            self.canvas.set_width(width as u32);
+            self.canvas.set_height(height as u32);
+        }
+
+        let image = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(&rgba_pixels(chip8, self.palette)),
+            width as u32,
+            height as u32,
+        )?;
+        self.context.put_image_data(&image, 0.0, 0.0)
+    }
+}
+
+/// Returns the RGBA pixels of the display, row by row (of the size of the display mode)
+///
+/// # Arguments
+///
+/// * `chip8` - The CHIP-8 instance
+/// * `palette` - The colors of the pixels
+fn rgba_pixels(chip8: &Chip8, palette: Palette) -> Vec<u8> {
+    let (on_r, on_g, on_b) = palette.blend(1.0).to_rgb();
+    let (off_r, off_g, off_b) = palette.blend(0.0).to_rgb();
+    let mut pixels = Vec::with_capacity(MAX_DISPLAY_SIZE * 4);
+    for on in chip8.display_pixels() {
+        if on {
+            pixels.extend_from_slice(&[on_r, on_g, on_b, 0xFF]);
+        } else {
+            pixels.extend_from_slice(&[off_r, off_g, off_b, 0xFF]);
+        }
+    }
+
+    pixels
+}
+
+/// Returns a CHIP-8 instance with a ROM loaded, or an error message if the ROM does not fit into memory
+///
+/// # Arguments
+///
+/// * `rom` - The ROM contents
+fn load_chip8(rom: &[u8]) -> Result<Chip8, String> {
+    let mut chip8 = Chip8::new();
+    if rom.len() > chip8.max_rom_size() {
+        return Err(format!(
+            "ROM too big: {} bytes (max {} bytes)",
+            rom.len(),
+            chip8.max_rom_size()
+        ));
+    }
+    chip8.load_rom_bytes(rom);
+
+    Ok(chip8)
+}
+
+/// Returns the keypad key mapped to a keyboard key, if any
+///
+/// # Arguments
+///
+/// * `code` - The `KeyboardEvent.code` of the keyboard key
+fn keypad_key(code: &str) -> Option<u8> {
+    KEYMAP
+        .iter()
+        .find(|(mapped, _)| *mapped == code)
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_core::MAX_ROM_SIZE;
+
+    #[test]
+    fn rom_bigger_than_memory_is_rejected() {
+        assert!(load_chip8(&[0x12, 0x00]).is_ok_and(|chip8| chip8.rom_loaded()));
+        assert!(load_chip8(&vec![0; MAX_ROM_SIZE]).is_ok());
+        assert_eq!(
+            load_chip8(&vec![0; MAX_ROM_SIZE + 1]).err(),
+            Some(format!(
+                "ROM too big: {} bytes (max {MAX_ROM_SIZE} bytes)",
+                MAX_ROM_SIZE + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn image_follows_the_display_mode() {
+        // 00FF (hires), A000, D005 (glyph of 0)
+        let mut chip8 = load_chip8(&[0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x05]).unwrap();
+        let palette = Palette::new(Theme::Mono, Some(Color::Rgb(1, 2, 3)), None);
+        assert_eq!(rgba_pixels(&chip8, palette).len(), 64 * 32 * 4);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..3 {
+            chip8.step(&mut rng).unwrap();
+        }
+        let pixels = rgba_pixels(&chip8, palette);
+        assert_eq!(pixels.len(), 128 * 64 * 4);
+        assert_eq!(pixels[..4], [1, 2, 3, 0xFF]);
+        assert_eq!(pixels[16..20], [0, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn keyboard_keys_are_mapped_to_the_keypad() {
+        assert_eq!(keypad_key("Digit1"), Some(0x1));
+        assert_eq!(keypad_key("KeyV"), Some(0xF));
+        assert_eq!(keypad_key("KeyP"), None);
+    }
+}
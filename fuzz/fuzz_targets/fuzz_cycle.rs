@@ -0,0 +1,16 @@
+//! Fuzz target of the decoder and the executor: the first 8 bytes of the input
+//! are the seed of the initial state, the rest is the memory window loaded as the ROM
+
+#![no_main]
+
+use chip8_core::fuzz_cycle;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 8 {
+        return;
+    }
+    let (seed, memory_window) = data.split_at(8);
+
+    fuzz_cycle(memory_window, u64::from_le_bytes(seed.try_into().unwrap()));
+});
@@ -0,0 +1,83 @@
+/*
+ * rust-chip-8 C API
+ *
+ * Build the shared library with: cargo build --release --features ffi
+ * (target/release/librust_chip_8.so) and link it with -lrust_chip_8.
+ *
+ * The host owns the emulation loop: call chip8_step() at the CHIP-8 clock
+ * frequency (500Hz), chip8_update_timers() at 60Hz and read the display with
+ * chip8_framebuffer().
+ */
+
+#ifndef RUST_CHIP_8_H
+#define RUST_CHIP_8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* max display size (pixels): 64x32, or 128x64 in the SUPER-CHIP hires mode
+ * (the current size is returned by chip8_display_size()) */
+#define CHIP8_MAX_DISPLAY_WIDTH 128
+#define CHIP8_MAX_DISPLAY_HEIGHT 64
+
+/* chip8_step() return codes */
+#define CHIP8_OK 0
+#define CHIP8_ERROR_ILLEGAL_OPCODE 1       /* illegal or unsupported opcode */
+#define CHIP8_ERROR_STACK_OVERFLOW 2       /* subroutine call with a full stack */
+#define CHIP8_ERROR_STACK_UNDERFLOW 3      /* subroutine return with an empty stack */
+#define CHIP8_ERROR_MEMORY_OUT_OF_BOUNDS 4 /* memory access beyond the end of memory */
+#define CHIP8_ERROR_MACHINE_CODE_CALL 5    /* machine code routine call (0NNN) */
+#define CHIP8_ERROR_INTERNAL (-1)          /* internal error of the interpreter */
+
+/* CHIP-8 instance (opaque) */
+typedef struct Chip8Handle Chip8Handle;
+
+/* Returns a new CHIP-8 instance, to be freed with chip8_free() */
+Chip8Handle *chip8_new(uint64_t seed);
+
+/* Frees a CHIP-8 instance (NULL is ignored) */
+void chip8_free(Chip8Handle *handle);
+
+/* Loads a ROM into memory. Returns false if the ROM does not fit into memory */
+bool chip8_load_rom(Chip8Handle *handle, const uint8_t *rom, size_t size);
+
+/* Resets the instance: if keep_rom is true the loaded ROM is loaded again (the program
+ * restarts from the beginning), otherwise the ROM must be loaded again */
+void chip8_reset(Chip8Handle *handle, bool keep_rom);
+
+/* Executes one instruction. Returns CHIP8_OK, or the CHIP8_ERROR_* code of the error
+ * if the instruction cannot be executed: the program cannot continue, the instance
+ * must be reset or loaded with another ROM */
+int32_t chip8_step(Chip8Handle *handle);
+
+/* Decrements the delay and sound timers: must be called at 60Hz */
+void chip8_update_timers(Chip8Handle *handle);
+
+/* Returns true if the beeper is sounding (sound timer > 0) */
+bool chip8_sound_active(const Chip8Handle *handle);
+
+/* Writes the size of the display in pixels: 64x32, or 128x64 in the SUPER-CHIP hires mode
+ * (the display mode can change at every instruction) */
+void chip8_display_size(const Chip8Handle *handle, size_t *width, size_t *height);
+
+/* Returns the display pixels, row by row (width x height bytes of chip8_display_size(),
+ * 1 if the pixel is on, 0 otherwise). The buffer is owned by the instance
+ * and is valid until the next call of chip8_framebuffer() or chip8_free() */
+const uint8_t *chip8_framebuffer(Chip8Handle *handle);
+
+/* Presses or releases a key of the hex keypad (0x0-0xF) */
+void chip8_set_key(Chip8Handle *handle, uint8_t key, bool pressed);
+
+/* Sets the keys of the hex keypad held down (bit n set if key n is pressed) */
+void chip8_set_keys(Chip8Handle *handle, uint16_t keys);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUST_CHIP_8_H */
@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+"""Generates the ROM database embedded in chip8-cli (crates/chip8-cli/src/programs.json)
+from the programs.json file of the CHIP-8 database (https://github.com/chip-8/chip-8-database),
+keeping only the fields read by the lookup: title, authors, and per ROM the file name, the platforms,
+the quirks of the platforms and the tick rate.
+
+Usage: scripts/programs_subset.py [programs.json] [output]
+
+Without arguments the file is downloaded from the main branch of the CHIP-8 database.
+"""
+
+import json
+import sys
+import urllib.request
+
+SOURCE_URL = "https://raw.githubusercontent.com/chip-8/chip-8-database/master/database/programs.json"
+OUTPUT = "crates/chip8-cli/src/programs.json"
+PROGRAM_FIELDS = ("title", "authors")
+ROM_FIELDS = ("file", "platforms", "quirkyPlatforms", "tickrate")
+
+
+def subset(programs):
+    """Returns the programs with only the fields read by the lookup"""
+    return [
+        {
+            **{field: program[field] for field in PROGRAM_FIELDS if field in program},
+            "roms": {
+                sha1.lower(): {field: rom[field] for field in ROM_FIELDS if field in rom}
+                for sha1, rom in program["roms"].items()
+            },
+        }
+        for program in programs
+    ]
+
+
+def main():
+    if len(sys.argv) > 1:
+        with open(sys.argv[1], encoding="utf-8") as source:
+            programs = json.load(source)
+    else:
+        with urllib.request.urlopen(SOURCE_URL) as source:
+            programs = json.load(source)
+
+    output = sys.argv[2] if len(sys.argv) > 2 else OUTPUT
+    with open(output, "w", encoding="utf-8") as target:
+        json.dump(subset(programs), target, indent=2, ensure_ascii=False)
+        target.write("\n")
+
+
+if __name__ == "__main__":
+    main()
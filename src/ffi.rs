@@ -0,0 +1,320 @@
+//! C ABI bindings: the interpreter is exported from the cdylib with a small C API,
+//! so that frontends written in other languages can embed it (see `include/chip8.h`).
+//!
+//! The host owns the emulation loop: it calls `chip8_step` at the CHIP-8 clock frequency (500Hz),
+//! `chip8_update_timers` at 60Hz and reads the display with `chip8_framebuffer`
+
+use crate::chip8::{Chip8, Error, MAX_DISPLAY_SIZE, MAX_ROM_SIZE};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    slice,
+};
+
+/// `chip8_step` return code: the instruction was executed
+pub const CHIP8_OK: i32 = 0;
+
+/// `chip8_step` return code: illegal or unsupported opcode
+pub const CHIP8_ERROR_ILLEGAL_OPCODE: i32 = 1;
+
+/// `chip8_step` return code: subroutine call with all the stack levels in use
+pub const CHIP8_ERROR_STACK_OVERFLOW: i32 = 2;
+
+/// `chip8_step` return code: subroutine return with an empty stack
+pub const CHIP8_ERROR_STACK_UNDERFLOW: i32 = 3;
+
+/// `chip8_step` return code: memory access beyond the end of memory
+pub const CHIP8_ERROR_MEMORY_OUT_OF_BOUNDS: i32 = 4;
+
+/// `chip8_step` return code: call of a machine code routine (`0NNN`)
+pub const CHIP8_ERROR_MACHINE_CODE_CALL: i32 = 5;
+
+/// `chip8_step` return code: internal error of the interpreter
+pub const CHIP8_ERROR_INTERNAL: i32 = -1;
+
+/// CHIP-8 instance handle given to C code
+pub struct Chip8Handle {
+    /// CHIP-8 instance
+    chip8: Chip8,
+
+    /// Random number generator
+    rng: StdRng,
+
+    /// Display pixels returned by `chip8_framebuffer` (1 if the pixel is on, 0 otherwise)
+    framebuffer: [u8; MAX_DISPLAY_SIZE],
+}
+
+/// Returns a new CHIP-8 instance, to be freed with `chip8_free`
+///
+/// # Arguments
+///
+/// * `seed` - The seed for the random number generator
+#[no_mangle]
+pub extern "C" fn chip8_new(seed: u64) -> *mut Chip8Handle {
+    Box::into_raw(Box::new(Chip8Handle {
+        chip8: Chip8::new(),
+        rng: StdRng::seed_from_u64(seed),
+        framebuffer: [0; MAX_DISPLAY_SIZE],
+    }))
+}
+
+/// Frees a CHIP-8 instance
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `chip8_new`, not already freed
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(handle: *mut Chip8Handle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Loads a ROM into the memory of a CHIP-8 instance.
+/// Returns false if the ROM does not fit into memory
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`,
+/// `rom` must point to `size` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    handle: *mut Chip8Handle,
+    rom: *const u8,
+    size: usize,
+) -> bool {
+    let handle = &mut *handle;
+    if rom.is_null() || size > MAX_ROM_SIZE {
+        return false;
+    }
+
+    handle
+        .chip8
+        .load_rom_bytes(slice::from_raw_parts(rom, size));
+    true
+}
+
+/// Resets a CHIP-8 instance: if `keep_rom` is true the loaded ROM is loaded again,
+/// so that the program restarts from the beginning, otherwise the ROM must be loaded again
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(handle: *mut Chip8Handle, keep_rom: bool) {
+    let handle = &mut *handle;
+    handle.chip8.reset(keep_rom);
+}
+
+/// Executes one CHIP-8 instruction.
+/// Returns `CHIP8_OK`, or the `CHIP8_ERROR_*` code of the error if the instruction cannot be executed:
+/// the program cannot continue, the instance must be reset or loaded with another ROM
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(handle: *mut Chip8Handle) -> i32 {
+    let handle = &mut *handle;
+
+    // panics must not unwind into C code
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        handle.chip8.try_emulate_cycle(&mut handle.rng)
+    }));
+    match result {
+        Ok(Ok(())) => CHIP8_OK,
+        Ok(Err(Error::IllegalOpcode(_))) => CHIP8_ERROR_ILLEGAL_OPCODE,
+        Ok(Err(Error::StackOverflow)) => CHIP8_ERROR_STACK_OVERFLOW,
+        Ok(Err(Error::StackUnderflow)) => CHIP8_ERROR_STACK_UNDERFLOW,
+        Ok(Err(Error::MemoryOutOfBounds(_))) => CHIP8_ERROR_MEMORY_OUT_OF_BOUNDS,
+        Ok(Err(Error::MachineCodeCall(_))) => CHIP8_ERROR_MACHINE_CODE_CALL,
+        Err(_) => CHIP8_ERROR_INTERNAL,
+    }
+}
+
+/// Decrements the delay and sound timers: must be called at 60Hz
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_update_timers(handle: *mut Chip8Handle) {
+    (*handle).chip8.update_timers();
+}
+
+/// Returns true if the beeper is sounding (sound timer > 0)
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(handle: *const Chip8Handle) -> bool {
+    (*handle).chip8.sound_timer() > 0
+}
+
+/// Writes the size of the display in pixels: 64x32, or 128x64 in the SUPER-CHIP hires mode
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`,
+/// `width` and `height` must be valid pointers
+#[no_mangle]
+pub unsafe extern "C" fn chip8_display_size(
+    handle: *const Chip8Handle,
+    width: *mut usize,
+    height: *mut usize,
+) {
+    (*width, *height) = (*handle).chip8.display_size();
+}
+
+/// Returns the display pixels, row by row (width x height bytes of `chip8_display_size`,
+/// 1 if the pixel is on, 0 otherwise). The buffer is owned by the instance and
+/// is valid until the next call of `chip8_framebuffer` or `chip8_free`
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(handle: *mut Chip8Handle) -> *const u8 {
+    let handle = &mut *handle;
+    for (byte, on) in handle
+        .framebuffer
+        .iter_mut()
+        .zip(handle.chip8.display_pixels())
+    {
+        *byte = on as u8;
+    }
+
+    handle.framebuffer.as_ptr()
+}
+
+/// Presses or releases a key of the hex keypad
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(handle: *mut Chip8Handle, key: u8, pressed: bool) {
+    let chip8 = &mut (*handle).chip8;
+    if pressed {
+        chip8.key_down(key);
+    } else {
+        chip8.key_up(key);
+    }
+}
+
+/// Sets the keys of the hex keypad held down (bit n set if key n is pressed)
+///
+/// # Safety
+///
+/// `handle` must be a valid pointer returned by `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_keys(handle: *mut Chip8Handle, keys: u16) {
+    (*handle).chip8.set_keys(keys);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH};
+    use std::ptr;
+
+    /// Returns a new instance with a ROM loaded
+    fn chip8(rom: &[u8]) -> *mut Chip8Handle {
+        let handle = chip8_new(0);
+        assert!(unsafe { chip8_load_rom(handle, rom.as_ptr(), rom.len()) });
+        handle
+    }
+
+    /// Returns the display size of an instance
+    fn display_size(handle: *const Chip8Handle) -> (usize, usize) {
+        let (mut width, mut height) = (0, 0);
+        unsafe { chip8_display_size(handle, &mut width, &mut height) };
+        (width, height)
+    }
+
+    #[test]
+    fn display_size_follows_the_display_mode() {
+        // 00FF (hires), A000, D005 (glyph of 0)
+        let handle = chip8(&[0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x05]);
+        assert_eq!(display_size(handle), (64, 32));
+
+        for _ in 0..3 {
+            assert_eq!(unsafe { chip8_step(handle) }, CHIP8_OK);
+        }
+        assert_eq!(
+            display_size(handle),
+            (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH)
+        );
+
+        // the first row of the glyph, then the second row (1001)
+        let framebuffer = unsafe { slice::from_raw_parts(chip8_framebuffer(handle), 256) };
+        assert_eq!(framebuffer[..5], [1, 1, 1, 1, 0]);
+        assert_eq!(framebuffer[128..133], [1, 0, 0, 1, 0]);
+
+        unsafe { chip8_free(handle) };
+    }
+
+    #[test]
+    fn step_returns_the_error_codes() {
+        let codes = [
+            // illegal opcode
+            (vec![0xE0, 0x00], CHIP8_ERROR_ILLEGAL_OPCODE),
+            // 00EE with an empty stack
+            (vec![0x00, 0xEE], CHIP8_ERROR_STACK_UNDERFLOW),
+            // 0NNN (machine code routine)
+            (vec![0x03, 0x00], CHIP8_ERROR_MACHINE_CODE_CALL),
+            // A000 (I = 0xFFF), F165 (load past the end of memory)
+            (
+                vec![0xAF, 0xFF, 0xF1, 0x65],
+                CHIP8_ERROR_MEMORY_OUT_OF_BOUNDS,
+            ),
+        ];
+        for (rom, code) in codes {
+            let handle = chip8(&rom);
+            let mut result = CHIP8_OK;
+            for _ in 0..rom.len() / 2 {
+                result = unsafe { chip8_step(handle) };
+            }
+            assert_eq!(result, code, "{rom:02X?}");
+            unsafe { chip8_free(handle) };
+        }
+
+        // 2200: calls itself until the stack is full
+        let handle = chip8(&[0x22, 0x00]);
+        for _ in 0..16 {
+            assert_eq!(unsafe { chip8_step(handle) }, CHIP8_OK);
+        }
+        assert_eq!(unsafe { chip8_step(handle) }, CHIP8_ERROR_STACK_OVERFLOW);
+        unsafe { chip8_free(handle) };
+    }
+
+    #[test]
+    fn rom_bigger_than_memory_is_rejected() {
+        let handle = chip8_new(0);
+        let rom = vec![0; MAX_ROM_SIZE + 1];
+        assert!(!unsafe { chip8_load_rom(handle, rom.as_ptr(), rom.len()) });
+        assert!(!unsafe { chip8_load_rom(handle, ptr::null(), 0) });
+        unsafe { chip8_free(handle) };
+    }
+
+    #[test]
+    fn keys_are_set_as_a_bitmask() {
+        // F30A: waits for a key (read when released) into V3
+        let handle = chip8(&[0xF3, 0x0A]);
+        unsafe { chip8_set_keys(handle, 0b1010_0000) };
+        assert_eq!(unsafe { (*handle).chip8.keys() }, 0b1010_0000);
+        assert_eq!(unsafe { chip8_step(handle) }, CHIP8_OK);
+        assert_eq!(unsafe { (*handle).chip8.pc() }, 0x200);
+
+        // key 5 released, key 7 still pressed
+        unsafe { chip8_set_keys(handle, 0b1000_0000) };
+        assert_eq!(unsafe { chip8_step(handle) }, CHIP8_OK);
+        assert_eq!(unsafe { (*handle).chip8.pc() }, 0x202);
+        assert_eq!(unsafe { (*handle).chip8.v()[3] }, 5);
+
+        unsafe { chip8_set_key(handle, 7, false) };
+        assert_eq!(unsafe { (*handle).chip8.keys() }, 0);
+        unsafe { chip8_free(handle) };
+    }
+}
@@ -0,0 +1,29 @@
+//! # rust-chip-8
+//!
+//! `rust-chip-8` is a simple implementation of CHIP-8 written in Rust for fun and training purposes.
+//!
+//! The library contains the C, libretro and Python bindings of the CHIP-8 interpreter, that is re-exported
+//! from the `chip8-core` crate as the `chip8` module. The command line frontend is the `chip8-cli` crate
+//! (the `rust-chip-8` binary), the emulation loop and the display renderers are the frontend crates.
+//!
+//! Without the default `std` feature the library is `no_std` (it requires `alloc`):
+//! only the interpreter core is available, the host drives it with `emulate_cycle`
+//! (or `try_emulate_cycle`, that returns execution errors instead of panicking),
+//! `update_timers`, `load_rom_bytes` and the keypad functions
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// on hosted targets the cdylib needs the panic handler and the allocator of std,
+// the name is not imported so that the core cannot use std without the feature
+#[cfg(all(not(feature = "std"), not(target_os = "none")))]
+extern crate std as _;
+
+pub use chip8_core as chip8;
+#[cfg(feature = "embedded-graphics")]
+pub use chip8_core::embedded;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+#[cfg(feature = "python")]
+pub mod python;
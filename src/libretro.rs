@@ -0,0 +1,690 @@
+//! libretro core: the interpreter is exported with the libretro C API,
+//! so that the `rust-chip-8` library can be loaded by libretro frontends (e.g. RetroArch).
+//!
+//! The frontend drives the emulation calling `retro_run` 60 times per second:
+//! every call executes one timer tick worth of instructions, decrements the timers,
+//! and sends the display (XRGB8888, 64x32 or 128x64 in the SUPER-CHIP hires mode)
+//! and the beeper audio to the frontend.
+//! An execution error stops the emulation until the game is reset or reloaded:
+//! it is reported with the log interface and an on-screen message of the frontend
+
+use crate::chip8::{
+    Chip8, Error, DISPLAY_HEIGTH, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH,
+    KEYPAD_SIZE, MAX_ROM_SIZE, TIMERS_FREQUENCY,
+};
+use chip8_frontend::{Palette, Theme};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    ffi::{c_char, c_uint, c_void, CString},
+    ptr, slice,
+    sync::Mutex,
+};
+
+/// libretro API version
+const RETRO_API_VERSION: c_uint = 1;
+
+/// environment command: show a message on screen
+const RETRO_ENVIRONMENT_SET_MESSAGE: c_uint = 6;
+
+/// environment command: set the pixel format of the video frames
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+
+/// environment command: get the log interface of the frontend
+const RETRO_ENVIRONMENT_GET_LOG_INTERFACE: c_uint = 27;
+
+/// log level: error
+const RETRO_LOG_ERROR: c_uint = 3;
+
+/// duration of the on-screen error message (frames)
+const ERROR_MESSAGE_FRAMES: c_uint = 180;
+
+/// pixel format: 32-bit XRGB
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+/// region: NTSC (60 frames per second)
+const RETRO_REGION_NTSC: c_uint = 0;
+
+/// input device: gamepad
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+
+/// input device: keyboard
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+
+/// audio sample rate (samples per second)
+const AUDIO_SAMPLE_RATE: u32 = 44100;
+
+/// core name
+const LIBRARY_NAME: &[u8] = b"rust-chip-8\0";
+
+/// core version
+const LIBRARY_VERSION: &[u8] = concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes();
+
+/// format of the log messages (printf-like)
+const LOG_FORMAT: &[u8] = b"%s\n\0";
+
+/// ROM file extensions
+const VALID_EXTENSIONS: &[u8] = b"ch8|c8\0";
+
+/// seed of the random number generator
+const RNG_SEED: u64 = 10;
+
+/// Keyboard to hex keypad mapping (libretro key codes are the ASCII codes of the keys)
+///
+/// ```text
+/// 1 2 3 4        1 2 3 C
+/// Q W E R   ->   4 5 6 D
+/// A S D F        7 8 9 E
+/// Z X C V        A 0 B F
+/// ```
+const KEYBOARD_KEYMAP: [(u8, u8); 16] = [
+    (b'1', 0x1),
+    (b'2', 0x2),
+    (b'3', 0x3),
+    (b'4', 0xC),
+    (b'q', 0x4),
+    (b'w', 0x5),
+    (b'e', 0x6),
+    (b'r', 0xD),
+    (b'a', 0x7),
+    (b's', 0x8),
+    (b'd', 0x9),
+    (b'f', 0xE),
+    (b'z', 0xA),
+    (b'x', 0x0),
+    (b'c', 0xB),
+    (b'v', 0xF),
+];
+
+/// Gamepad to hex keypad mapping (libretro joypad button ids):
+/// the D-pad is mapped to 2/4/6/8, the most common movement keys of CHIP-8 games
+const JOYPAD_KEYMAP: [(c_uint, u8); 8] = [
+    (4, 0x2), // up
+    (5, 0x8), // down
+    (6, 0x4), // left
+    (7, 0x6), // right
+    (8, 0x5), // A
+    (0, 0x0), // B
+    (9, 0x1), // X
+    (1, 0x3), // Y
+];
+
+/// Environment callback
+type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+
+/// Log callback of the frontend (printf-like)
+type LogPrintfFn = unsafe extern "C" fn(level: c_uint, fmt: *const c_char, ...);
+
+/// Video frame callback
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+
+/// Single audio sample callback
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+
+/// Audio samples batch callback (interleaved stereo frames)
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+
+/// Input polling callback
+type InputPollFn = unsafe extern "C" fn();
+
+/// Input state callback
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+/// libretro `retro_system_info` structure
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+/// libretro `retro_game_geometry` structure
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+/// libretro `retro_system_timing` structure
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+/// libretro `retro_system_av_info` structure
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+/// libretro `retro_log_callback` structure
+#[repr(C)]
+struct RetroLogCallback {
+    log: Option<LogPrintfFn>,
+}
+
+/// libretro `retro_message` structure
+#[repr(C)]
+struct RetroMessage {
+    msg: *const c_char,
+    frames: c_uint,
+}
+
+/// libretro `retro_game_info` structure
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+/// State of the core: frontend callbacks and loaded game
+struct Core {
+    /// Frontend callbacks
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+
+    /// CHIP-8 instance (None until a game is loaded)
+    chip8: Option<Chip8>,
+
+    /// Loaded ROM (kept for resets)
+    rom: Vec<u8>,
+
+    /// Random number generator
+    rng: Option<StdRng>,
+
+    /// Fractional audio samples carried between frames
+    samples_remainder: f64,
+
+    /// Execution error that stopped the emulation (None while running)
+    error: Option<Error>,
+}
+
+/// core state, shared by the exported functions
+static CORE: Mutex<Core> = Mutex::new(Core {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    chip8: None,
+    rom: Vec::new(),
+    rng: None,
+    samples_remainder: 0.0,
+    error: None,
+});
+
+/// Runs a function with exclusive access to the core state
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> T {
+    let mut core = match CORE.lock() {
+        Ok(core) => core,
+        // a panic in a previous call must not make the core unusable
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    f(&mut core)
+}
+
+impl Core {
+    /// Starts the emulation of the loaded ROM from the beginning
+    fn restart(&mut self) {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&self.rom);
+
+        self.chip8 = Some(chip8);
+        self.rng = Some(StdRng::seed_from_u64(RNG_SEED));
+        self.samples_remainder = 0.0;
+        self.error = None;
+    }
+
+    /// Reports an execution error to the frontend, in its log and on screen
+    ///
+    /// # Arguments
+    ///
+    /// * `e` - The execution error
+    fn report_error(&self, e: Error) {
+        let Some(environment) = self.environment else {
+            return;
+        };
+        let Ok(message) = CString::new(format!("rust-chip-8: emulation stopped: {e}")) else {
+            return;
+        };
+
+        // SAFETY: callback given by the frontend, the structures are valid for the duration of the calls
+        unsafe {
+            let mut log = RetroLogCallback { log: None };
+            if environment(
+                RETRO_ENVIRONMENT_GET_LOG_INTERFACE,
+                &mut log as *mut RetroLogCallback as *mut c_void,
+            ) {
+                if let Some(log) = log.log {
+                    log(
+                        RETRO_LOG_ERROR,
+                        LOG_FORMAT.as_ptr() as *const c_char,
+                        message.as_ptr(),
+                    );
+                }
+            }
+            let mut on_screen = RetroMessage {
+                msg: message.as_ptr(),
+                frames: ERROR_MESSAGE_FRAMES,
+            };
+            environment(
+                RETRO_ENVIRONMENT_SET_MESSAGE,
+                &mut on_screen as *mut RetroMessage as *mut c_void,
+            );
+        }
+    }
+
+    /// Updates the keypad with the state of the keyboard and gamepad buttons
+    fn poll_keypad(&mut self) {
+        let (Some(input_poll), Some(input_state), Some(chip8)) =
+            (self.input_poll, self.input_state, self.chip8.as_mut())
+        else {
+            return;
+        };
+
+        let mut pressed = [false; KEYPAD_SIZE];
+        // SAFETY: callbacks given by the frontend, called as required by the libretro API
+        unsafe {
+            input_poll();
+            for (code, key) in KEYBOARD_KEYMAP {
+                pressed[key as usize] |=
+                    input_state(0, RETRO_DEVICE_KEYBOARD, 0, code as c_uint) != 0;
+            }
+            for (id, key) in JOYPAD_KEYMAP {
+                pressed[key as usize] |= input_state(0, RETRO_DEVICE_JOYPAD, 0, id) != 0;
+            }
+        }
+
+        for (key, pressed) in pressed.into_iter().enumerate() {
+            if pressed {
+                chip8.key_down(key as u8);
+            } else {
+                chip8.key_up(key as u8);
+            }
+        }
+    }
+
+    /// Runs one frame (one timer tick) of emulation and sends video and audio to the frontend.
+    /// After an execution error the emulation is stopped: the last display is sent, with no sound
+    fn run_frame(&mut self) {
+        self.poll_keypad();
+
+        let (Some(chip8), Some(rng)) = (self.chip8.as_mut(), self.rng.as_mut()) else {
+            return;
+        };
+
+        // instructions of one timer tick
+        if self.error.is_none() {
+            if let Err(e) = chip8.run_frame(rng) {
+                self.error = Some(e);
+                self.report_error(e);
+            }
+        }
+        let Some(chip8) = self.chip8.as_mut() else {
+            return;
+        };
+
+        // beeper audio of the frame, in stereo 16-bit samples
+        let samples = AUDIO_SAMPLE_RATE as f64 / TIMERS_FREQUENCY + self.samples_remainder;
+        self.samples_remainder = samples.fract();
+        let mut tone = vec![0.0; samples as usize];
+        if self.error.is_none() {
+            chip8.fill_audio(&mut tone, AUDIO_SAMPLE_RATE);
+        }
+        let audio: Vec<i16> = tone
+            .iter()
+            .flat_map(|sample| {
+                let sample = (sample * i16::MAX as f32) as i16;
+                [sample, sample]
+            })
+            .collect();
+
+        // display in XRGB8888
+        let palette = Palette::new(Theme::Mono, None, None);
+        let color = |intensity: f64| {
+            let (r, g, b) = palette.blend(intensity).to_rgb();
+            (r as u32) << 16 | (g as u32) << 8 | b as u32
+        };
+        let (on, off) = (color(1.0), color(0.0));
+        // the frame size follows the display mode (64x32 or 128x64, within the max geometry)
+        let (width, height) = chip8.display_size();
+        let frame: Vec<u32> = chip8
+            .display_pixels()
+            .iter()
+            .map(|pixel| if *pixel { on } else { off })
+            .collect();
+
+        // SAFETY: callbacks given by the frontend, the buffers are valid for the duration of the calls
+        unsafe {
+            if let Some(video_refresh) = self.video_refresh {
+                video_refresh(
+                    frame.as_ptr() as *const c_void,
+                    width as c_uint,
+                    height as c_uint,
+                    width * 4,
+                );
+            }
+            if let Some(audio_sample_batch) = self.audio_sample_batch {
+                audio_sample_batch(audio.as_ptr(), audio.len() / 2);
+            }
+        }
+    }
+}
+
+/// Returns the libretro API version implemented by the core
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// Sets the environment callback
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    with_core(|core| core.environment = Some(callback));
+}
+
+/// Sets the video frame callback
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    with_core(|core| core.video_refresh = Some(callback));
+}
+
+/// Sets the single audio sample callback (unused: audio is sent in batches)
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+/// Sets the audio samples batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    with_core(|core| core.audio_sample_batch = Some(callback));
+}
+
+/// Sets the input polling callback
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    with_core(|core| core.input_poll = Some(callback));
+}
+
+/// Sets the input state callback
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    with_core(|core| core.input_state = Some(callback));
+}
+
+/// Initializes the core
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+/// Deinitializes the core
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    with_core(|core| {
+        core.chip8 = None;
+        core.rom.clear();
+    });
+}
+
+/// Fills the core information
+///
+/// # Safety
+///
+/// `info` must be a valid pointer to a `retro_system_info` structure
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: LIBRARY_NAME.as_ptr() as *const c_char,
+        library_version: LIBRARY_VERSION.as_ptr() as *const c_char,
+        valid_extensions: VALID_EXTENSIONS.as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// Fills the audio and video information
+///
+/// # Safety
+///
+/// `info` must be a valid pointer to a `retro_system_av_info` structure
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: DISPLAY_WIDTH as c_uint,
+            base_height: DISPLAY_HEIGTH as c_uint,
+            max_width: HIRES_DISPLAY_WIDTH as c_uint,
+            max_height: HIRES_DISPLAY_HEIGTH as c_uint,
+            aspect_ratio: DISPLAY_WIDTH as f32 / DISPLAY_HEIGTH as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: TIMERS_FREQUENCY,
+            sample_rate: AUDIO_SAMPLE_RATE as f64,
+        },
+    };
+}
+
+/// Sets the device of a controller port (every device is accepted)
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// Restarts the loaded game
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(|core| {
+        if core.chip8.is_some() {
+            core.restart();
+        }
+    });
+}
+
+/// Runs one frame of emulation
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_core(Core::run_frame);
+}
+
+/// Returns the size of the save states (not supported)
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+/// Saves a state (not supported)
+#[no_mangle]
+pub extern "C" fn retro_serialize(_data: *mut c_void, _size: usize) -> bool {
+    false
+}
+
+/// Loads a state (not supported)
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_data: *const c_void, _size: usize) -> bool {
+    false
+}
+
+/// Removes all the cheats (not supported)
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// Sets a cheat (not supported)
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// Loads a game: the ROM contents are given by the frontend
+///
+/// # Safety
+///
+/// `game` must be null or a valid pointer to a `retro_game_info` structure,
+/// whose `data` points to `size` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() || game.size > MAX_ROM_SIZE {
+        return false;
+    }
+    let rom = slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+
+    with_core(|core| {
+        let Some(environment) = core.environment else {
+            return false;
+        };
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+
+        core.rom = rom;
+        core.restart();
+        true
+    })
+}
+
+/// Loads a special game (not supported)
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+/// Unloads the game
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_core(|core| {
+        core.chip8 = None;
+        core.rom.clear();
+    });
+}
+
+/// Returns the region of the game
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// Returns a memory region of the core (not supported)
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+/// Returns the size of a memory region of the core (not supported)
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ffi::CStr, mem::MaybeUninit, sync::PoisonError};
+
+    /// on-screen messages sent to the test frontend
+    static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    /// size and first pixel of the last video frame sent to the test frontend
+    static FRAME: Mutex<Option<(c_uint, c_uint, u32)>> = Mutex::new(None);
+
+    /// the core state is global: the tests loading a game run one at a time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Environment callback of the test frontend (no log interface, the messages are recorded)
+    unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+        match cmd {
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => true,
+            RETRO_ENVIRONMENT_SET_MESSAGE => {
+                let message = &*(data as *const RetroMessage);
+                let text = CStr::from_ptr(message.msg).to_string_lossy().into_owned();
+                MESSAGES.lock().unwrap().push(text);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Video frame callback of the test frontend
+    unsafe extern "C" fn video_refresh(
+        data: *const c_void,
+        width: c_uint,
+        height: c_uint,
+        _: usize,
+    ) {
+        *FRAME.lock().unwrap() = Some((width, height, *(data as *const u32)));
+    }
+
+    /// Loads a ROM into the core
+    fn load_game(rom: &[u8]) {
+        let game = RetroGameInfo {
+            path: ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+        retro_set_environment(environment);
+        retro_set_video_refresh(video_refresh);
+        assert!(unsafe { retro_load_game(&game) });
+    }
+
+    #[test]
+    fn frames_follow_the_display_mode() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut info = MaybeUninit::<RetroSystemAvInfo>::uninit();
+        let info = unsafe {
+            retro_get_system_av_info(info.as_mut_ptr());
+            info.assume_init()
+        };
+        assert_eq!(
+            (info.geometry.max_width, info.geometry.max_height),
+            (128, 64)
+        );
+
+        // 00FF (hires), A000, D005 (glyph of 0), 1206 (idle loop)
+        load_game(&[0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x05, 0x12, 0x06]);
+        retro_run();
+        let (width, height, pixel) = FRAME.lock().unwrap().unwrap();
+        assert_eq!((width, height), (128, 64));
+        assert_eq!(pixel, 0xFFFFFF);
+        retro_unload_game();
+    }
+
+    #[test]
+    fn execution_error_stops_the_emulation_and_is_reported() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        // illegal opcode at the start of the ROM
+        load_game(&[0xE0, 0x00]);
+
+        retro_run();
+        retro_run();
+        assert_eq!(
+            with_core(|core| core.error),
+            Some(Error::IllegalOpcode(0xE000))
+        );
+        // reported once, the emulation is not stepped after the error
+        assert_eq!(
+            *MESSAGES.lock().unwrap(),
+            ["rust-chip-8: emulation stopped: Illegal opcode: `0xE000`"]
+        );
+
+        retro_reset();
+        assert_eq!(with_core(|core| core.error), None);
+        retro_unload_game();
+    }
+}
@@ -0,0 +1,339 @@
+//! Python bindings: the `pychip8` module wraps the interpreter, so that the machine can be
+//! scripted from Python (e.g. notebooks): load a ROM, step N cycles, inspect the registers
+//! and fetch the framebuffer.
+//!
+//! ```python
+//! import numpy, pychip8
+//!
+//! machine = pychip8.Chip8()
+//! machine.load_rom(open("roms/IBM_logo.ch8", "rb").read())
+//! machine.step(100)
+//! print(hex(machine.pc), machine.v)
+//! display = numpy.asarray(machine.framebuffer())  # (height, width) array of 0/1 bytes
+//! ```
+
+use crate::chip8::{
+    self, Chip8, DISPLAY_HEIGTH, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGTH, HIRES_DISPLAY_WIDTH,
+    MAX_ROM_SIZE,
+};
+use pyo3::{
+    exceptions::{PyBufferError, PyRuntimeError, PyValueError},
+    ffi,
+    prelude::*,
+    types::PyBytes,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    ffi::{c_char, c_int, c_void},
+    ptr,
+};
+
+/// default seed of the random number generator (as in the command line frontend)
+const DEFAULT_SEED: u64 = 10;
+
+/// format of the framebuffer items (unsigned bytes), for the buffer protocol
+const FRAMEBUFFER_FORMAT: &[u8] = b"B\0";
+
+/// CHIP-8 machine
+#[pyclass(name = "Chip8")]
+pub struct PyChip8 {
+    /// CHIP-8 instance
+    chip8: Chip8,
+
+    /// Random number generator
+    rng: StdRng,
+}
+
+#[pymethods]
+impl PyChip8 {
+    /// Returns a new CHIP-8 machine
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed for the random number generator
+    #[new]
+    #[pyo3(signature = (seed = DEFAULT_SEED))]
+    fn new(seed: u64) -> Self {
+        Self {
+            chip8: Chip8::new(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Loads a ROM into memory (raises ValueError if the ROM does not fit into memory)
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM contents
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        if rom.len() > MAX_ROM_SIZE {
+            return Err(PyValueError::new_err(format!(
+                "ROM too big: {} bytes (max {MAX_ROM_SIZE} bytes)",
+                rom.len()
+            )));
+        }
+        self.chip8.load_rom_bytes(rom);
+
+        Ok(())
+    }
+
+    /// Resets the machine: the loaded ROM is loaded again (the program restarts from the beginning)
+    /// unless `keep_rom` is false
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_rom` - Boolean set to true to load the current ROM again
+    #[pyo3(signature = (keep_rom = true))]
+    fn reset(&mut self, keep_rom: bool) {
+        self.chip8.reset(keep_rom);
+    }
+
+    /// Executes CHIP-8 instructions (raises RuntimeError if an instruction cannot be executed,
+    /// e.g. an illegal opcode or a stack overflow: the machine must not be stepped anymore).
+    /// Timers are not updated: `update_timers` must be called every 1/60 s of emulated time
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of instructions to execute
+    #[pyo3(signature = (cycles = 1))]
+    fn step(&mut self, cycles: u64) -> PyResult<()> {
+        for _ in 0..cycles {
+            self.chip8
+                .try_emulate_cycle(&mut self.rng)
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Decrements the delay and sound timers (one 60Hz tick)
+    fn update_timers(&mut self) {
+        self.chip8.update_timers();
+    }
+
+    /// Presses a key of the hex keypad (0x0-0xF)
+    fn key_down(&mut self, key: u8) {
+        self.chip8.key_down(key);
+    }
+
+    /// Releases a key of the hex keypad (0x0-0xF)
+    fn key_up(&mut self, key: u8) {
+        self.chip8.key_up(key);
+    }
+
+    /// Sets the keys of the hex keypad held down (bit n set if key n is pressed)
+    fn set_keys(&mut self, keys: u16) {
+        self.chip8.set_keys(keys);
+    }
+
+    /// Returns the display pixels (1 if the pixel is on, 0 otherwise), a `Framebuffer`
+    /// of the size of `display_size` read through the buffer protocol (e.g. `numpy.asarray`)
+    fn framebuffer(&self) -> Framebuffer {
+        Framebuffer::new(&self.chip8)
+    }
+
+    /// Size (width, height) of the display in pixels: 64x32, or 128x64 in the SUPER-CHIP hires mode
+    #[getter]
+    fn display_size(&self) -> (usize, usize) {
+        self.chip8.display_size()
+    }
+
+    /// Registers V0-VF (bytes)
+    #[getter]
+    fn v(&self) -> Vec<u8> {
+        self.chip8.v().to_vec()
+    }
+
+    /// Index register (I)
+    #[getter]
+    fn i(&self) -> u16 {
+        self.chip8.i()
+    }
+
+    /// Program counter (PC)
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.chip8.pc()
+    }
+
+    /// Stack levels in use (from the bottom to the top of the stack)
+    #[getter]
+    fn stack(&self) -> Vec<u16> {
+        self.chip8.stack().to_vec()
+    }
+
+    /// Delay timer
+    #[getter]
+    fn delay_timer(&self) -> u8 {
+        self.chip8.delay_timer()
+    }
+
+    /// Sound timer
+    #[getter]
+    fn sound_timer(&self) -> u8 {
+        self.chip8.sound_timer()
+    }
+
+    /// Last executed opcode
+    #[getter]
+    fn opcode(&self) -> u16 {
+        self.chip8.opcode()
+    }
+
+    /// RAM memory (4096 bytes by default)
+    #[getter]
+    fn memory<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.chip8.memory())
+    }
+}
+
+/// Display pixels of a CHIP-8 machine: a read-only buffer of unsigned bytes (1 if the pixel is on, 0 otherwise)
+/// of shape (height, width), e.g. `numpy.asarray(framebuffer)`, `memoryview(framebuffer)` or `bytes(framebuffer)`
+#[pyclass(frozen)]
+pub struct Framebuffer {
+    /// Pixels, row by row
+    pixels: Vec<u8>,
+
+    /// Shape of the buffer: height and width of the display
+    shape: [isize; 2],
+
+    /// Strides of the buffer in bytes: row and pixel
+    strides: [isize; 2],
+}
+
+impl Framebuffer {
+    /// Returns the framebuffer of the current display of a CHIP-8 instance
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The CHIP-8 instance
+    fn new(chip8: &Chip8) -> Self {
+        let (width, height) = chip8.display_size();
+        let pixels = chip8.framebuffer().map(|on| on as u8).collect();
+
+        Self {
+            pixels,
+            shape: [height as isize, width as isize],
+            strides: [width as isize, 1],
+        }
+    }
+}
+
+#[pymethods]
+impl Framebuffer {
+    /// Exports the pixels with the buffer protocol (read-only, 2 dimensions, C-contiguous)
+    ///
+    /// # Safety
+    ///
+    /// `view` must be null or a valid pointer to a `Py_buffer` structure, as given by Python
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("null buffer view"));
+        }
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("the framebuffer is read-only"));
+        }
+
+        // the buffer, its shape and its strides live as long as the framebuffer, kept alive by the view
+        let framebuffer = slf.get();
+        let view = &mut *view;
+        view.buf = framebuffer.pixels.as_ptr() as *mut c_void;
+        view.len = framebuffer.pixels.len() as isize;
+        view.readonly = 1;
+        view.itemsize = 1;
+        view.format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            FRAMEBUFFER_FORMAT.as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        view.ndim = 2;
+        view.shape = if flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+            framebuffer.shape.as_ptr() as *mut isize
+        } else {
+            ptr::null_mut()
+        };
+        view.strides = if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+            framebuffer.strides.as_ptr() as *mut isize
+        } else {
+            ptr::null_mut()
+        };
+        view.suboffsets = ptr::null_mut();
+        view.internal = ptr::null_mut();
+        view.obj = slf.into_any().into_ptr();
+
+        Ok(())
+    }
+
+    /// Size (width, height) of the display in pixels
+    #[getter]
+    fn display_size(&self) -> (usize, usize) {
+        (self.shape[1] as usize, self.shape[0] as usize)
+    }
+
+    /// Number of pixels
+    fn __len__(&self) -> usize {
+        self.pixels.len()
+    }
+}
+
+/// Returns the assembly mnemonic of a CHIP-8 opcode
+#[pyfunction]
+fn disassemble(opcode: u16) -> String {
+    chip8::disassemble(opcode)
+}
+
+/// CHIP-8 interpreter
+#[pymodule]
+fn pychip8(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyChip8>()?;
+    module.add_class::<Framebuffer>()?;
+    module.add_function(wrap_pyfunction!(disassemble, module)?)?;
+    module.add("DISPLAY_WIDTH", DISPLAY_WIDTH)?;
+    module.add("DISPLAY_HEIGHT", DISPLAY_HEIGTH)?;
+    module.add("HIRES_DISPLAY_WIDTH", HIRES_DISPLAY_WIDTH)?;
+    module.add("HIRES_DISPLAY_HEIGHT", HIRES_DISPLAY_HEIGTH)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framebuffer_follows_the_display_mode() {
+        // CLS; LD I, font of 0; DRW V0, V0, 5
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x00, 0xE0, 0xA0, 0x00, 0xD0, 0x05]);
+        let mut rng = StdRng::seed_from_u64(DEFAULT_SEED);
+        for _ in 0..3 {
+            chip8.try_emulate_cycle(&mut rng).unwrap();
+        }
+
+        let framebuffer = Framebuffer::new(&chip8);
+        assert_eq!(framebuffer.shape, [32, 64]);
+        assert_eq!(framebuffer.strides, [64, 1]);
+        // first row of the glyph of 0 (0xF0)
+        assert_eq!(&framebuffer.pixels[..8], [1, 1, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(
+            framebuffer
+                .pixels
+                .iter()
+                .filter(|&&pixel| pixel == 1)
+                .count(),
+            14
+        );
+
+        // HIGH
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x00, 0xFF]);
+        chip8.try_emulate_cycle(&mut rng).unwrap();
+        let framebuffer = Framebuffer::new(&chip8);
+        assert_eq!(framebuffer.shape, [64, 128]);
+        assert_eq!(framebuffer.pixels.len(), 128 * 64);
+    }
+}